@@ -0,0 +1,97 @@
+//! Ghost Pool operator CLI
+//!
+//! Signs with the Solana CLI keypair against the Solana CLI RPC URL (`--config`, `--url`,
+//! `--ws-url` and `--keypair` override them) and derives every Arcium account from the cluster
+//! offset (`--cluster-offset`, default `$ARCIUM_CLUSTER_OFFSET`). Commands that queue a
+//! computation wait for its callback to land (`--timeout <secs>`).
+//!
+//! `--keypair usb://ledger[?key=<n>]` signs on a Ledger instead, for authority-gated commands
+//! that shouldn't depend on a key file. Every transaction is named before the device prompts,
+//! and init-comp-defs sends one circuit per transaction so each approval is for one definition.
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+mod commands;
+mod operator;
+mod rescue;
+
+// The indexer's event decoding; watch leaves the indexer-only helpers unused
+#[allow(dead_code)]
+#[path = "../../indexer/src/decode.rs"]
+mod decode;
+
+const USAGE: &str = "usage: ghost-pool-cli <command> [--flag <value> ...]
+
+commands:
+  init-comp-defs [--circuit-urls <path>]          create every missing computation definition, pinning
+                                                  the off-chain circuits to circuit-urls.json on first run
+  propose-circuit-upgrade --circuit <c> --source <url> --hash <hex>
+  upgrade-circuit --circuit <c>                   apply the proposed pin once its timelock has run
+  init-pool --mint <m> [--pool-id <n>] [--threshold <amount>]
+  deposit --pool <p> --amount <a> [--lock-secs <s>] [--receipt-index <i>] [--referrer <pubkey>]
+  withdraw --pool <p> --amount <a>                authorize, then claim to the signer's token account
+  claim-referral --pool <p>                       pay the signer's referral rewards to their token account
+  check-invest --pool <p>                         let the MXE approve idle funds for investment
+  invest --pool <p> --reserve <r> [--min-collateral-out <c>]
+                                                  move the approved amount into Kamino and record it
+  harvest --pool <p> --reserve <r>                record the Kamino position's yield
+  status --pool <p>                               public pool state and recent computations
+  watch --pool <p>                                print the pool's events as they land
+
+flags: --config <path> --url <rpc> --ws-url <rpc ws> --keypair <path | usb://ledger?key=<n>>
+       --cluster-offset <n> --timeout <secs>
+deposit and withdraw read the password from $GHOST_POOL_PASSWORD";
+
+/// `--name value` pairs after the command
+pub struct Flags(HashMap<String, String>);
+
+impl Flags {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut flags = HashMap::new();
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            if !flag.starts_with("--") {
+                return Err(format!("unexpected argument {:?}", flag));
+            }
+            let value = it.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            flags.insert(flag.clone(), value.clone());
+        }
+        Ok(Self(flags))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    pub fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.get(name)
+            .map(|value| value.parse().map_err(|_| format!("bad {} {:?}", name, value)))
+            .transpose()
+    }
+
+    pub fn require<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        self.optional(name)?.ok_or_else(|| format!("missing {}", name))
+    }
+
+    pub fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        Ok(self.optional(name)?.unwrap_or(default))
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(command) = args.first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    match Flags::parse(&args[1..]).and_then(|flags| commands::run(command, &flags)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {}", command, e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Connection, signer and callback polling shared by the commands
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AccountDeserialize;
+use ghost_pool::{ComputationLog, GhostPool};
+use ghost_pool_client::{computation_log_address, ArciumEnv, PoolRef};
+use solana_clap_utils::keypair::{parse_signer_source, SignerSourceKind};
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_client::rpc_client::RpcClient;
+use solana_remote_wallet::remote_keypair::generate_remote_keypair;
+use solana_remote_wallet::remote_wallet::maybe_wallet_manager;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::Flags;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_TIMEOUT_SECS: u64 = 180;
+
+/// The MXE's x25519 key in its MXEAccount (same offset the frontend reads)
+const MXE_X25519_PUBKEY_OFFSET: usize = 95;
+
+pub struct Operator {
+    pub rpc: RpcClient,
+    pub url: String,
+    pub ws_url: String,
+    pub payer: Box<dyn Signer>,
+    pub env: ArciumEnv,
+    timeout: Duration,
+    /// The payer is a hardware wallet, which asks for every signature on the device
+    hardware: bool,
+}
+
+/// The signer behind `path`: a keypair file, or a hardware wallet such as `usb://ledger?key=0`
+fn signer_from_path(path: &str) -> Result<(Box<dyn Signer>, bool), String> {
+    let source = parse_signer_source(path).map_err(|e| format!("bad keypair {}: {}", path, e))?;
+    let file = match source.kind {
+        SignerSourceKind::Usb(locator) => {
+            let manager = maybe_wallet_manager()
+                .map_err(|e| format!("failed to open {}: {}", path, e))?
+                .ok_or_else(|| format!("no hardware wallet found for {}", path))?;
+            let derivation_path = source.derivation_path.unwrap_or_default();
+            let keypair = generate_remote_keypair(locator, derivation_path, &manager, false, "keypair")
+                .map_err(|e| format!("failed to use {}: {}", path, e))?;
+            return Ok((Box::new(keypair), true));
+        }
+        SignerSourceKind::Filepath(file) => file,
+        _ => path.to_string(),
+    };
+    let keypair = read_keypair_file(&file).map_err(|e| format!("failed to read keypair {}: {}", path, e))?;
+    Ok((Box::new(keypair), false))
+}
+
+impl Operator {
+    pub fn connect(flags: &Flags) -> Result<Self, String> {
+        let config = match flags.get("--config") {
+            Some(path) => Config::load(path).map_err(|e| format!("failed to load {}: {}", path, e))?,
+            None => CONFIG_FILE
+                .as_ref()
+                .and_then(|path| Config::load(path).ok())
+                .unwrap_or_default(),
+        };
+        let url = flags.get("--url").unwrap_or(&config.json_rpc_url);
+        let ws_url = match flags.get("--ws-url") {
+            Some(ws_url) => ws_url.to_string(),
+            None if flags.get("--url").is_some() => Config::compute_websocket_url(url),
+            None => config.websocket_url.clone(),
+        };
+        let keypair_path = flags.get("--keypair").unwrap_or(&config.keypair_path);
+        let (payer, hardware) = signer_from_path(keypair_path)?;
+
+        let cluster_offset = match flags.optional("--cluster-offset")? {
+            Some(offset) => offset,
+            None => std::env::var("ARCIUM_CLUSTER_OFFSET")
+                .map_err(|_| "set --cluster-offset or $ARCIUM_CLUSTER_OFFSET".to_string())?
+                .parse()
+                .map_err(|_| "bad $ARCIUM_CLUSTER_OFFSET".to_string())?,
+        };
+
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            url: url.to_string(),
+            ws_url,
+            payer,
+            env: ArciumEnv::new(cluster_offset),
+            timeout: Duration::from_secs(flags.parse_or("--timeout", DEFAULT_TIMEOUT_SECS)?),
+            hardware,
+        })
+    }
+
+    pub fn signer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    pub fn hardware(&self) -> bool {
+        self.hardware
+    }
+
+    pub fn send(&self, label: &str, instructions: &[Instruction]) -> Result<Signature, String> {
+        let blockhash = self
+            .rpc
+            .get_latest_blockhash()
+            .map_err(|e| format!("{}: {}", label, e))?;
+        if self.hardware {
+            println!("{}: approve {} instruction(s) on the hardware wallet", label, instructions.len());
+        }
+        let mut tx = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        tx.try_sign(&[self.payer.as_ref()], blockhash)
+            .map_err(|e| format!("{}: signing failed: {}", label, e))?;
+        let signature = self
+            .rpc
+            .send_and_confirm_transaction(&tx)
+            .map_err(|e| format!("{} failed: {}", label, e))?;
+        println!("{}: {}", label, signature);
+        Ok(signature)
+    }
+
+    pub fn exists(&self, address: &Pubkey) -> Result<bool, String> {
+        self.rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .map(|response| response.value.is_some())
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))
+    }
+
+    pub fn fetch_data(&self, address: &Pubkey) -> Result<Vec<u8>, String> {
+        self.rpc
+            .get_account_data(address)
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))
+    }
+
+    pub fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T, String> {
+        let data = self.fetch_data(address)?;
+        T::try_deserialize(&mut data.as_slice()).map_err(|e| format!("failed to decode {}: {}", address, e))
+    }
+
+    /// Program owning `address` (SPL Token or Token-2022 for a mint)
+    pub fn owner(&self, address: &Pubkey) -> Result<Pubkey, String> {
+        self.rpc
+            .get_account(address)
+            .map(|account| account.owner)
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))
+    }
+
+    pub fn pool(&self, address: &Pubkey) -> Result<(GhostPool, PoolRef), String> {
+        let pool: GhostPool = self.fetch(address)?;
+        let pool_ref = PoolRef {
+            address: *address,
+            deposit_mint: pool.deposit_mint,
+            token_program: self.owner(&pool.deposit_mint)?,
+            share_mint: pool.issues_shares().then_some(pool.share_mint),
+        };
+        Ok((pool, pool_ref))
+    }
+
+    /// Key the pool's MXE expects client inputs to be encrypted to
+    pub fn mxe_public_key(&self) -> Result<[u8; 32], String> {
+        let data = self.fetch_data(&self.env.mxe())?;
+        data.get(MXE_X25519_PUBKEY_OFFSET..MXE_X25519_PUBKEY_OFFSET + 32)
+            .and_then(|key| key.try_into().ok())
+            .filter(|key| *key != [0u8; 32])
+            .ok_or_else(|| "the MXE has no x25519 key yet (keygen incomplete?)".to_string())
+    }
+
+    /// Call `done` until it yields a value, giving up after the timeout
+    pub fn poll<T>(&self, what: &str, mut done: impl FnMut() -> Result<Option<T>, String>) -> Result<T, String> {
+        let start = Instant::now();
+        loop {
+            if let Some(value) = done()? {
+                return Ok(value);
+            }
+            if start.elapsed() > self.timeout {
+                return Err(format!("timed out after {}s waiting for {}", self.timeout.as_secs(), what));
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Wait for the callback of the computation queued under `computation_offset`
+    /// Returns whether it succeeded: rejected withdrawals and expired computations complete
+    /// without success
+    pub fn wait_for_callback(&self, pool: &Pubkey, computation_offset: u64) -> Result<bool, String> {
+        let log_address = computation_log_address(pool);
+        println!("waiting for computation {}...", computation_offset);
+        self.poll(&format!("computation {}", computation_offset), || {
+            let log: ComputationLog = self.fetch(&log_address)?;
+            let entry = log
+                .entries
+                .iter()
+                .find(|e| e.computation_offset == computation_offset)
+                .ok_or_else(|| format!("computation {} is no longer in the pool's log", computation_offset))?;
+            Ok((entry.completion_slot != 0).then_some(entry.succeeded))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{write_keypair_file, Keypair};
+
+    #[test]
+    fn ledger_uris_take_the_remote_wallet_path() {
+        for path in ["usb://ledger", "usb://ledger?key=0"] {
+            assert!(matches!(parse_signer_source(path).unwrap().kind, SignerSourceKind::Usb(_)));
+            // No device is attached here: the wallet manager fails, no keypair file is read
+            let err = signer_from_path(path).err().unwrap();
+            assert!(!err.starts_with("failed to read keypair"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn other_paths_read_a_keypair_file() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("ghost-pool-cli-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_keypair_file(&keypair, path).unwrap();
+        for source in [path.to_string(), format!("file://{}", path)] {
+            let (signer, hardware) = signer_from_path(&source).unwrap();
+            assert_eq!((signer.pubkey(), hardware), (keypair.pubkey(), false));
+        }
+        std::fs::remove_file(path).unwrap();
+
+        let err = signer_from_path(path).err().unwrap();
+        assert!(err.starts_with("failed to read keypair"), "{}", err);
+    }
+}
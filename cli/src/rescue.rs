@@ -0,0 +1,77 @@
+//! Rescue encryption through the TypeScript bindings (client/ts/rescue.ts), which run
+//! Arcium's own RescueCipher
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use ghost_pool_client::Cipher;
+use serde_json::{json, Value};
+
+pub struct NodeRescue {
+    shared_secret: [u8; 32],
+}
+
+impl NodeRescue {
+    pub fn new(shared_secret: [u8; 32]) -> Self {
+        Self { shared_secret }
+    }
+
+    fn run(&self, plaintext: &[u128], nonce: u128) -> Result<Vec<[u8; 32]>, String> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .expect("the cli lives inside the workspace");
+        let input = json!({
+            "sharedSecret": hex(&self.shared_secret),
+            "nonce": hex(&nonce.to_le_bytes()),
+            "plaintext": plaintext.iter().map(u128::to_string).collect::<Vec<_>>(),
+        });
+
+        let mut child = Command::new("npx")
+            .args(["ts-node", "client/ts/rescue.ts"])
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("failed to run npx: {}", e))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(input.to_string().as_bytes())
+            .map_err(|e| e.to_string())?;
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!("rescue.ts exited with {}", output.status));
+        }
+
+        let ciphertexts: Vec<Value> = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        ciphertexts
+            .iter()
+            .map(|c| c.as_str().and_then(unhex).ok_or_else(|| format!("bad ciphertext {}", c)))
+            .collect()
+    }
+}
+
+impl Cipher for NodeRescue {
+    fn encrypt(&self, plaintext: &[u128], nonce: u128) -> Vec<[u8; 32]> {
+        self.run(plaintext, nonce)
+            .unwrap_or_else(|e| panic!("rescue encryption failed: {}", e))
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 64 {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
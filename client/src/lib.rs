@@ -0,0 +1,1377 @@
+//! Ghost Pool client helpers
+//!
+//! Everything an integrator otherwise has to reverse-engineer from the program and the
+//! Arcium macros: the Arcium account PDAs each queue instruction takes, the x25519 key
+//! agreement with the MXE, the password hash and ciphertext layout of the encrypted
+//! arguments, computation offsets, and complete `initialize_pool` / `deposit` / `withdraw` /
+//! `claim_withdrawal` instructions, plus the cranks keepers send and the circuit registry.
+//!
+//! The Rescue cipher is the MXE's and isn't reimplemented here: encryption goes through
+//! [`Cipher`], backed by Arcium's `RescueCipher` (see `ts/index.ts` for the TypeScript side).
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{bpf_loader_upgradeable, sysvar};
+use anchor_lang::{system_program, Id, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use arcium_anchor::prelude::{
+    comp_def_offset, Arcium, ARCIUM_CLOCK_ACCOUNT_ADDRESS, ARCIUM_FEE_POOL_ACCOUNT_ADDRESS, SIGN_PDA_SEED,
+};
+use ghost_pool::{
+    exchange_rate_in, reserve_layout, AdminActionKind, PendingWithdrawal, KAMINO_LENDING_PROGRAM_ID,
+    LENDING_MARKET_AUTHORITY_SEED, MAX_ALLOWED_DESTINATIONS,
+};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use solana_sha256_hasher::hash;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+pub use ghost_pool::{amount_commitment, pubkey_fingerprint, COMP_DEF_CIRCUITS, ID as PROGRAM_ID, OFFCHAIN_CIRCUITS};
+
+// Arcium program PDA seeds (as derived by the derive_*_pda! macros)
+const MXE_SEED: &[u8] = b"MXEAccount";
+const MEMPOOL_SEED: &[u8] = b"Mempool";
+const EXECPOOL_SEED: &[u8] = b"Execpool";
+const COMPUTATION_SEED: &[u8] = b"ComputationAccount";
+const COMP_DEF_SEED: &[u8] = b"ComputationDefinitionAccount";
+const CLUSTER_SEED: &[u8] = b"Cluster";
+
+/// Circuits behind the instructions this crate builds
+pub const INIT_POOL_CIRCUIT: &str = "init_pool_state";
+pub const DEPOSIT_CIRCUIT: &str = "process_deposit";
+pub const WITHDRAW_CIRCUIT: &str = "authorize_withdrawal";
+pub const CLAIM_CIRCUIT: &str = "process_withdrawal";
+pub const CHECK_INVESTMENT_CIRCUIT: &str = "check_investment_needed";
+pub const RECORD_INVESTMENT_CIRCUIT: &str = "record_investment";
+pub const RECORD_YIELD_CIRCUIT: &str = "record_yield";
+pub const CLAIM_REFERRAL_CIRCUIT: &str = "claim_referral_rewards";
+pub const PUBLISH_TVL_CIRCUIT: &str = "publish_tvl";
+pub const POOL_STATS_CIRCUIT: &str = "compute_pool_stats";
+pub const COUNTER_EPOCH_CIRCUIT: &str = "reveal_deposit_count";
+
+// ============ PDAs ============
+
+/// Ghost Pool account of `seed_authority`'s pool `pool_id`
+pub fn ghost_pool_address(seed_authority: &Pubkey, pool_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"ghost_pool", seed_authority.as_ref(), &pool_id.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+/// Token account holding the pool's deposits
+pub fn vault_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", pool.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn computation_log_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"computation_log", pool.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn deposit_receipt_address(pool: &Pubkey, user: &Pubkey, receipt_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"receipt", pool.as_ref(), user.as_ref(), &receipt_index.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+/// Token account holding `deposit`s until their callback sweeps them into the vault
+pub fn share_mint_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"share_mint", pool.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn deposit_escrow_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", pool.as_ref()], &PROGRAM_ID).0
+}
+
+/// Record of a deposit's tokens until its callback lands, reclaimable if it never does
+pub fn failed_deposit_address(pool: &Pubkey, computation_offset: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"failed_deposit", pool.as_ref(), &computation_offset.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn withdrawal_receipt_address(pool: &Pubkey, computation_offset: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"withdrawal_receipt", pool.as_ref(), &computation_offset.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn pending_withdrawal_address(pool: &Pubkey, user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pending_withdrawal", pool.as_ref(), user.as_ref()], &PROGRAM_ID).0
+}
+
+/// Provisional state of a queued investment/yield computation, closed by its callback
+pub fn pending_computation_address(pool: &Pubkey, computation_offset: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"pending_computation", pool.as_ref(), &computation_offset.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn withdrawal_queue_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"withdrawal_queue", pool.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn deposit_intents_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"deposit_intents", pool.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn cranker_registry_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"cranker_registry", pool.as_ref()], &PROGRAM_ID).0
+}
+
+/// Co-signers required on privileged instructions once set_admin_multisig sets them
+pub fn admin_multisig_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"admin_multisig", pool.as_ref()], &PROGRAM_ID).0
+}
+
+/// Cranker account of `keeper`, the signer of its cranks
+pub fn cranker_address(pool: &Pubkey, keeper: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"cranker", pool.as_ref(), keeper.as_ref()], &PROGRAM_ID).0
+}
+
+/// Timelocked admin action of `kind` against `target` (a pool, or the circuit registry)
+pub fn admin_action_address(target: &Pubkey, kind: AdminActionKind) -> Pubkey {
+    Pubkey::find_program_address(&[b"admin_action", target.as_ref(), &[kind as u8]], &PROGRAM_ID).0
+}
+
+/// Program-wide registry of circuit sources, read by every init_*_comp_def
+pub fn circuit_registry_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"circuit_registry"], &PROGRAM_ID).0
+}
+
+/// The program's ProgramData account, whose upgrade authority creates the circuit registry
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+/// Arcium accounts of the cluster the pool's MXE is bound to
+#[derive(Clone, Copy, Debug)]
+pub struct ArciumEnv {
+    pub cluster_offset: u32,
+}
+
+impl ArciumEnv {
+    pub fn new(cluster_offset: u32) -> Self {
+        Self { cluster_offset }
+    }
+
+    fn arcium_pda(seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &Arcium::id()).0
+    }
+
+    /// Ghost Pool's signer PDA, which pays Arcium on the program's behalf
+    pub fn sign_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[&SIGN_PDA_SEED], &PROGRAM_ID).0
+    }
+
+    pub fn mxe(&self) -> Pubkey {
+        Self::arcium_pda(&[MXE_SEED, PROGRAM_ID.as_ref()])
+    }
+
+    pub fn mempool(&self) -> Pubkey {
+        Self::arcium_pda(&[MEMPOOL_SEED, &self.cluster_offset.to_le_bytes()])
+    }
+
+    pub fn execpool(&self) -> Pubkey {
+        Self::arcium_pda(&[EXECPOOL_SEED, &self.cluster_offset.to_le_bytes()])
+    }
+
+    pub fn cluster(&self) -> Pubkey {
+        Self::arcium_pda(&[CLUSTER_SEED, &self.cluster_offset.to_le_bytes()])
+    }
+
+    pub fn computation(&self, computation_offset: u64) -> Pubkey {
+        Self::arcium_pda(&[
+            COMPUTATION_SEED,
+            &self.cluster_offset.to_le_bytes(),
+            &computation_offset.to_le_bytes(),
+        ])
+    }
+
+    /// Computation definition of `circuit` (e.g. [`DEPOSIT_CIRCUIT`])
+    pub fn comp_def(&self, circuit: &str) -> Pubkey {
+        Self::arcium_pda(&[COMP_DEF_SEED, PROGRAM_ID.as_ref(), &comp_def_offset(circuit).to_le_bytes()])
+    }
+}
+
+// ============ Encryption ============
+
+/// Ephemeral x25519 key for one encrypted submission
+pub struct ClientKey(StaticSecret);
+
+impl ClientKey {
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(OsRng))
+    }
+
+    /// Passed to the program as `user_pubkey`
+    pub fn public_key(&self) -> [u8; 32] {
+        X25519PublicKey::from(&self.0).to_bytes()
+    }
+
+    /// Shared secret with the MXE's x25519 key, the key of the Rescue cipher
+    pub fn shared_secret(&self, mxe_public_key: &[u8; 32]) -> [u8; 32] {
+        self.0.diffie_hellman(&X25519PublicKey::from(*mxe_public_key)).to_bytes()
+    }
+}
+
+/// Rescue encryption under a client/MXE shared secret
+/// Each plaintext field element becomes one 32-byte ciphertext, all under one nonce
+pub trait Cipher {
+    fn encrypt(&self, plaintext: &[u128], nonce: u128) -> Vec<[u8; 32]>;
+}
+
+/// What the ledger stores for a password: SHA-256 of it, truncated to a u128 (little-endian)
+pub fn password_hash(password: &str) -> u128 {
+    u128::from_le_bytes(hash(password.as_bytes()).to_bytes()[..16].try_into().unwrap())
+}
+
+pub fn random_nonce() -> u128 {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    u128::from_le_bytes(bytes)
+}
+
+/// Fresh computation offset; it seeds the computation account, so it must not repeat
+pub fn random_computation_offset() -> u64 {
+    OsRng.next_u64()
+}
+
+/// Encrypted `DepositSecret` as the deposit instruction takes it
+pub struct EncryptedDeposit {
+    pub password_hash: [u8; 32],
+    pub allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+    pub beneficiary: [u8; 32],
+    pub user_pubkey: [u8; 32],
+    pub nonce: u128,
+    /// The referrer's fingerprint, under a nonce of its own
+    pub referrer: [u8; 32],
+    pub referrer_nonce: u128,
+}
+
+/// Encrypt a deposit's secret inputs together, in the circuit's field order
+/// `allowed_destinations` are owners the funds may later be withdrawn to (empty = any);
+/// `beneficiary` may recover the deposit after the pool's recovery period; `referrer` earns
+/// a share of the fees booked on it (see claim_referral_rewards)
+pub fn encrypt_deposit<C: Cipher>(
+    cipher: &C,
+    key: &ClientKey,
+    password: &str,
+    allowed_destinations: &[Pubkey],
+    beneficiary: Option<&Pubkey>,
+    referrer: Option<&Pubkey>,
+) -> EncryptedDeposit {
+    assert!(allowed_destinations.len() <= MAX_ALLOWED_DESTINATIONS, "too many allowed destinations");
+    let mut plaintext = vec![password_hash(password)];
+    plaintext.extend((0..MAX_ALLOWED_DESTINATIONS).map(|i| allowed_destinations.get(i).map_or(0, pubkey_fingerprint)));
+    plaintext.push(beneficiary.map_or(0, pubkey_fingerprint));
+
+    let nonce = random_nonce();
+    let ciphertexts = cipher.encrypt(&plaintext, nonce);
+    let referrer_nonce = random_nonce();
+    EncryptedDeposit {
+        password_hash: ciphertexts[0],
+        allowed_destinations: core::array::from_fn(|i| ciphertexts[1 + i]),
+        beneficiary: ciphertexts[1 + MAX_ALLOWED_DESTINATIONS],
+        user_pubkey: key.public_key(),
+        nonce,
+        referrer: cipher.encrypt(&[referrer.map_or(0, pubkey_fingerprint)], referrer_nonce)[0],
+        referrer_nonce,
+    }
+}
+
+/// Encrypted password for a withdrawal
+pub struct EncryptedPassword {
+    pub password_hash: [u8; 32],
+    pub user_pubkey: [u8; 32],
+    pub nonce: u128,
+}
+
+pub fn encrypt_password<C: Cipher>(cipher: &C, key: &ClientKey, password: &str) -> EncryptedPassword {
+    let nonce = random_nonce();
+    EncryptedPassword {
+        password_hash: cipher.encrypt(&[password_hash(password)], nonce)[0],
+        user_pubkey: key.public_key(),
+        nonce,
+    }
+}
+
+// ============ Instructions ============
+
+/// A pool as the instruction builders need it
+#[derive(Clone, Copy, Debug)]
+pub struct PoolRef {
+    pub address: Pubkey,
+    pub deposit_mint: Pubkey,
+    /// SPL Token or Token-2022, whichever owns `deposit_mint`
+    pub token_program: Pubkey,
+    /// The pool's share mint, if it issues share tokens
+    pub share_mint: Option<Pubkey>,
+}
+
+impl PoolRef {
+    /// `user`'s associated token account for the pool's shares, if it issues them
+    pub fn share_token_account(&self, user: &Pubkey) -> Option<Pubkey> {
+        self.share_mint
+            .map(|share_mint| get_associated_token_address_with_program_id(user, &share_mint, &self.token_program))
+    }
+}
+
+/// initialize_pool for `authority`'s pool `pool_id`
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_pool(
+    env: &ArciumEnv,
+    authority: &Pubkey,
+    deposit_mint: &Pubkey,
+    token_program: &Pubkey,
+    pool_id: u64,
+    investment_threshold: u64,
+    computation_offset: u64,
+) -> Instruction {
+    let ghost_pool = ghost_pool_address(authority, pool_id);
+    let accounts = ghost_pool::accounts::InitializePool {
+        authority: *authority,
+        ghost_pool,
+        deposit_mint: *deposit_mint,
+        vault: vault_address(&ghost_pool),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(INIT_POOL_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        token_program: *token_program,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::InitializePool {
+        computation_offset,
+        nonce: random_nonce(),
+        investment_threshold,
+        pool_id,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// Plaintext deposit parameters
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DepositParams {
+    pub amount: u64,
+    /// Seconds before nothing can be withdrawn (0 = unlocked)
+    pub lock_secs: u64,
+    /// Mint a DepositReceipt at this index, committing to `amount` under `amount_blinding`
+    pub receipt_index: Option<u32>,
+    pub amount_blinding: [u8; 32],
+    pub cu_price_micro: Option<u64>,
+}
+
+/// deposit from `user_token_account`; a rejected deposit is refunded to `refund_token_account`
+#[allow(clippy::too_many_arguments)]
+pub fn deposit(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    refund_token_account: &Pubkey,
+    computation_offset: u64,
+    encrypted: &EncryptedDeposit,
+    params: &DepositParams,
+) -> Instruction {
+    let accounts = deposit_accounts(
+        env,
+        pool,
+        user,
+        user,
+        user_token_account,
+        refund_token_account,
+        computation_offset,
+        params,
+    );
+    let args = ghost_pool::instruction::Deposit {
+        computation_offset,
+        amount: params.amount,
+        encrypted_password_hash: encrypted.password_hash,
+        user_pubkey: encrypted.user_pubkey,
+        nonce: encrypted.nonce,
+        encrypted_allowed_destinations: encrypted.allowed_destinations,
+        encrypted_beneficiary: encrypted.beneficiary,
+        encrypted_referrer: encrypted.referrer,
+        referrer_nonce: encrypted.referrer_nonce,
+        lock_secs: params.lock_secs,
+        receipt_index: params.receipt_index.unwrap_or_default(),
+        amount_blinding: params.amount_blinding,
+        cu_price_micro: params.cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// deposit_delegated: `relayer` deposits from `owner_token_account` as its token delegate (see
+/// `approve_delegate`), paying for the transaction; `encrypted` comes from `owner`, who owns the
+/// entry. The refund account must be the owner's too
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_delegated(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    relayer: &Pubkey,
+    owner: &Pubkey,
+    owner_token_account: &Pubkey,
+    refund_token_account: &Pubkey,
+    computation_offset: u64,
+    encrypted: &EncryptedDeposit,
+    params: &DepositParams,
+) -> Instruction {
+    let accounts = deposit_accounts(
+        env,
+        pool,
+        relayer,
+        owner,
+        owner_token_account,
+        refund_token_account,
+        computation_offset,
+        params,
+    );
+    let args = ghost_pool::instruction::DepositDelegated {
+        computation_offset,
+        amount: params.amount,
+        encrypted_password_hash: encrypted.password_hash,
+        user_pubkey: encrypted.user_pubkey,
+        nonce: encrypted.nonce,
+        encrypted_allowed_destinations: encrypted.allowed_destinations,
+        encrypted_beneficiary: encrypted.beneficiary,
+        encrypted_referrer: encrypted.referrer,
+        referrer_nonce: encrypted.referrer_nonce,
+        lock_secs: params.lock_secs,
+        receipt_index: params.receipt_index.unwrap_or_default(),
+        amount_blinding: params.amount_blinding,
+        cu_price_micro: params.cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// The token program's Approve: let `delegate` move up to `amount` out of `owner`'s
+/// `token_account`, for `owner` to sign ahead of deposit_delegated
+pub fn approve_delegate(
+    pool: &PoolRef,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    delegate: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![4];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction::new_with_bytes(
+        pool.token_program,
+        &data,
+        vec![
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*delegate, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+    )
+}
+
+/// Deposit accounts with `user` signing and paying, the refund and share accounts `owner`'s
+#[allow(clippy::too_many_arguments)]
+fn deposit_accounts(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    user: &Pubkey,
+    owner: &Pubkey,
+    user_token_account: &Pubkey,
+    refund_token_account: &Pubkey,
+    computation_offset: u64,
+    params: &DepositParams,
+) -> ghost_pool::accounts::Deposit {
+    ghost_pool::accounts::Deposit {
+        user: *user,
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        user_usdc_token: *user_token_account,
+        vault_usdc_token: vault_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        refund_token_account: *refund_token_account,
+        deposit_receipt: params
+            .receipt_index
+            .map(|index| deposit_receipt_address(&pool.address, user, index)),
+        failed_deposit: failed_deposit_address(&pool.address, computation_offset),
+        deposit_escrow: deposit_escrow_address(&pool.address),
+        share_token_account: pool.share_token_account(owner),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(DEPOSIT_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        token_program: pool.token_program,
+        arcium_program: Arcium::id(),
+    }
+}
+
+/// The user's wSOL associated token account under `token_program`
+pub fn wsol_account_address(user: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    let mint = if *token_program == anchor_spl::token_2022::ID {
+        anchor_spl::token_2022::spl_token_2022::native_mint::ID
+    } else {
+        anchor_spl::token::spl_token::native_mint::ID
+    };
+    get_associated_token_address_with_program_id(user, &mint, token_program)
+}
+
+/// The associated token program's CreateIdempotent for `user`'s account of `mint`
+fn create_associated_account(user: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        anchor_spl::associated_token::ID,
+        &[1],
+        vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(get_associated_token_address_with_program_id(user, mint, token_program), false),
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+    )
+}
+
+/// Create the user's wSOL account if it doesn't exist yet, to send ahead of deposit_sol
+pub fn create_wsol_account(pool: &PoolRef, user: &Pubkey) -> Instruction {
+    create_associated_account(user, &pool.deposit_mint, &pool.token_program)
+}
+
+/// Create the user's share token account if it doesn't exist yet, to send ahead of a deposit
+/// into a pool that issues share tokens (None if it doesn't)
+pub fn create_share_account(pool: &PoolRef, user: &Pubkey) -> Option<Instruction> {
+    let share_mint = pool.share_mint?;
+    Some(create_associated_account(user, &share_mint, &pool.token_program))
+}
+
+/// enable_share_tokens: make the pool mint share tokens on deposit (authority only, empty pool)
+pub fn enable_share_tokens(pool: &PoolRef, authority: &Pubkey) -> Instruction {
+    let accounts = ghost_pool::accounts::EnableShareTokens {
+        authority: *authority,
+        ghost_pool: pool.address,
+        vault: vault_address(&pool.address),
+        share_mint: share_mint_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        token_program: pool.token_program,
+        system_program: system_program::ID,
+    };
+    let args = ghost_pool::instruction::EnableShareTokens {};
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// deposit_sol: `deposit` from the user's wSOL account, wrapping `params.amount` lamports into
+/// it first; the account is closed again if it was empty and isn't `refund_token_account`
+pub fn deposit_sol(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    user: &Pubkey,
+    refund_token_account: &Pubkey,
+    computation_offset: u64,
+    encrypted: &EncryptedDeposit,
+    params: &DepositParams,
+) -> Instruction {
+    let wsol_account = wsol_account_address(user, &pool.token_program);
+    let mut ix = deposit(env, pool, user, &wsol_account, refund_token_account, computation_offset, encrypted, params);
+    ix.data = ghost_pool::instruction::DepositSol {
+        computation_offset,
+        amount: params.amount,
+        encrypted_password_hash: encrypted.password_hash,
+        user_pubkey: encrypted.user_pubkey,
+        nonce: encrypted.nonce,
+        encrypted_allowed_destinations: encrypted.allowed_destinations,
+        encrypted_beneficiary: encrypted.beneficiary,
+        encrypted_referrer: encrypted.referrer,
+        referrer_nonce: encrypted.referrer_nonce,
+        lock_secs: params.lock_secs,
+        receipt_index: params.receipt_index.unwrap_or_default(),
+        amount_blinding: params.amount_blinding,
+        cu_price_micro: params.cu_price_micro,
+    }
+    .data();
+    ix
+}
+
+/// reclaim_failed_deposit: take back the tokens of a vault-held deposit (top-up, swap, shard)
+/// whose computation aborted or timed out
+pub fn reclaim_failed_deposit(
+    pool: &PoolRef,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    computation_offset: u64,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::ReclaimFailedDeposit {
+        user: *user,
+        ghost_pool: pool.address,
+        failed_deposit: failed_deposit_address(&pool.address, computation_offset),
+        vault: vault_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        user_token_account: *user_token_account,
+        token_program: pool.token_program,
+    };
+    let args = ghost_pool::instruction::ReclaimFailedDeposit { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// reclaim_escrow: take back a `deposit` whose computation aborted or timed out
+pub fn reclaim_escrow(
+    pool: &PoolRef,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    computation_offset: u64,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::ReclaimEscrow {
+        user: *user,
+        ghost_pool: pool.address,
+        failed_deposit: failed_deposit_address(&pool.address, computation_offset),
+        deposit_escrow: deposit_escrow_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        user_token_account: *user_token_account,
+        token_program: pool.token_program,
+    };
+    let args = ghost_pool::instruction::ReclaimEscrow { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// withdraw `amount` to `user_token_account`, authorized by the encrypted password
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    computation_offset: u64,
+    amount: u64,
+    encrypted: &EncryptedPassword,
+    cu_price_micro: Option<u64>,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::Withdraw {
+        user: *user,
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        user_token_account: *user_token_account,
+        recipient_token_account: None,
+        withdrawal_receipt: withdrawal_receipt_address(&pool.address, computation_offset),
+        pending_withdrawal: pending_withdrawal_address(&pool.address, user),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(WITHDRAW_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::Withdraw {
+        computation_offset,
+        amount,
+        encrypted_password_hash: encrypted.password_hash,
+        user_pubkey: encrypted.user_pubkey,
+        nonce: encrypted.nonce,
+        cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// claim_withdrawal: pay out the withdrawal authorized under `withdraw_offset` to the
+/// destination it was requested for, and queue the ledger debit
+#[allow(clippy::too_many_arguments)]
+pub fn claim_withdrawal(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    user: &Pubkey,
+    destination_token_account: &Pubkey,
+    withdraw_offset: u64,
+    computation_offset: u64,
+    cu_price_micro: Option<u64>,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::ClaimWithdrawal {
+        user: *user,
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        pending_withdrawal: pending_withdrawal_address(&pool.address, user),
+        withdrawal_receipt: withdrawal_receipt_address(&pool.address, withdraw_offset),
+        vault: vault_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        destination_token_account: *destination_token_account,
+        token_program: pool.token_program,
+        share_mint: pool.share_mint,
+        share_token_account: pool.share_token_account(user),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(CLAIM_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::ClaimWithdrawal {
+        computation_offset,
+        cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// withdraw_relayed: `withdraw` with `relayer` paying the transaction and the computation;
+/// `user` signs as well, but needs no SOL
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_relayed(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    relayer: &Pubkey,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    computation_offset: u64,
+    amount: u64,
+    encrypted: &EncryptedPassword,
+    cu_price_micro: Option<u64>,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::WithdrawRelayed {
+        relayer: *relayer,
+        user: *user,
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        user_token_account: *user_token_account,
+        withdrawal_receipt: withdrawal_receipt_address(&pool.address, computation_offset),
+        pending_withdrawal: pending_withdrawal_address(&pool.address, user),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(WITHDRAW_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::WithdrawRelayed {
+        computation_offset,
+        amount,
+        encrypted_password_hash: encrypted.password_hash,
+        user_pubkey: encrypted.user_pubkey,
+        nonce: encrypted.nonce,
+        cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// claim_withdrawal_relayed: claim a withdraw_relayed withdrawal with `relayer` paying, `rebate`
+/// of it going to `relayer_token_account`
+#[allow(clippy::too_many_arguments)]
+pub fn claim_withdrawal_relayed(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    relayer: &Pubkey,
+    relayer_token_account: &Pubkey,
+    user: &Pubkey,
+    destination_token_account: &Pubkey,
+    withdraw_offset: u64,
+    computation_offset: u64,
+    rebate: u64,
+    cu_price_micro: Option<u64>,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::ClaimWithdrawalRelayed {
+        relayer: *relayer,
+        user: *user,
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        pending_withdrawal: pending_withdrawal_address(&pool.address, user),
+        withdrawal_receipt: withdrawal_receipt_address(&pool.address, withdraw_offset),
+        vault: vault_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        destination_token_account: *destination_token_account,
+        token_program: pool.token_program,
+        relayer_token_account: *relayer_token_account,
+        share_mint: pool.share_mint,
+        share_token_account: pool.share_token_account(user),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(CLAIM_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::ClaimWithdrawalRelayed {
+        computation_offset,
+        rebate,
+        cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// withdraw_sol: claim_withdrawal into the user's wSOL account (the withdraw destination),
+/// then close it so the payout arrives as SOL
+pub fn withdraw_sol(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    user: &Pubkey,
+    withdraw_offset: u64,
+    computation_offset: u64,
+    cu_price_micro: Option<u64>,
+) -> Instruction {
+    let wsol_account = wsol_account_address(user, &pool.token_program);
+    let mut ix = claim_withdrawal(env, pool, user, &wsol_account, withdraw_offset, computation_offset, cu_price_micro);
+    ix.data = ghost_pool::instruction::WithdrawSol {
+        computation_offset,
+        cu_price_micro,
+    }
+    .data();
+    ix
+}
+
+// ============ Cranks ============
+
+/// Cranker registry and cranker accounts a crank passes, where they exist
+/// (authorize_crank accepts the pool authority without either)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrankAccounts {
+    pub cranker_registry: Option<Pubkey>,
+    pub cranker: Option<Pubkey>,
+}
+
+/// check_and_invest: ask the MXE whether idle funds cross the investment threshold
+pub fn check_and_invest(
+    env: &ArciumEnv,
+    pool: &Pubkey,
+    signer: &Pubkey,
+    crank: CrankAccounts,
+    computation_offset: u64,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::CheckAndInvest {
+        authority: *signer,
+        ghost_pool: *pool,
+        computation_log: computation_log_address(pool),
+        cranker_registry: crank.cranker_registry,
+        cranker: crank.cranker,
+        vault: vault_address(pool),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(CHECK_INVESTMENT_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::CheckAndInvest { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// Mock Kamino's reserve for `liquidity_mint` in `lending_market` (KLend reserves aren't PDAs)
+pub fn mock_reserve_address(lending_market: &Pubkey, liquidity_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"reserve", lending_market.as_ref(), liquidity_mint.as_ref()],
+        &KAMINO_LENDING_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Keys of a Kamino reserve, read at the offsets the program reads them from
+/// `program` is the lending program that owns it, the pool's `lending_program`
+#[derive(Clone, Copy, Debug)]
+pub struct KaminoReserve {
+    pub address: Pubkey,
+    pub program: Pubkey,
+    pub lending_market: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub liquidity_supply: Pubkey,
+    /// Liquidity per cToken * 1e6 when the account was read
+    pub exchange_rate: u64,
+}
+
+impl KaminoReserve {
+    pub fn from_account_data(address: Pubkey, program: Pubkey, data: &[u8]) -> Option<Self> {
+        let key = |offset: usize| {
+            data.get(offset..offset + 32)
+                .and_then(|key| <[u8; 32]>::try_from(key).ok())
+                .map(Pubkey::new_from_array)
+        };
+        Some(Self {
+            address,
+            program,
+            lending_market: key(reserve_layout::LENDING_MARKET)?,
+            collateral_mint: key(reserve_layout::COLLATERAL_MINT)?,
+            liquidity_supply: key(reserve_layout::LIQUIDITY_SUPPLY)?,
+            exchange_rate: exchange_rate_in(data)?,
+        })
+    }
+
+    /// min_collateral_out for investing `amount`: the cTokens it mints at the rate read,
+    /// less `tolerance_bps` for the rate moving before the transaction lands
+    pub fn min_collateral_out(&self, amount: u64, tolerance_bps: u16) -> u64 {
+        if self.exchange_rate == 0 {
+            return 0;
+        }
+        less_tolerance(amount as u128 * 1_000_000 / self.exchange_rate as u128, tolerance_bps)
+    }
+
+    /// min_liquidity_out for redeeming `collateral` cTokens: the liquidity they're worth at
+    /// the rate read, less `tolerance_bps`
+    pub fn min_liquidity_out(&self, collateral: u64, tolerance_bps: u16) -> u64 {
+        less_tolerance(collateral as u128 * self.exchange_rate as u128 / 1_000_000, tolerance_bps)
+    }
+
+    pub fn lending_market_authority(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[LENDING_MARKET_AUTHORITY_SEED, self.lending_market.as_ref()],
+            &self.program,
+        )
+        .0
+    }
+}
+
+/// `quote` less `tolerance_bps` of it
+fn less_tolerance(quote: u128, tolerance_bps: u16) -> u64 {
+    let floor = quote * 10_000u128.saturating_sub(tolerance_bps as u128) / 10_000;
+    u64::try_from(floor).unwrap_or(u64::MAX)
+}
+
+/// invest_in_kamino: deposit the approved amount into `reserve` (authority only), failing
+/// unless it mints at least `min_collateral_out` cTokens
+pub fn invest_in_kamino(
+    pool: &Pubkey,
+    authority: &Pubkey,
+    deposit_mint: &Pubkey,
+    collateral_token_account: &Pubkey,
+    reserve: &KaminoReserve,
+    min_collateral_out: u64,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::InvestInKamino {
+        authority: *authority,
+        ghost_pool: *pool,
+        vault: vault_address(pool),
+        kamino_lending_market: reserve.lending_market,
+        kamino_lending_market_authority: reserve.lending_market_authority(),
+        kamino_reserve: reserve.address,
+        reserve_liquidity_mint: *deposit_mint,
+        reserve_collateral_mint: reserve.collateral_mint,
+        reserve_liquidity_supply: reserve.liquidity_supply,
+        user_destination_collateral: *collateral_token_account,
+        token_program: anchor_spl::token::ID,
+        instructions_sysvar: sysvar::instructions::ID,
+        kamino_program: reserve.program,
+        system_program: system_program::ID,
+    };
+    let args = ghost_pool::instruction::InvestInKamino { min_collateral_out };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// redeem_from_kamino: redeem `collateral_amount` of the pool's cTokens back into the vault
+/// (authority only), failing unless it returns at least `min_liquidity_out`
+pub fn redeem_from_kamino(
+    pool: &Pubkey,
+    authority: &Pubkey,
+    deposit_mint: &Pubkey,
+    collateral_token_account: &Pubkey,
+    reserve: &KaminoReserve,
+    collateral_amount: u64,
+    min_liquidity_out: u64,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::RedeemFromKamino {
+        authority: *authority,
+        ghost_pool: *pool,
+        vault: vault_address(pool),
+        kamino_lending_market: reserve.lending_market,
+        kamino_lending_market_authority: reserve.lending_market_authority(),
+        kamino_reserve: reserve.address,
+        reserve_liquidity_mint: *deposit_mint,
+        reserve_collateral_mint: reserve.collateral_mint,
+        reserve_liquidity_supply: reserve.liquidity_supply,
+        collateral_token_account: *collateral_token_account,
+        token_program: anchor_spl::token::ID,
+        instructions_sysvar: sysvar::instructions::ID,
+        kamino_program: reserve.program,
+    };
+    let args = ghost_pool::instruction::RedeemFromKamino {
+        collateral_amount,
+        min_liquidity_out,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// record_investment_mpc: fold invested-but-unrecorded funds into the encrypted state
+/// (authority only)
+pub fn record_investment(env: &ArciumEnv, pool: &Pubkey, authority: &Pubkey, computation_offset: u64) -> Instruction {
+    let accounts = ghost_pool::accounts::RecordInvestmentMpc {
+        authority: *authority,
+        ghost_pool: *pool,
+        computation_log: computation_log_address(pool),
+        pending_computation: pending_computation_address(pool, computation_offset),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(RECORD_INVESTMENT_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::RecordInvestmentMpc { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// harvest_yield: record the yield of the pool's position in `kamino_reserve`
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_yield(
+    env: &ArciumEnv,
+    pool: &Pubkey,
+    signer: &Pubkey,
+    crank: CrankAccounts,
+    kamino_reserve: &Pubkey,
+    collateral_token_account: &Pubkey,
+    computation_offset: u64,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::HarvestYield {
+        authority: *signer,
+        ghost_pool: *pool,
+        computation_log: computation_log_address(pool),
+        pending_computation: pending_computation_address(pool, computation_offset),
+        cranker_registry: crank.cranker_registry,
+        cranker: crank.cranker,
+        kamino_reserve: *kamino_reserve,
+        collateral_token_account: *collateral_token_account,
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(RECORD_YIELD_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::HarvestYield { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// fulfill_withdrawal: pay `pending`, the head of the pool's WithdrawalQueue
+pub fn fulfill_withdrawal(
+    pool: &PoolRef,
+    fulfiller: &Pubkey,
+    crank: CrankAccounts,
+    pending: &PendingWithdrawal,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::FulfillWithdrawal {
+        fulfiller: *fulfiller,
+        ghost_pool: pool.address,
+        withdrawal_queue: withdrawal_queue_address(&pool.address),
+        pending_withdrawal: pending_withdrawal_address(&pool.address, &pending.user),
+        withdrawal_receipt: pending.receipt,
+        vault: vault_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        destination_token_account: pending.destination,
+        user: pending.user,
+        token_program: pool.token_program,
+        cranker_registry: crank.cranker_registry,
+        cranker: crank.cranker,
+    };
+    let args = ghost_pool::instruction::FulfillWithdrawal {};
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// reconcile: check the public books against the vault; `mark` (authority only) records the
+/// result in needs_attention
+pub fn reconcile(pool: &Pubkey, caller: &Pubkey, mark: bool) -> Instruction {
+    let accounts = ghost_pool::accounts::Reconcile {
+        caller: *caller,
+        ghost_pool: *pool,
+        vault: vault_address(pool),
+    };
+    let args = ghost_pool::instruction::Reconcile { mark };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// sync_position: value the pool's cTokens at `reserve`'s exchange rate (anyone)
+pub fn sync_position(
+    pool: &Pubkey,
+    caller: &Pubkey,
+    reserve: &Pubkey,
+    collateral_token_account: &Pubkey,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::SyncPosition {
+        caller: *caller,
+        ghost_pool: *pool,
+        kamino_reserve: *reserve,
+        collateral_token_account: *collateral_token_account,
+    };
+    let args = ghost_pool::instruction::SyncPosition {};
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// set_referral_share: referrers' share of the fees booked on referred deposits (authority only)
+pub fn set_referral_share(pool: &Pubkey, authority: &Pubkey, referral_share_bps: u16) -> Instruction {
+    let accounts = ghost_pool::accounts::SetReferralShare {
+        authority: *authority,
+        ghost_pool: *pool,
+    };
+    let args = ghost_pool::instruction::SetReferralShare { referral_share_bps };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// set_admin_multisig: require `threshold` of `members` to co-sign privileged instructions
+/// (authority only); empty `members` turns it off. Once one is set, changing it needs its quorum
+/// too, see with_admin_quorum
+pub fn set_admin_multisig(pool: &Pubkey, authority: &Pubkey, threshold: u8, members: Vec<Pubkey>) -> Instruction {
+    let accounts = ghost_pool::accounts::SetAdminMultisig {
+        authority: *authority,
+        ghost_pool: *pool,
+        admin_multisig: admin_multisig_address(pool),
+        system_program: system_program::ID,
+    };
+    let args = ghost_pool::instruction::SetAdminMultisig { threshold, members };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// set_tvl_disclosure: let publish_tvl and publish_stats reveal amounts rounded to `bucket`,
+/// each at most once per `epoch_slots` (authority only); a 0 bucket turns them off
+pub fn set_tvl_disclosure(pool: &Pubkey, authority: &Pubkey, bucket: u64, epoch_slots: u64) -> Instruction {
+    let accounts = ghost_pool::accounts::UpdatePoolConfig {
+        authority: *authority,
+        ghost_pool: *pool,
+    };
+    let args = ghost_pool::instruction::SetTvlDisclosure { bucket, epoch_slots };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// set_counter_privacy: publish total_deposits and total_withdrawals only at counter epoch
+/// close, rounded to `bucket`, at most once per `epoch_slots` (authority only); a 0 bucket
+/// keeps them exact
+pub fn set_counter_privacy(pool: &Pubkey, authority: &Pubkey, bucket: u16, epoch_slots: u64) -> Instruction {
+    let accounts = ghost_pool::accounts::SetCounterPrivacy {
+        authority: *authority,
+        ghost_pool: *pool,
+    };
+    let args = ghost_pool::instruction::SetCounterPrivacy { bucket, epoch_slots };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// Append the admin multisig and its `signers` to a privileged instruction, for pools with
+/// one set
+pub fn with_admin_quorum(mut ix: Instruction, pool: &Pubkey, signers: &[Pubkey]) -> Instruction {
+    ix.accounts.push(AccountMeta::new_readonly(admin_multisig_address(pool), false));
+    ix.accounts.extend(signers.iter().map(|signer| AccountMeta::new_readonly(*signer, true)));
+    ix
+}
+
+/// claim_referral_rewards: pay `referrer` what the deposits naming them have earned, to
+/// `referrer_token_account`
+pub fn claim_referral_rewards(
+    env: &ArciumEnv,
+    pool: &PoolRef,
+    referrer: &Pubkey,
+    referrer_token_account: &Pubkey,
+    computation_offset: u64,
+    cu_price_micro: Option<u64>,
+) -> Instruction {
+    let accounts = ghost_pool::accounts::ClaimReferralRewards {
+        referrer: *referrer,
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        vault: vault_address(&pool.address),
+        deposit_mint: pool.deposit_mint,
+        referrer_token_account: *referrer_token_account,
+        token_program: pool.token_program,
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(CLAIM_REFERRAL_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::ClaimReferralRewards {
+        computation_offset,
+        cu_price_micro,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// publish_tvl: reveal the pool's noise-padded, bucketed TVL in a TvlPublishedEvent
+pub fn publish_tvl(env: &ArciumEnv, pool: &Pubkey, payer: &Pubkey, computation_offset: u64) -> Instruction {
+    let accounts = ghost_pool::accounts::PublishTvl {
+        payer: *payer,
+        ghost_pool: *pool,
+        computation_log: computation_log_address(pool),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(PUBLISH_TVL_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::PublishTvl { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// publish_stats: reveal the pool's bucketed aggregate stats in a PoolStatsEvent
+pub fn publish_stats(env: &ArciumEnv, pool: &Pubkey, payer: &Pubkey, computation_offset: u64) -> Instruction {
+    let accounts = ghost_pool::accounts::PublishStats {
+        payer: *payer,
+        ghost_pool: *pool,
+        computation_log: computation_log_address(pool),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(POOL_STATS_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::PublishStats { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// close_counter_epoch: republish the pool's bucketed lifetime deposit and withdrawal counts
+pub fn close_counter_epoch(env: &ArciumEnv, pool: &Pubkey, payer: &Pubkey, computation_offset: u64) -> Instruction {
+    let accounts = ghost_pool::accounts::CloseCounterEpoch {
+        payer: *payer,
+        ghost_pool: *pool,
+        computation_log: computation_log_address(pool),
+        sign_pda_account: env.sign_pda(),
+        mxe_account: env.mxe(),
+        mempool_account: env.mempool(),
+        executing_pool: env.execpool(),
+        computation_account: env.computation(computation_offset),
+        comp_def_account: env.comp_def(COUNTER_EPOCH_CIRCUIT),
+        cluster_account: env.cluster(),
+        pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+        system_program: system_program::ID,
+        arcium_program: Arcium::id(),
+    };
+    let args = ghost_pool::instruction::CloseCounterEpoch { computation_offset };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+// ============ Circuit registry ============
+
+/// Computation definitions one init_comp_defs transaction can create, within the transaction
+/// size limit and a 1.4M compute unit budget
+pub const COMP_DEFS_PER_TX: usize = 11;
+
+/// init_comp_defs: create the computation definitions of the circuits selected by `mask`
+/// (bit `i` selects `COMP_DEF_CIRCUITS[i]`), each from its circuit registry pin
+pub fn init_comp_defs(env: &ArciumEnv, payer: &Pubkey, mask: u32) -> Instruction {
+    let accounts = ghost_pool::accounts::InitCompDefs {
+        payer: *payer,
+        mxe_account: env.mxe(),
+        circuit_registry: circuit_registry_address(),
+        arcium_program: Arcium::id(),
+        system_program: system_program::ID,
+    };
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(
+        COMP_DEF_CIRCUITS
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| mask & (1 << bit) != 0)
+            .map(|(_, circuit)| AccountMeta::new(env.comp_def(circuit), false)),
+    );
+    let args = ghost_pool::instruction::InitCompDefs { mask };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), metas)
+}
+
+/// init_circuit_registry: create the registry, pinning the off-chain circuits to `urls`, one per
+/// OFFCHAIN_CIRCUITS entry in its order (program upgrade authority only)
+pub fn init_circuit_registry(authority: &Pubkey, urls: Vec<String>) -> Instruction {
+    let accounts = ghost_pool::accounts::InitCircuitRegistry {
+        authority: *authority,
+        circuit_registry: circuit_registry_address(),
+        program: PROGRAM_ID,
+        program_data: program_data_address(),
+        system_program: system_program::ID,
+    };
+    let args = ghost_pool::instruction::InitCircuitRegistry { urls };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// propose_circuit_upgrade: propose pinning `circuit` to the build at `url` whose circuit hash
+/// is `hash` (registry authority only)
+pub fn propose_circuit_upgrade(authority: &Pubkey, circuit: &str, url: &str, hash: [u8; 32]) -> Instruction {
+    let registry = circuit_registry_address();
+    let accounts = ghost_pool::accounts::ProposeCircuitUpgrade {
+        authority: *authority,
+        circuit_registry: registry,
+        pending_action: admin_action_address(&registry, AdminActionKind::CircuitPin),
+        system_program: system_program::ID,
+    };
+    let args = ghost_pool::instruction::ProposeCircuitUpgrade {
+        circuit: circuit.to_string(),
+        url: url.to_string(),
+        hash,
+    };
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// upgrade_circuit: apply the pending pin once its timelock has run (registry authority only)
+pub fn upgrade_circuit(authority: &Pubkey) -> Instruction {
+    let registry = circuit_registry_address();
+    let accounts = ghost_pool::accounts::UpgradeCircuit {
+        authority: *authority,
+        circuit_registry: registry,
+        pending_action: admin_action_address(&registry, AdminActionKind::CircuitPin),
+    };
+    let args = ghost_pool::instruction::UpgradeCircuit {};
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
+
+/// cancel_action: withdraw a timelocked admin action before it runs (its proposer only)
+pub fn cancel_action(authority: &Pubkey, pending_action: &Pubkey) -> Instruction {
+    let accounts = ghost_pool::accounts::CancelAdminAction {
+        authority: *authority,
+        pending_action: *pending_action,
+    };
+    let args = ghost_pool::instruction::CancelAction {};
+    Instruction::new_with_bytes(PROGRAM_ID, &args.data(), accounts.to_account_metas(None))
+}
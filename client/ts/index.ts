@@ -0,0 +1,486 @@
+// TypeScript counterpart of the ghost-pool-client crate: PDAs, input encryption and
+// instruction building for Ghost Pool, on top of @arcium-hq/client.
+
+import { BN, Program } from '@coral-xyz/anchor';
+import { PublicKey, TransactionInstruction } from '@solana/web3.js';
+import {
+  RescueCipher,
+  deserializeLE,
+  getClockAccAddress,
+  getClusterAccAddress,
+  getCompDefAccAddress,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getFeePoolAccAddress,
+  getMXEAccAddress,
+  getMempoolAccAddress,
+  x25519,
+} from '@arcium-hq/client';
+import { createHash, randomBytes } from 'crypto';
+import { GhostPool } from '../../target/types/ghost_pool';
+
+/** Withdrawal destination owners per deposit (MAX_ALLOWED_DESTINATIONS in the program) */
+export const MAX_ALLOWED_DESTINATIONS = 2;
+
+// ============ PDAs ============
+
+const u32le = (n: number) => {
+  const buf = Buffer.alloc(4);
+  buf.writeUInt32LE(n);
+  return buf;
+};
+const u64le = (n: BN | number) => new BN(n).toArrayLike(Buffer, 'le', 8);
+
+export function ghostPoolAddress(programId: PublicKey, seedAuthority: PublicKey, poolId: BN | number): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('ghost_pool'), seedAuthority.toBuffer(), u64le(poolId)],
+    programId
+  )[0];
+}
+
+export function vaultAddress(programId: PublicKey, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from('vault'), pool.toBuffer()], programId)[0];
+}
+
+export function computationLogAddress(programId: PublicKey, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from('computation_log'), pool.toBuffer()], programId)[0];
+}
+
+export function depositReceiptAddress(
+  programId: PublicKey,
+  pool: PublicKey,
+  user: PublicKey,
+  receiptIndex: number
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('receipt'), pool.toBuffer(), user.toBuffer(), u32le(receiptIndex)],
+    programId
+  )[0];
+}
+
+/** Token account holding `deposit`s until their callback sweeps them into the vault */
+export function depositEscrowAddress(programId: PublicKey, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from('escrow'), pool.toBuffer()], programId)[0];
+}
+
+/** Mint of the pool's share tokens, once `enable_share_tokens` creates it */
+export function shareMintAddress(programId: PublicKey, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from('share_mint'), pool.toBuffer()], programId)[0];
+}
+
+/** Record of a deposit's tokens until its callback lands, reclaimable if it never does */
+export function failedDepositAddress(programId: PublicKey, pool: PublicKey, computationOffset: BN): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('failed_deposit'), pool.toBuffer(), u64le(computationOffset)],
+    programId
+  )[0];
+}
+
+export function withdrawalReceiptAddress(programId: PublicKey, pool: PublicKey, computationOffset: BN): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('withdrawal_receipt'), pool.toBuffer(), u64le(computationOffset)],
+    programId
+  )[0];
+}
+
+export function pendingWithdrawalAddress(programId: PublicKey, pool: PublicKey, user: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('pending_withdrawal'), pool.toBuffer(), user.toBuffer()],
+    programId
+  )[0];
+}
+
+/** Program-wide registry of circuit sources, read by every init_*_comp_def */
+export function circuitRegistryAddress(programId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from('circuit_registry')], programId)[0];
+}
+
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111');
+
+/** The program's ProgramData account, whose upgrade authority creates the circuit registry */
+export function programDataAddress(programId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([programId.toBuffer()], BPF_LOADER_UPGRADEABLE_PROGRAM_ID)[0];
+}
+
+/** Comp def offset of a circuit: first 4 bytes of sha256(name), little-endian */
+export function compDefOffset(circuit: string): number {
+  return createHash('sha256').update(circuit).digest().readUInt32LE(0);
+}
+
+/** Arcium accounts a queue instruction takes for `circuit` */
+export function arciumAccounts(programId: PublicKey, clusterOffset: number, computationOffset: BN, circuit: string) {
+  return {
+    mxeAccount: getMXEAccAddress(programId),
+    mempoolAccount: getMempoolAccAddress(clusterOffset),
+    executingPool: getExecutingPoolAccAddress(clusterOffset),
+    computationAccount: getComputationAccAddress(clusterOffset, computationOffset),
+    compDefAccount: getCompDefAccAddress(programId, compDefOffset(circuit)),
+    clusterAccount: getClusterAccAddress(clusterOffset),
+    poolAccount: getFeePoolAccAddress(),
+    clockAccount: getClockAccAddress(),
+  };
+}
+
+// ============ Encryption ============
+
+/** Ephemeral x25519 key and the Rescue cipher it shares with the MXE */
+export function clientCipher(mxePublicKey: Uint8Array): { publicKey: Uint8Array; cipher: RescueCipher } {
+  const secretKey = x25519.utils.randomSecretKey();
+  return {
+    publicKey: x25519.getPublicKey(secretKey),
+    cipher: new RescueCipher(x25519.getSharedSecret(secretKey, mxePublicKey)),
+  };
+}
+
+/** What the ledger stores for a password: SHA-256 of it, first 16 bytes little-endian */
+export function passwordHash(password: string): bigint {
+  return deserializeLE(createHash('sha256').update(password).digest().subarray(0, 16));
+}
+
+/** Ledger fingerprint of a pubkey: first 16 bytes, little-endian */
+export function pubkeyFingerprint(owner: PublicKey): bigint {
+  return deserializeLE(owner.toBuffer().subarray(0, 16));
+}
+
+/** Fresh computation offset; it seeds the computation account, so it must not repeat */
+export function randomComputationOffset(): BN {
+  return new BN(randomBytes(8), 'le');
+}
+
+const toArgs = (ciphertext: number[] | Uint8Array) => Array.from(ciphertext);
+
+export interface EncryptedDeposit {
+  passwordHash: number[];
+  allowedDestinations: number[][];
+  beneficiary: number[];
+  userPubkey: number[];
+  nonce: BN;
+  /** The referrer's fingerprint, under a nonce of its own */
+  referrer: number[];
+  referrerNonce: BN;
+}
+
+/**
+ * Encrypt a deposit's secret inputs together, in the circuit's field order.
+ * `allowedDestinations` are owners the funds may later be withdrawn to (empty = any);
+ * `beneficiary` may recover the deposit after the pool's recovery period; `referrer` earns a
+ * share of the fees booked on it (see claim_referral_rewards).
+ */
+export function encryptDeposit(
+  mxePublicKey: Uint8Array,
+  password: string,
+  allowedDestinations: PublicKey[] = [],
+  beneficiary?: PublicKey,
+  referrer?: PublicKey
+): EncryptedDeposit {
+  if (allowedDestinations.length > MAX_ALLOWED_DESTINATIONS) {
+    throw new Error('too many allowed destinations');
+  }
+  const { publicKey, cipher } = clientCipher(mxePublicKey);
+  const plaintext = [passwordHash(password)];
+  for (let i = 0; i < MAX_ALLOWED_DESTINATIONS; i++) {
+    plaintext.push(allowedDestinations[i] ? pubkeyFingerprint(allowedDestinations[i]) : BigInt(0));
+  }
+  plaintext.push(beneficiary ? pubkeyFingerprint(beneficiary) : BigInt(0));
+
+  const nonce = randomBytes(16);
+  const ciphertext = cipher.encrypt(plaintext, nonce);
+  const referrerNonce = randomBytes(16);
+  const referrerPlaintext = [referrer ? pubkeyFingerprint(referrer) : BigInt(0)];
+  return {
+    passwordHash: toArgs(ciphertext[0]),
+    allowedDestinations: ciphertext.slice(1, 1 + MAX_ALLOWED_DESTINATIONS).map(toArgs),
+    beneficiary: toArgs(ciphertext[1 + MAX_ALLOWED_DESTINATIONS]),
+    userPubkey: toArgs(publicKey),
+    nonce: new BN(deserializeLE(nonce).toString()),
+    referrer: toArgs(cipher.encrypt(referrerPlaintext, referrerNonce)[0]),
+    referrerNonce: new BN(deserializeLE(referrerNonce).toString()),
+  };
+}
+
+export interface EncryptedPassword {
+  passwordHash: number[];
+  userPubkey: number[];
+  nonce: BN;
+}
+
+export function encryptPassword(mxePublicKey: Uint8Array, password: string): EncryptedPassword {
+  const { publicKey, cipher } = clientCipher(mxePublicKey);
+  const nonce = randomBytes(16);
+  return {
+    passwordHash: toArgs(cipher.encrypt([passwordHash(password)], nonce)[0]),
+    userPubkey: toArgs(publicKey),
+    nonce: new BN(deserializeLE(nonce).toString()),
+  };
+}
+
+// ============ Instructions ============
+
+/** A pool as the instruction builders need it */
+export interface PoolRef {
+  address: PublicKey;
+  depositMint: PublicKey;
+  /** SPL Token or Token-2022, whichever owns `depositMint` */
+  tokenProgram: PublicKey;
+}
+
+export async function initializePoolIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  authority: PublicKey,
+  depositMint: PublicKey,
+  tokenProgram: PublicKey,
+  poolId: BN,
+  investmentThreshold: BN,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  const ghostPool = ghostPoolAddress(program.programId, authority, poolId);
+  return program.methods
+    .initializePool(computationOffset, new BN(deserializeLE(randomBytes(16)).toString()), investmentThreshold, poolId)
+    .accountsPartial({
+      authority,
+      ghostPool,
+      depositMint,
+      vault: vaultAddress(program.programId, ghostPool),
+      tokenProgram,
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'init_pool_state'),
+    })
+    .instruction();
+}
+
+export interface DepositParams {
+  amount: BN;
+  /** Seconds before nothing can be withdrawn (0 = unlocked) */
+  lockSecs?: BN;
+  /** Mint a DepositReceipt at this index, committing to `amount` under `amountBlinding` */
+  receiptIndex?: number;
+  amountBlinding?: number[];
+  cuPriceMicro?: BN;
+  /** The user's share-mint token account, required once the pool issues shares */
+  shareTokenAccount?: PublicKey;
+}
+
+/** deposit from `userTokenAccount`; a rejected deposit is refunded to `refundTokenAccount` */
+export async function depositIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  user: PublicKey,
+  userTokenAccount: PublicKey,
+  refundTokenAccount: PublicKey,
+  encrypted: EncryptedDeposit,
+  params: DepositParams,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  const receiptIndex = params.receiptIndex ?? null;
+  return program.methods
+    .deposit(
+      computationOffset,
+      params.amount,
+      encrypted.passwordHash,
+      encrypted.userPubkey,
+      encrypted.nonce,
+      encrypted.allowedDestinations,
+      encrypted.beneficiary,
+      encrypted.referrer,
+      encrypted.referrerNonce,
+      params.lockSecs ?? new BN(0),
+      receiptIndex ?? 0,
+      params.amountBlinding ?? Array.from(randomBytes(32)),
+      params.cuPriceMicro ?? null
+    )
+    .accountsPartial({
+      user,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      userUsdcToken: userTokenAccount,
+      vaultUsdcToken: vaultAddress(program.programId, pool.address),
+      depositMint: pool.depositMint,
+      refundTokenAccount,
+      depositReceipt:
+        receiptIndex === null ? null : depositReceiptAddress(program.programId, pool.address, user, receiptIndex),
+      failedDeposit: failedDepositAddress(program.programId, pool.address, computationOffset),
+      depositEscrow: depositEscrowAddress(program.programId, pool.address),
+      shareTokenAccount: params.shareTokenAccount ?? null,
+      tokenProgram: pool.tokenProgram,
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'process_deposit'),
+    })
+    .instruction();
+}
+
+/**
+ * deposit_delegated: `relayer` deposits from `ownerTokenAccount` as its SPL token delegate and
+ * pays for the transaction; `encrypted` comes from the account's owner, who owns the entry and
+ * must also own `refundTokenAccount` (and the share account, if any)
+ */
+export async function depositDelegatedIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  relayer: PublicKey,
+  ownerTokenAccount: PublicKey,
+  refundTokenAccount: PublicKey,
+  encrypted: EncryptedDeposit,
+  params: DepositParams,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  const receiptIndex = params.receiptIndex ?? null;
+  return program.methods
+    .depositDelegated(
+      computationOffset,
+      params.amount,
+      encrypted.passwordHash,
+      encrypted.userPubkey,
+      encrypted.nonce,
+      encrypted.allowedDestinations,
+      encrypted.beneficiary,
+      encrypted.referrer,
+      encrypted.referrerNonce,
+      params.lockSecs ?? new BN(0),
+      receiptIndex ?? 0,
+      params.amountBlinding ?? Array.from(randomBytes(32)),
+      params.cuPriceMicro ?? null
+    )
+    .accountsPartial({
+      user: relayer,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      userUsdcToken: ownerTokenAccount,
+      vaultUsdcToken: vaultAddress(program.programId, pool.address),
+      depositMint: pool.depositMint,
+      refundTokenAccount,
+      depositReceipt:
+        receiptIndex === null ? null : depositReceiptAddress(program.programId, pool.address, relayer, receiptIndex),
+      failedDeposit: failedDepositAddress(program.programId, pool.address, computationOffset),
+      depositEscrow: depositEscrowAddress(program.programId, pool.address),
+      shareTokenAccount: params.shareTokenAccount ?? null,
+      tokenProgram: pool.tokenProgram,
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'process_deposit'),
+    })
+    .instruction();
+}
+
+/** claim_referral_rewards: pay `referrer` what the deposits naming them have earned */
+export async function claimReferralRewardsIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  referrer: PublicKey,
+  referrerTokenAccount: PublicKey,
+  cuPriceMicro: BN | null = null,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  return program.methods
+    .claimReferralRewards(computationOffset, cuPriceMicro)
+    .accountsPartial({
+      referrer,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      vault: vaultAddress(program.programId, pool.address),
+      depositMint: pool.depositMint,
+      referrerTokenAccount,
+      tokenProgram: pool.tokenProgram,
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'claim_referral_rewards'),
+    })
+    .instruction();
+}
+
+/** publish_tvl: reveal the pool's noise-padded, bucketed TVL in a TvlPublishedEvent */
+export async function publishTvlIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  payer: PublicKey,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  return program.methods
+    .publishTvl(computationOffset)
+    .accountsPartial({
+      payer,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'publish_tvl'),
+    })
+    .instruction();
+}
+
+/** publish_stats: reveal the pool's bucketed aggregate stats in a PoolStatsEvent */
+export async function publishStatsIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  payer: PublicKey,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  return program.methods
+    .publishStats(computationOffset)
+    .accountsPartial({
+      payer,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'compute_pool_stats'),
+    })
+    .instruction();
+}
+
+/** withdraw `amount` to `userTokenAccount`, authorized by the encrypted password */
+export async function withdrawIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  user: PublicKey,
+  userTokenAccount: PublicKey,
+  amount: BN,
+  encrypted: EncryptedPassword,
+  cuPriceMicro: BN | null = null,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  return program.methods
+    .withdraw(computationOffset, amount, encrypted.passwordHash, encrypted.userPubkey, encrypted.nonce, cuPriceMicro)
+    .accountsPartial({
+      user,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      userTokenAccount,
+      recipientTokenAccount: null,
+      withdrawalReceipt: withdrawalReceiptAddress(program.programId, pool.address, computationOffset),
+      pendingWithdrawal: pendingWithdrawalAddress(program.programId, pool.address, user),
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'authorize_withdrawal'),
+    })
+    .instruction();
+}
+
+/** withdraw_relayed: `withdraw` with `relayer` paying; `user` co-signs without needing SOL */
+export async function withdrawRelayedIx(
+  program: Program<GhostPool>,
+  clusterOffset: number,
+  pool: PoolRef,
+  relayer: PublicKey,
+  user: PublicKey,
+  userTokenAccount: PublicKey,
+  amount: BN,
+  encrypted: EncryptedPassword,
+  cuPriceMicro: BN | null = null,
+  computationOffset: BN = randomComputationOffset()
+): Promise<TransactionInstruction> {
+  return program.methods
+    .withdrawRelayed(
+      computationOffset,
+      amount,
+      encrypted.passwordHash,
+      encrypted.userPubkey,
+      encrypted.nonce,
+      cuPriceMicro
+    )
+    .accountsPartial({
+      relayer,
+      user,
+      ghostPool: pool.address,
+      computationLog: computationLogAddress(program.programId, pool.address),
+      userTokenAccount,
+      withdrawalReceipt: withdrawalReceiptAddress(program.programId, pool.address, computationOffset),
+      pendingWithdrawal: pendingWithdrawalAddress(program.programId, pool.address, user),
+      ...arciumAccounts(program.programId, clusterOffset, computationOffset, 'authorize_withdrawal'),
+    })
+    .instruction();
+}
@@ -0,0 +1,17 @@
+// Rescue encryption for Rust clients (ghost-pool-cli): reads
+//   { "sharedSecret": hex, "nonce": hex, "plaintext": [decimal, ...] }
+// on stdin and prints the ciphertexts as a JSON array of hex strings.
+
+import { RescueCipher } from '@arcium-hq/client';
+
+let input = '';
+process.stdin.on('data', (chunk) => (input += chunk));
+process.stdin.on('end', () => {
+  const { sharedSecret, nonce, plaintext } = JSON.parse(input);
+  const cipher = new RescueCipher(Uint8Array.from(Buffer.from(sharedSecret, 'hex')));
+  const ciphertext = cipher.encrypt(
+    (plaintext as string[]).map((x) => BigInt(x)),
+    Uint8Array.from(Buffer.from(nonce, 'hex'))
+  );
+  console.log(JSON.stringify(ciphertext.map((c) => Buffer.from(c).toString('hex'))));
+});
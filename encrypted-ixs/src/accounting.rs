@@ -0,0 +1,75 @@
+//! Ledger arithmetic shared by the circuits and the yield accounting tests
+//!
+//! Plain integer functions: the circuits call them on the MXE's values, the tests in
+//! `tests/yield_accounting.rs` on their own model of the ledger, so both run the same code.
+
+/// Narrow a u128 intermediate to u64, saturating at u64::MAX where `as u64` would wrap
+pub fn saturating_u64(value: u128) -> u64 {
+    if value > u64::MAX as u128 {
+        u64::MAX
+    } else {
+        value as u64
+    }
+}
+
+/// Yield `principal` earned while an index moved `index_delta`: principal × delta / yield_scale
+pub fn yield_earned(principal: u64, index_delta: u64, yield_scale: u64) -> u64 {
+    saturating_u64((principal as u128 * index_delta as u128) / yield_scale as u128)
+}
+
+/// `index` after sharing `amount` over `total` (non-zero): + amount × yield_scale / total
+/// An index that would pass u64::MAX stays there, so the excess goes unallocated in
+/// total_deposited instead of wrapping round and wiping out every entry's accrued yield
+pub fn index_after(index: u64, amount: u64, total: u64, yield_scale: u64) -> u64 {
+    let step = (amount as u128 * yield_scale as u128) / total as u128;
+    saturating_u64(index as u128 + step)
+}
+
+/// Share `yield_amount` over the ledger: the new (yield_per_share, total_deposited)
+/// An empty ledger has nobody to credit, so nothing moves
+pub fn share_yield(yield_per_share: u64, total_deposited: u64, yield_amount: u64, yield_scale: u64) -> (u64, u64) {
+    if total_deposited > 0 {
+        (
+            index_after(yield_per_share, yield_amount, total_deposited, yield_scale),
+            total_deposited + yield_amount,
+        )
+    } else {
+        (yield_per_share, total_deposited)
+    }
+}
+
+/// Share a withdrawal's pool-bound penalty over what the debit left: the new
+/// (yield_per_share, total_deposited)
+/// Only while a depositor is left to take it. A withdrawal that empties the ledger can leave
+/// rounding dust in total_deposited, and sharing over that would overflow the index; nor does
+/// the penalty join total_deposited then, where it would dilute the yield of whoever deposits
+/// next. It stays in the vault unallocated
+pub fn share_penalty(
+    yield_per_share: u64,
+    total_deposited: u64,
+    deposit_count: u16,
+    pool_penalty: u64,
+    yield_scale: u64,
+) -> (u64, u64) {
+    if total_deposited > 0 && deposit_count > 0 {
+        (
+            index_after(yield_per_share, pool_penalty, total_deposited, yield_scale),
+            total_deposited + pool_penalty,
+        )
+    } else {
+        (yield_per_share, total_deposited)
+    }
+}
+
+/// Early-exit penalty on withdrawing `amount` from a deposit `age` seconds old
+/// Decays linearly from penalty_bps at deposit time to 0 at the end of the window
+pub fn early_exit_penalty(amount: u64, penalty_bps: u16, penalty_window_secs: u64, age: u64) -> u64 {
+    if age < penalty_window_secs {
+        saturating_u64(
+            (amount as u128 * penalty_bps as u128 * (penalty_window_secs - age) as u128)
+                / (penalty_window_secs as u128 * 10_000),
+        )
+    } else {
+        0
+    }
+}
@@ -1,43 +1,170 @@
 use arcis::*;
 
+pub mod accounting;
+
 #[encrypted]
 mod circuits {
     use arcis::*;
+    use crate::accounting::{early_exit_penalty, index_after, share_penalty, share_yield, yield_earned};
 
     /// Maximum number of concurrent depositors
-    /// Reduced to 2 to fit MPC callback size limit (~500 bytes)
-    /// 2 deposits × 4 FE + 5 globals = 13 FE = 416 bytes
+    /// 2 deposits × 15 FE + 10 globals = 40 FE = 1280 bytes (two callback txs)
     pub const MAX_DEPOSITS: usize = 2;
 
+    /// Deposits held by each DepositShard account
+    /// 2 deposits × 15 FE = 30 FE = 960 bytes
+    pub const SHARD_SIZE: usize = 2;
+
+    /// Destination owners a depositor may pre-approve for withdrawals
+    pub const MAX_ALLOWED_DESTINATIONS: usize = 2;
+
+    /// Yield venues an investment is split across (0 = Kamino, 1 = the pool's yield adapter)
+    pub const STRATEGY_VENUES: usize = 2;
+
+    /// Queued deposit intents folded by one process_deposit_batch computation
+    pub const DEPOSIT_BATCH_SIZE: usize = 2;
+
     /// Individual deposit entry in the private ledger
+    /// A position is spent by whoever knows the password commitment AND signs as `owner`
     #[derive(Copy, Clone)]
     pub struct DepositEntry {
-        pub password_hash: u128,        // Hash of user's secret password
-        pub principal: u64,              // Original deposit amount (6 decimals)
-        pub last_yield_checkpoint: u64,  // Yield index when last updated (scaled by 1e9)
+        pub password_commitment: u128,  // Client-side hash of (password, owner pubkey, random salt)
+        pub owner: u128,                 // Fingerprint of the depositing Solana signer
+        pub principal: u64,              // Deposit amount in base units of the pool's mint
+        pub last_yield_checkpoint: u64,  // Yield index when last updated (scaled by yield_scale)
         pub is_active: bool,             // Whether this slot is occupied
+        pub allowed_destinations: [u128; MAX_ALLOWED_DESTINATIONS], // Owner fingerprints, all zero = any owner
+        pub deposited_at: u64,           // Unix time of the deposit, starts the early-exit penalty window
+        pub last_withdrawal_slot: u64,   // Slot of the last debit (withdrawal or transfer out)
+        pub withdrawn_this_epoch: u64,   // Debited since the start of last_withdrawal_slot's epoch
+        pub beneficiary: u128,           // Fingerprint of the recovery signer, 0 = no recovery
+        pub last_active_slot: u64,       // Last owner action (deposit, debit, rotation), starts the recovery wait
+        pub lock_until: u64,             // Unix time before which nothing can be withdrawn or transferred out
+        pub referrer: u128,              // Fingerprint of the referring signer, 0 = not referred
+        pub referral_checkpoint: u64,    // fee_per_share when the referrer was last credited for this entry
+    }
+
+    /// Secret inputs supplied by the depositor (encrypted together under one nonce)
+    #[derive(Copy, Clone)]
+    pub struct DepositSecret {
+        pub password_commitment: u128,
+        pub allowed_destinations: [u128; MAX_ALLOWED_DESTINATIONS],
+        pub beneficiary: u128,
+    }
+
+    /// Old and new password commitments for rotate_password (encrypted together under one nonce)
+    #[derive(Copy, Clone)]
+    pub struct PasswordRotation {
+        pub old_commitment: u128,
+        pub new_commitment: u128,
+    }
+
+    /// Target investment weights per venue in bps (set_strategy_weights, encrypted by the authority)
+    #[derive(Copy, Clone)]
+    pub struct StrategyWeights {
+        pub weights: [u16; STRATEGY_VENUES],
     }
 
     /// Private pool state (MXE-only, never revealed)
-    /// Size: 2 deposits × 4 FE + 5 globals = 13 FE = 416 bytes
+    /// Size: 2 deposits × 15 FE + 10 globals = 40 FE = 1280 bytes
     #[derive(Copy, Clone)]
     pub struct PoolState {
         pub deposits: [DepositEntry; MAX_DEPOSITS],
         pub total_deposited: u64,
         pub total_invested: u64,
         pub pending_deposits: u64,
-        pub yield_per_share: u64,        // Cumulative yield per deposited token (scaled by 1e9)
-        pub deposit_count: u8,
+        pub yield_per_share: u64,        // Cumulative yield per deposited base unit (scaled by yield_scale)
+        pub deposit_count: u16,          // Includes deposits held in shards
+        pub strategy_weights: [u16; STRATEGY_VENUES], // Share of each investment per venue, bps summing to 10_000
+        pub fee_per_share: u64,          // Cumulative fees per deposited base unit (scaled by yield_scale)
+        pub deposits_made: u64,          // Lifetime count of new deposits, shards included (top-ups excluded)
+        pub withdrawals_made: u64,       // Lifetime count of withdrawal debits, shards included
+    }
+
+    /// Private shard state (MXE-only), extends the pool ledger beyond MAX_DEPOSITS
+    /// Pool-wide totals and yield index stay in PoolState
+    #[derive(Copy, Clone)]
+    pub struct ShardState {
+        pub deposits: [DepositEntry; SHARD_SIZE],
+    }
+
+    /// DepositEntry before referrals (13 FE), only read by the upgrade_* circuits
+    #[derive(Copy, Clone)]
+    pub struct LegacyDepositEntry {
+        pub password_commitment: u128,
+        pub owner: u128,
+        pub principal: u64,
+        pub last_yield_checkpoint: u64,
+        pub is_active: bool,
+        pub allowed_destinations: [u128; MAX_ALLOWED_DESTINATIONS],
+        pub deposited_at: u64,
+        pub last_withdrawal_slot: u64,
+        pub withdrawn_this_epoch: u64,
+        pub beneficiary: u128,
+        pub last_active_slot: u64,
+        pub lock_until: u64,
+    }
+
+    /// PoolState before referrals (33 FE), re-laid out by upgrade_pool_state
+    #[derive(Copy, Clone)]
+    pub struct LegacyPoolState {
+        pub deposits: [LegacyDepositEntry; MAX_DEPOSITS],
+        pub total_deposited: u64,
+        pub total_invested: u64,
+        pub pending_deposits: u64,
+        pub yield_per_share: u64,
+        pub deposit_count: u16,
+        pub strategy_weights: [u16; STRATEGY_VENUES],
+    }
+
+    /// ShardState before referrals (26 FE), re-laid out by upgrade_shard_state
+    #[derive(Copy, Clone)]
+    pub struct LegacyShardState {
+        pub deposits: [LegacyDepositEntry; SHARD_SIZE],
     }
 
     // Note: DepositRequest and WithdrawalRequest are not needed as structs
-    // because password_hash is encrypted via Enc<Shared, u128> and amount is plaintext
+    // because password_commitment is encrypted via Enc<Shared, u128> and amount is plaintext
 
     /// Investment decision (revealed to trigger on-chain action)
     #[derive(Copy, Clone)]
     pub struct InvestmentDecision {
         pub should_invest: bool,
         pub amount_to_invest: u64,
+        pub venue_amounts: [u64; STRATEGY_VENUES], // amount_to_invest split by strategy_weights
+    }
+
+    /// Aggregate pool statistics (revealed by publish_stats, each rounded to a bucket)
+    /// Median and unclaimed yield cover the ledger slots held in the pool, not its shards
+    #[derive(Copy, Clone)]
+    pub struct PoolStats {
+        pub active_depositors: u16,      // deposit_count, shards included
+        pub median_principal: u64,       // Lower median principal of the active entries
+        pub unclaimed_yield: u64,        // Yield accrued by the active entries but not yet withdrawn
+    }
+
+    /// Lifetime deposit and withdrawal counts, rounded (revealed at counter epoch close)
+    #[derive(Copy, Clone)]
+    pub struct CounterTotals {
+        pub deposits: u64,
+        pub withdrawals: u64,
+    }
+
+    /// Deposit outcome (revealed so the callback can refund a rejected deposit)
+    #[derive(Copy, Clone)]
+    pub struct DepositResult {
+        pub accepted: bool,
+        pub merged: bool,                // Topped up the depositor's existing entry
+        pub slot_found: bool,            // False when the ledger was full
+        pub amount: u64,                 // Echoed back as the refund amount
+        pub over_cap: bool,              // Rejected for taking the position past the per-user cap
+    }
+
+    /// Per-intent outcome of process_deposit_batch (revealed so the callback can refund rejects)
+    #[derive(Copy, Clone)]
+    pub struct BatchDepositResult {
+        pub accepted: [bool; DEPOSIT_BATCH_SIZE],
+        pub merged: [bool; DEPOSIT_BATCH_SIZE],   // Topped up the depositor's existing entry
     }
 
     /// Withdrawal authorization (revealed to trigger transfer)
@@ -46,6 +173,10 @@ mod circuits {
         pub authorized: bool,
         pub amount: u64,
         pub found_idx: u8,
+        pub destination_allowed: bool,   // False only when the password matched but the destination didn't
+        pub penalty: u64,                // Early-exit penalty kept back from the requested amount
+        pub matured: bool,               // False only when the password matched but the deposit is still locked
+        pub destination: u128,           // Destination owner fingerprint the allowlist was checked against
     }
 
     /// Initialize empty pool state
@@ -53,10 +184,20 @@ mod circuits {
     #[instruction]
     pub fn init_pool_state(mxe: Mxe) -> EncData<PoolState> {
         let empty_entry = DepositEntry {
-            password_hash: 0u128,
+            password_commitment: 0u128,
+            owner: 0u128,
             principal: 0,
             last_yield_checkpoint: 0,
             is_active: false,
+            allowed_destinations: [0u128; MAX_ALLOWED_DESTINATIONS],
+            deposited_at: 0,
+            last_withdrawal_slot: 0,
+            withdrawn_this_epoch: 0,
+            beneficiary: 0u128,
+            last_active_slot: 0,
+            lock_until: 0,
+            referrer: 0u128,
+            referral_checkpoint: 0,
         };
 
         let initial_state = PoolState {
@@ -66,64 +207,723 @@ mod circuits {
             pending_deposits: 0,
             yield_per_share: 0,
             deposit_count: 0,
+            strategy_weights: [10_000, 0], // Everything to Kamino until weights are set
+            fee_per_share: 0,
+            deposits_made: 0,
+            withdrawals_made: 0,
         };
 
         mxe.from_arcis(initial_state).data
     }
 
+    /// Initialize an empty deposit shard
+    #[instruction]
+    pub fn init_shard_state(mxe: Mxe) -> EncData<ShardState> {
+        let empty_entry = DepositEntry {
+            password_commitment: 0u128,
+            owner: 0u128,
+            principal: 0,
+            last_yield_checkpoint: 0,
+            is_active: false,
+            allowed_destinations: [0u128; MAX_ALLOWED_DESTINATIONS],
+            deposited_at: 0,
+            last_withdrawal_slot: 0,
+            withdrawn_this_epoch: 0,
+            beneficiary: 0u128,
+            last_active_slot: 0,
+            lock_until: 0,
+            referrer: 0u128,
+            referral_checkpoint: 0,
+        };
+
+        let initial_shard = ShardState {
+            deposits: [empty_entry; SHARD_SIZE],
+        };
+
+        mxe.from_arcis(initial_shard).data
+    }
+
     /// Process a user deposit
-    /// Password hash is encrypted, amount is plaintext (visible in token transfer anyway)
-    /// Returns EncData to minimize callback size
+    /// Password commitment is encrypted, amount is plaintext (visible in token transfer anyway)
+    /// An active entry with the same commitment is topped up if it belongs to the same owner
+    /// (its allowlist, beneficiary and referrer are kept, its lock only ever extends, its
+    /// referral accrual restarts) and rejects the deposit otherwise, as does a full ledger.
+    /// `lock_secs` locks the deposit until now + lock_secs. `referrer_ctxt` is the fingerprint
+    /// of the signer credited for referring a new entry, 0 for none (as is the owner themselves).
+    /// `user_cap` bounds what `owner` holds once the deposit lands, summed over all its entries
+    /// with settled yield, so neither top-ups nor fresh commitments add up past it; 0 = no cap.
+    /// A rejected deposit leaves the state untouched and is refunded by the callback
     #[instruction]
     pub fn process_deposit(
-        password_hash_ctxt: Enc<Shared, u128>,
+        secret_ctxt: Enc<Shared, DepositSecret>,
+        referrer_ctxt: Enc<Shared, u128>,
         amount: u64,
+        now: u64,
+        slot: u64,
+        lock_secs: u64,
+        owner: u128,
+        yield_scale: u64,
+        user_cap: u64,
         state_ctxt: Enc<Mxe, PoolState>,
-    ) -> EncData<PoolState> {
-        let password_hash = password_hash_ctxt.to_arcis();
+    ) -> (Enc<Mxe, PoolState>, DepositResult) {
+        let secret = secret_ctxt.to_arcis();
+        let referrer = referrer_ctxt.to_arcis();
         let mut state = state_ctxt.to_arcis();
 
-        // Find first inactive slot
+        // Find first inactive slot, and any active entry under the same commitment
         let mut found_slot = false;
         let mut slot_idx = 0u8;
+        let mut duplicate = false;
+        let mut duplicate_idx = 0u8;
+        let mut duplicate_is_own = false;
 
         for i in 0..MAX_DEPOSITS {
             if !state.deposits[i].is_active && !found_slot {
                 found_slot = true;
                 slot_idx = i as u8;
             }
+            if state.deposits[i].is_active
+                && state.deposits[i].password_commitment == secret.password_commitment
+                && !duplicate
+            {
+                duplicate = true;
+                duplicate_idx = i as u8;
+                duplicate_is_own = state.deposits[i].owner == owner;
+            }
+        }
+
+        // What the depositor holds after this deposit, across every entry bound to `owner`,
+        // checked against the per-user cap
+        let mut balance = amount;
+        for i in 0..MAX_DEPOSITS {
+            if state.deposits[i].is_active && state.deposits[i].owner == owner {
+                let principal = state.deposits[i].principal;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                balance += yield_earned(principal, yield_delta, yield_scale) + principal;
+            }
+        }
+        let over_cap = user_cap > 0 && balance > user_cap;
+
+        let merged = duplicate && duplicate_is_own && !over_cap;
+        let accepted = merged || (!duplicate && found_slot && !over_cap);
+
+        // Top up: settle the entry's yield first, and restart its penalty window
+        for i in 0..MAX_DEPOSITS {
+            if merged && i == duplicate_idx as usize {
+                let principal = state.deposits[i].principal;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                state.deposits[i].principal = principal + accrued_yield + amount;
+                state.deposits[i].last_yield_checkpoint = state.yield_per_share;
+                state.deposits[i].deposited_at = now;
+                state.deposits[i].last_active_slot = slot;
+                state.deposits[i].referral_checkpoint = state.fee_per_share;
+                if now + lock_secs > state.deposits[i].lock_until {
+                    state.deposits[i].lock_until = now + lock_secs;
+                }
+            }
         }
 
         // Add deposit if slot found
-        if found_slot {
+        if accepted && !merged {
             let idx = slot_idx as usize;
             state.deposits[idx] = DepositEntry {
-                password_hash,
+                password_commitment: secret.password_commitment,
+                owner,
                 principal: amount,
                 last_yield_checkpoint: state.yield_per_share,
                 is_active: true,
+                allowed_destinations: secret.allowed_destinations,
+                deposited_at: now,
+                last_withdrawal_slot: 0,
+                withdrawn_this_epoch: 0,
+                beneficiary: secret.beneficiary,
+                last_active_slot: slot,
+                lock_until: now + lock_secs,
+                // Referring oneself earns nothing
+                referrer: if referrer == owner { 0u128 } else { referrer },
+                referral_checkpoint: state.fee_per_share,
+            };
+            state.deposit_count += 1;
+            state.deposits_made += 1;
+        }
+        if accepted {
+            state.total_deposited += amount;
+            state.pending_deposits += amount;
+        }
+
+        (
+            state_ctxt.owner.from_arcis(state),
+            DepositResult { accepted, merged, slot_found: found_slot, amount, over_cap }.reveal(),
+        )
+    }
+
+    /// Fold up to DEPOSIT_BATCH_SIZE queued deposit intents into the state in one computation
+    /// Each intent follows the process_deposit rules in queue order, so a later intent sees
+    /// the slots and commitments taken by earlier ones. Intents at or past `count` are padding
+    /// and leave the state untouched. All intents are credited at the batch's `now`/`slot`
+    /// and held to the same per-user cap; a new entry takes its intent's referrer
+    #[instruction]
+    pub fn process_deposit_batch(
+        secret_0: Enc<Shared, DepositSecret>,
+        referrer_0: Enc<Shared, u128>,
+        amount_0: u64,
+        lock_secs_0: u64,
+        owner_0: u128,
+        secret_1: Enc<Shared, DepositSecret>,
+        referrer_1: Enc<Shared, u128>,
+        amount_1: u64,
+        lock_secs_1: u64,
+        owner_1: u128,
+        count: u8,
+        now: u64,
+        slot: u64,
+        yield_scale: u64,
+        user_cap: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> (Enc<Mxe, PoolState>, BatchDepositResult) {
+        let secrets = [secret_0.to_arcis(), secret_1.to_arcis()];
+        let referrers = [referrer_0.to_arcis(), referrer_1.to_arcis()];
+        let amounts = [amount_0, amount_1];
+        let lock_secs = [lock_secs_0, lock_secs_1];
+        let owners = [owner_0, owner_1];
+        let mut state = state_ctxt.to_arcis();
+        let mut result = BatchDepositResult {
+            accepted: [false; DEPOSIT_BATCH_SIZE],
+            merged: [false; DEPOSIT_BATCH_SIZE],
+        };
+
+        for b in 0..DEPOSIT_BATCH_SIZE {
+            let valid = (b as u8) < count;
+            let secret = secrets[b];
+            let amount = amounts[b];
+            let owner = owners[b];
+            let referrer = referrers[b];
+
+            let mut found_slot = false;
+            let mut slot_idx = 0u8;
+            let mut duplicate = false;
+            let mut duplicate_idx = 0u8;
+            let mut duplicate_is_own = false;
+
+            for i in 0..MAX_DEPOSITS {
+                if !state.deposits[i].is_active && !found_slot {
+                    found_slot = true;
+                    slot_idx = i as u8;
+                }
+                if state.deposits[i].is_active
+                    && state.deposits[i].password_commitment == secret.password_commitment
+                    && !duplicate
+                {
+                    duplicate = true;
+                    duplicate_idx = i as u8;
+                    duplicate_is_own = state.deposits[i].owner == owner;
+                }
+            }
+
+            let mut balance = amount;
+            for i in 0..MAX_DEPOSITS {
+                if state.deposits[i].is_active && state.deposits[i].owner == owner {
+                    let principal = state.deposits[i].principal;
+                    let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                    balance += yield_earned(principal, yield_delta, yield_scale) + principal;
+                }
+            }
+            let within_cap = user_cap == 0 || balance <= user_cap;
+
+            let merged = valid && duplicate && duplicate_is_own && within_cap;
+            let accepted = merged || (valid && !duplicate && found_slot && within_cap);
+
+            for i in 0..MAX_DEPOSITS {
+                if merged && i == duplicate_idx as usize {
+                    let principal = state.deposits[i].principal;
+                    let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                    let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                    state.deposits[i].principal = principal + accrued_yield + amount;
+                    state.deposits[i].last_yield_checkpoint = state.yield_per_share;
+                    state.deposits[i].deposited_at = now;
+                    state.deposits[i].last_active_slot = slot;
+                    state.deposits[i].referral_checkpoint = state.fee_per_share;
+                    if now + lock_secs[b] > state.deposits[i].lock_until {
+                        state.deposits[i].lock_until = now + lock_secs[b];
+                    }
+                }
+            }
+
+            if accepted && !merged {
+                let idx = slot_idx as usize;
+                state.deposits[idx] = DepositEntry {
+                    password_commitment: secret.password_commitment,
+                    owner,
+                    principal: amount,
+                    last_yield_checkpoint: state.yield_per_share,
+                    is_active: true,
+                    allowed_destinations: secret.allowed_destinations,
+                    deposited_at: now,
+                    last_withdrawal_slot: 0,
+                    withdrawn_this_epoch: 0,
+                    beneficiary: secret.beneficiary,
+                    last_active_slot: slot,
+                    lock_until: now + lock_secs[b],
+                    // Referring oneself earns nothing
+                    referrer: if referrer == owner { 0u128 } else { referrer },
+                    referral_checkpoint: state.fee_per_share,
+                };
+                state.deposit_count += 1;
+                state.deposits_made += 1;
+            }
+            if accepted {
+                state.total_deposited += amount;
+                state.pending_deposits += amount;
+            }
+
+            result.accepted[b] = accepted;
+            result.merged[b] = merged;
+        }
+
+        (
+            state_ctxt.owner.from_arcis(state),
+            result.reveal(),
+        )
+    }
+
+    /// Top up an existing position instead of taking a new slot
+    /// The entry must match the password commitment and be bound to `owner`; its accrued
+    /// yield is settled into principal before `amount` is added, and its penalty window and
+    /// referral accrual restart. A top-up never needs a free slot, so the result is always `slot_found`.
+    /// The top-up is rejected if it would take the owner's entries past `user_cap` (0 = no cap)
+    #[instruction]
+    pub fn top_up_deposit(
+        password_hash_ctxt: Enc<Shared, u128>,
+        amount: u64,
+        now: u64,
+        slot: u64,
+        owner: u128,
+        yield_scale: u64,
+        user_cap: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> (Enc<Mxe, PoolState>, DepositResult) {
+        let password_hash = password_hash_ctxt.to_arcis();
+        let mut state = state_ctxt.to_arcis();
+
+        let mut found = false;
+        let mut found_idx = 0u8;
+
+        for i in 0..MAX_DEPOSITS {
+            let matches = state.deposits[i].is_active &&
+                         state.deposits[i].password_commitment == password_hash &&
+                         state.deposits[i].owner == owner;
+
+            if matches && !found {
+                found = true;
+                found_idx = i as u8;
+            }
+        }
+
+        // Held to the per-user cap across every entry bound to `owner`
+        let mut balance = amount;
+        for i in 0..MAX_DEPOSITS {
+            if state.deposits[i].is_active && state.deposits[i].owner == owner {
+                let principal = state.deposits[i].principal;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                balance += yield_earned(principal, yield_delta, yield_scale) + principal;
+            }
+        }
+        let over_cap = found && user_cap > 0 && balance > user_cap;
+        let accepted = found && !over_cap;
+
+        for i in 0..MAX_DEPOSITS {
+            if accepted && i == found_idx as usize {
+                let principal = state.deposits[i].principal;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                state.deposits[i].principal = principal + accrued_yield + amount;
+                state.deposits[i].last_yield_checkpoint = state.yield_per_share;
+                state.deposits[i].deposited_at = now;
+                state.deposits[i].last_active_slot = slot;
+                state.deposits[i].referral_checkpoint = state.fee_per_share;
+            }
+        }
+
+        if accepted {
+            state.total_deposited += amount;
+            state.pending_deposits += amount;
+        }
+
+        (
+            state_ctxt.owner.from_arcis(state),
+            DepositResult { accepted, merged: accepted, slot_found: true, amount, over_cap }.reveal(),
+        )
+    }
+
+    /// Process a user deposit into a shard
+    /// Entry goes into the shard, pool-wide totals are updated in PoolState
+    /// Returns Enc (with nonces) since each output is re-encrypted under its own nonce
+    #[instruction]
+    pub fn process_deposit_shard(
+        secret_ctxt: Enc<Shared, DepositSecret>,
+        amount: u64,
+        now: u64,
+        slot: u64,
+        lock_secs: u64,
+        owner: u128,
+        state_ctxt: Enc<Mxe, PoolState>,
+        shard_ctxt: Enc<Mxe, ShardState>,
+    ) -> (Enc<Mxe, PoolState>, Enc<Mxe, ShardState>) {
+        let secret = secret_ctxt.to_arcis();
+        let mut state = state_ctxt.to_arcis();
+        let mut shard = shard_ctxt.to_arcis();
+
+        // Find first inactive slot in the shard
+        let mut found_slot = false;
+        let mut slot_idx = 0u8;
+
+        for i in 0..SHARD_SIZE {
+            if !shard.deposits[i].is_active && !found_slot {
+                found_slot = true;
+                slot_idx = i as u8;
+            }
+        }
+
+        if found_slot {
+            let idx = slot_idx as usize;
+            shard.deposits[idx] = DepositEntry {
+                password_commitment: secret.password_commitment,
+                owner,
+                principal: amount,
+                last_yield_checkpoint: state.yield_per_share,
+                is_active: true,
+                allowed_destinations: secret.allowed_destinations,
+                deposited_at: now,
+                last_withdrawal_slot: 0,
+                withdrawn_this_epoch: 0,
+                beneficiary: secret.beneficiary,
+                last_active_slot: slot,
+                lock_until: now + lock_secs,
+                referrer: 0u128,
+                referral_checkpoint: 0,
             };
             state.total_deposited += amount;
             state.pending_deposits += amount;
             state.deposit_count += 1;
+            state.deposits_made += 1;
+        }
+
+        (
+            state_ctxt.owner.from_arcis(state),
+            shard_ctxt.owner.from_arcis(shard),
+        )
+    }
+
+    /// Move one legacy PoolState slot into a shard (legacy pool migration)
+    /// The entry keeps its principal and yield checkpoint, so balances are unchanged
+    /// Reveals only whether the slot moved and whether the legacy slots are now empty
+    #[instruction]
+    pub fn migrate_legacy_slot(
+        state_ctxt: Enc<Mxe, PoolState>,
+        shard_ctxt: Enc<Mxe, ShardState>,
+        idx: u8,
+    ) -> (Enc<Mxe, PoolState>, Enc<Mxe, ShardState>, bool, bool) {
+        let mut state = state_ctxt.to_arcis();
+        let mut shard = shard_ctxt.to_arcis();
+
+        let empty_entry = DepositEntry {
+            password_commitment: 0u128,
+            owner: 0u128,
+            principal: 0,
+            last_yield_checkpoint: 0,
+            is_active: false,
+            allowed_destinations: [0u128; MAX_ALLOWED_DESTINATIONS],
+            deposited_at: 0,
+            last_withdrawal_slot: 0,
+            withdrawn_this_epoch: 0,
+            beneficiary: 0u128,
+            last_active_slot: 0,
+            lock_until: 0,
+            referrer: 0u128,
+            referral_checkpoint: 0,
+        };
+
+        // Find first inactive slot in the shard
+        let mut found_slot = false;
+        let mut slot_idx = 0u8;
+
+        for i in 0..SHARD_SIZE {
+            if !shard.deposits[i].is_active && !found_slot {
+                found_slot = true;
+                slot_idx = i as u8;
+            }
+        }
+
+        let mut moved = false;
+        for i in 0..MAX_DEPOSITS {
+            if i == idx as usize && state.deposits[i].is_active && found_slot {
+                for j in 0..SHARD_SIZE {
+                    if j == slot_idx as usize {
+                        shard.deposits[j] = state.deposits[i];
+                    }
+                }
+                state.deposits[i] = empty_entry;
+                moved = true;
+            }
+        }
+
+        let mut legacy_empty = true;
+        for i in 0..MAX_DEPOSITS {
+            if state.deposits[i].is_active {
+                legacy_empty = false;
+            }
+        }
+
+        (
+            state_ctxt.owner.from_arcis(state),
+            shard_ctxt.owner.from_arcis(shard),
+            moved.reveal(),
+            legacy_empty.reveal(),
+        )
+    }
+
+    /// Re-lay a PoolState written before referrals into the current layout
+    /// Every entry starts unreferred; the fee index starts at zero. The legacy state kept no
+    /// lifetime counts, so they start from the pool's public counters
+    #[instruction]
+    pub fn upgrade_pool_state(
+        state_ctxt: Enc<Mxe, LegacyPoolState>,
+        deposits_made: u64,
+        withdrawals_made: u64,
+    ) -> EncData<PoolState> {
+        let legacy = state_ctxt.to_arcis();
+
+        let mut deposits = [DepositEntry {
+            password_commitment: 0u128,
+            owner: 0u128,
+            principal: 0,
+            last_yield_checkpoint: 0,
+            is_active: false,
+            allowed_destinations: [0u128; MAX_ALLOWED_DESTINATIONS],
+            deposited_at: 0,
+            last_withdrawal_slot: 0,
+            withdrawn_this_epoch: 0,
+            beneficiary: 0u128,
+            last_active_slot: 0,
+            lock_until: 0,
+            referrer: 0u128,
+            referral_checkpoint: 0,
+        }; MAX_DEPOSITS];
+        for i in 0..MAX_DEPOSITS {
+            let entry = legacy.deposits[i];
+            deposits[i].password_commitment = entry.password_commitment;
+            deposits[i].owner = entry.owner;
+            deposits[i].principal = entry.principal;
+            deposits[i].last_yield_checkpoint = entry.last_yield_checkpoint;
+            deposits[i].is_active = entry.is_active;
+            deposits[i].allowed_destinations = entry.allowed_destinations;
+            deposits[i].deposited_at = entry.deposited_at;
+            deposits[i].last_withdrawal_slot = entry.last_withdrawal_slot;
+            deposits[i].withdrawn_this_epoch = entry.withdrawn_this_epoch;
+            deposits[i].beneficiary = entry.beneficiary;
+            deposits[i].last_active_slot = entry.last_active_slot;
+            deposits[i].lock_until = entry.lock_until;
         }
 
+        let state = PoolState {
+            deposits,
+            total_deposited: legacy.total_deposited,
+            total_invested: legacy.total_invested,
+            pending_deposits: legacy.pending_deposits,
+            yield_per_share: legacy.yield_per_share,
+            deposit_count: legacy.deposit_count,
+            strategy_weights: legacy.strategy_weights,
+            fee_per_share: 0,
+            deposits_made,
+            withdrawals_made,
+        };
+
         state_ctxt.owner.from_arcis(state).data
     }
 
+    /// Re-lay a ShardState written before referrals into the current layout
+    #[instruction]
+    pub fn upgrade_shard_state(shard_ctxt: Enc<Mxe, LegacyShardState>) -> EncData<ShardState> {
+        let legacy = shard_ctxt.to_arcis();
+
+        let mut deposits = [DepositEntry {
+            password_commitment: 0u128,
+            owner: 0u128,
+            principal: 0,
+            last_yield_checkpoint: 0,
+            is_active: false,
+            allowed_destinations: [0u128; MAX_ALLOWED_DESTINATIONS],
+            deposited_at: 0,
+            last_withdrawal_slot: 0,
+            withdrawn_this_epoch: 0,
+            beneficiary: 0u128,
+            last_active_slot: 0,
+            lock_until: 0,
+            referrer: 0u128,
+            referral_checkpoint: 0,
+        }; SHARD_SIZE];
+        for i in 0..SHARD_SIZE {
+            let entry = legacy.deposits[i];
+            deposits[i].password_commitment = entry.password_commitment;
+            deposits[i].owner = entry.owner;
+            deposits[i].principal = entry.principal;
+            deposits[i].last_yield_checkpoint = entry.last_yield_checkpoint;
+            deposits[i].is_active = entry.is_active;
+            deposits[i].allowed_destinations = entry.allowed_destinations;
+            deposits[i].deposited_at = entry.deposited_at;
+            deposits[i].last_withdrawal_slot = entry.last_withdrawal_slot;
+            deposits[i].withdrawn_this_epoch = entry.withdrawn_this_epoch;
+            deposits[i].beneficiary = entry.beneficiary;
+            deposits[i].last_active_slot = entry.last_active_slot;
+            deposits[i].lock_until = entry.lock_until;
+        }
+
+        shard_ctxt.owner.from_arcis(ShardState { deposits }).data
+    }
+
     /// Check if investment threshold reached
+    /// `buffer_bps` of total_deposited stays liquid in the vault, only pending deposits
+    /// above it are investable
     #[instruction]
     pub fn check_investment_needed(
         state_ctxt: Enc<Mxe, PoolState>,
         threshold: u64,
+        buffer_bps: u16,
     ) -> InvestmentDecision {
         let state = state_ctxt.to_arcis();
 
-        let should_invest = state.pending_deposits >= threshold;
+        let buffer = (state.total_deposited as u128 * buffer_bps as u128 / 10_000) as u64;
+        let investable = if state.pending_deposits > buffer {
+            state.pending_deposits - buffer
+        } else {
+            0
+        };
+        let should_invest = investable >= threshold;
+        let amount_to_invest = if should_invest { investable } else { 0 };
+
+        // Every venue but the last gets its weighted share rounded down, the last the
+        // remainder, so the split always adds up to amount_to_invest
+        let mut venue_amounts = [0u64; STRATEGY_VENUES];
+        let mut allocated = 0u64;
+        for i in 0..STRATEGY_VENUES {
+            if i + 1 < STRATEGY_VENUES {
+                venue_amounts[i] = (amount_to_invest as u128 * state.strategy_weights[i] as u128 / 10_000) as u64;
+                allocated += venue_amounts[i];
+            } else {
+                venue_amounts[i] = amount_to_invest - allocated;
+            }
+        }
 
         InvestmentDecision {
             should_invest,
-            amount_to_invest: if should_invest { state.pending_deposits } else { 0 },
+            amount_to_invest,
+            venue_amounts,
+        }.reveal()
+    }
+
+    /// Replace the pool's target investment weights
+    /// Weights that don't sum to 10_000 bps are refused and the state is left as it was.
+    /// Reveals only whether the update happened, never the weights
+    #[instruction]
+    pub fn set_strategy_weights(
+        weights_ctxt: Enc<Shared, StrategyWeights>,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> (Enc<Mxe, PoolState>, bool) {
+        let weights = weights_ctxt.to_arcis();
+        let mut state = state_ctxt.to_arcis();
+
+        let mut total = 0u32;
+        for i in 0..STRATEGY_VENUES {
+            total += weights.weights[i] as u32;
+        }
+        let valid = total == 10_000;
+
+        if valid {
+            state.strategy_weights = weights.weights;
+        }
+
+        (state_ctxt.owner.from_arcis(state), valid.reveal())
+    }
+
+    /// Reveal the lifetime deposit and withdrawal counts rounded to the nearest `bucket`
+    /// Used at counter epoch close so the public counters never expose the exact counts
+    #[instruction]
+    pub fn reveal_deposit_count(
+        state_ctxt: Enc<Mxe, PoolState>,
+        bucket: u16,
+    ) -> CounterTotals {
+        let state = state_ctxt.to_arcis();
+        let bucket = bucket as u64;
+
+        CounterTotals {
+            deposits: ((state.deposits_made + bucket / 2) / bucket) * bucket,
+            withdrawals: ((state.withdrawals_made + bucket / 2) / bucket) * bucket,
+        }.reveal()
+    }
+
+    /// Reveal total_deposited rounded down to a multiple of `bucket` after adding secret noise
+    /// drawn uniformly from [0, bucket): the published TVL is right on average, but whether a
+    /// single deposit moves it to the next bucket is left to chance
+    #[instruction]
+    pub fn publish_tvl(
+        state_ctxt: Enc<Mxe, PoolState>,
+        bucket: u64,
+    ) -> u64 {
+        let state = state_ctxt.to_arcis();
+
+        let noise = (ArcisRNG::gen_integer_from_width(64) as u64) % bucket;
+        let rounded = ((state.total_deposited + noise) / bucket) * bucket;
+
+        rounded.reveal()
+    }
+
+    /// Aggregate stats safe to publish: the depositor count rounded to `count_bucket`, and the
+    /// median position and unclaimed yield rounded to `amount_bucket`
+    #[instruction]
+    pub fn compute_pool_stats(
+        state_ctxt: Enc<Mxe, PoolState>,
+        yield_scale: u64,
+        count_bucket: u16,
+        amount_bucket: u64,
+    ) -> PoolStats {
+        let state = state_ctxt.to_arcis();
+
+        let mut active = 0u16;
+        let mut unclaimed_yield = 0u64;
+        for i in 0..MAX_DEPOSITS {
+            if state.deposits[i].is_active {
+                active += 1;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                unclaimed_yield += yield_earned(state.deposits[i].principal, yield_delta, yield_scale);
+            }
+        }
+
+        // The lower median has fewer than `rank` active principals below it, and at least
+        // `rank` at or below it (ties all pick the same value)
+        let rank = (active + 1) / 2;
+        let mut median_principal = 0u64;
+        for i in 0..MAX_DEPOSITS {
+            let principal = state.deposits[i].principal;
+            let mut below = 0u16;
+            let mut at_or_below = 0u16;
+            for j in 0..MAX_DEPOSITS {
+                if state.deposits[j].is_active && state.deposits[j].principal < principal {
+                    below += 1;
+                }
+                if state.deposits[j].is_active && state.deposits[j].principal <= principal {
+                    at_or_below += 1;
+                }
+            }
+            if state.deposits[i].is_active && below < rank && at_or_below >= rank {
+                median_principal = principal;
+            }
+        }
+
+        PoolStats {
+            active_depositors: ((state.deposit_count + count_bucket / 2) / count_bucket) * count_bucket,
+            median_principal: ((median_principal + amount_bucket / 2) / amount_bucket) * amount_bucket,
+            unclaimed_yield: ((unclaimed_yield + amount_bucket / 2) / amount_bucket) * amount_bucket,
         }.reveal()
     }
 
@@ -144,34 +944,103 @@ mod circuits {
 
     /// Record yield and distribute proportionally (lazy accumulation)
     /// This now uses O(1) complexity instead of O(n) - no loop needed!
+    /// `yield_scale` is the pool's index precision, 10^(mint decimals + 3)
     /// Returns EncData to minimize callback size
     #[instruction]
     pub fn record_yield(
         state_ctxt: Enc<Mxe, PoolState>,
         yield_amount: u64,
+        yield_scale: u64,
     ) -> EncData<PoolState> {
         let mut state = state_ctxt.to_arcis();
 
-        // Update global yield index (scaled by 1e9 for precision)
+        // Update global yield index (scaled by yield_scale for precision)
         // Users claim their proportional share when they withdraw
+        // Yield per token: (yield_amount * yield_scale) / total_deposited
+        // This avoids expensive per-user calculations in MPC
+        let (yield_per_share, total_deposited) =
+            share_yield(state.yield_per_share, state.total_deposited, yield_amount, yield_scale);
+        state.yield_per_share = yield_per_share;
+        state.total_deposited = total_deposited;
+
+        state_ctxt.owner.from_arcis(state).data
+    }
+
+    /// Record yield net of protocol fees
+    /// The performance fee (bps of the gross yield) plus the management fee accrued since
+    /// the last harvest are kept out of the yield index, capped at the gross yield, and added
+    /// to the fee index referral rewards are paid from.
+    /// Must match GhostPool::yield_fee on-chain, which books the same fee for collect_fees
+    #[instruction]
+    pub fn record_yield_with_fees(
+        state_ctxt: Enc<Mxe, PoolState>,
+        yield_amount: u64,
+        performance_fee_bps: u16,
+        management_fee: u64,
+        yield_scale: u64,
+    ) -> EncData<PoolState> {
+        let mut state = state_ctxt.to_arcis();
+
+        let performance_fee = (yield_amount as u128 * performance_fee_bps as u128 / 10_000) as u64;
+        let total_fee = performance_fee + management_fee;
+        let fee = if total_fee < yield_amount { total_fee } else { yield_amount };
+        let user_yield = yield_amount - fee;
+
         if state.total_deposited > 0 {
-            // Calculate yield per token: (yield_amount * 1e9) / total_deposited
-            // This avoids expensive per-user calculations in MPC
-            let yield_per_token = (yield_amount * 1_000_000_000) / state.total_deposited;
-            state.yield_per_share += yield_per_token;
-            state.total_deposited += yield_amount;
+            state.fee_per_share = index_after(state.fee_per_share, fee, state.total_deposited, yield_scale);
+            state.yield_per_share =
+                index_after(state.yield_per_share, user_yield, state.total_deposited, yield_scale);
+            state.total_deposited += user_yield;
         }
 
         state_ctxt.owner.from_arcis(state).data
     }
 
+    /// Credit `referrer` with its share of the fees charged on its referees' yield
+    /// Every active entry referred by `referrer` contributes `referral_share_bps` of the fees
+    /// its principal bore since its referral checkpoint, which moves up to the fee index.
+    /// Reveals only the total, so no single referee's balance shows
+    #[instruction]
+    pub fn claim_referral_rewards(
+        referrer: u128,
+        referral_share_bps: u16,
+        yield_scale: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> (Enc<Mxe, PoolState>, u64) {
+        let mut state = state_ctxt.to_arcis();
+
+        let mut reward = 0u64;
+        for i in 0..MAX_DEPOSITS {
+            let entry = state.deposits[i];
+            if entry.is_active && entry.referrer != 0 && entry.referrer == referrer {
+                let fee_delta = state.fee_per_share - entry.referral_checkpoint;
+                let fees = yield_earned(entry.principal, fee_delta, yield_scale);
+                reward += (fees as u128 * referral_share_bps as u128 / 10_000) as u64;
+                state.deposits[i].referral_checkpoint = state.fee_per_share;
+            }
+        }
+
+        (state_ctxt.owner.from_arcis(state), reward.reveal())
+    }
+
     /// Authorize withdrawal by verifying password (step 1: check only)
-    /// Password hash is encrypted, amount is plaintext (visible anyway)
+    /// Password commitment is encrypted, amount is plaintext (visible anyway); the entry
+    /// must also be bound to the withdrawal's signer, so a leaked password alone can't spend it.
+    /// `destination` is echoed in the result so the program can check the authorization is for
+    /// the account it pays
     /// Now calculates accrued yield on-demand for the withdrawing user
     #[instruction]
     pub fn authorize_withdrawal(
         password_hash_ctxt: Enc<Shared, u128>,
         amount: u64,
+        destination: u128,
+        signer: u128,
+        yield_scale: u64,
+        now: u64,
+        penalty_bps: u16,
+        penalty_window_secs: u64,
+        epoch_start_slot: u64,
+        withdrawal_cap: u64,
         state_ctxt: Enc<Mxe, PoolState>,
     ) -> WithdrawalAuth {
         let password_hash = password_hash_ctxt.to_arcis();
@@ -181,10 +1050,15 @@ mod circuits {
         let mut found = false;
         let mut found_idx = 0u8;
         let mut actual_balance = 0u64;
+        let mut allowed = false;
+        let mut deposited_at = 0u64;
+        let mut withdrawn = 0u64;
+        let mut lock_until = 0u64;
 
         for i in 0..MAX_DEPOSITS {
             let matches = state.deposits[i].is_active &&
-                         state.deposits[i].password_hash == password_hash;
+                         state.deposits[i].password_commitment == password_hash &&
+                         state.deposits[i].owner == signer;
 
             if matches && !found {
                 found = true;
@@ -195,30 +1069,467 @@ mod circuits {
                 let checkpoint = state.deposits[i].last_yield_checkpoint;
                 let yield_delta = state.yield_per_share - checkpoint;
 
-                // Unscale: (principal * yield_delta) / 1e9
-                let accrued_yield = (principal * yield_delta) / 1_000_000_000;
+                // Unscale: (principal * yield_delta) / yield_scale
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
                 actual_balance = principal + accrued_yield;
+                deposited_at = state.deposits[i].deposited_at;
+                lock_until = state.deposits[i].lock_until;
+                if state.deposits[i].last_withdrawal_slot >= epoch_start_slot {
+                    withdrawn = state.deposits[i].withdrawn_this_epoch;
+                }
+
+                // Empty allowlist accepts any destination owner
+                let mut unrestricted = true;
+                let mut listed = false;
+                for j in 0..MAX_ALLOWED_DESTINATIONS {
+                    let allowed_owner = state.deposits[i].allowed_destinations[j];
+                    if allowed_owner != 0 {
+                        unrestricted = false;
+                    }
+                    if allowed_owner == destination {
+                        listed = true;
+                    }
+                }
+                allowed = unrestricted || listed;
             }
         }
 
-        // Check sufficient balance (including accrued yield)
-        let sufficient = found && actual_balance >= amount;
+        // Check sufficient balance (including accrued yield), destination, maturity and the per-epoch
+        // cap (0 = uncapped). A capped-out withdrawal looks like any other rejection
+        let within_cap = withdrawal_cap == 0 || withdrawn + amount <= withdrawal_cap;
+        let matured = now >= lock_until;
+        let sufficient = found && actual_balance >= amount && allowed && matured && within_cap;
+
+        // Early-exit penalty decays linearly from penalty_bps at deposit time to 0 at the
+        // end of the window. The ledger is debited the full amount, only the net is paid
+        let age = if now > deposited_at { now - deposited_at } else { 0 };
+        let penalty = if sufficient {
+            early_exit_penalty(amount, penalty_bps, penalty_window_secs, age)
+        } else {
+            0
+        };
 
         WithdrawalAuth {
             authorized: sufficient,
-            amount: if sufficient { amount } else { 0 },
+            amount: if sufficient { amount - penalty } else { 0 },
             found_idx,
+            destination_allowed: !found || allowed,
+            penalty,
+            matured: !found || matured,
+            destination,
         }.reveal()
     }
 
+    /// Read a position's current balance (principal plus accrued yield)
+    /// Matched like a withdrawal (password commitment and bound signer). The balance is
+    /// re-encrypted to the caller's shared key, so only the caller can read it; a position
+    /// that doesn't match reads as 0
+    #[instruction]
+    pub fn get_balance(
+        password_hash_ctxt: Enc<Shared, u128>,
+        signer: u128,
+        yield_scale: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> Enc<Shared, u64> {
+        let password_hash = password_hash_ctxt.to_arcis();
+        let state = state_ctxt.to_arcis();
+
+        let mut found = false;
+        let mut balance = 0u64;
+
+        for i in 0..MAX_DEPOSITS {
+            let matches = state.deposits[i].is_active &&
+                         state.deposits[i].password_commitment == password_hash &&
+                         state.deposits[i].owner == signer;
+
+            if matches && !found {
+                found = true;
+                let principal = state.deposits[i].principal;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                balance = principal + accrued_yield;
+            }
+        }
+
+        password_hash_ctxt.owner.from_arcis(balance)
+    }
+
+    /// Authorize withdrawing a whole position (principal plus accrued yield)
+    /// Same checks as authorize_withdrawal, but the amount is the position's full balance,
+    /// which is revealed as the authorized amount since the user can't know it otherwise.
+    /// The per-epoch cap applies to the full balance, so a capped position can't exit in one go
+    #[instruction]
+    pub fn authorize_withdrawal_all(
+        password_hash_ctxt: Enc<Shared, u128>,
+        destination: u128,
+        signer: u128,
+        yield_scale: u64,
+        now: u64,
+        penalty_bps: u16,
+        penalty_window_secs: u64,
+        epoch_start_slot: u64,
+        withdrawal_cap: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> WithdrawalAuth {
+        let password_hash = password_hash_ctxt.to_arcis();
+        let state = state_ctxt.to_arcis();
+
+        let mut found = false;
+        let mut found_idx = 0u8;
+        let mut actual_balance = 0u64;
+        let mut allowed = false;
+        let mut deposited_at = 0u64;
+        let mut withdrawn = 0u64;
+        let mut lock_until = 0u64;
+
+        for i in 0..MAX_DEPOSITS {
+            let matches = state.deposits[i].is_active &&
+                         state.deposits[i].password_commitment == password_hash &&
+                         state.deposits[i].owner == signer;
+
+            if matches && !found {
+                found = true;
+                found_idx = i as u8;
+
+                let principal = state.deposits[i].principal;
+                let checkpoint = state.deposits[i].last_yield_checkpoint;
+                let yield_delta = state.yield_per_share - checkpoint;
+
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                actual_balance = principal + accrued_yield;
+                deposited_at = state.deposits[i].deposited_at;
+                lock_until = state.deposits[i].lock_until;
+                if state.deposits[i].last_withdrawal_slot >= epoch_start_slot {
+                    withdrawn = state.deposits[i].withdrawn_this_epoch;
+                }
+
+                let mut unrestricted = true;
+                let mut listed = false;
+                for j in 0..MAX_ALLOWED_DESTINATIONS {
+                    let allowed_owner = state.deposits[i].allowed_destinations[j];
+                    if allowed_owner != 0 {
+                        unrestricted = false;
+                    }
+                    if allowed_owner == destination {
+                        listed = true;
+                    }
+                }
+                allowed = unrestricted || listed;
+            }
+        }
+
+        let within_cap = withdrawal_cap == 0 || withdrawn + actual_balance <= withdrawal_cap;
+        let matured = now >= lock_until;
+        let sufficient = found && actual_balance > 0 && allowed && matured && within_cap;
+
+        let age = if now > deposited_at { now - deposited_at } else { 0 };
+        let penalty = if sufficient {
+            early_exit_penalty(actual_balance, penalty_bps, penalty_window_secs, age)
+        } else {
+            0
+        };
+
+        WithdrawalAuth {
+            authorized: sufficient,
+            amount: if sufficient { actual_balance - penalty } else { 0 },
+            found_idx,
+            destination_allowed: !found || allowed,
+            penalty,
+            matured: !found || matured,
+            destination,
+        }.reveal()
+    }
+
+    /// Authorize a beneficiary to withdraw a position its owner has abandoned
+    /// `beneficiary` is the fingerprint of the signing beneficiary, matched against the one
+    /// stored encrypted at deposit; the position must also have seen no owner action for
+    /// `inactivity_slots` and be past its lock. The full balance is authorized with no early-exit
+    /// penalty, and the owner's destination allowlist and withdrawal cap don't apply to the beneficiary
+    #[instruction]
+    pub fn recover_deposit(
+        beneficiary: u128,
+        yield_scale: u64,
+        now: u64,
+        slot: u64,
+        inactivity_slots: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> WithdrawalAuth {
+        let state = state_ctxt.to_arcis();
+
+        let mut found = false;
+        let mut found_idx = 0u8;
+        let mut actual_balance = 0u64;
+
+        for i in 0..MAX_DEPOSITS {
+            let entry = state.deposits[i];
+            let dormant = slot >= entry.last_active_slot + inactivity_slots;
+            let matches = entry.is_active
+                && entry.beneficiary != 0
+                && entry.beneficiary == beneficiary
+                && dormant
+                && now >= entry.lock_until;
+
+            if matches && !found {
+                found = true;
+                found_idx = i as u8;
+
+                let yield_delta = state.yield_per_share - entry.last_yield_checkpoint;
+                let accrued_yield = yield_earned(entry.principal, yield_delta, yield_scale);
+                actual_balance = entry.principal + accrued_yield;
+            }
+        }
+
+        let authorized = found && actual_balance > 0;
+
+        WithdrawalAuth {
+            authorized,
+            amount: if authorized { actual_balance } else { 0 },
+            found_idx,
+            destination_allowed: true,
+            penalty: 0,
+            matured: true,
+            destination: 0,
+        }.reveal()
+    }
+
+    /// Authorize withdrawal against a shard entry
+    /// Yield index is read from PoolState, the matching entry from the shard
+    #[instruction]
+    pub fn authorize_withdrawal_shard(
+        password_hash_ctxt: Enc<Shared, u128>,
+        amount: u64,
+        destination: u128,
+        signer: u128,
+        yield_scale: u64,
+        now: u64,
+        penalty_bps: u16,
+        penalty_window_secs: u64,
+        epoch_start_slot: u64,
+        withdrawal_cap: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+        shard_ctxt: Enc<Mxe, ShardState>,
+    ) -> WithdrawalAuth {
+        let password_hash = password_hash_ctxt.to_arcis();
+        let state = state_ctxt.to_arcis();
+        let shard = shard_ctxt.to_arcis();
+
+        let mut found = false;
+        let mut found_idx = 0u8;
+        let mut actual_balance = 0u64;
+        let mut allowed = false;
+        let mut deposited_at = 0u64;
+        let mut withdrawn = 0u64;
+        let mut lock_until = 0u64;
+
+        for i in 0..SHARD_SIZE {
+            let matches = shard.deposits[i].is_active &&
+                         shard.deposits[i].password_commitment == password_hash &&
+                         shard.deposits[i].owner == signer;
+
+            if matches && !found {
+                found = true;
+                found_idx = i as u8;
+
+                let principal = shard.deposits[i].principal;
+                let checkpoint = shard.deposits[i].last_yield_checkpoint;
+                let yield_delta = state.yield_per_share - checkpoint;
+
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                actual_balance = principal + accrued_yield;
+                deposited_at = shard.deposits[i].deposited_at;
+                lock_until = shard.deposits[i].lock_until;
+                if shard.deposits[i].last_withdrawal_slot >= epoch_start_slot {
+                    withdrawn = shard.deposits[i].withdrawn_this_epoch;
+                }
+
+                let mut unrestricted = true;
+                let mut listed = false;
+                for j in 0..MAX_ALLOWED_DESTINATIONS {
+                    let allowed_owner = shard.deposits[i].allowed_destinations[j];
+                    if allowed_owner != 0 {
+                        unrestricted = false;
+                    }
+                    if allowed_owner == destination {
+                        listed = true;
+                    }
+                }
+                allowed = unrestricted || listed;
+            }
+        }
+
+        let within_cap = withdrawal_cap == 0 || withdrawn + amount <= withdrawal_cap;
+        let matured = now >= lock_until;
+        let sufficient = found && actual_balance >= amount && allowed && matured && within_cap;
+
+        let age = if now > deposited_at { now - deposited_at } else { 0 };
+        let penalty = if sufficient {
+            early_exit_penalty(amount, penalty_bps, penalty_window_secs, age)
+        } else {
+            0
+        };
+
+        WithdrawalAuth {
+            authorized: sufficient,
+            amount: if sufficient { amount - penalty } else { 0 },
+            found_idx,
+            destination_allowed: !found || allowed,
+            penalty,
+            matured: !found || matured,
+            destination,
+        }.reveal()
+    }
+
+    /// Replace the password commitment guarding a position
+    /// The old commitment and bound signer authorize, as for a withdrawal. A new commitment
+    /// already guarding another active position is refused, so commitments stay unique.
+    /// Balance, yield checkpoint and allowlist are untouched. Reveals only whether it happened
+    #[instruction]
+    pub fn rotate_password(
+        rotation_ctxt: Enc<Shared, PasswordRotation>,
+        signer: u128,
+        slot: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> (Enc<Mxe, PoolState>, bool) {
+        let rotation = rotation_ctxt.to_arcis();
+        let mut state = state_ctxt.to_arcis();
+
+        let mut found = false;
+        let mut found_idx = 0u8;
+        let mut taken = false;
+
+        for i in 0..MAX_DEPOSITS {
+            let entry = state.deposits[i];
+            if entry.is_active && entry.password_commitment == rotation.old_commitment && entry.owner == signer && !found {
+                found = true;
+                found_idx = i as u8;
+            }
+            if entry.is_active && entry.password_commitment == rotation.new_commitment {
+                taken = true;
+            }
+        }
+
+        let rotated = found && !taken;
+
+        for i in 0..MAX_DEPOSITS {
+            if rotated && i == found_idx as usize {
+                state.deposits[i].password_commitment = rotation.new_commitment;
+                state.deposits[i].last_active_slot = slot;
+            }
+        }
+
+        (state_ctxt.owner.from_arcis(state), rotated.reveal())
+    }
+
+    /// Move `amount` between two ledger positions, no tokens leave the vault
+    /// The sender's password and bound signer authorize; the recipient is named by its own password hash,
+    /// encrypted by the recipient and handed to the sender. Both balances are settled at the
+    /// current yield index first. Senders with a destination allowlist or a lock that hasn't
+    /// matured can't transfer, so neither can be sidestepped. Reveals only whether the transfer happened
+    #[instruction]
+    pub fn transfer_position(
+        sender_hash_ctxt: Enc<Shared, u128>,
+        amount: u64,
+        sender: u128,
+        recipient_hash_ctxt: Enc<Shared, u128>,
+        yield_scale: u64,
+        now: u64,
+        slot: u64,
+        epoch_start_slot: u64,
+        withdrawal_cap: u64,
+        state_ctxt: Enc<Mxe, PoolState>,
+    ) -> (Enc<Mxe, PoolState>, bool) {
+        let sender_hash = sender_hash_ctxt.to_arcis();
+        let recipient_hash = recipient_hash_ctxt.to_arcis();
+        let mut state = state_ctxt.to_arcis();
+
+        let mut sender_found = false;
+        let mut sender_idx = 0u8;
+        let mut sender_balance = 0u64;
+        let mut sender_deposited_at = 0u64;
+        let mut sender_withdrawn = 0u64;
+        let mut sender_unrestricted = true;
+        let mut sender_matured = false;
+        let mut recipient_found = false;
+        let mut recipient_idx = 0u8;
+
+        for i in 0..MAX_DEPOSITS {
+            let entry = state.deposits[i];
+            if entry.is_active && entry.password_commitment == sender_hash && entry.owner == sender && !sender_found {
+                sender_found = true;
+                sender_idx = i as u8;
+                let yield_delta = state.yield_per_share - entry.last_yield_checkpoint;
+                let accrued_yield = yield_earned(entry.principal, yield_delta, yield_scale);
+                sender_balance = entry.principal + accrued_yield;
+                sender_deposited_at = entry.deposited_at;
+                sender_matured = now >= entry.lock_until;
+                if entry.last_withdrawal_slot >= epoch_start_slot {
+                    sender_withdrawn = entry.withdrawn_this_epoch;
+                }
+                for j in 0..MAX_ALLOWED_DESTINATIONS {
+                    if entry.allowed_destinations[j] != 0 {
+                        sender_unrestricted = false;
+                    }
+                }
+            }
+            if entry.is_active && entry.password_commitment == recipient_hash && !recipient_found {
+                recipient_found = true;
+                recipient_idx = i as u8;
+            }
+        }
+
+        let transferred = sender_found
+            && recipient_found
+            && sender_idx != recipient_idx
+            && sender_unrestricted
+            && sender_matured
+            && amount > 0
+            && sender_balance >= amount
+            && (withdrawal_cap == 0 || sender_withdrawn + amount <= withdrawal_cap);
+
+        for i in 0..MAX_DEPOSITS {
+            if transferred && i == sender_idx as usize {
+                let new_balance = sender_balance - amount;
+                state.deposits[i].principal = new_balance;
+                state.deposits[i].last_yield_checkpoint = state.yield_per_share;
+                state.deposits[i].withdrawn_this_epoch = sender_withdrawn + amount;
+                state.deposits[i].last_withdrawal_slot = slot;
+                state.deposits[i].last_active_slot = slot;
+                if new_balance == 0 {
+                    state.deposits[i].is_active = false;
+                    state.deposit_count -= 1;
+                }
+            }
+            if transferred && i == recipient_idx as usize {
+                let principal = state.deposits[i].principal;
+                let yield_delta = state.yield_per_share - state.deposits[i].last_yield_checkpoint;
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                state.deposits[i].principal = principal + accrued_yield + amount;
+                state.deposits[i].last_yield_checkpoint = state.yield_per_share;
+                state.deposits[i].referral_checkpoint = state.fee_per_share;
+                // The later deposit time wins, so a transfer can't age funds past the penalty window
+                if sender_deposited_at > state.deposits[i].deposited_at {
+                    state.deposits[i].deposited_at = sender_deposited_at;
+                }
+            }
+        }
+
+        (state_ctxt.owner.from_arcis(state), transferred.reveal())
+    }
+
     /// Update state after successful withdrawal (step 2: update)
     /// Note: This should only be called after authorize_withdrawal returns true
+    /// `amount` is the full debit (payout plus early-exit penalty); `pool_penalty` is the part
+    /// of the penalty that stays with the pool, shared out to the remaining balances like yield
     /// Returns EncData to minimize callback size
     #[instruction]
     pub fn process_withdrawal(
         state_ctxt: Enc<Mxe, PoolState>,
         idx: u8,
         amount: u64,
+        pool_penalty: u64,
+        slot: u64,
+        epoch_start_slot: u64,
+        yield_scale: u64,
     ) -> EncData<PoolState> {
         let mut state = state_ctxt.to_arcis();
 
@@ -230,7 +1541,7 @@ mod circuits {
                 let principal = state.deposits[i].principal;
                 let checkpoint = state.deposits[i].last_yield_checkpoint;
                 let yield_delta = state.yield_per_share - checkpoint;
-                let accrued_yield = (principal * yield_delta) / 1_000_000_000;
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
                 let current_balance = principal + accrued_yield;
 
                 // Deduct withdrawal amount
@@ -240,6 +1551,15 @@ mod circuits {
                 state.deposits[i].principal = new_balance;
                 state.deposits[i].last_yield_checkpoint = state.yield_per_share;
 
+                // Count the debit against the entry's withdrawal cap for this epoch
+                if state.deposits[i].last_withdrawal_slot >= epoch_start_slot {
+                    state.deposits[i].withdrawn_this_epoch += amount;
+                } else {
+                    state.deposits[i].withdrawn_this_epoch = amount;
+                }
+                state.deposits[i].last_withdrawal_slot = slot;
+                state.deposits[i].last_active_slot = slot;
+
                 // Mark inactive if balance is now zero
                 let is_zero = new_balance == 0;
                 if is_zero {
@@ -250,7 +1570,84 @@ mod circuits {
         }
 
         state.total_deposited -= amount;
+        state.withdrawals_made += 1;
+
+        // Shared only while a depositor is left to take it, see share_penalty
+        let (yield_per_share, total_deposited) = share_penalty(
+            state.yield_per_share,
+            state.total_deposited,
+            state.deposit_count,
+            pool_penalty,
+            yield_scale,
+        );
+        state.yield_per_share = yield_per_share;
+        state.total_deposited = total_deposited;
 
         state_ctxt.owner.from_arcis(state).data
     }
+
+    /// Update state after a paid shard withdrawal (step 2 of withdraw_from_shard)
+    /// The shard counterpart of process_withdrawal: the entry at `idx` is debited in the
+    /// shard, pool-wide totals and the penalty share move in PoolState
+    /// Returns Enc (with nonces) since each output is re-encrypted under its own nonce
+    #[instruction]
+    pub fn process_withdrawal_shard(
+        state_ctxt: Enc<Mxe, PoolState>,
+        shard_ctxt: Enc<Mxe, ShardState>,
+        idx: u8,
+        amount: u64,
+        pool_penalty: u64,
+        slot: u64,
+        epoch_start_slot: u64,
+        yield_scale: u64,
+    ) -> (Enc<Mxe, PoolState>, Enc<Mxe, ShardState>) {
+        let mut state = state_ctxt.to_arcis();
+        let mut shard = shard_ctxt.to_arcis();
+
+        // Assume idx is valid (checked by authorize_withdrawal_shard)
+        for i in 0..SHARD_SIZE {
+            if i == idx as usize {
+                let principal = shard.deposits[i].principal;
+                let checkpoint = shard.deposits[i].last_yield_checkpoint;
+                let yield_delta = state.yield_per_share - checkpoint;
+                let accrued_yield = yield_earned(principal, yield_delta, yield_scale);
+                let new_balance = principal + accrued_yield - amount;
+
+                shard.deposits[i].principal = new_balance;
+                shard.deposits[i].last_yield_checkpoint = state.yield_per_share;
+
+                if shard.deposits[i].last_withdrawal_slot >= epoch_start_slot {
+                    shard.deposits[i].withdrawn_this_epoch += amount;
+                } else {
+                    shard.deposits[i].withdrawn_this_epoch = amount;
+                }
+                shard.deposits[i].last_withdrawal_slot = slot;
+                shard.deposits[i].last_active_slot = slot;
+
+                if new_balance == 0 {
+                    shard.deposits[i].is_active = false;
+                    state.deposit_count -= 1;
+                }
+            }
+        }
+
+        state.total_deposited -= amount;
+        state.withdrawals_made += 1;
+
+        // Shared only while a depositor is left to take it, see share_penalty
+        let (yield_per_share, total_deposited) = share_penalty(
+            state.yield_per_share,
+            state.total_deposited,
+            state.deposit_count,
+            pool_penalty,
+            yield_scale,
+        );
+        state.yield_per_share = yield_per_share;
+        state.total_deposited = total_deposited;
+
+        (
+            state_ctxt.owner.from_arcis(state),
+            shard_ctxt.owner.from_arcis(shard),
+        )
+    }
 }
@@ -0,0 +1,376 @@
+//! Property tests for the yield-per-share accounting
+//!
+//! Circuits only run under the MXE, so the ledger fields their arithmetic touches are
+//! modelled here as plain Rust: process_deposit (accepted deposits), record_yield,
+//! authorize_withdrawal and process_withdrawal. The arithmetic itself is
+//! `encrypted_ixs::accounting`, the same functions the circuits call.
+//!
+//! Random sequences of deposits, yields and withdrawals must keep:
+//! - every balance covered: the balances (principal plus accrued yield) never add up past
+//!   `total_deposited`, and no subtraction underflows (overflow checks are on in tests)
+//! - nothing wraps: the indexes and accrued yield saturate at u64::MAX
+//! - rounding dust bounded: until an index saturates, each balance is within 1 base unit plus
+//!   principal × yield events / yield_scale of what exact arithmetic of the same lazy scheme
+//!   would credit
+//!
+//! The boundary tests below pin the saturation itself, and where a withdrawal penalty goes.
+
+use encrypted_ixs::accounting::{
+    early_exit_penalty, index_after, saturating_u64, share_penalty, share_yield, yield_earned,
+};
+use proptest::prelude::*;
+
+/// Ledger slots, pool and shards together (they share one yield index)
+const SLOTS: usize = 6;
+/// Digits of index precision beyond one base unit, as on-chain (YIELD_SCALE_EXTRA_DIGITS)
+const YIELD_SCALE_EXTRA_DIGITS: u32 = 3;
+/// Extra bits the exact reference index carries below the circuits' precision
+const REFERENCE_BITS: u32 = 32;
+const PENALTY_WINDOW_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Default)]
+struct DepositEntry {
+    principal: u64,
+    last_yield_checkpoint: u64,
+    is_active: bool,
+}
+
+#[derive(Clone, Default)]
+struct PoolState {
+    deposits: [DepositEntry; SLOTS],
+    total_deposited: u64,
+    yield_per_share: u64,
+    deposit_count: u16,
+}
+
+// ============ Ledger model ============
+
+/// Narrow a test-side u128 intermediate (amounts picked as a fraction of a balance)
+fn narrow(value: u128) -> u64 {
+    u64::try_from(value).expect("a test amount overflowed u64")
+}
+
+fn accrued_yield(entry: &DepositEntry, yield_per_share: u64, yield_scale: u64) -> u64 {
+    yield_earned(entry.principal, yield_per_share - entry.last_yield_checkpoint, yield_scale)
+}
+
+fn balance(state: &PoolState, idx: usize, yield_scale: u64) -> u64 {
+    let entry = &state.deposits[idx];
+    entry.principal + accrued_yield(entry, state.yield_per_share, yield_scale)
+}
+
+/// process_deposit, accepted: a new entry in a free slot, or a top-up of an active one
+fn process_deposit(state: &mut PoolState, idx: usize, amount: u64, yield_scale: u64) {
+    let yield_per_share = state.yield_per_share;
+    let entry = &mut state.deposits[idx];
+    if entry.is_active {
+        let accrued_yield = accrued_yield(entry, yield_per_share, yield_scale);
+        entry.principal = entry.principal + accrued_yield + amount;
+        entry.last_yield_checkpoint = yield_per_share;
+    } else {
+        *entry = DepositEntry {
+            principal: amount,
+            last_yield_checkpoint: yield_per_share,
+            is_active: true,
+        };
+        state.deposit_count += 1;
+    }
+    state.total_deposited += amount;
+}
+
+fn record_yield(state: &mut PoolState, yield_amount: u64, yield_scale: u64) {
+    (state.yield_per_share, state.total_deposited) =
+        share_yield(state.yield_per_share, state.total_deposited, yield_amount, yield_scale);
+}
+
+/// authorize_withdrawal of the entry at `idx` (the password and signer matched):
+/// (authorized, payout, penalty)
+fn authorize_withdrawal(
+    state: &PoolState,
+    idx: usize,
+    amount: u64,
+    yield_scale: u64,
+    age: u64,
+    penalty_bps: u16,
+) -> (bool, u64, u64) {
+    let found = state.deposits[idx].is_active;
+    let actual_balance = if found { balance(state, idx, yield_scale) } else { 0 };
+    let sufficient = found && actual_balance >= amount;
+
+    let penalty = if sufficient {
+        early_exit_penalty(amount, penalty_bps, PENALTY_WINDOW_SECS, age)
+    } else {
+        0
+    };
+    (sufficient, if sufficient { amount - penalty } else { 0 }, penalty)
+}
+
+/// process_withdrawal: `amount` is the full debit, `pool_penalty` what's shared out like yield
+fn process_withdrawal(state: &mut PoolState, idx: usize, amount: u64, pool_penalty: u64, yield_scale: u64) {
+    let current_balance = balance(state, idx, yield_scale);
+    let new_balance = current_balance - amount;
+    let yield_per_share = state.yield_per_share;
+    let entry = &mut state.deposits[idx];
+    entry.principal = new_balance;
+    entry.last_yield_checkpoint = yield_per_share;
+    if new_balance == 0 {
+        entry.is_active = false;
+        state.deposit_count -= 1;
+    }
+
+    state.total_deposited -= amount;
+    (state.yield_per_share, state.total_deposited) = share_penalty(
+        state.yield_per_share,
+        state.total_deposited,
+        state.deposit_count,
+        pool_penalty,
+        yield_scale,
+    );
+}
+
+// ============ Exact reference ============
+
+/// The same lazy scheme with the index carried `REFERENCE_BITS` finer, and how many
+/// distributions each entry has seen since its checkpoint
+#[derive(Clone, Default)]
+struct Reference {
+    yield_per_share: u128,
+    distributions: u64,
+    checkpoints: [(u128, u64); SLOTS],
+}
+
+impl Reference {
+    /// Share `amount` over `total_deposited`, the total the circuit divides by
+    fn distribute(&mut self, total_deposited: u64, amount: u64, yield_scale: u64) {
+        self.yield_per_share += ((amount as u128 * yield_scale as u128) << REFERENCE_BITS) / total_deposited as u128;
+        self.distributions += 1;
+    }
+
+    fn settle(&mut self, idx: usize) {
+        self.checkpoints[idx] = (self.yield_per_share, self.distributions);
+    }
+
+    /// The balance exact arithmetic credits the entry at `idx`, and the rounding the circuits
+    /// may lose on it: under 1 per index step (principal / yield_scale per distribution), plus
+    /// the final unscale
+    fn balance(&self, state: &PoolState, idx: usize, yield_scale: u64) -> (u128, u128) {
+        let (checkpoint, distributions) = self.checkpoints[idx];
+        let principal = state.deposits[idx].principal as u128;
+        let scale = (yield_scale as u128) << REFERENCE_BITS;
+        let exact = principal + principal * (self.yield_per_share - checkpoint) / scale;
+        let dust = 1 + (principal * (self.distributions - distributions) as u128).div_ceil(yield_scale as u128);
+        (exact, dust)
+    }
+}
+
+// ============ Operations ============
+
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit { idx: usize, amount: u64 },
+    /// Yield of `bps` of the pool, as a harvest of the pool's own position
+    Yield { bps: u16 },
+    /// Withdraw `fraction_bps` of the entry's balance (over 10_000 must be refused)
+    Withdraw {
+        idx: usize,
+        fraction_bps: u16,
+        age: u64,
+        penalty_bps: u16,
+        penalty_to_fees: bool,
+    },
+}
+
+fn op(max_amount: u64) -> impl Strategy<Value = Op> {
+    // Whole-balance withdrawals are the ones that empty the ledger
+    let fraction_bps = prop_oneof![Just(10_000u16), 1..=12_000u16];
+    prop_oneof![
+        (0..SLOTS, 1..=max_amount).prop_map(|(idx, amount)| Op::Deposit { idx, amount }),
+        (0..=1_000u16).prop_map(|bps| Op::Yield { bps }),
+        (0..SLOTS, fraction_bps, 0..=2 * PENALTY_WINDOW_SECS, 0..=500u16, any::<bool>()).prop_map(
+            |(idx, fraction_bps, age, penalty_bps, penalty_to_fees)| Op::Withdraw {
+                idx,
+                fraction_bps,
+                age,
+                penalty_bps,
+                penalty_to_fees,
+            }
+        ),
+    ]
+}
+
+fn apply(state: &mut PoolState, reference: &mut Reference, op: &Op, yield_scale: u64) {
+    match *op {
+        Op::Deposit { idx, amount } => {
+            process_deposit(state, idx, amount, yield_scale);
+            reference.settle(idx);
+        }
+        Op::Yield { bps } => {
+            // A pool nobody is in has no position to yield
+            if state.deposit_count == 0 {
+                return;
+            }
+            let yield_amount = narrow(state.total_deposited as u128 * bps as u128 / 10_000);
+            reference.distribute(state.total_deposited, yield_amount, yield_scale);
+            record_yield(state, yield_amount, yield_scale);
+        }
+        Op::Withdraw { idx, fraction_bps, age, penalty_bps, penalty_to_fees } => {
+            let entry_balance = if state.deposits[idx].is_active { balance(state, idx, yield_scale) } else { 0 };
+            let amount = narrow(entry_balance as u128 * fraction_bps as u128 / 10_000);
+            if amount == 0 {
+                return;
+            }
+            let (authorized, payout, penalty) = authorize_withdrawal(state, idx, amount, yield_scale, age, penalty_bps);
+            assert_eq!(authorized, amount <= entry_balance, "authorized {} of a {} balance", amount, entry_balance);
+            if !authorized {
+                return;
+            }
+
+            // WithdrawalReceipt::ledger_debit
+            let debit = payout + penalty;
+            let pool_penalty = if penalty_to_fees { 0 } else { penalty };
+            let remaining = state.total_deposited - debit;
+            process_withdrawal(state, idx, debit, pool_penalty, yield_scale);
+            reference.settle(idx);
+            if remaining > 0 && state.deposit_count > 0 {
+                reference.distribute(remaining, pool_penalty, yield_scale);
+            }
+        }
+    }
+}
+
+fn check_invariants(state: &PoolState, reference: &Reference, yield_scale: u64) {
+    let mut balances = 0u128;
+    let mut active = 0u16;
+    for idx in 0..SLOTS {
+        if !state.deposits[idx].is_active {
+            continue;
+        }
+        active += 1;
+        let entry_balance = balance(state, idx, yield_scale) as u128;
+        balances += entry_balance;
+
+        let (exact, dust) = reference.balance(state, idx, yield_scale);
+        // The reference's own floors can put it up to 1 below the exact value
+        assert!(entry_balance <= exact + 1, "slot {} credited {} of an exact {}", idx, entry_balance, exact);
+        // A saturated index gives up whatever it couldn't hold
+        assert!(
+            state.yield_per_share == u64::MAX || exact.saturating_sub(entry_balance) <= dust,
+            "slot {} lost {} to rounding, past the {} bound",
+            idx,
+            exact - entry_balance,
+            dust
+        );
+    }
+    assert!(
+        balances <= state.total_deposited as u128,
+        "balances {} exceed total_deposited {}",
+        balances,
+        state.total_deposited
+    );
+    assert_eq!(active, state.deposit_count);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    /// USDC-like (6 decimals) and SOL-like (9 decimals) mints, deposits of up to 10^12 base units
+    #[test]
+    fn yield_accounting_conserves_balances(
+        decimals in prop::sample::select(vec![6u32, 9]),
+        ops in prop::collection::vec(op(1_000_000_000_000), 1..64),
+    ) {
+        let yield_scale = 10u64.pow(decimals + YIELD_SCALE_EXTRA_DIGITS);
+        let mut state = PoolState::default();
+        let mut reference = Reference::default();
+        for op in &ops {
+            apply(&mut state, &mut reference, op, yield_scale);
+            check_invariants(&state, &reference, yield_scale);
+        }
+    }
+}
+
+// ============ Boundaries ============
+
+#[test]
+fn narrowing_saturates_instead_of_wrapping() {
+    assert_eq!(saturating_u64(u64::MAX as u128), u64::MAX);
+    assert_eq!(saturating_u64(u64::MAX as u128 + 1), u64::MAX);
+    assert_eq!(saturating_u64(u128::MAX), u64::MAX);
+    assert_eq!(yield_earned(u64::MAX, u64::MAX, u64::MAX), u64::MAX);
+    assert_eq!(yield_earned(u64::MAX, u64::MAX, 1), u64::MAX);
+    assert_eq!(yield_earned(u64::MAX, 2, 2), u64::MAX);
+    assert_eq!(index_after(0, 3, 2, 1_000), 1_500);
+    assert_eq!(index_after(u64::MAX - 1, 1, 1, 1), u64::MAX);
+    assert_eq!(index_after(u64::MAX, u64::MAX, 1, u64::MAX), u64::MAX);
+    assert_eq!(share_yield(u64::MAX - 1, 1, u64::MAX - 1, 2), (u64::MAX, u64::MAX));
+    assert_eq!(early_exit_penalty(u64::MAX, 10_000, PENALTY_WINDOW_SECS, 0), u64::MAX);
+    assert_eq!(early_exit_penalty(1_000_000, 500, PENALTY_WINDOW_SECS, PENALTY_WINDOW_SECS / 2), 25_000);
+    assert_eq!(early_exit_penalty(1_000_000, 500, PENALTY_WINDOW_SECS, PENALTY_WINDOW_SECS), 0);
+}
+
+/// A pool down to 1 base unit of a 9-decimal mint whose position still yields
+#[test]
+fn record_yield_saturates_the_index() {
+    let yield_scale = 10u64.pow(9 + YIELD_SCALE_EXTRA_DIGITS);
+    let mut state = PoolState::default();
+    process_deposit(&mut state, 0, 1, yield_scale);
+
+    record_yield(&mut state, 1_000_000_000_000, yield_scale);
+    assert_eq!(state.yield_per_share, u64::MAX);
+    // Wrapped, the index would have credited (10^24 mod 2^64) / 10^12 instead
+    assert_eq!(balance(&state, 0, yield_scale), 1 + u64::MAX / yield_scale);
+
+    // Later yield can't move the index, and stays unallocated
+    record_yield(&mut state, 5, yield_scale);
+    assert_eq!(state.yield_per_share, u64::MAX);
+    assert_eq!(state.total_deposited, 1 + 1_000_000_000_005);
+    assert!(balance(&state, 0, yield_scale) <= state.total_deposited);
+}
+
+/// An early exit that leaves a lone 1-unit depositor to share its penalty
+#[test]
+fn penalty_over_a_dust_position_saturates_the_index() {
+    let yield_scale = 10u64.pow(6 + YIELD_SCALE_EXTRA_DIGITS);
+    let mut state = PoolState::default();
+    process_deposit(&mut state, 0, 1, yield_scale);
+    process_deposit(&mut state, 1, 1_000_000_000_000, yield_scale);
+
+    let (authorized, payout, penalty) = authorize_withdrawal(&state, 1, 1_000_000_000_000, yield_scale, 0, 500);
+    assert!(authorized);
+    assert_eq!(penalty, 50_000_000_000);
+    process_withdrawal(&mut state, 1, payout + penalty, penalty, yield_scale);
+
+    assert_eq!(state.yield_per_share, u64::MAX);
+    assert_eq!(state.total_deposited, 1 + penalty);
+    assert_eq!(balance(&state, 0, yield_scale), 1 + u64::MAX / yield_scale);
+    assert!(balance(&state, 0, yield_scale) <= state.total_deposited);
+}
+
+#[test]
+fn penalty_is_shared_only_while_a_depositor_remains() {
+    assert_eq!(share_penalty(0, 1_000, 1, 100, 1_000), (100, 1_100));
+    // Rounding dust left by an emptied ledger takes neither the index step nor the penalty
+    assert_eq!(share_penalty(7, 3, 0, 100, 1_000), (7, 3));
+    assert_eq!(share_penalty(7, 0, 0, 100, 1_000), (7, 0));
+}
+
+/// The last depositor leaves early: the penalty stays out of the ledger, so whoever deposits
+/// next earns the next yield in full
+#[test]
+fn penalty_from_an_emptied_ledger_does_not_dilute_later_yield() {
+    let yield_scale = 10u64.pow(6 + YIELD_SCALE_EXTRA_DIGITS);
+    let mut state = PoolState::default();
+    process_deposit(&mut state, 0, 1_000_000, yield_scale);
+
+    let (authorized, payout, penalty) = authorize_withdrawal(&state, 0, 1_000_000, yield_scale, 0, 500);
+    assert!(authorized);
+    process_withdrawal(&mut state, 0, payout + penalty, penalty, yield_scale);
+    assert_eq!(state.deposit_count, 0);
+    assert_eq!(state.total_deposited, 0);
+    assert_eq!(state.yield_per_share, 0);
+
+    process_deposit(&mut state, 1, 2_000_000, yield_scale);
+    record_yield(&mut state, 20_000, yield_scale);
+    assert_eq!(balance(&state, 1, yield_scale), 2_020_000);
+    assert_eq!(state.total_deposited, 2_020_000);
+}
@@ -0,0 +1,372 @@
+//! Read-only HTTP API over the indexed data, so frontends don't each need the database
+//!
+//! Every listing is a page of at most `limit` items, newest first (pools in key order), as
+//! `{"items": [...], "next": <cursor>}`; passing `next` back as `before` (`after` for pools)
+//! fetches the following page, and a null `next` marks the last. Clients are rate limited per
+//! API key (`x-api-key`) when keys are configured, else per IP address.
+//!
+//! - `GET /pools`: known pools and their latest snapshot
+//! - `GET /pools/{pool}/tvl`: TVL history, one entry per snapshot
+//! - `GET /pools/{pool}/harvests`: harvest and recorded yield events
+//! - `GET /pools/{pool}/queues`: withdrawal queue and deposit intent depths per snapshot
+//! - `GET /wallets/{wallet}/activity`: events naming the wallet, only with `--wallet-activity true`
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use axum::extract::{ConnectInfo, Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::store::{Page, Store};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1_000;
+
+/// Rate limit window; a client's count resets at the end of each
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Clients tracked before those whose window has passed are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+pub struct Config {
+    /// Accepted API keys; None serves anyone, limited per IP address
+    pub keys: Option<HashSet<String>>,
+    /// Requests per client per RATE_WINDOW
+    pub rate_limit: u32,
+    /// Serve /wallets/{wallet}/activity. Off by default: an index of what each wallet did is
+    /// easier to mine than the same events scattered over the chain
+    pub wallet_activity: bool,
+}
+
+struct Api {
+    store: Mutex<Store>,
+    config: Config,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl Api {
+    fn new(store: Store, config: Config) -> Self {
+        Self { store: Mutex::new(store), config, windows: Mutex::new(HashMap::new()) }
+    }
+
+    /// Count a request by `client`, false once it is over the limit for this window
+    fn allow(&self, client: &str) -> bool {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= MAX_TRACKED_CLIENTS {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_WINDOW);
+        }
+        let (start, count) = windows.entry(client.to_string()).or_insert((now, 0));
+        if now.duration_since(*start) >= RATE_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.config.rate_limit
+    }
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    before: Option<i64>,
+    after: Option<String>,
+    limit: Option<usize>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// Serve the API on `addr` from its own thread, reading the database at `db`
+pub fn serve(addr: &str, db: &str, config: Config) -> Result<(), String> {
+    let listener = std::net::TcpListener::bind(addr).map_err(|e| format!("failed to bind {}: {}", addr, e))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let app = router(Arc::new(Api::new(Store::open_read_only(db)?, config)));
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("failed to start the API runtime: {}", e))?;
+    thread::spawn(move || {
+        runtime.block_on(async move {
+            let listener = match tokio::net::TcpListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => return eprintln!("[api] failed to listen: {}", e),
+            };
+            let service = app.into_make_service_with_connect_info::<SocketAddr>();
+            if let Err(e) = axum::serve(listener, service).await {
+                eprintln!("[api] server stopped: {}", e);
+            }
+        })
+    });
+    Ok(())
+}
+
+fn router(api: Arc<Api>) -> Router {
+    Router::new()
+        .route("/pools", get(pools))
+        .route("/pools/{pool}/tvl", get(tvl))
+        .route("/pools/{pool}/harvests", get(harvests))
+        .route("/pools/{pool}/queues", get(queues))
+        .route("/wallets/{wallet}/activity", get(wallet_activity))
+        .layer(middleware::from_fn_with_state(api.clone(), rate_limit))
+        .with_state(api)
+}
+
+async fn rate_limit(
+    State(api): State<Arc<Api>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = match &api.config.keys {
+        Some(keys) => match request.headers().get(API_KEY_HEADER).and_then(|key| key.to_str().ok()) {
+            Some(key) if keys.contains(key) => key.to_string(),
+            _ => return error(StatusCode::UNAUTHORIZED, "missing or unknown API key"),
+        },
+        None => peer.ip().to_string(),
+    };
+    if !api.allow(&client) {
+        return error(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded");
+    }
+    next.run(request).await
+}
+
+async fn pools(State(api): State<Arc<Api>>, Query(query): Query<PageQuery>) -> Response {
+    let limit = query.limit();
+    respond(read(api, move |store| store.pool_page(query.after.as_deref(), limit)).await)
+}
+
+async fn tvl(State(api): State<Arc<Api>>, Path(pool): Path<String>, Query(query): Query<PageQuery>) -> Response {
+    let Some(pool) = pubkey(&pool) else {
+        return error(StatusCode::BAD_REQUEST, "bad pool address");
+    };
+    respond(read(api, move |store| store.tvl_page(&pool, query.before, query.limit())).await)
+}
+
+async fn harvests(State(api): State<Arc<Api>>, Path(pool): Path<String>, Query(query): Query<PageQuery>) -> Response {
+    let Some(pool) = pubkey(&pool) else {
+        return error(StatusCode::BAD_REQUEST, "bad pool address");
+    };
+    respond(read(api, move |store| store.harvest_page(&pool, query.before, query.limit())).await)
+}
+
+async fn queues(State(api): State<Arc<Api>>, Path(pool): Path<String>, Query(query): Query<PageQuery>) -> Response {
+    let Some(pool) = pubkey(&pool) else {
+        return error(StatusCode::BAD_REQUEST, "bad pool address");
+    };
+    respond(read(api, move |store| store.queue_page(&pool, query.before, query.limit())).await)
+}
+
+async fn wallet_activity(
+    State(api): State<Arc<Api>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> Response {
+    if !api.config.wallet_activity {
+        return error(StatusCode::NOT_FOUND, "wallet activity is not served here");
+    }
+    let Some(wallet) = pubkey(&wallet) else {
+        return error(StatusCode::BAD_REQUEST, "bad wallet address");
+    };
+    respond(read(api, move |store| store.wallet_page(&wallet, query.before, query.limit())).await)
+}
+
+/// Run a blocking store read off the async workers
+async fn read<T: Send + 'static>(
+    api: Arc<Api>,
+    f: impl FnOnce(&Store) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(move || f(&api.store.lock().unwrap_or_else(|e| e.into_inner())))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn respond<C: serde::Serialize>(page: Result<Page<C>, String>) -> Response {
+    match page {
+        Ok(page) => Json(json!({ "items": page.items, "next": page.next })).into_response(),
+        Err(e) => {
+            eprintln!("[api] query failed: {}", e);
+            error(StatusCode::INTERNAL_SERVER_ERROR, "query failed")
+        }
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+fn pubkey(value: &str) -> Option<Pubkey> {
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::extract::connect_info::MockConnectInfo;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::store::Snapshot;
+
+    const POOL: Pubkey = Pubkey::new_from_array([7; 32]);
+
+    fn config() -> Config {
+        Config { keys: None, rate_limit: 100, wallet_activity: false }
+    }
+
+    /// An API over an in-memory store holding POOL's snapshots at slots 1..=5 and two more pools
+    fn api(config: Config) -> Arc<Api> {
+        let store = Store::open(":memory:").unwrap();
+        for slot in 1..=5 {
+            store
+                .record_snapshot(&Snapshot {
+                    pool: POOL,
+                    slot,
+                    time: slot as i64,
+                    status: "Active".to_string(),
+                    total_deposits: 0,
+                    total_withdrawals: 0,
+                    total_invested: 0,
+                    kamino_position_value: 0,
+                    cash_principal: 0,
+                    vault_balance: slot * 100,
+                    queued_withdrawals: 0,
+                    accrued_fees: 0,
+                    withdrawal_queue_len: 0,
+                    deposit_intents_len: 0,
+                    queued_deposits: 0,
+                })
+                .unwrap();
+        }
+        for pool in [POOL, Pubkey::new_from_array([1; 32]), Pubkey::new_from_array([9; 32])] {
+            store.add_pool(&pool).unwrap();
+        }
+        Arc::new(Api::new(store, config))
+    }
+
+    async fn get(api: &Arc<Api>, uri: &str, key: Option<&str>) -> (StatusCode, Value) {
+        let app = router(api.clone()).layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let mut request = axum::http::Request::builder().uri(uri);
+        if let Some(key) = key {
+            request = request.header(API_KEY_HEADER, key);
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn slots(page: &Value) -> Vec<i64> {
+        page["items"].as_array().unwrap().iter().map(|item| item["slot"].as_i64().unwrap()).collect()
+    }
+
+    #[test]
+    fn allow_counts_each_client_per_window() {
+        let api = api(Config { rate_limit: 2, ..config() });
+        assert!(api.allow("a") && api.allow("a"));
+        assert!(!api.allow("a"));
+        assert!(api.allow("b"));
+
+        // Once the window has passed the count starts over
+        let expired = Instant::now().checked_sub(RATE_WINDOW).unwrap();
+        api.windows.lock().unwrap().get_mut("a").unwrap().0 = expired;
+        assert!(api.allow("a"));
+    }
+
+    #[test]
+    fn limit_is_clamped() {
+        let query = |limit| PageQuery { before: None, after: None, limit };
+        assert_eq!(query(None).limit(), DEFAULT_LIMIT);
+        assert_eq!(query(Some(0)).limit(), 1);
+        assert_eq!(query(Some(MAX_LIMIT + 1)).limit(), MAX_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn pages_follow_the_before_cursor() {
+        let api = api(config());
+        let uri = format!("/pools/{}/tvl", POOL);
+
+        let (status, first) = get(&api, &format!("{}?limit=2", uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(slots(&first), [5, 4]);
+        assert_eq!(first["items"][0]["tvl"], 500);
+        assert_eq!(first["next"], 4);
+
+        let (_, second) = get(&api, &format!("{}?limit=2&before=4", uri), None).await;
+        assert_eq!(slots(&second), [3, 2]);
+
+        let (_, last) = get(&api, &format!("{}?limit=2&before=2", uri), None).await;
+        assert_eq!(slots(&last), [1]);
+        assert!(last["next"].is_null());
+
+        // An out-of-range limit is clamped rather than refused
+        let (status, all) = get(&api, &format!("{}?limit=0", uri), None).await;
+        assert_eq!((status, slots(&all)), (StatusCode::OK, vec![5]));
+        let (_, all) = get(&api, &format!("{}?limit=100000", uri), None).await;
+        assert_eq!(slots(&all).len(), 5);
+        assert!(all["next"].is_null());
+    }
+
+    #[tokio::test]
+    async fn pools_page_after_the_last_key() {
+        let api = api(config());
+        let mut keys = vec![POOL.to_string(), Pubkey::new_from_array([1; 32]).to_string()];
+        keys.push(Pubkey::new_from_array([9; 32]).to_string());
+        keys.sort();
+
+        let (_, first) = get(&api, "/pools?limit=2", None).await;
+        let pools: Vec<&str> = first["items"].as_array().unwrap().iter().map(|p| p["pool"].as_str().unwrap()).collect();
+        assert_eq!(pools, keys[..2]);
+        assert_eq!(first["next"], keys[1].as_str());
+
+        let (_, last) = get(&api, &format!("/pools?limit=2&after={}", keys[1]), None).await;
+        assert_eq!(last["items"].as_array().unwrap().len(), 1);
+        assert_eq!(last["items"][0]["pool"], keys[2].as_str());
+        assert!(last["next"].is_null());
+    }
+
+    #[tokio::test]
+    async fn unknown_api_keys_are_unauthorized() {
+        let keys = HashSet::from(["secret".to_string()]);
+        let api = api(Config { keys: Some(keys), ..config() });
+        assert_eq!(get(&api, "/pools", None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(get(&api, "/pools", Some("guess")).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(get(&api, "/pools", Some("secret")).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn clients_over_the_limit_are_turned_away() {
+        let api = api(Config { rate_limit: 1, ..config() });
+        assert_eq!(get(&api, "/pools", None).await.0, StatusCode::OK);
+        let (status, body) = get(&api, "/pools", None).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["error"], "rate limit exceeded");
+    }
+
+    #[tokio::test]
+    async fn wallet_activity_is_off_unless_enabled() {
+        let uri = format!("/wallets/{}/activity", POOL);
+        assert_eq!(get(&api(config()), &uri, None).await.0, StatusCode::NOT_FOUND);
+
+        let api = api(Config { wallet_activity: true, ..config() });
+        let (status, body) = get(&api, &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["next"].is_null());
+    }
+}
@@ -0,0 +1,265 @@
+//! Program logs to events: every ghost_pool event (Anchor or packed) and the mock Kamino events
+//! (the CLI's watch command includes this file too)
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ghost_pool::{
+    AdminActionKind, OpKind, PackedEvent, PoolStatus, PositionStatus, WithdrawalFailure, KAMINO_LENDING_PROGRAM_ID,
+    PACKED_EVENT_MAGIC,
+};
+use serde_json::{Map, Value};
+
+/// A decoded event; `pool` is set for ghost_pool events, the rest of the fields go in `fields`
+pub struct Event {
+    pub program: &'static str,
+    pub name: &'static str,
+    pub pool: Option<Pubkey>,
+    pub fields: Map<String, Value>,
+}
+
+/// `Program data:` payloads in `logs`, each with the program that logged it
+/// Attribution follows the invoke/success lines, since ghost_pool and mock_kamino share event names
+pub fn program_data(logs: &[String]) -> Vec<(Pubkey, Vec<u8>)> {
+    let mut stack: Vec<Pubkey> = Vec::new();
+    let mut out = Vec::new();
+    for log in logs {
+        if let Some(data) = log.strip_prefix("Program data: ") {
+            if let (Some(program), Ok(data)) = (stack.last(), STANDARD.decode(data)) {
+                out.push((*program, data));
+            }
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            let program = words.next().and_then(|word| word.parse::<Pubkey>().ok());
+            match (program, words.next()) {
+                (Some(program), Some("invoke")) => stack.push(program),
+                (Some(_), Some("success" | "failed:")) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
+/// Packed events leave the pool key out; the caller has to find it in the transaction
+pub fn is_packed(program: &Pubkey, data: &[u8]) -> bool {
+    *program == ghost_pool::ID && data.starts_with(&PACKED_EVENT_MAGIC)
+}
+
+/// Decode one payload, `pool` being the transaction's pool for packed events
+pub fn decode(program: &Pubkey, data: &[u8], pool: Option<&Pubkey>) -> Option<Event> {
+    if is_packed(program, data) {
+        ghost_pool_event(&unpack(data, pool?)?)
+    } else if *program == ghost_pool::ID {
+        ghost_pool_event(data)
+    } else if *program == KAMINO_LENDING_PROGRAM_ID {
+        kamino_event(data)
+    } else {
+        None
+    }
+}
+
+/// Rebuild the Anchor encoding of a packed event: `PackedEvent::pack` writes the fields after
+/// the pool key in declaration order, little-endian, which is exactly their borsh layout
+fn unpack(data: &[u8], pool: &Pubkey) -> Option<Vec<u8>> {
+    use ghost_pool::{
+        DepositEvent, WithdrawalAuthorizedEvent, WithdrawalClaimedEvent, WithdrawalCompletedEvent,
+        WithdrawalFailedEvent,
+    };
+
+    let tag = *data.get(PACKED_EVENT_MAGIC.len())?;
+    let discriminator = match tag {
+        DepositEvent::TAG => DepositEvent::DISCRIMINATOR,
+        WithdrawalAuthorizedEvent::TAG => WithdrawalAuthorizedEvent::DISCRIMINATOR,
+        WithdrawalClaimedEvent::TAG => WithdrawalClaimedEvent::DISCRIMINATOR,
+        WithdrawalCompletedEvent::TAG => WithdrawalCompletedEvent::DISCRIMINATOR,
+        WithdrawalFailedEvent::TAG => WithdrawalFailedEvent::DISCRIMINATOR,
+        _ => return None,
+    };
+    Some([discriminator, pool.as_ref(), &data[PACKED_EVENT_MAGIC.len() + 1..]].concat())
+}
+
+/// Event field as JSON: u128 and keys as strings (past the JSON/SQLite integer range), enums by name
+trait Field {
+    fn json(&self) -> Value;
+}
+
+macro_rules! number_fields {
+    ($($ty:ty),*) => {
+        $(impl Field for $ty {
+            fn json(&self) -> Value {
+                Value::from(*self)
+            }
+        })*
+    };
+}
+
+macro_rules! enum_fields {
+    ($($ty:ty),*) => {
+        $(impl Field for $ty {
+            fn json(&self) -> Value {
+                Value::String(format!("{:?}", self))
+            }
+        })*
+    };
+}
+
+number_fields!(u8, u16, u64, i64, bool);
+enum_fields!(AdminActionKind, OpKind, PoolStatus, PositionStatus, WithdrawalFailure);
+
+impl Field for u128 {
+    fn json(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl Field for String {
+    fn json(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl Field for Pubkey {
+    fn json(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl Field for [u8; 32] {
+    fn json(&self) -> Value {
+        Value::String(self.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+/// Try each event's discriminator in turn; `|e| pool` picks the pool column out of the event
+macro_rules! anchor_events {
+    ($program:literal, $data:ident, |$e:ident| $pool:expr; $($event:ident { $($field:ident),* })*) => {
+        $(if $data.starts_with($event::DISCRIMINATOR) {
+            let $e = <$event as AnchorDeserialize>::deserialize(&mut &$data[$event::DISCRIMINATOR.len()..]).ok()?;
+            let mut fields = Map::new();
+            $(fields.insert(stringify!($field).to_string(), $e.$field.json());)*
+            return Some(Event { program: $program, name: stringify!($event), pool: $pool, fields });
+        })*
+    };
+}
+
+fn ghost_pool_event(data: &[u8]) -> Option<Event> {
+    use ghost_pool::{
+        AdminActionCancelledEvent, AdminActionProposedEvent, AdminMultisigUpdatedEvent, AuthorityTransferredEvent,
+        CashRedeemedEvent, CashSweptEvent, CircuitUpgradedEvent, ClusterRotationAcknowledgedEvent,
+        ClusterRotationDetectedEvent, CompoundedEvent, ComputationExpiredEvent, ComputationFailedEvent,
+        ComputationQueuedEvent, CounterEpochClosedEvent, CrankBountyClaimedEvent, CrankBountyCreditedEvent,
+        DepositBatchProcessedEvent, DepositEvent, DepositIntentQueuedEvent, DepositIntentRejectedEvent,
+        DepositReclaimedEvent, DepositRecoveredEvent, DepositRejectedEvent, DepositSwappedEvent, DepositToppedUpEvent,
+        FeesCollectedEvent, InvestmentApprovedEvent, InvestmentExecutedEvent, InvestmentRecordedEvent,
+        LegacyDepositMigratedEvent, LegacyPoolFrozenEvent, LendingProgramChangedEvent, LendingProgramProposedEvent,
+        PasswordRotatedEvent, PayoutFailedEvent, PayoutRecreditedEvent, PoolClosedEvent, PoolConfigUpdatedEvent,
+        PoolInitializedEvent, PoolMigratedEvent, PoolPauseChangedEvent, PoolStatsEvent, PoolStatusChangedEvent,
+        PositionStatusChangedEvent, PositionSyncedEvent, PositionTransferredEvent, ReconciliationEvent,
+        RedemptionExecutedEvent, ReferralRewardsClaimedEvent, RelayerRebatePaidEvent, RotatorForwardedEvent,
+        ShardInitializedEvent, ShareTokensEnabledEvent, StateNonceResyncedEvent, StateUpgradedEvent,
+        StrategyWeightsUpdatedEvent, TvlPublishedEvent, WithdrawalAuthorizedEvent, WithdrawalClaimedEvent,
+        WithdrawalCompletedEvent, WithdrawalFailedEvent, WithdrawalQueuedEvent, YieldHarvestedEvent, YieldRecordedEvent,
+    };
+
+    anchor_events!("ghost_pool", data, |e| Some(e.pool);
+        PoolInitializedEvent { authority }
+        ShardInitializedEvent { shard, shard_index }
+        ShareTokensEnabledEvent { share_mint }
+        CounterEpochClosedEvent { bucketed_deposits, bucketed_withdrawals }
+        TvlPublishedEvent { tvl, bucket, slot }
+        PoolStatsEvent { active_depositors, median_principal, unclaimed_yield, bucket, slot }
+        RotatorForwardedEvent { amount }
+        InvestmentRecordedEvent { amount, computation_offset, state_nonce_before, state_nonce_after, timestamp }
+        YieldHarvestedEvent { yield_amount, exchange_rate, fee_amount }
+        FeesCollectedEvent { amount, recipient }
+        StateNonceResyncedEvent { previous_nonce, nonce }
+        ComputationExpiredEvent { computation_offset, kind }
+        ComputationFailedEvent { kind, computation_offset }
+        DepositReclaimedEvent { user, amount, computation_offset }
+        YieldRecordedEvent {
+            position_value, yield_amount, fee_amount, computation_offset, state_nonce_before, state_nonce_after,
+            timestamp
+        }
+        LegacyPoolFrozenEvent { slot }
+        LegacyDepositMigratedEvent { shard_index, moved, legacy_retired }
+        PositionTransferredEvent {}
+        DepositRecoveredEvent { beneficiary, amount }
+        CrankBountyCreditedEvent { keeper, amount }
+        CrankBountyClaimedEvent { keeper, amount }
+        CompoundedEvent { amount, new_total_invested }
+        StrategyWeightsUpdatedEvent {}
+        PasswordRotatedEvent { rotation_count }
+        DepositEvent { deposit_count, amount, computation_offset, state_nonce_before, state_nonce_after, timestamp }
+        PoolClosedEvent { vault_swept, collateral_swept, forced }
+        PoolMigratedEvent { from_version, to_version }
+        StateUpgradedEvent { account }
+        ReferralRewardsClaimedEvent { referrer_token_account, amount }
+        RelayerRebatePaidEvent { relayer_token_account, amount }
+        DepositIntentQueuedEvent { position }
+        DepositIntentRejectedEvent { amount, refunded }
+        DepositBatchProcessedEvent { processed, accepted }
+        DepositRejectedEvent { amount, ledger_full, over_user_cap }
+        DepositToppedUpEvent { amount }
+        DepositSwappedEvent { swap_mint, amount_in, amount_out }
+        InvestmentApprovedEvent { amount }
+        InvestmentExecutedEvent { amount, collateral_received, total_collateral_received }
+        AuthorityTransferredEvent { previous_authority, new_authority }
+        AdminMultisigUpdatedEvent { admin_multisig, threshold, member_count }
+        LendingProgramProposedEvent { lending_program, effective_slot }
+        LendingProgramChangedEvent { previous_lending_program, lending_program }
+        PoolConfigUpdatedEvent {
+            investment_threshold, min_deposit, max_deposit_per_user, max_pool_cap, investment_cooldown_secs,
+            liquidity_buffer_bps, withdrawal_cap_per_epoch, min_slots_between_checks
+        }
+        PoolPauseChangedEvent { paused }
+        ClusterRotationDetectedEvent { expected_key_hash, observed_key_hash }
+        ClusterRotationAcknowledgedEvent { previous_key_hash, mxe_key_hash }
+        PoolStatusChangedEvent { from, to }
+        PositionStatusChangedEvent { user, from, to }
+        CashSweptEvent { amount, collateral_amount }
+        CashRedeemedEvent { collateral_amount, liquidity_amount, yield_amount }
+        RedemptionExecutedEvent { collateral_amount, liquidity_amount }
+        PositionSyncedEvent { collateral_amount, exchange_rate, position_value, kamino_position_value }
+        ReconciliationEvent {
+            vault_balance, earmarked, vault_delta, kamino_position_value, total_invested, position_delta, balanced,
+            needs_attention
+        }
+        WithdrawalAuthorizedEvent { user, amount, idx, computation_offset, timestamp }
+        WithdrawalFailedEvent { user, reason, computation_offset, timestamp }
+        WithdrawalClaimedEvent { amount }
+        WithdrawalQueuedEvent { amount, position }
+        PayoutFailedEvent { attempts }
+        PayoutRecreditedEvent { amount }
+        WithdrawalCompletedEvent {
+            user, amount, computation_offset, state_nonce_before, state_nonce_after, timestamp
+        }
+        ComputationQueuedEvent { kind, computation_offset, state_nonce, slot, timestamp }
+    );
+    // Program-wide, or aimed at either a pool or the circuit registry: no pool column
+    anchor_events!("ghost_pool", data, |e| None;
+        CircuitUpgradedEvent { circuit, url, hash, version }
+        AdminActionProposedEvent { target, kind, eta_slot }
+        AdminActionCancelledEvent { target, kind }
+    );
+    None
+}
+
+fn kamino_event(data: &[u8]) -> Option<Event> {
+    use mock_kamino::{
+        BorrowEvent, DepositEvent, RedeemEvent, RepayEvent, ReserveConfigUpdatedEvent, ReserveLimitsUpdatedEvent,
+    };
+
+    anchor_events!("mock_kamino", data, |e| None;
+        DepositEvent { reserve, liquidity_amount, collateral_amount, exchange_rate }
+        RedeemEvent { reserve, collateral_amount, liquidity_amount, exchange_rate }
+        BorrowEvent { reserve, obligation, liquidity_amount, borrowed_liquidity, exchange_rate }
+        RepayEvent { reserve, obligation, liquidity_amount, borrowed_liquidity, exchange_rate }
+        ReserveConfigUpdatedEvent { reserve, yield_rate_bps, deposit_cap, base_borrow_rate_bps, borrow_rate_slope_bps }
+        ReserveLimitsUpdatedEvent { reserve, pause_flags, max_withdraw_per_slot, fail_until_slot }
+    );
+    None
+}
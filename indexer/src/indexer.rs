@@ -0,0 +1,352 @@
+//! Catching up on the programs' transactions over RPC, backfilling an address's history, and
+//! snapshotting the pools they touched
+
+use std::collections::HashSet;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use ghost_pool::{DepositIntentQueue, GhostPool, WithdrawalQueue, KAMINO_LENDING_PROGRAM_ID};
+use ghost_pool_client::{deposit_intents_address, vault_address, withdrawal_queue_address};
+use solana_client::pubsub_client::PubsubClient;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::option_serializer::OptionSerializer;
+use solana_transaction_status_client_types::{UiTransactionEncoding, UiTransactionStatusMeta, UiTransactionTokenBalance};
+
+use crate::decode::{self, Event};
+use crate::store::{Listed, Progress, Recorded, Snapshot, Store};
+
+/// Programs whose transactions are indexed: ghost_pool, and the (mock) Kamino program it invests through
+pub const PROGRAMS: [Pubkey; 2] = [ghost_pool::ID, KAMINO_LENDING_PROGRAM_ID];
+
+/// getSignaturesForAddress page size (the RPC maximum)
+const PAGE: usize = 1_000;
+
+/// Delay before resubscribing after the websocket drops
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+pub struct Indexer {
+    rpc: RpcClient,
+    store: Store,
+    pools: HashSet<Pubkey>,
+    touched: HashSet<Pubkey>,
+}
+
+impl Indexer {
+    pub fn new(url: &str, store: Store) -> Result<Self, String> {
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            pools: store.pools()?.into_iter().collect(),
+            store,
+            touched: HashSet::new(),
+        })
+    }
+
+    pub fn pools(&self) -> Vec<Pubkey> {
+        self.pools.iter().copied().collect()
+    }
+
+    /// Pools with events indexed since the last call
+    pub fn take_touched(&mut self) -> HashSet<Pubkey> {
+        std::mem::take(&mut self.touched)
+    }
+
+    /// Index every transaction of `program` after its cursor, oldest first, returning how many
+    /// The cursor moves with each transaction, so an error part-way resumes from there
+    pub fn catch_up(&mut self, program: &Pubkey) -> Result<usize, String> {
+        let until = self
+            .store
+            .cursor(program)?
+            .map(|signature| signature.parse::<Signature>())
+            .transpose()
+            .map_err(|e| format!("bad cursor: {}", e))?;
+
+        let mut pending = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    program,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until,
+                        limit: Some(PAGE),
+                        commitment: Some(self.rpc.commitment()),
+                    },
+                )
+                .map_err(|e| format!("failed to list signatures: {}", e))?;
+            let full = page.len() == PAGE;
+            before = page.last().and_then(|status| status.signature.parse().ok());
+            pending.extend(page);
+            if !full || before.is_none() {
+                break;
+            }
+        }
+
+        for status in pending.iter().rev() {
+            self.index(Progress::Cursor(program), &listed(status))?;
+        }
+        Ok(pending.len())
+    }
+
+    /// Index `address`'s history back to its first transaction, returning how many were indexed
+    /// Signatures are listed into the store a page at a time, then indexed oldest first; both
+    /// steps checkpoint as they go, so an interrupted backfill resumes where it stopped and a
+    /// rerun indexes nothing twice
+    pub fn backfill(&mut self, address: &Pubkey) -> Result<usize, String> {
+        let mut state = self.store.backfill(address)?;
+        while !state.as_ref().is_some_and(|state| state.listed) {
+            let before = state
+                .as_ref()
+                .map(|state| state.oldest.parse::<Signature>())
+                .transpose()
+                .map_err(|e| format!("bad backfill checkpoint: {}", e))?;
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(PAGE),
+                        commitment: Some(self.rpc.commitment()),
+                    },
+                )
+                .map_err(|e| format!("failed to list signatures: {}", e))?;
+            let page: Vec<Listed> = page.iter().map(listed).collect();
+            if state.is_none() && PROGRAMS.contains(address) {
+                if let Some(newest) = page.first() {
+                    self.store.start_cursor(address, &newest.signature)?;
+                }
+            }
+            self.store.queue_backfill(address, &page, page.len() < PAGE)?;
+            if page.is_empty() {
+                break;
+            }
+            println!("[{}] listed back to slot {}", address, page.last().map_or(0, |entry| entry.slot));
+            state = self.store.backfill(address)?;
+        }
+
+        let mut indexed = 0;
+        loop {
+            let batch = self.store.backfill_batch(address, PAGE)?;
+            if batch.is_empty() {
+                return Ok(indexed);
+            }
+            for entry in &batch {
+                self.index(Progress::Backfill(address), entry)?;
+            }
+            indexed += batch.len();
+            let slot = batch.last().map_or(0, |entry| entry.slot);
+            println!("[{}] indexed {} transactions, up to slot {}", address, indexed, slot);
+        }
+    }
+
+    fn index(&mut self, progress: Progress, entry: &Listed) -> Result<(), String> {
+        // Failed transactions roll their events back, but still count as progress
+        if entry.failed {
+            let recorded = Recorded {
+                signature: &entry.signature,
+                slot: entry.slot,
+                block_time: entry.block_time,
+                succeeded: false,
+                events: &[],
+                vault_balances: &[],
+            };
+            return self.store.record_transaction(progress, &recorded);
+        }
+
+        let signature: Signature = entry.signature.parse().map_err(|e| format!("bad signature: {}", e))?;
+        let tx = self
+            .rpc
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(self.rpc.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .map_err(|e| format!("failed to fetch {}: {}", signature, e))?;
+        // Pools are never looked up through address tables, so the static keys are enough
+        let keys = tx
+            .transaction
+            .transaction
+            .decode()
+            .map(|decoded| decoded.message.static_account_keys().to_vec())
+            .unwrap_or_default();
+        let meta = tx.transaction.meta;
+        let logs = meta
+            .as_ref()
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages.clone()))
+            .unwrap_or_default();
+
+        let payloads = decode::program_data(&logs);
+        let pool = if payloads.iter().any(|(program, data)| decode::is_packed(program, data)) {
+            self.pool_among(&keys)?
+        } else {
+            None
+        };
+        let events: Vec<Event> = payloads
+            .iter()
+            .filter_map(|(program, data)| decode::decode(program, data, pool.as_ref()))
+            .collect();
+
+        // Vaults of the known pools among the accounts, and of any pool an event named
+        let mut pools: Vec<Pubkey> = keys.iter().filter(|key| self.pools.contains(key)).copied().collect();
+        pools.extend(events.iter().filter_map(|event| event.pool));
+        pools.sort();
+        pools.dedup();
+        let vault_balances: Vec<(Pubkey, u64)> = match &meta {
+            Some(meta) => pools
+                .iter()
+                .filter_map(|pool| Some((*pool, vault_change(meta, &keys, &vault_address(pool))?)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let recorded = Recorded {
+            signature: &entry.signature,
+            slot: tx.slot,
+            block_time: tx.block_time,
+            succeeded: true,
+            events: &events,
+            vault_balances: &vault_balances,
+        };
+        self.store.record_transaction(progress, &recorded)?;
+
+        for pool in events.iter().filter_map(|event| event.pool) {
+            self.pools.insert(pool);
+            self.touched.insert(pool);
+        }
+        Ok(())
+    }
+
+    /// The pool a packed event belongs to: a known pool among the transaction's accounts, else
+    /// whichever of them is a GhostPool account
+    fn pool_among(&mut self, keys: &[Pubkey]) -> Result<Option<Pubkey>, String> {
+        if let Some(pool) = keys.iter().find(|key| self.pools.contains(key)) {
+            return Ok(Some(*pool));
+        }
+        let accounts = self
+            .rpc
+            .get_multiple_accounts(keys)
+            .map_err(|e| format!("failed to fetch the transaction's accounts: {}", e))?;
+        let pool = keys
+            .iter()
+            .zip(accounts)
+            .find(|(_, account)| {
+                account.as_ref().is_some_and(|account| {
+                    account.owner == ghost_pool::ID && account.data.starts_with(GhostPool::DISCRIMINATOR)
+                })
+            })
+            .map(|(key, _)| *key);
+        if let Some(pool) = pool {
+            self.store.add_pool(&pool)?;
+            self.pools.insert(pool);
+        }
+        Ok(pool)
+    }
+
+    /// Record the pool's public counters and queue depths as of now; closed pools are skipped
+    pub fn snapshot(&self, pool: &Pubkey) -> Result<(), String> {
+        let response = self
+            .rpc
+            .get_account_with_commitment(pool, self.rpc.commitment())
+            .map_err(|e| format!("failed to fetch {}: {}", pool, e))?;
+        let Some(account) = response.value else {
+            return Ok(());
+        };
+        let state = GhostPool::try_deserialize(&mut account.data.as_slice())
+            .map_err(|e| format!("failed to decode {}: {}", pool, e))?;
+        let vault = self
+            .rpc
+            .get_token_account_balance(&vault_address(pool))
+            .map_err(|e| format!("failed to fetch the vault balance of {}: {}", pool, e))?;
+        // Either queue is only created with the feature that uses it
+        let queues = self
+            .rpc
+            .get_multiple_accounts(&[withdrawal_queue_address(pool), deposit_intents_address(pool)])
+            .map_err(|e| format!("failed to fetch the queues of {}: {}", pool, e))?;
+        let withdrawal_queue_len = queues[0]
+            .as_ref()
+            .and_then(|account| WithdrawalQueue::try_deserialize(&mut account.data.as_slice()).ok())
+            .map_or(0, |queue| queue.tail - queue.head);
+        let deposit_intents_len = queues[1]
+            .as_ref()
+            .and_then(|account| DepositIntentQueue::try_deserialize(&mut account.data.as_slice()).ok())
+            .map_or(0, |queue| queue.tail - queue.head);
+        self.store.record_snapshot(&Snapshot {
+            pool: *pool,
+            slot: response.context.slot,
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64),
+            status: format!("{:?}", state.status),
+            total_deposits: state.total_deposits,
+            total_withdrawals: state.total_withdrawals,
+            total_invested: state.total_invested,
+            kamino_position_value: state.kamino_position_value,
+            cash_principal: state.cash_principal,
+            vault_balance: vault.amount.parse().map_err(|_| "bad vault balance".to_string())?,
+            queued_withdrawals: state.queued_withdrawals,
+            accrued_fees: state.accrued_fees,
+            withdrawal_queue_len,
+            deposit_intents_len,
+            queued_deposits: state.queued_deposits,
+        })
+    }
+}
+
+fn listed(status: &RpcConfirmedTransactionStatusWithSignature) -> Listed {
+    Listed {
+        signature: status.signature.clone(),
+        slot: status.slot,
+        block_time: status.block_time,
+        failed: status.err.is_some(),
+    }
+}
+
+/// `vault`'s balance after the transaction, if the transaction changed it
+fn vault_change(meta: &UiTransactionStatusMeta, keys: &[Pubkey], vault: &Pubkey) -> Option<u64> {
+    let index = keys.iter().position(|key| key == vault)?;
+    let balance = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>| {
+        Option::<&Vec<UiTransactionTokenBalance>>::from(balances.as_ref())?
+            .iter()
+            .find(|balance| balance.account_index as usize == index)
+            .and_then(|balance| balance.ui_token_amount.amount.parse::<u64>().ok())
+    };
+    let after = balance(&meta.post_token_balances)?;
+    (balance(&meta.pre_token_balances) != Some(after)).then_some(after)
+}
+
+/// Wake the indexer whenever a confirmed transaction mentions `program`, resubscribing when
+/// the socket drops; polling still catches whatever a dropped socket missed
+pub fn subscribe(ws_url: String, program: Pubkey, wakeups: Sender<()>) {
+    thread::spawn(move || loop {
+        let subscription = PubsubClient::logs_subscribe(
+            &ws_url,
+            RpcTransactionLogsFilter::Mentions(vec![program.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        );
+        match subscription {
+            Ok((_subscription, logs)) => {
+                for _ in logs.iter() {
+                    if wakeups.send(()).is_err() {
+                        return;
+                    }
+                }
+                eprintln!("[{}] subscription closed, resubscribing", program);
+            }
+            Err(e) => eprintln!("[{}] failed to subscribe to {}: {}", program, ws_url, e),
+        }
+        thread::sleep(RESUBSCRIBE_DELAY);
+    });
+}
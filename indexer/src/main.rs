@@ -0,0 +1,188 @@
+//! Ghost Pool indexer
+//!
+//! Decodes every ghost_pool event (Anchor and packed) and the mock Kamino deposit/redeem and
+//! borrow/repay events into SQLite, along with snapshots of the public counters of each pool
+//! they touch.
+//! Transactions are fetched over RPC from a per-program cursor, so a restart picks up where
+//! the last run stopped; a logs subscription on each program wakes it between polls.
+//!
+//! With `--api <host:port>` it also serves the indexed data over HTTP (see `api.rs`), keyed
+//! by the keys in `--api-keys` (one per line) if given.
+//!
+//! `ghost-pool-indexer backfill` instead indexes the full history of `--pool` (or of both
+//! programs) into the same database and exits. It checkpoints in the database as it goes, so it
+//! can be stopped and rerun at any point, alongside the live indexer or not.
+//!
+//! Reads the Solana CLI RPC URL (`--config`, `--url` and `--ws-url` override it). See
+//! `store.rs` for the schema and the views dashboards query.
+
+use std::collections::{HashMap, HashSet};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use solana_cli_config::{Config, CONFIG_FILE};
+
+mod api;
+mod decode;
+mod indexer;
+mod store;
+
+use indexer::{Indexer, PROGRAMS};
+use store::Store;
+
+const USAGE: &str = "usage: ghost-pool-indexer [--flag <value> ...]
+       ghost-pool-indexer backfill [--pool <pool>] [--db <path>] [--config <path>] [--url <rpc>]
+
+flags: --db <path> --config <path> --url <rpc> --ws-url <rpc ws> --poll-secs <s>
+       --api <host:port> --api-keys <path> --rate-limit <requests/min> --wallet-activity <true|false>";
+
+const DEFAULT_DB: &str = "ghost-pool-indexer.sqlite";
+const DEFAULT_POLL_SECS: u64 = 30;
+const DEFAULT_RATE_LIMIT: u32 = 120;
+
+/// `--name value` pairs
+struct Flags(HashMap<String, String>);
+
+impl Flags {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut flags = HashMap::new();
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            if !flag.starts_with("--") {
+                return Err(format!("unexpected argument {:?}", flag));
+            }
+            let value = it.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            flags.insert(flag.clone(), value.clone());
+        }
+        Ok(Self(flags))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.get(name) {
+            Some(value) => value.parse().map_err(|_| format!("bad {} {:?}", name, value)),
+            None => Ok(default),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("backfill") => Flags::parse(&args[1..]).and_then(|flags| backfill(&flags)),
+        _ => Flags::parse(&args).and_then(|flags| run(&flags)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ghost-pool-indexer: {}", e);
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// API keys, one per line; blank lines and `#` comments are skipped
+fn read_keys(path: &str) -> Result<HashSet<String>, String> {
+    let keys = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    Ok(keys
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+fn config(flags: &Flags) -> Result<Config, String> {
+    match flags.get("--config") {
+        Some(path) => Config::load(path).map_err(|e| format!("failed to load {}: {}", path, e)),
+        None => Ok(CONFIG_FILE
+            .as_ref()
+            .and_then(|path| Config::load(path).ok())
+            .unwrap_or_default()),
+    }
+}
+
+/// Index the history of `--pool`, or of every program, then snapshot the pools it touched
+fn backfill(flags: &Flags) -> Result<(), String> {
+    let config = config(flags)?;
+    let url = flags.get("--url").unwrap_or(&config.json_rpc_url);
+    let db = flags.get("--db").unwrap_or(DEFAULT_DB);
+    let addresses = match flags.get("--pool") {
+        Some(pool) => vec![pool.parse().map_err(|_| format!("bad --pool {:?}", pool))?],
+        None => PROGRAMS.to_vec(),
+    };
+
+    let mut indexer = Indexer::new(url, Store::open(db)?)?;
+    for address in &addresses {
+        let indexed = indexer.backfill(address)?;
+        println!("[{}] backfill done, {} transactions indexed", address, indexed);
+    }
+    for pool in indexer.take_touched() {
+        if let Err(e) = indexer.snapshot(&pool) {
+            eprintln!("[{}] snapshot failed: {}", pool, e);
+        }
+    }
+    Ok(())
+}
+
+fn run(flags: &Flags) -> Result<(), String> {
+    let config = config(flags)?;
+    let url = flags.get("--url").unwrap_or(&config.json_rpc_url).to_string();
+    let ws_url = match flags.get("--ws-url") {
+        Some(ws_url) => ws_url.to_string(),
+        None if flags.get("--url").is_some() => Config::compute_websocket_url(&url),
+        None => config.websocket_url.clone(),
+    };
+    let db = flags.get("--db").unwrap_or(DEFAULT_DB);
+    let poll = Duration::from_secs(flags.parse_or("--poll-secs", DEFAULT_POLL_SECS)?);
+
+    let mut indexer = Indexer::new(&url, Store::open(db)?)?;
+    if let Some(addr) = flags.get("--api") {
+        let keys = match flags.get("--api-keys") {
+            Some(path) => Some(read_keys(path)?),
+            None => None,
+        };
+        let config = api::Config {
+            keys,
+            rate_limit: flags.parse_or("--rate-limit", DEFAULT_RATE_LIMIT)?,
+            wallet_activity: flags.parse_or("--wallet-activity", false)?,
+        };
+        api::serve(addr, db, config)?;
+        println!("serving the API on {}", addr);
+    }
+    let (wake, wakeups) = mpsc::channel();
+    for program in PROGRAMS {
+        indexer::subscribe(ws_url.clone(), program, wake.clone());
+    }
+    println!("indexing {} into {}", url, db);
+
+    // Every known pool gets a fresh snapshot on start, then only those with new events
+    let mut snapshot = indexer.pools();
+    loop {
+        for program in PROGRAMS {
+            match indexer.catch_up(&program) {
+                Ok(0) => {}
+                Ok(indexed) => println!("[{}] indexed {} transactions", program, indexed),
+                Err(e) => eprintln!("[{}] catch-up failed: {}", program, e),
+            }
+        }
+        snapshot.extend(indexer.take_touched());
+        snapshot.sort();
+        snapshot.dedup();
+        for pool in snapshot.drain(..) {
+            if let Err(e) = indexer.snapshot(&pool) {
+                eprintln!("[{}] snapshot failed: {}", pool, e);
+            }
+        }
+
+        // One catch-up covers every notification that arrived meanwhile
+        let _ = wakeups.recv_timeout(poll);
+        while wakeups.try_recv().is_ok() {}
+    }
+}
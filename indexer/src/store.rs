@@ -0,0 +1,605 @@
+//! SQLite schema and writes
+//!
+//! `events` holds every decoded event, its fields as JSON in `data`. `pool_snapshots` holds
+//! the public counters of a pool as of the slot they were read at; events can't rebuild them
+//! (redemptions retire a share of principal nobody logs), so TVL and invested history start
+//! when the indexer first saw the pool; `vault_balances`, which a backfill also fills, reaches
+//! back further for the vault's share. The views on top are what dashboards read, the paged
+//! reads at the bottom what the HTTP API serves.
+
+use anchor_lang::prelude::Pubkey;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Params};
+use serde_json::{Map, Value};
+
+use crate::decode::Event;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cursors (
+    program TEXT PRIMARY KEY,
+    signature TEXT NOT NULL                  -- Newest signature indexed for this program
+);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    succeeded INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS events (
+    signature TEXT NOT NULL,
+    log_index INTEGER NOT NULL,              -- Position among the transaction's events
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    program TEXT NOT NULL,                   -- ghost_pool or mock_kamino
+    name TEXT NOT NULL,
+    pool TEXT,                               -- NULL for mock_kamino events
+    data TEXT NOT NULL,
+    PRIMARY KEY (signature, log_index)
+);
+CREATE INDEX IF NOT EXISTS events_by_name ON events (name, pool, slot);
+
+CREATE TABLE IF NOT EXISTS pools (
+    pool TEXT PRIMARY KEY
+);
+
+-- A pool vault's balance after each indexed transaction that moved it
+CREATE TABLE IF NOT EXISTS vault_balances (
+    pool TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    balance INTEGER NOT NULL,
+    PRIMARY KEY (pool, signature)
+);
+
+-- One row per backfilled address; listing resumes before `oldest` until `listed`
+CREATE TABLE IF NOT EXISTS backfills (
+    address TEXT PRIMARY KEY,
+    oldest TEXT NOT NULL,                    -- Oldest signature listed so far
+    listed INTEGER NOT NULL                  -- Listing reached the address's first transaction
+);
+
+-- Signatures a backfill listed and hasn't indexed yet, each deleted with the transaction it records
+CREATE TABLE IF NOT EXISTS backfill_queue (
+    address TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    failed INTEGER NOT NULL,
+    PRIMARY KEY (address, signature)
+);
+
+CREATE TABLE IF NOT EXISTS pool_snapshots (
+    pool TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    total_deposits INTEGER NOT NULL,
+    total_withdrawals INTEGER NOT NULL,
+    total_invested INTEGER NOT NULL,
+    kamino_position_value INTEGER NOT NULL,
+    cash_principal INTEGER NOT NULL,
+    vault_balance INTEGER NOT NULL,
+    queued_withdrawals INTEGER NOT NULL,
+    accrued_fees INTEGER NOT NULL,
+    PRIMARY KEY (pool, slot)
+);
+
+-- Taken with each pool snapshot, at the same slot
+CREATE TABLE IF NOT EXISTS queue_snapshots (
+    pool TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    withdrawals INTEGER NOT NULL,            -- Entries waiting in the WithdrawalQueue
+    withdrawal_amount INTEGER NOT NULL,      -- queued_withdrawals
+    deposit_intents INTEGER NOT NULL,        -- Entries waiting in the DepositIntentQueue
+    deposit_amount INTEGER NOT NULL,         -- queued_deposits
+    PRIMARY KEY (pool, slot)
+);
+
+CREATE VIEW IF NOT EXISTS daily_deposits AS
+SELECT pool, date(block_time, 'unixepoch') AS day, COUNT(*) AS deposits,
+       SUM(json_extract(data, '$.amount')) AS amount
+FROM events WHERE program = 'ghost_pool' AND name = 'DepositEvent'
+GROUP BY pool, day;
+
+CREATE VIEW IF NOT EXISTS daily_withdrawals AS
+SELECT pool, date(block_time, 'unixepoch') AS day, COUNT(*) AS withdrawals,
+       SUM(json_extract(data, '$.amount')) AS amount
+FROM events WHERE program = 'ghost_pool' AND name = 'WithdrawalCompletedEvent'
+GROUP BY pool, day;
+
+CREATE VIEW IF NOT EXISTS daily_yield AS
+SELECT pool, date(block_time, 'unixepoch') AS day,
+       SUM(json_extract(data, '$.yield_amount')) AS yield_amount,
+       SUM(json_extract(data, '$.fee_amount')) AS fee_amount
+FROM events WHERE program = 'ghost_pool' AND name = 'YieldRecordedEvent'
+GROUP BY pool, day;
+
+-- Same bound as max_pool_cap: vault + Kamino position + cash principal
+CREATE VIEW IF NOT EXISTS tvl_history AS
+SELECT pool, slot, time, vault_balance + kamino_position_value + cash_principal AS tvl,
+       total_invested, kamino_position_value
+FROM pool_snapshots;
+
+-- What publish_tvl disclosed: total deposited, noise-padded and rounded to `bucket`
+CREATE VIEW IF NOT EXISTS published_tvl AS
+SELECT pool, slot, block_time, json_extract(data, '$.tvl') AS tvl, json_extract(data, '$.bucket') AS bucket
+FROM events WHERE program = 'ghost_pool' AND name = 'TvlPublishedEvent';
+
+-- What publish_stats disclosed, amounts rounded to `bucket`
+CREATE VIEW IF NOT EXISTS published_stats AS
+SELECT pool, slot, block_time, json_extract(data, '$.active_depositors') AS active_depositors,
+       json_extract(data, '$.median_principal') AS median_principal,
+       json_extract(data, '$.unclaimed_yield') AS unclaimed_yield, json_extract(data, '$.bucket') AS bucket
+FROM events WHERE program = 'ghost_pool' AND name = 'PoolStatsEvent';
+
+-- The reserve's rate from mock_kamino deposits/redeems/borrows/repays, the pool's from each harvest
+CREATE VIEW IF NOT EXISTS exchange_rates AS
+SELECT slot, block_time, program, COALESCE(json_extract(data, '$.reserve'), pool) AS market,
+       json_extract(data, '$.exchange_rate') AS exchange_rate
+FROM events WHERE json_extract(data, '$.exchange_rate') IS NOT NULL;
+";
+
+/// Public pool counters as of `slot`
+pub struct Snapshot {
+    pub pool: Pubkey,
+    pub slot: u64,
+    pub time: i64,
+    pub status: String,
+    pub total_deposits: u64,
+    pub total_withdrawals: u64,
+    pub total_invested: u64,
+    pub kamino_position_value: u64,
+    pub cash_principal: u64,
+    pub vault_balance: u64,
+    pub queued_withdrawals: u64,
+    pub accrued_fees: u64,
+    pub withdrawal_queue_len: u64,
+    pub deposit_intents_len: u64,
+    pub queued_deposits: u64,
+}
+
+/// A signature as getSignaturesForAddress lists it
+pub struct Listed {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+}
+
+/// A transaction to record: its events, and the balance of each pool vault it moved
+pub struct Recorded<'a> {
+    pub signature: &'a str,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub succeeded: bool,
+    pub events: &'a [Event],
+    pub vault_balances: &'a [(Pubkey, u64)],
+}
+
+/// What recording a transaction moves forward with it
+#[derive(Clone, Copy)]
+pub enum Progress<'a> {
+    /// Live catch-up: the program's cursor
+    Cursor(&'a Pubkey),
+    /// Backfill of an address: its queued signature is done
+    Backfill(&'a Pubkey),
+}
+
+/// How far a backfill got: the oldest signature listed and whether that was the first
+pub struct Backfill {
+    pub oldest: String,
+    pub listed: bool,
+}
+
+/// One page of a listing and the cursor of the next, None on the last page
+pub struct Page<C> {
+    pub items: Vec<Value>,
+    pub next: Option<C>,
+}
+
+/// ghost_pool events the harvest history is made of
+const HARVEST_EVENTS: &str = "('YieldHarvestedEvent', 'YieldRecordedEvent')";
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| format!("failed to create the schema: {}", e))?;
+        // Lets the API read while the indexer writes
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| format!("failed to enable WAL on {}: {}", path, e))?;
+        // A backfill may write alongside the live indexer
+        conn.busy_timeout(std::time::Duration::from_secs(5)).map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// A second connection for the API; `open` must have created the schema first
+    pub fn open_read_only(path: &str) -> Result<Self, String> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("failed to open {}: {}", path, e))?;
+        conn.busy_timeout(std::time::Duration::from_secs(5)).map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// Newest signature indexed for `program`, where catching up stops
+    pub fn cursor(&self, program: &Pubkey) -> Result<Option<String>, String> {
+        self.conn
+            .query_row("SELECT signature FROM cursors WHERE program = ?1", [program.to_string()], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn pools(&self) -> Result<Vec<Pubkey>, String> {
+        let mut statement = self.conn.prepare("SELECT pool FROM pools").map_err(|e| e.to_string())?;
+        let pools = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(|pool| pool.ok()?.parse().ok())
+            .collect();
+        Ok(pools)
+    }
+
+    pub fn add_pool(&self, pool: &Pubkey) -> Result<(), String> {
+        self.conn
+            .execute("INSERT OR IGNORE INTO pools (pool) VALUES (?1)", [pool.to_string()])
+            .map(drop)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Record a transaction, its events and vault balances, and its `progress`, atomically
+    /// A transaction seen through the other program, or again by a backfill, keeps its first
+    /// set of events
+    pub fn record_transaction(&mut self, progress: Progress, recorded: &Recorded) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let inserted = tx
+            .execute(
+                "INSERT OR IGNORE INTO transactions (signature, slot, block_time, succeeded) VALUES (?1, ?2, ?3, ?4)",
+                params![recorded.signature, recorded.slot, recorded.block_time, recorded.succeeded],
+            )
+            .map_err(|e| e.to_string())?;
+        if inserted > 0 {
+            for (log_index, event) in recorded.events.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO events (signature, log_index, slot, block_time, program, name, pool, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        recorded.signature,
+                        log_index,
+                        recorded.slot,
+                        recorded.block_time,
+                        event.program,
+                        event.name,
+                        event.pool.map(|pool| pool.to_string()),
+                        serde_json::Value::Object(event.fields.clone()).to_string(),
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+                if let Some(pool) = event.pool {
+                    tx.execute("INSERT OR IGNORE INTO pools (pool) VALUES (?1)", [pool.to_string()])
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            for (pool, balance) in recorded.vault_balances {
+                tx.execute(
+                    "INSERT OR IGNORE INTO vault_balances (pool, signature, slot, block_time, balance)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![pool.to_string(), recorded.signature, recorded.slot, recorded.block_time, balance],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        match progress {
+            Progress::Cursor(program) => tx.execute(
+                "INSERT INTO cursors (program, signature) VALUES (?1, ?2)
+                 ON CONFLICT (program) DO UPDATE SET signature = excluded.signature",
+                params![program.to_string(), recorded.signature],
+            ),
+            Progress::Backfill(address) => tx.execute(
+                "DELETE FROM backfill_queue WHERE address = ?1 AND signature = ?2",
+                params![address.to_string(), recorded.signature],
+            ),
+        }
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Where the backfill of `address` stands, None before its first page
+    pub fn backfill(&self, address: &Pubkey) -> Result<Option<Backfill>, String> {
+        self.conn
+            .query_row(
+                "SELECT oldest, listed FROM backfills WHERE address = ?1",
+                [address.to_string()],
+                |row| Ok(Backfill { oldest: row.get(0)?, listed: row.get(1)? }),
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Queue a page of `address`'s signatures, newest first, and checkpoint past it, atomically
+    /// Signatures already indexed are left out; `listed` marks the address's last page
+    pub fn queue_backfill(&mut self, address: &Pubkey, page: &[Listed], listed: bool) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for entry in page {
+            tx.execute(
+                "INSERT OR IGNORE INTO backfill_queue (address, signature, slot, block_time, failed)
+                 SELECT ?1, ?2, ?3, ?4, ?5 WHERE NOT EXISTS (SELECT 1 FROM transactions WHERE signature = ?2)",
+                params![address.to_string(), entry.signature, entry.slot, entry.block_time, entry.failed],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        match page.last() {
+            Some(oldest) => tx.execute(
+                "INSERT INTO backfills (address, oldest, listed) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (address) DO UPDATE SET oldest = excluded.oldest, listed = excluded.listed",
+                params![address.to_string(), oldest.signature, listed],
+            ),
+            // The previous page ended exactly at the first transaction
+            None => tx.execute(
+                "UPDATE backfills SET listed = ?2 WHERE address = ?1",
+                params![address.to_string(), listed],
+            ),
+        }
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Up to `limit` of `address`'s queued signatures, oldest first (pages were queued newest
+    /// first, so within a slot the later row is the earlier transaction)
+    pub fn backfill_batch(&self, address: &Pubkey, limit: usize) -> Result<Vec<Listed>, String> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT signature, slot, block_time, failed FROM backfill_queue
+                 WHERE address = ?1 ORDER BY slot, rowid DESC LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let batch = statement
+            .query_map(params![address.to_string(), limit], |row| {
+                Ok(Listed {
+                    signature: row.get(0)?,
+                    slot: row.get(1)?,
+                    block_time: row.get(2)?,
+                    failed: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(batch)
+    }
+
+    /// Start `program`'s cursor at `signature` unless it has one: a backfill of the program
+    /// covers everything up to there, so catching up needn't list it again
+    pub fn start_cursor(&self, program: &Pubkey, signature: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO cursors (program, signature) VALUES (?1, ?2)",
+                params![program.to_string(), signature],
+            )
+            .map(drop)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn record_snapshot(&self, snapshot: &Snapshot) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO pool_snapshots (pool, slot, time, status, total_deposits, total_withdrawals,
+                 total_invested, kamino_position_value, cash_principal, vault_balance, queued_withdrawals, accrued_fees)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    snapshot.pool.to_string(),
+                    snapshot.slot,
+                    snapshot.time,
+                    snapshot.status,
+                    snapshot.total_deposits,
+                    snapshot.total_withdrawals,
+                    snapshot.total_invested,
+                    snapshot.kamino_position_value,
+                    snapshot.cash_principal,
+                    snapshot.vault_balance,
+                    snapshot.queued_withdrawals,
+                    snapshot.accrued_fees,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO queue_snapshots (pool, slot, time, withdrawals, withdrawal_amount,
+                 deposit_intents, deposit_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    snapshot.pool.to_string(),
+                    snapshot.slot,
+                    snapshot.time,
+                    snapshot.withdrawal_queue_len,
+                    snapshot.queued_withdrawals,
+                    snapshot.deposit_intents_len,
+                    snapshot.queued_deposits,
+                ],
+            )
+            .map(drop)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Known pools in key order after `after`, each with its latest snapshot (null fields
+    /// before the first)
+    pub fn pool_page(&self, after: Option<&str>, limit: usize) -> Result<Page<String>, String> {
+        let items = self.query(
+            "SELECT p.pool, s.slot, s.time, s.status, s.total_deposits, s.total_withdrawals, s.total_invested,
+                    s.vault_balance + s.kamino_position_value + s.cash_principal AS tvl, s.accrued_fees
+             FROM pools p LEFT JOIN pool_snapshots s
+               ON s.pool = p.pool AND s.slot = (SELECT MAX(slot) FROM pool_snapshots WHERE pool = p.pool)
+             WHERE ?1 IS NULL OR p.pool > ?1 ORDER BY p.pool LIMIT ?2",
+            params![after, limit],
+        )?;
+        Ok(page(items, limit, "pool", |v| v.as_str().map(str::to_string)))
+    }
+
+    /// `pool`'s TVL history, newest first, from before slot `before`
+    pub fn tvl_page(&self, pool: &Pubkey, before: Option<i64>, limit: usize) -> Result<Page<i64>, String> {
+        let items = self.query(
+            "SELECT slot, time, tvl, total_invested, kamino_position_value FROM tvl_history
+             WHERE pool = ?1 AND (?2 IS NULL OR slot < ?2) ORDER BY slot DESC LIMIT ?3",
+            params![pool.to_string(), before, limit],
+        )?;
+        Ok(page(items, limit, "slot", Value::as_i64))
+    }
+
+    /// `pool`'s withdrawal and deposit queue depths, newest first, from before slot `before`
+    pub fn queue_page(&self, pool: &Pubkey, before: Option<i64>, limit: usize) -> Result<Page<i64>, String> {
+        let items = self.query(
+            "SELECT slot, time, withdrawals, withdrawal_amount, deposit_intents, deposit_amount FROM queue_snapshots
+             WHERE pool = ?1 AND (?2 IS NULL OR slot < ?2) ORDER BY slot DESC LIMIT ?3",
+            params![pool.to_string(), before, limit],
+        )?;
+        Ok(page(items, limit, "slot", Value::as_i64))
+    }
+
+    /// `pool`'s harvests and recorded yield, newest first, from before event `before`
+    pub fn harvest_page(&self, pool: &Pubkey, before: Option<i64>, limit: usize) -> Result<Page<i64>, String> {
+        let filter = format!("pool = ?1 AND name IN {}", HARVEST_EVENTS);
+        self.event_page(&filter, &pool.to_string(), before, limit)
+    }
+
+    /// Events that name `wallet` in any field, newest first, from before event `before`
+    pub fn wallet_page(&self, wallet: &Pubkey, before: Option<i64>, limit: usize) -> Result<Page<i64>, String> {
+        let filter = "EXISTS (SELECT 1 FROM json_each(events.data) WHERE json_each.value = ?1)";
+        self.event_page(filter, &wallet.to_string(), before, limit)
+    }
+
+    /// ghost_pool events matching `filter` (over ?1 = `key`), in the order they were indexed;
+    /// the cursor is the event's rowid
+    fn event_page(&self, filter: &str, key: &str, before: Option<i64>, limit: usize) -> Result<Page<i64>, String> {
+        let sql = format!(
+            "SELECT rowid AS id, signature, slot, block_time, name, pool, data FROM events
+             WHERE program = 'ghost_pool' AND {} AND (?2 IS NULL OR rowid < ?2) ORDER BY rowid DESC LIMIT ?3",
+            filter
+        );
+        let mut items = self.query(&sql, params![key, before, limit])?;
+        for item in items.iter_mut() {
+            if let Some(data) = item.get_mut("data") {
+                *data = data.as_str().and_then(|data| serde_json::from_str(data).ok()).unwrap_or(Value::Null);
+            }
+        }
+        Ok(page(items, limit, "id", Value::as_i64))
+    }
+
+    /// Every row of `sql` as a JSON object keyed by column name
+    fn query(&self, sql: &str, params: impl Params) -> Result<Vec<Value>, String> {
+        let mut statement = self.conn.prepare(sql).map_err(|e| e.to_string())?;
+        let columns: Vec<String> = statement.column_names().into_iter().map(str::to_string).collect();
+        let mut rows = statement.query(params).map_err(|e| e.to_string())?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let mut object = Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(i).map_err(|e| e.to_string())? {
+                    ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+                    ValueRef::Integer(n) => Value::from(n),
+                    ValueRef::Real(x) => Value::from(x),
+                    ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).into_owned()),
+                };
+                object.insert(column.clone(), value);
+            }
+            out.push(Value::Object(object));
+        }
+        Ok(out)
+    }
+}
+
+/// A full page continues after its last item's `cursor` column
+fn page<C>(items: Vec<Value>, limit: usize, cursor: &str, read: impl Fn(&Value) -> Option<C>) -> Page<C> {
+    let next = if items.len() == limit {
+        items.last().and_then(|item| item.get(cursor)).and_then(read)
+    } else {
+        None
+    };
+    Page { items, next }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: Pubkey = Pubkey::new_from_array([3; 32]);
+
+    fn listed(n: u64) -> Listed {
+        Listed { signature: format!("sig{}", n), slot: n, block_time: None, failed: false }
+    }
+
+    fn event() -> Event {
+        Event { program: "ghost_pool", name: "DepositEvent", pool: None, fields: Map::new() }
+    }
+
+    /// Index what backfill_batch hands out, as Indexer::backfill does
+    fn index(store: &mut Store, entry: &Listed) {
+        let events = [event(), event()];
+        let recorded = Recorded {
+            signature: &entry.signature,
+            slot: entry.slot,
+            block_time: entry.block_time,
+            succeeded: true,
+            events: &events,
+            vault_balances: &[],
+        };
+        store.record_transaction(Progress::Backfill(&ADDRESS), &recorded).unwrap();
+    }
+
+    fn signatures(batch: &[Listed]) -> Vec<&str> {
+        batch.iter().map(|entry| entry.signature.as_str()).collect()
+    }
+
+    fn count(store: &Store, table: &str) -> i64 {
+        store.conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn interrupted_backfill_resumes_from_its_checkpoint() {
+        let path = std::env::temp_dir().join(format!("ghost-pool-backfill-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let remove = || {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+            }
+        };
+        remove();
+
+        // First run: one page listed, newest first, and its oldest transaction indexed
+        {
+            let mut store = Store::open(path).unwrap();
+            assert!(store.backfill(&ADDRESS).unwrap().is_none());
+            store.queue_backfill(&ADDRESS, &[listed(5), listed(4), listed(3)], false).unwrap();
+            let batch = store.backfill_batch(&ADDRESS, 1).unwrap();
+            assert_eq!(signatures(&batch), ["sig3"]);
+            index(&mut store, &batch[0]);
+        }
+
+        // Second run picks up listing before the checkpoint; the overlap isn't queued again
+        let mut store = Store::open(path).unwrap();
+        let state = store.backfill(&ADDRESS).unwrap().unwrap();
+        assert_eq!((state.oldest.as_str(), state.listed), ("sig3", false));
+        store.queue_backfill(&ADDRESS, &[listed(3), listed(2), listed(1)], true).unwrap();
+        assert!(store.backfill(&ADDRESS).unwrap().unwrap().listed);
+
+        let batch = store.backfill_batch(&ADDRESS, 10).unwrap();
+        assert_eq!(signatures(&batch), ["sig1", "sig2", "sig4", "sig5"]);
+        for entry in &batch {
+            index(&mut store, entry);
+        }
+        assert_eq!(count(&store, "backfill_queue"), 0);
+        assert_eq!((count(&store, "transactions"), count(&store, "events")), (5, 10));
+
+        // A transaction seen again keeps its first set of events
+        index(&mut store, &listed(3));
+        assert_eq!(count(&store, "events"), 10);
+
+        drop(store);
+        remove();
+    }
+}
@@ -0,0 +1,77 @@
+//! Pool events that pull tasks forward, from a logs subscription on the pool account
+
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ghost_pool::{DepositEvent, InvestmentApprovedEvent, PackedEvent, WithdrawalQueuedEvent, PACKED_EVENT_MAGIC};
+use solana_client::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::keeper::Task;
+use crate::metrics::Metrics;
+
+/// Delay before resubscribing after the websocket drops
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Task an event makes due now
+fn task_for(data: &[u8]) -> Option<Task> {
+    if data.starts_with(DepositEvent::DISCRIMINATOR) {
+        Some(Task::CheckInvestment)
+    } else if data.starts_with(InvestmentApprovedEvent::DISCRIMINATOR) {
+        Some(Task::Invest)
+    } else if data.starts_with(WithdrawalQueuedEvent::DISCRIMINATOR) {
+        Some(Task::Fulfill)
+    } else if data.starts_with(&PACKED_EVENT_MAGIC) && data.get(2) == Some(&DepositEvent::TAG) {
+        Some(Task::CheckInvestment)
+    } else {
+        None
+    }
+}
+
+/// Forward the pool's events to the scheduler, resubscribing whenever the socket drops
+/// The subscription is only a latency optimization: every task also runs on its interval
+pub fn subscribe(ws_url: String, pool: Pubkey, wakeups: Sender<Task>, metrics: Arc<Metrics>) {
+    thread::spawn(move || loop {
+        let subscription = PubsubClient::logs_subscribe(
+            &ws_url,
+            RpcTransactionLogsFilter::Mentions(vec![pool.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        );
+        match subscription {
+            Ok((_subscription, logs)) => {
+                for response in logs.iter() {
+                    if response.value.err.is_some() {
+                        continue;
+                    }
+                    let events = response
+                        .value
+                        .logs
+                        .iter()
+                        .filter_map(|log| log.strip_prefix("Program data: "))
+                        .filter_map(|data| STANDARD.decode(data).ok());
+                    for data in events {
+                        if let Some(task) = task_for(&data) {
+                            metrics.record_event(task);
+                            if wakeups.send(task).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+                eprintln!("[events] subscription closed, resubscribing");
+            }
+            Err(e) => eprintln!("[events] failed to subscribe to {}: {}", ws_url, e),
+        }
+        metrics.record_resubscribe();
+        thread::sleep(RESUBSCRIBE_DELAY);
+    });
+}
@@ -0,0 +1,267 @@
+//! The keeper's tasks against one pool
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AccountDeserialize;
+use ghost_pool::{
+    GhostPool, PendingWithdrawal, WithdrawalQueue, PAUSE_INVESTMENT, PAUSE_WITHDRAWALS, VENUE_KAMINO,
+    WITHDRAWAL_QUEUE_LEN,
+};
+use ghost_pool_client::{
+    check_and_invest, cranker_address, cranker_registry_address, fulfill_withdrawal, harvest_yield, invest_in_kamino,
+    random_computation_offset, record_investment, sync_position, vault_address, withdrawal_queue_address, ArciumEnv,
+    CrankAccounts, KaminoReserve, PoolRef,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::metrics::Metrics;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    /// check_and_invest once the investment cooldown has passed
+    CheckInvestment,
+    /// invest_in_kamino for an approved amount, then record_investment_mpc
+    Invest,
+    /// sync_position, then harvest_yield on the Kamino position
+    Harvest,
+    /// fulfill_withdrawal for every queued withdrawal the vault can cover, in order
+    Fulfill,
+}
+
+impl Task {
+    pub const ALL: [Task; 4] = [Task::CheckInvestment, Task::Invest, Task::Harvest, Task::Fulfill];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Task::CheckInvestment => "check_investment",
+            Task::Invest => "invest",
+            Task::Harvest => "harvest",
+            Task::Fulfill => "fulfill",
+        }
+    }
+}
+
+pub struct Keeper {
+    rpc: RpcClient,
+    payer: Keypair,
+    env: ArciumEnv,
+    pool: Pubkey,
+    reserve: Option<Pubkey>,
+    /// How far below the reserve's quoted rate an investment may mint
+    slippage_bps: u16,
+    metrics: Arc<Metrics>,
+}
+
+impl Keeper {
+    pub fn new(
+        url: &str,
+        payer: Keypair,
+        env: ArciumEnv,
+        pool: Pubkey,
+        reserve: Option<Pubkey>,
+        slippage_bps: u16,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            payer,
+            env,
+            pool,
+            reserve,
+            slippage_bps,
+            metrics,
+        }
+    }
+
+    pub fn signer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    pub fn run(&self, task: Task) -> Result<(), String> {
+        self.metrics.record_run(task);
+        let result = match task {
+            Task::CheckInvestment => self.check_investment(),
+            Task::Invest => self.invest(),
+            Task::Harvest => self.harvest(),
+            Task::Fulfill => self.fulfill(),
+        };
+        match &result {
+            Ok(()) => self.metrics.record_success(task),
+            Err(_) => self.metrics.record_failure(task),
+        }
+        result
+    }
+
+    fn send(&self, task: Task, label: &str, instruction: Instruction) -> Result<Signature, String> {
+        let blockhash = self.rpc.get_latest_blockhash().map_err(|e| e.to_string())?;
+        let tx = Transaction::new_signed_with_payer(&[instruction], Some(&self.signer()), &[&self.payer], blockhash);
+        let signature = self
+            .rpc
+            .send_and_confirm_transaction(&tx)
+            .map_err(|e| format!("{} failed: {}", label, e))?;
+        self.metrics.record_tx(task);
+        println!("[{}] {}: {}", task.name(), label, signature);
+        Ok(signature)
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T, String> {
+        let data = self
+            .rpc
+            .get_account_data(address)
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))?;
+        T::try_deserialize(&mut data.as_slice()).map_err(|e| format!("failed to decode {}: {}", address, e))
+    }
+
+    fn exists(&self, address: &Pubkey) -> Result<bool, String> {
+        self.rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .map(|response| response.value.is_some())
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))
+    }
+
+    /// Registry and cranker accounts authorize_crank looks at, where they exist
+    fn crank_accounts(&self) -> Result<CrankAccounts, String> {
+        let registry = cranker_registry_address(&self.pool);
+        let cranker = cranker_address(&self.pool, &self.signer());
+        Ok(CrankAccounts {
+            cranker_registry: self.exists(&registry)?.then_some(registry),
+            cranker: self.exists(&cranker)?.then_some(cranker),
+        })
+    }
+
+    fn check_investment(&self) -> Result<(), String> {
+        let pool: GhostPool = self.fetch(&self.pool)?;
+        if !pool.status.accepts_investment() || pool.is_paused(PAUSE_INVESTMENT) {
+            return Ok(());
+        }
+        // Already approved: Invest picks it up
+        if pool.pending_investment_amount > 0 {
+            return Ok(());
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        if now < pool.last_investment_time + pool.investment_cooldown_secs {
+            return Ok(());
+        }
+        // The approval arrives as InvestmentApprovedEvent, which wakes Invest
+        let ix = check_and_invest(
+            &self.env,
+            &self.pool,
+            &self.signer(),
+            self.crank_accounts()?,
+            random_computation_offset(),
+        );
+        self.send(Task::CheckInvestment, "check_and_invest", ix).map(drop)
+    }
+
+    fn invest(&self) -> Result<(), String> {
+        let mut pool: GhostPool = self.fetch(&self.pool)?;
+        if pool.authority != self.signer() {
+            return Ok(());
+        }
+
+        if pool.pending_venue_investments[VENUE_KAMINO] > 0 {
+            let Some(reserve) = self.reserve else {
+                return Err("an investment is approved but no --reserve was given".to_string());
+            };
+            if pool.collateral_token_account == Pubkey::default() {
+                return Err("the pool has no collateral token account".to_string());
+            }
+            let data = self
+                .rpc
+                .get_account_data(&reserve)
+                .map_err(|e| format!("failed to fetch {}: {}", reserve, e))?;
+            let reserve = KaminoReserve::from_account_data(reserve, pool.lending_program, &data)
+                .ok_or("reserve account too short")?;
+            let min_collateral_out =
+                reserve.min_collateral_out(pool.pending_venue_investments[VENUE_KAMINO], self.slippage_bps);
+            let ix = invest_in_kamino(
+                &self.pool,
+                &self.signer(),
+                &pool.deposit_mint,
+                &pool.collateral_token_account,
+                &reserve,
+                min_collateral_out,
+            );
+            self.send(Task::Invest, "invest_in_kamino", ix)?;
+            pool = self.fetch(&self.pool)?;
+        }
+
+        if pool.unrecorded_investment_amount > 0 {
+            let ix = record_investment(&self.env, &self.pool, &self.signer(), random_computation_offset());
+            self.send(Task::Invest, "record_investment_mpc", ix)?;
+        }
+        Ok(())
+    }
+
+    fn harvest(&self) -> Result<(), String> {
+        let Some(reserve) = self.reserve else {
+            return Ok(());
+        };
+        let pool: GhostPool = self.fetch(&self.pool)?;
+        if !pool.status.is_initialized() || pool.total_collateral_received == 0 {
+            return Ok(());
+        }
+        // Keep position_value current even when there is no yield to harvest
+        let ix = sync_position(&self.pool, &self.signer(), &reserve, &pool.collateral_token_account);
+        self.send(Task::Harvest, "sync_position", ix)?;
+        let ix = harvest_yield(
+            &self.env,
+            &self.pool,
+            &self.signer(),
+            self.crank_accounts()?,
+            &reserve,
+            &pool.collateral_token_account,
+            random_computation_offset(),
+        );
+        self.send(Task::Harvest, "harvest_yield", ix).map(drop)
+    }
+
+    fn fulfill(&self) -> Result<(), String> {
+        let queue_address = withdrawal_queue_address(&self.pool);
+        if !self.exists(&queue_address)? {
+            return Ok(());
+        }
+        let pool: GhostPool = self.fetch(&self.pool)?;
+        if !pool.status.accepts_withdrawals() || pool.is_paused(PAUSE_WITHDRAWALS) {
+            return Ok(());
+        }
+        let pool_ref = PoolRef {
+            address: self.pool,
+            deposit_mint: pool.deposit_mint,
+            token_program: self
+                .rpc
+                .get_account(&pool.deposit_mint)
+                .map_err(|e| format!("failed to fetch {}: {}", pool.deposit_mint, e))?
+                .owner,
+            share_mint: pool.issues_shares().then_some(pool.share_mint),
+        };
+        let crank = self.crank_accounts()?;
+
+        let queue: WithdrawalQueue = self.fetch(&queue_address)?;
+        self.metrics.set_queue_depth(queue.tail - queue.head);
+        for seq in queue.head..queue.tail {
+            let entry = &queue.entries[(seq % WITHDRAWAL_QUEUE_LEN as u64) as usize];
+            let vault = self
+                .rpc
+                .get_token_account_balance(&vault_address(&self.pool))
+                .map_err(|e| format!("failed to fetch the vault balance: {}", e))?;
+            let liquidity: u64 = vault.amount.parse().map_err(|_| "bad vault balance".to_string())?;
+            // Strict FIFO: nothing behind the head can be paid before it
+            if liquidity < entry.amount {
+                println!("[fulfill] head needs {}, vault holds {}", entry.amount, liquidity);
+                break;
+            }
+            let pending: PendingWithdrawal = self.fetch(&entry.pending_withdrawal)?;
+            let ix = fulfill_withdrawal(&pool_ref, &self.signer(), crank, &pending);
+            self.send(Task::Fulfill, "fulfill_withdrawal", ix)?;
+            self.metrics.set_queue_depth(queue.tail - seq - 1);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! Ghost Pool keeper
+//!
+//! Long-running crank service for one pool: asks the MXE for investment approval, moves
+//! approved funds into Kamino and records them, harvests yield, and fulfills queued
+//! withdrawals as the vault refills. Every task runs on its own interval and is pulled
+//! forward by the pool's events; failures back off exponentially. Counters are served in
+//! the Prometheus text format on `--metrics-addr`.
+//!
+//! Signs with the Solana CLI keypair against the Solana CLI RPC URL (`--config`, `--url`,
+//! `--ws-url` and `--keypair` override them). Investment needs the pool authority's key;
+//! checks, harvests and fulfillment also run as a registered cranker.
+//!
+//! `ghost-pool-keeper snapshot` instead writes one JSON snapshot of every pool (or of `--pool`)
+//! to `--out` or stdout and exits; it reads public accounts and events only and needs no key.
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use ghost_pool_client::ArciumEnv;
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_sdk::signature::read_keypair_file;
+
+mod events;
+mod keeper;
+mod metrics;
+mod snapshot;
+
+use keeper::{Keeper, Task};
+use metrics::Metrics;
+use snapshot::Snapshot;
+
+const USAGE: &str = "usage: ghost-pool-keeper --pool <pool> [--reserve <kamino reserve>] [--flag <value> ...]
+       ghost-pool-keeper snapshot [--pool <pool>] [--out <path>] [--flag <value> ...]
+
+flags: --config <path> --url <rpc> --ws-url <rpc ws> --keypair <path> --cluster-offset <n>
+       --check-secs <s> --harvest-secs <s> --fulfill-secs <s> --metrics-addr <host:port>
+       --slippage-bps <bps> --harvest-lookback <signatures> (snapshot)";
+
+const DEFAULT_CHECK_SECS: u64 = 300;
+const DEFAULT_HARVEST_SECS: u64 = 3_600;
+const DEFAULT_FULFILL_SECS: u64 = 30;
+const DEFAULT_METRICS_ADDR: &str = "127.0.0.1:9184";
+/// Investments must mint within this many bps of the cTokens the reserve's rate quotes
+const DEFAULT_SLIPPAGE_BPS: u16 = 50;
+
+/// First retry delay after a failure, doubled per consecutive failure
+const RETRY_BASE: Duration = Duration::from_secs(5);
+const RETRY_MAX: Duration = Duration::from_secs(600);
+
+/// `--name value` pairs
+struct Flags(HashMap<String, String>);
+
+impl Flags {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut flags = HashMap::new();
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            if !flag.starts_with("--") {
+                return Err(format!("unexpected argument {:?}", flag));
+            }
+            let value = it.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            flags.insert(flag.clone(), value.clone());
+        }
+        Ok(Self(flags))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.get(name)
+            .map(|value| value.parse().map_err(|_| format!("bad {} {:?}", name, value)))
+            .transpose()
+    }
+
+    fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        Ok(self.optional(name)?.unwrap_or(default))
+    }
+}
+
+/// A task's schedule: its interval, when it is next due, and its failure streak
+struct Schedule {
+    task: Task,
+    interval: Duration,
+    due: Instant,
+    failures: u32,
+}
+
+impl Schedule {
+    fn new(task: Task, interval: Duration) -> Self {
+        Self { task, interval, due: Instant::now(), failures: 0 }
+    }
+
+    fn succeeded(&mut self) {
+        self.failures = 0;
+        self.due = Instant::now() + self.interval;
+    }
+
+    fn failed(&mut self) -> Duration {
+        let delay = RETRY_BASE
+            .saturating_mul(1 << self.failures.min(16))
+            .min(RETRY_MAX)
+            .min(self.interval.max(RETRY_BASE));
+        self.failures += 1;
+        self.due = Instant::now() + delay;
+        delay
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("snapshot") => Flags::parse(&args[1..]).and_then(|flags| snapshot(&flags)),
+        _ => Flags::parse(&args).and_then(|flags| run(&flags)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ghost-pool-keeper: {}", e);
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn config(flags: &Flags) -> Result<Config, String> {
+    match flags.get("--config") {
+        Some(path) => Config::load(path).map_err(|e| format!("failed to load {}: {}", path, e)),
+        None => Ok(CONFIG_FILE
+            .as_ref()
+            .and_then(|path| Config::load(path).ok())
+            .unwrap_or_default()),
+    }
+}
+
+/// Write the snapshot of every pool, or of `--pool`, to `--out` (stdout without it)
+fn snapshot(flags: &Flags) -> Result<(), String> {
+    let config = config(flags)?;
+    let url = flags.get("--url").unwrap_or(&config.json_rpc_url);
+    let pools = flags.optional::<Pubkey>("--pool")?.map(|pool| vec![pool]);
+    let lookback = flags.parse_or("--harvest-lookback", snapshot::DEFAULT_HARVEST_LOOKBACK)?;
+
+    let document = Snapshot::new(url, lookback).collect(pools)?;
+    let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+    match flags.get("--out") {
+        Some(path) => std::fs::write(path, json + "\n").map_err(|e| format!("failed to write {}: {}", path, e)),
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+fn run(flags: &Flags) -> Result<(), String> {
+    let config = config(flags)?;
+    let url = flags.get("--url").unwrap_or(&config.json_rpc_url).to_string();
+    let ws_url = match flags.get("--ws-url") {
+        Some(ws_url) => ws_url.to_string(),
+        None if flags.get("--url").is_some() => Config::compute_websocket_url(&url),
+        None => config.websocket_url.clone(),
+    };
+    let keypair_path = flags.get("--keypair").unwrap_or(&config.keypair_path);
+    let payer = read_keypair_file(keypair_path)
+        .map_err(|e| format!("failed to read keypair {}: {}", keypair_path, e))?;
+    let cluster_offset = match flags.optional("--cluster-offset")? {
+        Some(offset) => offset,
+        None => std::env::var("ARCIUM_CLUSTER_OFFSET")
+            .map_err(|_| "set --cluster-offset or $ARCIUM_CLUSTER_OFFSET".to_string())?
+            .parse()
+            .map_err(|_| "bad $ARCIUM_CLUSTER_OFFSET".to_string())?,
+    };
+    let pool: Pubkey = flags.optional("--pool")?.ok_or("missing --pool")?;
+    let reserve: Option<Pubkey> = flags.optional("--reserve")?;
+    let slippage_bps: u16 = flags.parse_or("--slippage-bps", DEFAULT_SLIPPAGE_BPS)?;
+    if slippage_bps > 10_000 {
+        return Err("--slippage-bps is at most 10000".to_string());
+    }
+
+    let metrics = Arc::new(Metrics::default());
+    metrics::serve(flags.get("--metrics-addr").unwrap_or(DEFAULT_METRICS_ADDR), metrics.clone())?;
+
+    let (triggers, wakeups) = mpsc::channel();
+    events::subscribe(ws_url, pool, triggers, metrics.clone());
+
+    let env = ArciumEnv::new(cluster_offset);
+    let keeper = Keeper::new(&url, payer, env, pool, reserve, slippage_bps, metrics.clone());
+    let check = Duration::from_secs(flags.parse_or("--check-secs", DEFAULT_CHECK_SECS)?);
+    let mut schedules = vec![
+        Schedule::new(Task::Fulfill, Duration::from_secs(flags.parse_or("--fulfill-secs", DEFAULT_FULFILL_SECS)?)),
+        Schedule::new(Task::CheckInvestment, check),
+        Schedule::new(Task::Invest, check),
+        Schedule::new(Task::Harvest, Duration::from_secs(flags.parse_or("--harvest-secs", DEFAULT_HARVEST_SECS)?)),
+    ];
+    println!("keeping pool {} as {}", pool, keeper.signer());
+
+    loop {
+        let next_due = schedules.iter().map(|s| s.due).min().expect("at least one task");
+        // An event pulls its task forward; a timeout means something is due
+        if let Ok(task) = wakeups.recv_timeout(next_due.saturating_duration_since(Instant::now())) {
+            if let Some(schedule) = schedules.iter_mut().find(|s| s.task == task) {
+                schedule.due = Instant::now();
+            }
+        }
+
+        let now = Instant::now();
+        for schedule in schedules.iter_mut().filter(|s| s.due <= now) {
+            match keeper.run(schedule.task) {
+                Ok(()) => schedule.succeeded(),
+                Err(e) => {
+                    let delay = schedule.failed();
+                    eprintln!(
+                        "[{}] failed ({} in a row), retrying in {}s: {}",
+                        schedule.task.name(),
+                        schedule.failures,
+                        delay.as_secs(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
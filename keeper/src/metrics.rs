@@ -0,0 +1,103 @@
+//! Keeper counters, served in the Prometheus text format
+
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::keeper::Task;
+
+const TASKS: usize = Task::ALL.len();
+
+#[derive(Default)]
+pub struct Metrics {
+    runs: [AtomicU64; TASKS],
+    failures: [AtomicU64; TASKS],
+    transactions: [AtomicU64; TASKS],
+    last_success: [AtomicU64; TASKS],
+    events: [AtomicU64; TASKS],
+    resubscribes: AtomicU64,
+    queue_depth: AtomicU64,
+}
+
+fn index(task: Task) -> usize {
+    Task::ALL.iter().position(|t| *t == task).expect("every task is in Task::ALL")
+}
+
+impl Metrics {
+    pub fn record_run(&self, task: Task) {
+        self.runs[index(task)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_success(&self, task: Task) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.last_success[index(task)].store(now, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self, task: Task) {
+        self.failures[index(task)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_tx(&self, task: Task) {
+        self.transactions[index(task)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An event that woke `task`
+    pub fn record_event(&self, task: Task) {
+        self.events[index(task)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_resubscribe(&self) {
+        self.resubscribes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_queue_depth(&self, depth: u64) {
+        self.queue_depth.store(depth, Ordering::Relaxed);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let per_task = [
+            ("runs_total", "counter", "Task runs", &self.runs),
+            ("failures_total", "counter", "Task runs that failed", &self.failures),
+            ("transactions_total", "counter", "Transactions confirmed", &self.transactions),
+            ("last_success_timestamp_seconds", "gauge", "Unix time of the last successful run", &self.last_success),
+            ("event_wakeups_total", "counter", "Pool events that made a task due", &self.events),
+        ];
+        for (name, kind, help, values) in per_task {
+            let _ = writeln!(out, "# HELP ghost_pool_keeper_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE ghost_pool_keeper_{} {}", name, kind);
+            for task in Task::ALL {
+                let value = values[index(task)].load(Ordering::Relaxed);
+                let _ = writeln!(out, "ghost_pool_keeper_{}{{task=\"{}\"}} {}", name, task.name(), value);
+            }
+        }
+        let _ = writeln!(out, "# TYPE ghost_pool_keeper_resubscribes_total counter");
+        let _ = writeln!(out, "ghost_pool_keeper_resubscribes_total {}", self.resubscribes.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# TYPE ghost_pool_keeper_withdrawal_queue_depth gauge");
+        let _ = writeln!(out, "ghost_pool_keeper_withdrawal_queue_depth {}", self.queue_depth.load(Ordering::Relaxed));
+        out
+    }
+}
+
+/// Answer every connection on `addr` with the current metrics
+pub fn serve(addr: &str, metrics: Arc<Metrics>) -> Result<(), String> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("failed to bind {}: {}", addr, e))?;
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // The request itself doesn't matter, but read it so the client sees a clean close
+            let _ = stream.read(&mut [0u8; 1024]);
+            let body = metrics.render();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    Ok(())
+}
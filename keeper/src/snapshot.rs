@@ -0,0 +1,452 @@
+//! `ghost-pool-keeper snapshot`: one JSON document describing every pool, for dashboards and
+//! incident postmortems
+//!
+//! Only public data goes in: the GhostPool accounts' plaintext fields, token balances, the
+//! computation logs, pending computations and queues, and the last YieldRecordedEvent. Nothing
+//! needs a key, so the snapshot can be taken by anyone and shared as-is.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ghost_pool::{
+    ComputationLog, ComputationLogEntry, DepositIntentQueue, GhostPool, OpKind, PendingComputation, WithdrawalQueue,
+    YieldRecordedEvent, COMPUTATION_LOG_LEN, VENUE_ADAPTER, VENUE_KAMINO,
+};
+use ghost_pool_client::{computation_log_address, deposit_intents_address, vault_address, withdrawal_queue_address};
+use serde_json::{json, Value};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{RpcProgramAccountsConfig, RpcTransactionConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::UiTransactionEncoding;
+
+/// Most pool signatures walked back looking for the last harvest
+pub const DEFAULT_HARVEST_LOOKBACK: usize = 1_000;
+
+/// Byte offset of `pool` in a PendingComputation: discriminator and bump
+const PENDING_COMPUTATION_POOL_OFFSET: usize = 8 + 1;
+
+pub struct Snapshot {
+    rpc: RpcClient,
+    harvest_lookback: usize,
+}
+
+impl Snapshot {
+    pub fn new(url: &str, harvest_lookback: usize) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            harvest_lookback,
+        }
+    }
+
+    /// Every pool the program owns, or only `pools` when given
+    /// A pool that fails to load is reported with its error instead of failing the whole snapshot
+    pub fn collect(&self, pools: Option<Vec<Pubkey>>) -> Result<Value, String> {
+        let slot = self.rpc.get_slot().map_err(|e| format!("failed to fetch the slot: {}", e))?;
+        let pools = match pools {
+            Some(pools) => pools,
+            None => self.pool_addresses()?,
+        };
+        let entries: Vec<Value> = pools
+            .iter()
+            .map(|address| {
+                self.pool(address, slot)
+                    .unwrap_or_else(|e| json!({ "address": address.to_string(), "error": e }))
+            })
+            .collect();
+        Ok(json!({
+            "program": ghost_pool::ID.to_string(),
+            "slot": slot,
+            "taken_at": SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            "pools": entries,
+        }))
+    }
+
+    fn pool_addresses(&self) -> Result<Vec<Pubkey>, String> {
+        let mut pools: Vec<Pubkey> = self
+            .program_accounts(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, GhostPool::DISCRIMINATOR.to_vec()))])?
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect();
+        pools.sort();
+        Ok(pools)
+    }
+
+    fn pool(&self, address: &Pubkey, slot: u64) -> Result<Value, String> {
+        let pool: GhostPool = self.fetch(address)?.ok_or("no such account")?;
+        let log: Option<ComputationLog> = self.fetch(&computation_log_address(address))?;
+        let accounts = PoolAccounts {
+            vault_balance: self.token_balance(&vault_address(address))?,
+            collateral_balance: self.token_balance(&pool.collateral_token_account)?,
+            withdrawals: self.fetch(&withdrawal_queue_address(address))?,
+            intents: self.fetch(&deposit_intents_address(address))?,
+            staged: self.staged_computations(address, slot)?,
+            last_harvest: self.last_harvest(address, log.as_ref())?,
+            pool,
+            log,
+        };
+        Ok(pool_json(address, &accounts, slot))
+    }
+
+    /// PendingComputation accounts: queued computations holding provisional state until their
+    /// callback, or expire_computation, closes them
+    fn staged_computations(&self, pool: &Pubkey, slot: u64) -> Result<Vec<Value>, String> {
+        let accounts = self.program_accounts(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, PendingComputation::DISCRIMINATOR.to_vec())),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(PENDING_COMPUTATION_POOL_OFFSET, pool.to_bytes().to_vec())),
+        ])?;
+        let mut staged: Vec<PendingComputation> = accounts
+            .into_iter()
+            .filter_map(|(_, data)| PendingComputation::try_deserialize(&mut data.as_slice()).ok())
+            .collect();
+        staged.sort_by_key(|pending| pending.queue_slot);
+        Ok(staged
+            .iter()
+            .map(|pending| {
+                json!({
+                    "kind": format!("{:?}", pending.kind),
+                    "computation_offset": pending.computation_offset,
+                    "queue_slot": pending.queue_slot,
+                    "age_slots": slot.saturating_sub(pending.queue_slot),
+                })
+            })
+            .collect())
+    }
+
+    /// The newest YieldRecordedEvent, found by walking the pool's signatures back
+    /// When the computation log still holds a RecordYield entry only the transactions in its
+    /// completion slot are fetched; when it has wrapped past the last one, every one within the
+    /// lookback is
+    fn last_harvest(&self, pool: &Pubkey, log: Option<&ComputationLog>) -> Result<Value, String> {
+        let latest = log.and_then(|log| newest_first(log).find(|entry| entry.kind == OpKind::RecordYield));
+        let in_flight = latest
+            .filter(|entry| entry.completion_slot == 0)
+            .map(|entry| json!({ "computation_offset": entry.computation_offset, "queue_slot": entry.queue_slot }));
+        let target_slot = latest.map(|entry| entry.completion_slot).filter(|slot| *slot > 0);
+        // A log that has never wrapped holds every computation the pool queued
+        if latest.is_none() && log.is_some_and(|log| log.total_queued <= COMPUTATION_LOG_LEN as u64) {
+            return Ok(json!({ "recorded": null, "in_flight": null }));
+        }
+
+        let mut before = None;
+        let mut walked = 0;
+        while walked < self.harvest_lookback {
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    pool,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some((self.harvest_lookback - walked).min(1_000)),
+                        commitment: Some(self.rpc.commitment()),
+                    },
+                )
+                .map_err(|e| format!("failed to list signatures for {}: {}", pool, e))?;
+            if page.is_empty() {
+                break;
+            }
+            walked += page.len();
+            before = page.last().and_then(|status| status.signature.parse().ok());
+
+            for status in &page {
+                if status.err.is_some() || target_slot.is_some_and(|slot| status.slot > slot) {
+                    continue;
+                }
+                if target_slot.is_some_and(|slot| status.slot < slot) {
+                    // Past the harvest the log names without finding it: pruned by the RPC node
+                    return Ok(json!({ "recorded": null, "in_flight": in_flight }));
+                }
+                if let Some(recorded) = self.yield_recorded(&status.signature, pool)? {
+                    let recorded = json!({
+                        "signature": status.signature,
+                        "slot": status.slot,
+                        "timestamp": recorded.timestamp,
+                        "yield_amount": recorded.yield_amount,
+                        "fee_amount": recorded.fee_amount,
+                        "position_value": recorded.position_value,
+                        "computation_offset": recorded.computation_offset,
+                    });
+                    return Ok(json!({ "recorded": recorded, "in_flight": in_flight }));
+                }
+            }
+            if before.is_none() {
+                break;
+            }
+        }
+        Ok(json!({ "recorded": null, "in_flight": in_flight }))
+    }
+
+    fn yield_recorded(&self, signature: &str, pool: &Pubkey) -> Result<Option<YieldRecordedEvent>, String> {
+        let signature: Signature = signature.parse().map_err(|e| format!("bad signature: {}", e))?;
+        let tx = self
+            .rpc
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(self.rpc.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .map_err(|e| format!("failed to fetch {}: {}", signature, e))?;
+        let logs = tx
+            .transaction
+            .meta
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+            .unwrap_or_default();
+        Ok(logs
+            .iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| STANDARD.decode(data).ok())
+            .filter_map(|data| {
+                let body = data.strip_prefix(YieldRecordedEvent::DISCRIMINATOR)?;
+                YieldRecordedEvent::deserialize(&mut &body[..]).ok()
+            })
+            .find(|event| event.pool == *pool))
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>, String> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))?
+            .value;
+        account
+            .map(|account| {
+                T::try_deserialize(&mut account.data.as_slice())
+                    .map_err(|e| format!("failed to decode {}: {}", address, e))
+            })
+            .transpose()
+    }
+
+    /// Raw amount held by a token account, None for a disabled (default) or missing one
+    fn token_balance(&self, address: &Pubkey) -> Result<Option<u64>, String> {
+        if *address == Pubkey::default() || !self.exists(address)? {
+            return Ok(None);
+        }
+        let balance = self
+            .rpc
+            .get_token_account_balance(address)
+            .map_err(|e| format!("failed to fetch the balance of {}: {}", address, e))?;
+        balance.amount.parse().map(Some).map_err(|_| format!("bad balance for {}", address))
+    }
+
+    fn exists(&self, address: &Pubkey) -> Result<bool, String> {
+        self.rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .map(|response| response.value.is_some())
+            .map_err(|e| format!("failed to fetch {}: {}", address, e))
+    }
+
+    fn program_accounts(&self, filters: Vec<RpcFilterType>) -> Result<Vec<(Pubkey, Vec<u8>)>, String> {
+        let accounts = self
+            .rpc
+            .get_program_accounts_with_config(
+                &ghost_pool::ID,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .map_err(|e| format!("failed to list program accounts: {}", e))?;
+        Ok(accounts.into_iter().map(|(address, account)| (address, account.data)).collect())
+    }
+}
+
+/// Everything one pool's entry is built from, fetched ahead of building it
+struct PoolAccounts {
+    pool: GhostPool,
+    log: Option<ComputationLog>,
+    withdrawals: Option<WithdrawalQueue>,
+    intents: Option<DepositIntentQueue>,
+    vault_balance: Option<u64>,
+    collateral_balance: Option<u64>,
+    staged: Vec<Value>,
+    last_harvest: Value,
+}
+
+/// One pool's entry in the snapshot
+fn pool_json(address: &Pubkey, accounts: &PoolAccounts, slot: u64) -> Value {
+    let pool = &accounts.pool;
+    let balances = json!({
+        "vault": accounts.vault_balance,
+        "total_deposits": pool.total_deposits,
+        "total_withdrawals": pool.total_withdrawals,
+        "pending_payouts": pool.pending_payouts,
+        "queued_withdrawals": pool.queued_withdrawals,
+        "queued_deposits": pool.queued_deposits,
+        "accrued_fees": pool.accrued_fees,
+        "uncompounded_yield": pool.uncompounded_yield,
+        "rotator": pool.rotator_balance,
+    });
+    let kamino = json!({
+        "lending_program": pool.lending_program.to_string(),
+        "approved": pool.pending_venue_investments[VENUE_KAMINO],
+        "collateral": accounts.collateral_balance,
+        "position_value": pool.kamino_position_value,
+        "synced_position_value": pool.position_value,
+        "position_synced_slot": pool.position_synced_slot,
+    });
+    let strategies = json!({
+        "total_invested": pool.total_invested,
+        "approved_uninvested": pool.pending_investment_amount,
+        "invested_unrecorded": pool.unrecorded_investment_amount,
+        "last_investment_time": pool.last_investment_time,
+        "kamino": kamino,
+        "adapter": { "approved": pool.pending_venue_investments[VENUE_ADAPTER] },
+        "cash": {
+            "reserve": enabled(&pool.cash_reserve),
+            "principal": pool.cash_principal,
+            "yield_realized": pool.cash_yield_realized,
+        },
+    });
+    let computations = json!({
+        "total_queued": accounts.log.as_ref().map(|log| log.total_queued),
+        "state_write_in_flight": enabled(&pool.state_write_in_flight),
+        "pending": accounts.log.as_ref().map(|log| pending_entries(log, slot)),
+        "staged": accounts.staged,
+    });
+
+    json!({
+        "address": address.to_string(),
+        "pool_id": pool.pool_id,
+        "authority": pool.authority.to_string(),
+        "deposit_mint": pool.deposit_mint.to_string(),
+        "deposit_decimals": pool.deposit_decimals,
+        "version": pool.version,
+        "status": format!("{:?}", pool.status),
+        "paused": pool.paused,
+        "needs_attention": pool.needs_attention,
+        "balances": balances,
+        "strategies": strategies,
+        "computations": computations,
+        "queues": {
+            "withdrawals": accounts.withdrawals.as_ref().map(|queue| queue.tail - queue.head),
+            "deposit_intents": accounts.intents.as_ref().map(|queue| queue.tail - queue.head),
+        },
+        "last_harvest": accounts.last_harvest,
+    })
+}
+
+/// Log entries newest first, skipping the never-written ones
+fn newest_first(log: &ComputationLog) -> impl Iterator<Item = &ComputationLogEntry> {
+    let len = log.entries.len();
+    (1..=len)
+        .map(move |i| &log.entries[(log.head as usize + len - i) % len])
+        .filter(|entry| entry.queue_slot != 0)
+}
+
+/// Logged computations without a callback yet; aborted ones stay here until pushed out
+fn pending_entries(log: &ComputationLog, slot: u64) -> Vec<Value> {
+    newest_first(log)
+        .filter(|entry| entry.completion_slot == 0)
+        .map(|entry| {
+            json!({
+                "kind": format!("{:?}", entry.kind),
+                "computation_offset": entry.computation_offset,
+                "queue_slot": entry.queue_slot,
+                "age_slots": slot.saturating_sub(entry.queue_slot),
+            })
+        })
+        .collect()
+}
+
+/// An optional address field, null while it holds the default pubkey
+fn enabled(address: &Pubkey) -> Option<String> {
+    (*address != Pubkey::default()).then(|| address.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ghost_pool::{PoolStatus, WithdrawalQueueEntry, WITHDRAWAL_QUEUE_LEN};
+
+    /// A pool decoded from zeroed account data, as every field starts out
+    fn zeroed_pool() -> GhostPool {
+        let mut data = GhostPool::DISCRIMINATOR.to_vec();
+        data.resize(GhostPool::SPACE, 0);
+        GhostPool::try_deserialize(&mut data.as_slice()).expect("zeroed pool decodes")
+    }
+
+    #[test]
+    fn pool_json_shape() {
+        let address = Pubkey::new_unique();
+        let mut pool = zeroed_pool();
+        pool.pool_id = 7;
+        pool.authority = Pubkey::new_unique();
+        pool.status = PoolStatus::Active;
+        pool.total_deposits = 1_000;
+        pool.pending_venue_investments[VENUE_KAMINO] = 250;
+        pool.cash_reserve = Pubkey::new_unique();
+
+        let mut entries = [ComputationLogEntry::default(); COMPUTATION_LOG_LEN];
+        entries[0] = ComputationLogEntry {
+            kind: OpKind::Deposit,
+            computation_offset: 42,
+            queue_slot: 90,
+            ..ComputationLogEntry::default()
+        };
+        let log = ComputationLog { bump: 0, pool: address, head: 1, total_queued: 1, entries };
+        let withdrawals = WithdrawalQueue {
+            bump: 0,
+            pool: address,
+            head: 3,
+            tail: 5,
+            entries: [WithdrawalQueueEntry::default(); WITHDRAWAL_QUEUE_LEN],
+        };
+        let accounts = PoolAccounts {
+            pool,
+            log: Some(log),
+            withdrawals: Some(withdrawals),
+            intents: None,
+            vault_balance: Some(600),
+            collateral_balance: None,
+            staged: Vec::new(),
+            last_harvest: json!({ "recorded": null, "in_flight": null }),
+        };
+        let json = pool_json(&address, &accounts, 100);
+
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "address",
+                "authority",
+                "balances",
+                "computations",
+                "deposit_decimals",
+                "deposit_mint",
+                "last_harvest",
+                "needs_attention",
+                "paused",
+                "pool_id",
+                "queues",
+                "status",
+                "strategies",
+                "version",
+            ]
+        );
+        assert_eq!(json["address"], address.to_string());
+        assert_eq!(json["pool_id"], 7);
+        assert_eq!(json["status"], "Active");
+        assert_eq!(json["balances"]["vault"], 600);
+        assert_eq!(json["balances"]["total_deposits"], 1_000);
+        assert_eq!(json["strategies"]["kamino"]["approved"], 250);
+        assert_eq!(json["strategies"]["kamino"]["collateral"], Value::Null);
+        assert_eq!(json["strategies"]["cash"]["reserve"], accounts.pool.cash_reserve.to_string());
+        assert_eq!(json["computations"]["total_queued"], 1);
+        assert_eq!(json["computations"]["state_write_in_flight"], Value::Null);
+        assert_eq!(
+            json["computations"]["pending"],
+            json!([{ "kind": "Deposit", "computation_offset": 42, "queue_slot": 90, "age_slots": 10 }])
+        );
+        assert_eq!(json["queues"], json!({ "withdrawals": 2, "deposit_intents": null }));
+        assert_eq!(json["last_harvest"]["recorded"], Value::Null);
+    }
+}
@@ -0,0 +1,25 @@
+//! Fails the build on an account context that breaks the unchecked-account policy
+//! (`cargo xtask check-accounts` runs the same check on its own)
+
+use std::process::exit;
+
+#[path = "../../xtask/src/accounts.rs"]
+mod accounts;
+
+const SOURCE: &str = "src/lib.rs";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SOURCE);
+    println!("cargo:rerun-if-changed=../../xtask/src/accounts.rs");
+
+    let src = std::fs::read_to_string(SOURCE).unwrap_or_else(|e| panic!("failed to read {}: {}", SOURCE, e));
+    let report = accounts::check_source(&src);
+    if report.violations.is_empty() {
+        return;
+    }
+    for v in &report.violations {
+        eprintln!("{}:{}: {}", SOURCE, v.line, v.describe());
+    }
+    eprintln!("unchecked-account policy: {} violation(s), see xtask/src/accounts.rs", report.violations.len());
+    exit(1);
+}
@@ -1,19 +1,17 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::instruction::Instruction;
-use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::instruction::{get_stack_height, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+use solana_sha256_hasher::{hash, hashv};
+use anchor_lang::solana_program::log::sol_log_data;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::program_option::COption;
 use arcium_anchor::prelude::*;
 use arcium_client::idl::arcium::types::{CallbackAccount, CircuitSource, OffChainCircuitSource};
 use arcium_macros::circuit_hash;
-use anchor_spl::token::{Mint, Token, TokenAccount, Transfer, transfer};
-
-// Circuit URLs on IPFS (v4 - 2 deposits, EncData output, fits callback limit)
-const INIT_POOL_STATE_URL: &str = "https://gateway.pinata.cloud/ipfs/bafkreig7wc7tesauxb2hbrr5ypbej7z3yoblrzm6iziuvxnybmlz7oidbq";
-const PROCESS_DEPOSIT_URL: &str = "https://gateway.pinata.cloud/ipfs/bafybeigw3az26fvgrr6jlgjxkcbbfx26i2tsqwp3m3clmfzcxphlytgf34";
-const CHECK_INVESTMENT_NEEDED_URL: &str = "https://gateway.pinata.cloud/ipfs/bafkreickglqz4lr4p5dihj55iobzbmkedqcdxkjlffeu7xwi75t7lf4pl4";
-const RECORD_INVESTMENT_URL: &str = "https://gateway.pinata.cloud/ipfs/bafybeiaznsrclf6sy6e2iiwwnubmzx57tdysu3syvpbm2nsa2zsdj2uljq";
-const RECORD_YIELD_URL: &str = "https://gateway.pinata.cloud/ipfs/bafybeia3up67csa37rbv3fxzgk3zpcja6ow2la5kb6jo43qancffgn5k54";
-const AUTHORIZE_WITHDRAWAL_URL: &str = "https://gateway.pinata.cloud/ipfs/bafybeidkmrkn4r6mgwquuwqkhxbw66nzu6y2vgojbqpyan5ln7nhcohv2q";
-const PROCESS_WITHDRAWAL_URL: &str = "https://gateway.pinata.cloud/ipfs/bafybeihqlyozdkqbwv7vy2cfdkzdtqb4yxwf4jtzoucjkof3pabzbh36c4";
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_interface::{self, TokenInterface, TransferChecked, transfer_checked};
 
 const COMP_DEF_OFFSET_INIT_POOL: u32 = comp_def_offset("init_pool_state");
 const COMP_DEF_OFFSET_DEPOSIT: u32 = comp_def_offset("process_deposit");
@@ -22,10 +20,263 @@ const COMP_DEF_OFFSET_RECORD_INVESTMENT: u32 = comp_def_offset("record_investmen
 const COMP_DEF_OFFSET_RECORD_YIELD: u32 = comp_def_offset("record_yield");
 const COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL: u32 = comp_def_offset("authorize_withdrawal");
 const COMP_DEF_OFFSET_PROCESS_WITHDRAWAL: u32 = comp_def_offset("process_withdrawal");
+const COMP_DEF_OFFSET_INIT_SHARD: u32 = comp_def_offset("init_shard_state");
+const COMP_DEF_OFFSET_DEPOSIT_SHARD: u32 = comp_def_offset("process_deposit_shard");
+const COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL_SHARD: u32 = comp_def_offset("authorize_withdrawal_shard");
+const COMP_DEF_OFFSET_REVEAL_DEPOSIT_COUNT: u32 = comp_def_offset("reveal_deposit_count");
+const COMP_DEF_OFFSET_MIGRATE_LEGACY_SLOT: u32 = comp_def_offset("migrate_legacy_slot");
+const COMP_DEF_OFFSET_TRANSFER_POSITION: u32 = comp_def_offset("transfer_position");
+const COMP_DEF_OFFSET_RECORD_YIELD_WITH_FEES: u32 = comp_def_offset("record_yield_with_fees");
+const COMP_DEF_OFFSET_TOP_UP_DEPOSIT: u32 = comp_def_offset("top_up_deposit");
+const COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL_ALL: u32 = comp_def_offset("authorize_withdrawal_all");
+const COMP_DEF_OFFSET_GET_BALANCE: u32 = comp_def_offset("get_balance");
+const COMP_DEF_OFFSET_ROTATE_PASSWORD: u32 = comp_def_offset("rotate_password");
+const COMP_DEF_OFFSET_RECOVER_DEPOSIT: u32 = comp_def_offset("recover_deposit");
+const COMP_DEF_OFFSET_SET_STRATEGY_WEIGHTS: u32 = comp_def_offset("set_strategy_weights");
+const COMP_DEF_OFFSET_PROCESS_DEPOSIT_BATCH: u32 = comp_def_offset("process_deposit_batch");
+const COMP_DEF_OFFSET_CLAIM_REFERRAL_REWARDS: u32 = comp_def_offset("claim_referral_rewards");
+const COMP_DEF_OFFSET_UPGRADE_POOL_STATE: u32 = comp_def_offset("upgrade_pool_state");
+const COMP_DEF_OFFSET_UPGRADE_SHARD_STATE: u32 = comp_def_offset("upgrade_shard_state");
+const COMP_DEF_OFFSET_PUBLISH_TVL: u32 = comp_def_offset("publish_tvl");
+const COMP_DEF_OFFSET_COMPUTE_POOL_STATS: u32 = comp_def_offset("compute_pool_stats");
+const COMP_DEF_OFFSET_PROCESS_WITHDRAWAL_SHARD: u32 = comp_def_offset("process_withdrawal_shard");
+
+/// Circuits too large to upload on-chain, served from IPFS: init_circuit_registry takes their
+/// URLs in this order, and upgrade_circuit re-pins them without a program upgrade
+pub const OFFCHAIN_CIRCUITS: [&str; 7] = [
+    "init_pool_state",
+    "process_deposit",
+    "check_investment_needed",
+    "record_investment",
+    "record_yield",
+    "authorize_withdrawal",
+    "process_withdrawal",
+];
+
+/// Circuits init_comp_defs can create: bit `i` of its mask selects `COMP_DEF_CIRCUITS[i]`
+pub const COMP_DEF_CIRCUITS: [&str; 27] = [
+    "init_pool_state",
+    "process_deposit",
+    "check_investment_needed",
+    "record_investment",
+    "record_yield",
+    "authorize_withdrawal",
+    "process_withdrawal",
+    "init_shard_state",
+    "process_deposit_shard",
+    "authorize_withdrawal_shard",
+    "reveal_deposit_count",
+    "migrate_legacy_slot",
+    "transfer_position",
+    "record_yield_with_fees",
+    "top_up_deposit",
+    "authorize_withdrawal_all",
+    "get_balance",
+    "rotate_password",
+    "recover_deposit",
+    "set_strategy_weights",
+    "process_deposit_batch",
+    "claim_referral_rewards",
+    "upgrade_pool_state",
+    "upgrade_shard_state",
+    "publish_tvl",
+    "compute_pool_stats",
+    "process_withdrawal_shard",
+];
+
+/// Entries kept in each pool's ComputationLog ring buffer
+pub const COMPUTATION_LOG_LEN: usize = 16;
+
+/// Withdrawals each pool's WithdrawalQueue can hold unfunded at once
+pub const WITHDRAWAL_QUEUE_LEN: usize = 32;
+
+/// Circuits the CircuitRegistry can pin, and the longest source URL it stores
+pub const CIRCUIT_REGISTRY_LEN: usize = 32;
+pub const MAX_CIRCUIT_URL_LEN: usize = 128;
+
+/// Field elements in PoolState (v12: 2 deposits × 15 FE + 10 globals)
+pub const POOL_STATE_FIELDS: usize = 40;
+
+/// Field elements in the v11 PoolState held by version 0 and 1 pools (2 deposits × 13 FE + 7 globals)
+pub const LEGACY_POOL_STATE_FIELDS: usize = 33;
+
+/// Current GhostPool account version; migrate_pool upgrades older pools to it
+pub const GHOST_POOL_VERSION: u8 = 2;
+
+/// Deposits each pool's DepositIntentQueue can hold until a batch folds them in
+pub const DEPOSIT_INTENT_QUEUE_LEN: usize = 16;
+
+/// Intents folded per process_deposit_batch computation (must match encrypted-ixs)
+pub const DEPOSIT_BATCH_SIZE: usize = 2;
+
+/// Deposits held by each DepositShard (must match SHARD_SIZE in encrypted-ixs)
+pub const SHARD_SIZE: u8 = 2;
+
+/// Field elements in ShardState (SHARD_SIZE deposits × 15 FE), and in the v11 one (× 13 FE)
+pub const SHARD_STATE_FIELDS: usize = 30;
+pub const LEGACY_SHARD_STATE_FIELDS: usize = 26;
+
+/// Slots an authorized withdrawal stays claimable (~10 min); also bounds a missing callback
+pub const PENDING_WITHDRAWAL_TTL_SLOTS: u64 = 1500;
+
+/// Slots after which expire_computation may roll back a PendingComputation (~20 min)
+pub const COMPUTATION_TIMEOUT_SLOTS: u64 = 3000;
+
+/// Slots a pool state write may block the next one before it is presumed lost (~1 min)
+pub const STATE_WRITE_TIMEOUT_SLOTS: u64 = 150;
+
+/// How long a pool must have been Closing before close_pool may force it shut with
+/// deposits still on the books (30 days)
+pub const POOL_CLOSE_GRACE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Length of a withdrawal cap epoch (~1 day at 400ms slots), see withdrawal_cap_per_epoch
+pub const WITHDRAWAL_EPOCH_SLOTS: u64 = 216_000;
+
+/// Shortest inactivity before a beneficiary may recover a deposit (~30 days at 400ms slots)
+pub const MIN_RECOVERY_INACTIVITY_SLOTS: u64 = 30 * WITHDRAWAL_EPOCH_SLOTS;
+
+/// Payout attempts (the claim included) before an undeliverable payout can only be re-credited
+pub const MAX_PAYOUT_ATTEMPTS: u8 = 3;
+
+/// Deposit slots in the legacy pool-level state (must match MAX_DEPOSITS in encrypted-ixs)
+pub const MAX_DEPOSITS: u8 = 2;
+
+/// Withdrawal destination owners per deposit (must match MAX_ALLOWED_DESTINATIONS in encrypted-ixs)
+pub const MAX_ALLOWED_DESTINATIONS: usize = 2;
+
+/// Signers an AdminMultisig can list
+pub const MAX_ADMIN_MULTISIG_MEMBERS: usize = 7;
+
+/// Yield venues check_investment_needed splits across (must match STRATEGY_VENUES in encrypted-ixs)
+pub const STRATEGY_VENUES: usize = 2;
+
+/// Strategy venue indices: invest_in_kamino spends the first share, invest_via_adapter the second
+pub const VENUE_KAMINO: usize = 0;
+pub const VENUE_ADAPTER: usize = 1;
+
+/// Longest cooldown update_pool_config accepts between Kamino investments (30 days)
+pub const MAX_INVESTMENT_COOLDOWN_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Longest gap update_pool_config accepts between check_and_invest calls (~1 day)
+pub const MAX_SLOTS_BETWEEN_CHECKS: u64 = WITHDRAWAL_EPOCH_SLOTS;
+
+/// Basis-point denominator, also the largest liquidity_buffer_bps (everything stays liquid)
+pub const MAX_BPS: u16 = 10_000;
+
+/// Loosest oracle slippage bound set_swap_config accepts for deposit_with_swap (1%)
+pub const MAX_SWAP_SLIPPAGE_BPS: u16 = 100;
+
+/// Oldest oracle price deposit_with_swap accepts
+pub const MAX_ORACLE_AGE_SECS: i64 = 60;
+
+/// Highest performance fee set_fee_config accepts (30% of harvested yield)
+pub const MAX_PERFORMANCE_FEE_BPS: u16 = 3_000;
+
+/// Highest management fee set_fee_config accepts (5% of the Kamino position per year)
+pub const MAX_MANAGEMENT_FEE_BPS: u16 = 500;
+
+/// Highest early-exit penalty set_early_exit_penalty accepts (10% of the withdrawal)
+pub const MAX_EARLY_EXIT_PENALTY_BPS: u16 = 1_000;
+
+/// Longest early-exit penalty window (one year)
+pub const MAX_EARLY_EXIT_WINDOW_SECS: i64 = 365 * 24 * 60 * 60;
+
+const SECONDS_PER_YEAR: u128 = 365 * 24 * 60 * 60;
+
+/// Longest lock a deposit can be given at deposit time (four years)
+pub const MAX_DEPOSIT_LOCK_SECS: u64 = 4 * 365 * 24 * 60 * 60;
+
+/// Largest deposit mint decimals a pool accepts (wSOL has 9)
+pub const MAX_DEPOSIT_DECIMALS: u8 = 12;
+
+/// Token-2022 mint extensions a pool accepts. Anything that lets a third party move vault
+/// funds (permanent delegate), needs extra transfer accounts (transfer hook) or blocks the
+/// vault's transfers (non-transferable, confidential, default-frozen) is rejected.
+/// Transfer fees are fine: deposits are credited with what lands in the vault
+const ALLOWED_MINT_EXTENSIONS: [ExtensionType; 4] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+];
+
+/// Digits of yield index precision beyond one base unit: yield_scale = 10^(decimals + 3)
+/// (1e9 for 6-decimal USDC, 1e12 for 9-decimal wSOL)
+const YIELD_SCALE_EXTRA_DIGITS: u32 = 3;
 
 // Mock Kamino Lending program ID (devnet) - use for testing
+// These are the lending_program new pools start with, see propose_lending_program
+#[cfg(not(feature = "kamino-mainnet"))]
 pub const KAMINO_LENDING_PROGRAM_ID: Pubkey = pubkey!("B4HMWFxLVtCiv9cxbsqRo77LGdcZa6P1tt8YcmEWNwC2");
 
+// Kamino Lending (KLend) mainnet program ID, selected by the `kamino-mainnet` feature
+#[cfg(feature = "kamino-mainnet")]
+pub const KAMINO_LENDING_PROGRAM_ID: Pubkey = pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
+
+/// Shortest timelock_duration a pool can run (~2 days), and the circuit registry's timelock
+pub const MIN_TIMELOCK_SLOTS: u64 = 2 * WITHDRAWAL_EPOCH_SLOTS;
+
+/// Solend (SPL token-lending) program, reachable through a `YieldVenue::Solend` adapter
+pub const SOLEND_PROGRAM_ID: Pubkey = pubkey!("So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo");
+
+/// MarginFi v2 program (`YieldVenue::MarginFi`)
+pub const MARGINFI_PROGRAM_ID: Pubkey = pubkey!("MFv2hWf31Z9kbCa1snEPYctwafyhdvnV7FZnsebVacA");
+
+/// SPL token-lending instruction tags used by Solend
+const TOKEN_LENDING_DEPOSIT_RESERVE_LIQUIDITY: u8 = 4;
+const TOKEN_LENDING_REDEEM_RESERVE_COLLATERAL: u8 = 5;
+
+/// Seed of the lending market authority PDA
+#[cfg(not(feature = "kamino-mainnet"))]
+pub const LENDING_MARKET_AUTHORITY_SEED: &[u8] = b"lending_market_authority";
+#[cfg(feature = "kamino-mainnet")]
+pub const LENDING_MARKET_AUTHORITY_SEED: &[u8] = b"lma";
+
+/// Byte offsets in Mock Kamino's Reserve account
+/// (8 disc + 1 bump, then lending_market, liquidity_mint, collateral_mint, liquidity_supply,
+/// exchange_rate, last_update_slot, total_liquidity, total_collateral, yield_rate_bps and
+/// version, then borrowed_liquidity from RESERVE_VERSION 2)
+#[cfg(not(feature = "kamino-mainnet"))]
+pub mod reserve_layout {
+    pub const LENDING_MARKET: usize = 9;
+    pub const LIQUIDITY_MINT: usize = 41;
+    pub const COLLATERAL_MINT: usize = 73;
+    pub const LIQUIDITY_SUPPLY: usize = 105;
+    pub const EXCHANGE_RATE: usize = 137;
+    pub const TOTAL_LIQUIDITY: usize = 153;
+    pub const BORROWED_LIQUIDITY: usize = 178;
+}
+
+/// Byte offsets in KLend's zero-copy Reserve account
+/// (8 disc + version + last_update, lending_market, two farm keys, then ReserveLiquidity
+/// from 128 and ReserveCollateral from 2560; `_sf` fields are u128 scaled by 2^60)
+#[cfg(feature = "kamino-mainnet")]
+pub mod reserve_layout {
+    pub const LENDING_MARKET: usize = 32;
+    pub const LIQUIDITY_MINT: usize = 128;
+    pub const LIQUIDITY_SUPPLY: usize = 160;
+    pub const AVAILABLE_AMOUNT: usize = 224;
+    pub const BORROWED_AMOUNT_SF: usize = 232;
+    pub const ACCUMULATED_PROTOCOL_FEES_SF: usize = 344;
+    pub const ACCUMULATED_REFERRER_FEES_SF: usize = 360;
+    pub const PENDING_REFERRER_FEES_SF: usize = 376;
+    pub const COLLATERAL_MINT: usize = 2560;
+    pub const COLLATERAL_MINT_TOTAL_SUPPLY: usize = 2592;
+    pub const FRACTION_BITS: u32 = 60;
+}
+
+/// Pyth receiver program, owner of PriceUpdateV2 accounts
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Byte offsets in a fully verified PriceUpdateV2 account
+/// (8 disc + 32 write_authority, then a 1-byte VerificationLevel::Full and 32-byte feed_id)
+const PYTH_VERIFICATION_LEVEL_OFFSET: usize = 40;
+const PYTH_PRICE_OFFSET: usize = 73;
+const PYTH_EXPONENT_OFFSET: usize = 89; // after price i64 + conf u64
+const PYTH_PUBLISH_TIME_OFFSET: usize = 93;
+const PYTH_VERIFICATION_FULL: u8 = 1;
+
+
 // Optimized version with lazy yield accumulation
 declare_id!("JDCZqN5FRigifouF9PsNMQRt3MxdsVTqYcbaHxS9Y3D3");
 
@@ -34,358 +285,758 @@ pub mod ghost_pool {
     use super::*;
 
     /// Initialize computation definitions for all circuits
+    /// Each takes its source from the CircuitRegistry; circuits without a pin are uploaded on-chain
     pub fn init_pool_comp_def(ctx: Context<InitPoolCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: INIT_POOL_STATE_URL.to_string(),
-                hash: circuit_hash!("init_pool_state"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("init_pool_state");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
     pub fn init_deposit_comp_def(ctx: Context<InitDepositCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: PROCESS_DEPOSIT_URL.to_string(),
-                hash: circuit_hash!("process_deposit"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("process_deposit");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
     pub fn init_check_investment_needed_comp_def(ctx: Context<InitCheckInvestmentNeededCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: CHECK_INVESTMENT_NEEDED_URL.to_string(),
-                hash: circuit_hash!("check_investment_needed"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("check_investment_needed");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
     pub fn init_record_investment_comp_def(ctx: Context<InitRecordInvestmentCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: RECORD_INVESTMENT_URL.to_string(),
-                hash: circuit_hash!("record_investment"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("record_investment");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
     pub fn init_record_yield_comp_def(ctx: Context<InitRecordYieldCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: RECORD_YIELD_URL.to_string(),
-                hash: circuit_hash!("record_yield"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("record_yield");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
     pub fn init_authorize_withdrawal_comp_def(ctx: Context<InitAuthorizeWithdrawalCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: AUTHORIZE_WITHDRAWAL_URL.to_string(),
-                hash: circuit_hash!("authorize_withdrawal"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("authorize_withdrawal");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
     pub fn init_process_withdrawal_comp_def(ctx: Context<InitProcessWithdrawalCompDef>) -> Result<()> {
-        init_comp_def(
-            ctx.accounts,
-            Some(CircuitSource::OffChain(OffChainCircuitSource {
-                source: PROCESS_WITHDRAWAL_URL.to_string(),
-                hash: circuit_hash!("process_withdrawal"),
-            })),
-            None,
-        )?;
+        let source = ctx.accounts.circuit_registry.source("process_withdrawal");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
-    /// Initialize the Ghost Pool
-    pub fn initialize_pool(
-        ctx: Context<InitializePool>,
-        computation_offset: u64,
-        nonce: u128,
-        investment_threshold: u64,
+    pub fn init_shard_comp_def(ctx: Context<InitShardCompDef>) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("init_shard_state");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_deposit_shard_comp_def(ctx: Context<InitDepositShardCompDef>) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("process_deposit_shard");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_authorize_withdrawal_shard_comp_def(
+        ctx: Context<InitAuthorizeWithdrawalShardCompDef>,
     ) -> Result<()> {
-        let pool = &mut ctx.accounts.ghost_pool;
-        pool.bump = ctx.bumps.ghost_pool;
-        pool.authority = ctx.accounts.authority.key();
-        pool.usdc_mint = ctx.accounts.usdc_mint.key();
-        pool.vault_bump = ctx.bumps.vault;
-        pool.investment_threshold = investment_threshold;
-        pool.last_investment_time = 0;
-        pool.state_nonce = nonce;
-        // Initialize encrypted_state with zeros (avoid large stack array)
-        // v4: 13 field elements (2 deposits × 4 FE + 5 globals = 416 bytes)
-        for i in 0..13 {
-            pool.encrypted_state[i] = [0u8; 32];
-        }
-        pool.total_deposits = 0;
-        pool.total_withdrawals = 0;
-        pool.total_invested = 0;
-        pool.pending_investment_amount = 0;
-        pool.collateral_token_account = Pubkey::default();
-        pool.total_collateral_received = 0;
+        let source = ctx.accounts.circuit_registry.source("authorize_withdrawal_shard");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+    pub fn init_process_withdrawal_shard_comp_def(
+        ctx: Context<InitProcessWithdrawalShardCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("process_withdrawal_shard");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
-        let args = ArgBuilder::new()
-            .plaintext_u128(nonce)
-            .build();
+    pub fn init_reveal_deposit_count_comp_def(
+        ctx: Context<InitRevealDepositCountCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("reveal_deposit_count");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
-        queue_computation(
-            ctx.accounts,
-            computation_offset,
-            args,
-            None,
-            vec![InitPoolStateCallback::callback_ix(
-                computation_offset,
-                &ctx.accounts.mxe_account,
-                &[CallbackAccount {
-                    pubkey: ctx.accounts.ghost_pool.key(),
-                    is_writable: true,
-                }],
-            )?],
-            1,
-            0,
-        )?;
+    pub fn init_publish_tvl_comp_def(ctx: Context<InitPublishTvlCompDef>) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("publish_tvl");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
+    pub fn init_compute_pool_stats_comp_def(ctx: Context<InitComputePoolStatsCompDef>) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("compute_pool_stats");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
-    #[arcium_callback(encrypted_ix = "init_pool_state")]
-    pub fn init_pool_state_callback(
-        ctx: Context<InitPoolStateCallback>,
-        output: SignedComputationOutputs<InitPoolStateOutput>,
+    pub fn init_migrate_legacy_slot_comp_def(
+        ctx: Context<InitMigrateLegacySlotCompDef>,
     ) -> Result<()> {
-        let o = match output.verify_output(
-            &ctx.accounts.cluster_account,
-            &ctx.accounts.computation_account,
-        ) {
-            Ok(InitPoolStateOutput { field_0 }) => field_0,
-            Err(_) => return Err(ErrorCode::AbortedComputation.into()),
-        };
+        let source = ctx.accounts.circuit_registry.source("migrate_legacy_slot");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
-        let pool = &mut ctx.accounts.ghost_pool;
-        // EncData output: only ciphertexts, no nonce (nonce managed by MXE)
-        pool.encrypted_state = o.ciphertexts;
-        // CRITICAL: MXE increments nonce by 1 when re-encrypting outputs
-        // We must update state_nonce to match for future operations
-        pool.state_nonce = pool.state_nonce.wrapping_add(1);
+    pub fn init_transfer_position_comp_def(
+        ctx: Context<InitTransferPositionCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("transfer_position");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
-        let pool_key = pool.key();
-        let authority_key = pool.authority;
+    pub fn init_record_yield_with_fees_comp_def(
+        ctx: Context<InitRecordYieldWithFeesCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("record_yield_with_fees");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
-        emit!(PoolInitializedEvent {
-            pool: pool_key,
-            authority: authority_key,
-        });
+    pub fn init_top_up_deposit_comp_def(
+        ctx: Context<InitTopUpDepositCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("top_up_deposit");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
 
+    pub fn init_authorize_withdrawal_all_comp_def(
+        ctx: Context<InitAuthorizeWithdrawalAllCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("authorize_withdrawal_all");
+        init_comp_def(ctx.accounts, source, None)?;
         Ok(())
     }
 
-    /// User deposits USDC into the pool
-    pub fn deposit(
-        ctx: Context<Deposit>,
-        computation_offset: u64,
-        amount: u64,
-        encrypted_password_hash: [u8; 32],  // Will be interpreted as u128
-        user_pubkey: [u8; 32],
-        nonce: u128,
+    pub fn init_get_balance_comp_def(
+        ctx: Context<InitGetBalanceCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("get_balance");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_rotate_password_comp_def(
+        ctx: Context<InitRotatePasswordCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("rotate_password");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_recover_deposit_comp_def(
+        ctx: Context<InitRecoverDepositCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("recover_deposit");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_set_strategy_weights_comp_def(
+        ctx: Context<InitSetStrategyWeightsCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("set_strategy_weights");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_process_deposit_batch_comp_def(
+        ctx: Context<InitProcessDepositBatchCompDef>,
     ) -> Result<()> {
-        // Transfer USDC from user to vault
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_usdc_token.to_account_info(),
-            to: ctx.accounts.vault_usdc_token.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
+        let source = ctx.accounts.circuit_registry.source("process_deposit_batch");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_claim_referral_rewards_comp_def(
+        ctx: Context<InitClaimReferralRewardsCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("claim_referral_rewards");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_upgrade_pool_state_comp_def(
+        ctx: Context<InitUpgradePoolStateCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("upgrade_pool_state");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    pub fn init_upgrade_shard_state_comp_def(
+        ctx: Context<InitUpgradeShardStateCompDef>,
+    ) -> Result<()> {
+        let source = ctx.accounts.circuit_registry.source("upgrade_shard_state");
+        init_comp_def(ctx.accounts, source, None)?;
+        Ok(())
+    }
+
+    /// Initialize several computation definitions in one instruction: bit `i` of `mask` selects
+    /// `COMP_DEF_CIRCUITS[i]`, and the remaining accounts are the selected comp def PDAs in bit
+    /// order. Sources come from the CircuitRegistry, as for the single init_*_comp_def
+    #[allow(unused_assignments)]
+    pub fn init_comp_defs<'info>(ctx: Context<'_, '_, 'info, 'info, InitCompDefs<'info>>, mask: u32) -> Result<()> {
+        require!(
+            mask != 0
+                && mask >> COMP_DEF_CIRCUITS.len() == 0
+                && ctx.remaining_accounts.len() == mask.count_ones() as usize,
+            ErrorCode::InvalidCompDefMask
+        );
+        let mut comp_defs = ctx.remaining_accounts.iter();
+        let mut payer = ctx.accounts.payer.clone();
+        let mut mxe_account = ctx.accounts.mxe_account.clone();
+        let mut circuit_registry = ctx.accounts.circuit_registry.clone();
+        let mut arcium_program = ctx.accounts.arcium_program.clone();
+        let mut system_program = ctx.accounts.system_program.clone();
+
+        // The circuit interface init_comp_def registers comes with each Init*CompDef context, so
+        // the shared accounts are moved through each selected circuit's context in turn (moved
+        // rather than cloned: the registry is too large to copy per circuit on a 32KB heap)
+        macro_rules! init_selected {
+            ($($bit:literal => $context:ident),* $(,)?) => {$(
+                if mask & (1 << $bit) != 0 {
+                    let source = circuit_registry.source(COMP_DEF_CIRCUITS[$bit]);
+                    let mut accounts = $context {
+                        payer,
+                        mxe_account,
+                        comp_def_account: UncheckedAccount::try_from(comp_defs.next().unwrap()),
+                        circuit_registry,
+                        arcium_program,
+                        system_program,
+                    };
+                    init_comp_def(&mut accounts, source, None)?;
+                    $context { payer, mxe_account, circuit_registry, arcium_program, system_program, .. } = accounts;
+                }
+            )*};
+        }
+        init_selected!(
+            0 => InitPoolCompDef,
+            1 => InitDepositCompDef,
+            2 => InitCheckInvestmentNeededCompDef,
+            3 => InitRecordInvestmentCompDef,
+            4 => InitRecordYieldCompDef,
+            5 => InitAuthorizeWithdrawalCompDef,
+            6 => InitProcessWithdrawalCompDef,
+            7 => InitShardCompDef,
+            8 => InitDepositShardCompDef,
+            9 => InitAuthorizeWithdrawalShardCompDef,
+            10 => InitRevealDepositCountCompDef,
+            11 => InitMigrateLegacySlotCompDef,
+            12 => InitTransferPositionCompDef,
+            13 => InitRecordYieldWithFeesCompDef,
+            14 => InitTopUpDepositCompDef,
+            15 => InitAuthorizeWithdrawalAllCompDef,
+            16 => InitGetBalanceCompDef,
+            17 => InitRotatePasswordCompDef,
+            18 => InitRecoverDepositCompDef,
+            19 => InitSetStrategyWeightsCompDef,
+            20 => InitProcessDepositBatchCompDef,
+            21 => InitClaimReferralRewardsCompDef,
+            22 => InitUpgradePoolStateCompDef,
+            23 => InitUpgradeShardStateCompDef,
+            24 => InitPublishTvlCompDef,
+            25 => InitComputePoolStatsCompDef,
+            26 => InitProcessWithdrawalShardCompDef,
+        );
+        Ok(())
+    }
+
+    /// Create the circuit registry (program upgrade authority only), pinning each of
+    /// OFFCHAIN_CIRCUITS to the matching `urls` entry, as uploaded by scripts/upload-to-pinata.ts
+    /// into circuit-urls.json, and to the local build's circuit hash. A stale upload fails comp
+    /// def verification instead of running
+    pub fn init_circuit_registry(ctx: Context<InitCircuitRegistry>, urls: Vec<String>) -> Result<()> {
+        require!(urls.len() == OFFCHAIN_CIRCUITS.len(), ErrorCode::InvalidCircuitUrl);
+        let registry = &mut ctx.accounts.circuit_registry;
+        registry.bump = ctx.bumps.circuit_registry;
+        registry.authority = ctx.accounts.authority.key();
+        registry.count = 0;
+        let hashes = [
+            circuit_hash!("init_pool_state"),
+            circuit_hash!("process_deposit"),
+            circuit_hash!("check_investment_needed"),
+            circuit_hash!("record_investment"),
+            circuit_hash!("record_yield"),
+            circuit_hash!("authorize_withdrawal"),
+            circuit_hash!("process_withdrawal"),
+        ];
+        for ((circuit, url), hash) in OFFCHAIN_CIRCUITS.iter().zip(&urls).zip(hashes) {
+            registry.pin(circuit, url, hash)?;
+        }
+        Ok(())
+    }
+
+    /// Propose pinning a circuit to a new off-chain source (registry authority only).
+    /// upgrade_circuit applies it after MIN_TIMELOCK_SLOTS; one pin is pending at a time
+    pub fn propose_circuit_upgrade(
+        ctx: Context<ProposeCircuitUpgrade>,
+        circuit: String,
+        url: String,
+        hash: [u8; 32],
+    ) -> Result<()> {
+        circuit_name(&circuit)?;
+        require!(
+            !url.is_empty() && url.len() <= MAX_CIRCUIT_URL_LEN && !url.contains('\0'),
+            ErrorCode::InvalidCircuitUrl
+        );
+
+        let action = &mut ctx.accounts.pending_action;
+        action.bump = ctx.bumps.pending_action;
+        action.target = ctx.accounts.circuit_registry.key();
+        action.proposer = ctx.accounts.authority.key();
+        action.eta_slot = Clock::get()?.slot + MIN_TIMELOCK_SLOTS;
+        action.action = AdminAction::CircuitPin { circuit, url, hash };
+
+        emit!(AdminActionProposedEvent {
+            target: action.target,
+            kind: action.action.kind(),
+            eta_slot: action.eta_slot,
+        });
+        Ok(())
+    }
+
+    /// Pin a circuit to the source proposed by propose_circuit_upgrade once its timelock has
+    /// run (registry authority only)
+    /// Arcium computation definitions can't be re-pointed once created, so the pin applies the
+    /// next time the circuit's init_*_comp_def runs: before its first init, or on a fresh MXE
+    pub fn upgrade_circuit(ctx: Context<UpgradeCircuit>) -> Result<()> {
+        let AdminAction::CircuitPin { circuit, url, hash } = ctx.accounts.pending_action.action.clone() else {
+            return err!(ErrorCode::AdminActionMismatch);
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, amount)?;
+        require!(
+            Clock::get()?.slot >= ctx.accounts.pending_action.eta_slot,
+            ErrorCode::AdminActionTimelocked
+        );
+        let version = ctx.accounts.circuit_registry.pin(&circuit, &url, hash)?;
+        msg!("Circuit {} pinned to {} (v{})", circuit, url, version);
+        emit!(CircuitUpgradedEvent { circuit, url, hash, version });
+        Ok(())
+    }
 
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+    /// Create the pool's computation log (authority only)
+    pub fn init_computation_log(ctx: Context<InitComputationLog>) -> Result<()> {
+        let log = &mut ctx.accounts.computation_log;
+        log.bump = ctx.bumps.computation_log;
+        log.pool = ctx.accounts.ghost_pool.key();
+        log.head = 0;
+        log.total_queued = 0;
+        log.entries = [ComputationLogEntry::default(); COMPUTATION_LOG_LEN];
+        Ok(())
+    }
+
+    /// Create the pool's withdrawal queue (authority only)
+    pub fn init_withdrawal_queue(ctx: Context<InitWithdrawalQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.withdrawal_queue;
+        queue.bump = ctx.bumps.withdrawal_queue;
+        queue.pool = ctx.accounts.ghost_pool.key();
+        queue.head = 0;
+        queue.tail = 0;
+        queue.entries = [WithdrawalQueueEntry::default(); WITHDRAWAL_QUEUE_LEN];
+        Ok(())
+    }
+
+    /// Bring a pool written under an older GhostPool layout up to GHOST_POOL_VERSION
+    /// (authority only). Grows the account to the current size, paying the extra rent,
+    /// then applies each version's upgrade in turn. A pool coming from version 1 or older
+    /// still holds the v11 PoolState afterwards and refuses computations until
+    /// upgrade_pool_state has re-encrypted it
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        let info = ctx.accounts.ghost_pool.to_account_info();
+        {
+            // Read by hand: an old layout doesn't deserialize until it has been resized.
+            // The authority sits right after the bump in every version
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= 41 && data[..8] == *GhostPool::DISCRIMINATOR,
+                ErrorCode::InvalidPoolAccount
+            );
+            require!(
+                data[9..41] == ctx.accounts.authority.key().to_bytes(),
+                ErrorCode::Unauthorized
+            );
+        }
+
+        let from_len = info.data_len();
+        // Every layout up to version 1 holds the v11 PoolState
+        let legacy_blob = from_len <= GhostPool::V1_SPACE;
+        if from_len < GhostPool::SPACE {
+            // New bytes are zeroed, which is the default for every appended field
+            resize_with_rent(&info, &ctx.accounts.authority, &ctx.accounts.system_program, GhostPool::SPACE)?;
+        }
+        if legacy_blob {
+            // 1 -> 2: the blob grew in place, so the fields after it move up by the difference.
+            // The gap keeps the v11 state's tail zeroed until upgrade_pool_state rewrites it
+            let tail = (GhostPool::ENCRYPTED_STATE_OFFSET + GhostPool::LEGACY_ENCRYPTED_STATE_LEN) as usize;
+            let shift = (GhostPool::ENCRYPTED_STATE_LEN - GhostPool::LEGACY_ENCRYPTED_STATE_LEN) as usize;
+            let mut data = info.try_borrow_mut_data()?;
+            if from_len > tail {
+                data.copy_within(tail..from_len, tail + shift);
+                data[tail..tail + shift].fill(0);
+            }
+        }
+
+        let mut pool = GhostPool::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        let from_version = pool.version;
+        require!(from_version <= GHOST_POOL_VERSION, ErrorCode::UnsupportedStateLayout);
+        // 0 -> 1: pools created before the version was recorded; the resize is the whole upgrade
+        if pool.lending_program == Pubkey::default() {
+            // Pools from before lending_program keep the program this build was deployed against
+            pool.lending_program = KAMINO_LENDING_PROGRAM_ID;
+        }
+        if legacy_blob {
+            // An in-flight callback would write the v11 layout over the moved blob
+            require!(pool.state_write_in_flight == Pubkey::default(), ErrorCode::StateWriteInFlight);
+            pool.state_upgrade_pending = pool.status.is_initialized();
+        }
+        pool.version = GHOST_POOL_VERSION;
+        pool.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!("Pool migrated from v{} ({} bytes) to v{}", from_version, from_len, GHOST_POOL_VERSION);
+        emit!(PoolMigratedEvent {
+            pool: info.key(),
+            from_version,
+            to_version: GHOST_POOL_VERSION,
+        });
+        Ok(())
+    }
 
-        // Convert encrypted_password_hash to u128
-        let mut hash_bytes = [0u8; 16];
-        hash_bytes.copy_from_slice(&encrypted_password_hash[..16]);
-        let _password_hash_u128 = u128::from_le_bytes(hash_bytes);
+    /// Re-encrypt a migrated pool's v11 PoolState into the current layout (authority only)
+    /// New deposit fields start empty: existing deposits have no referrer
+    pub fn upgrade_pool_state(ctx: Context<UpgradePoolState>, computation_offset: u64) -> Result<()> {
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
-        // Queue MPC computation
         let args = ArgBuilder::new()
-            .x25519_pubkey(user_pubkey)
-            .plaintext_u128(nonce)
-            .encrypted_u128(encrypted_password_hash)
-            .plaintext_u64(amount)
             .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
             .account(
                 ctx.accounts.ghost_pool.key(),
-                106, // Offset to encrypted_state (8 disc + 1 bump + 32 auth + 32 mint + 1 vault_bump + 8 threshold + 8 time + 16 nonce = 106)
-                416, // 13 * 32 bytes (2 deposits, v4)
+                GhostPool::ENCRYPTED_STATE_OFFSET,
+                GhostPool::LEGACY_ENCRYPTED_STATE_LEN,
             )
+            .plaintext_u64(ctx.accounts.ghost_pool.total_deposits)
+            .plaintext_u64(ctx.accounts.ghost_pool.total_withdrawals)
             .build();
 
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::UpgradeState,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
         queue_computation(
             ctx.accounts,
             computation_offset,
             args,
             None,
-            vec![ProcessDepositCallback::callback_ix(
+            vec![UpgradePoolStateCallback::callback_ix(
                 computation_offset,
                 &ctx.accounts.mxe_account,
-                &[CallbackAccount {
-                    pubkey: ctx.accounts.ghost_pool.key(),
-                    is_writable: true,
-                }],
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
             )?],
-            1,
-            0,
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
         )?;
 
         Ok(())
     }
 
-    #[arcium_callback(encrypted_ix = "process_deposit")]
-    pub fn process_deposit_callback(
-        ctx: Context<ProcessDepositCallback>,
-        output: SignedComputationOutputs<ProcessDepositOutput>,
+    #[arcium_callback(encrypted_ix = "upgrade_pool_state")]
+    pub fn upgrade_pool_state_callback(
+        ctx: Context<UpgradePoolStateCallback>,
+        output: SignedComputationOutputs<UpgradePoolStateOutput>,
     ) -> Result<()> {
         let o = match output.verify_output(
             &ctx.accounts.cluster_account,
             &ctx.accounts.computation_account,
         ) {
-            Ok(ProcessDepositOutput { field_0 }) => field_0,
-            Err(_) => return Err(ErrorCode::AbortedComputation.into()),
+            Ok(UpgradePoolStateOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
         };
 
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
         let pool = &mut ctx.accounts.ghost_pool;
-        // EncData output: only ciphertexts, no nonce (nonce managed by MXE)
+        let before = pool.counters();
         pool.encrypted_state = o.ciphertexts;
-        // CRITICAL: MXE increments nonce by 1 when re-encrypting outputs
         pool.state_nonce = pool.state_nonce.wrapping_add(1);
-        pool.total_deposits += 1;
-
-        let pool_key = pool.key();
-        let deposit_count = pool.total_deposits;
+        pool.state_upgrade_pending = false;
+        pool.check_invariants(&before, StateWrite::Reencrypted, None);
 
-        emit!(DepositEvent {
-            pool: pool_key,
-            deposit_count,
+        msg!("Pool state upgraded to the current layout");
+        emit!(StateUpgradedEvent {
+            pool: pool.key(),
+            account: pool.key(),
         });
 
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
         Ok(())
     }
 
-    /// Check if investment threshold reached and invest in Kamino
-    pub fn check_and_invest(
-        ctx: Context<CheckAndInvest>,
+    /// Bring a deposit shard created before the current ShardState up to date (authority only)
+    /// Grows the account, paying the extra rent, and queues the re-encryption; the shard
+    /// refuses deposits and withdrawals until its callback lands. Holds the pool's state write
+    /// slot meanwhile, as every other shard computation does
+    pub fn upgrade_shard_state(
+        ctx: Context<UpgradeShardState>,
         computation_offset: u64,
+        shard_index: u16,
     ) -> Result<()> {
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        let info = ctx.accounts.deposit_shard.to_account_info();
+        if info.data_len() == DepositShard::LEGACY_SPACE {
+            require!(
+                info.try_borrow_data()?[..8] == *DepositShard::DISCRIMINATOR,
+                ErrorCode::InvalidShardIndex
+            );
+            resize_with_rent(&info, &ctx.accounts.authority, &ctx.accounts.system_program, DepositShard::SPACE)?;
+            // slots_used follows the blob, so it moves up to the end of the grown one
+            let mut data = info.try_borrow_mut_data()?;
+            data[DepositShard::SPACE - 3] = data[DepositShard::LEGACY_SPACE - 1];
+            data[DepositShard::LEGACY_SPACE - 1] = 0;
+            data[DepositShard::SPACE - 2] = true as u8; // state_upgrade_pending
+        }
+        let shard = DepositShard::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(shard.state_upgrade_pending, ErrorCode::NoStateUpgradePending);
 
-        let threshold = ctx.accounts.ghost_pool.investment_threshold;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
         let args = ArgBuilder::new()
-            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .plaintext_u128(shard.state_nonce)
             .account(
-                ctx.accounts.ghost_pool.key(),
-                106, // Offset to encrypted_state
-                416, // 13 * 32 bytes (2 deposits, v4)
+                info.key(),
+                DepositShard::ENCRYPTED_STATE_OFFSET,
+                DepositShard::LEGACY_ENCRYPTED_STATE_LEN,
             )
-            .plaintext_u64(threshold)
             .build();
 
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::UpgradeState,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
         queue_computation(
             ctx.accounts,
             computation_offset,
             args,
             None,
-            vec![CheckInvestmentNeededCallback::callback_ix(
+            vec![UpgradeShardStateCallback::callback_ix(
                 computation_offset,
                 &ctx.accounts.mxe_account,
-                &[CallbackAccount {
-                    pubkey: ctx.accounts.ghost_pool.key(),
-                    is_writable: true,
-                }],
+                &[
+                    CallbackAccount {
+                        pubkey: info.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
             )?],
-            1,
-            0,
+            2, // ShardState (30 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
         )?;
 
+        msg!("Shard {} state upgrade queued", shard_index);
         Ok(())
     }
 
-    #[arcium_callback(encrypted_ix = "check_investment_needed")]
-    pub fn check_investment_needed_callback(
-        ctx: Context<CheckInvestmentNeededCallback>,
-        output: SignedComputationOutputs<CheckInvestmentNeededOutput>,
+    #[arcium_callback(encrypted_ix = "upgrade_shard_state")]
+    pub fn upgrade_shard_state_callback(
+        ctx: Context<UpgradeShardStateCallback>,
+        output: SignedComputationOutputs<UpgradeShardStateOutput>,
     ) -> Result<()> {
-        let decision = match output.verify_output(
+        let o = match output.verify_output(
             &ctx.accounts.cluster_account,
             &ctx.accounts.computation_account,
         ) {
-            Ok(CheckInvestmentNeededOutput { field_0 }) => field_0,
-            Err(_) => return Err(ErrorCode::AbortedComputation.into()),
+            Ok(UpgradeShardStateOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
         };
 
-        // If should invest, store the pending investment amount
-        // Actual Kamino CPI happens in a separate instruction
-        if decision.field_0 && decision.field_1 > 0 {
-            msg!("Investment approved by MPC: {} USDC", decision.field_1);
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
 
-            let pool = &mut ctx.accounts.ghost_pool;
-            pool.pending_investment_amount = decision.field_1;
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.encrypted_state = o.ciphertexts;
+        shard.state_nonce = shard.state_nonce.wrapping_add(1);
+        shard.state_upgrade_pending = false;
 
-            emit!(InvestmentApprovedEvent {
-                pool: pool.key(),
-                amount: decision.field_1,
-            });
-        } else {
-            msg!("Investment not needed at this time");
-        }
+        emit!(StateUpgradedEvent {
+            pool: shard.pool,
+            account: shard.key(),
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
 
         Ok(())
     }
 
-    /// Withdraw USDC from the pool (with password verification)
-    pub fn withdraw(
-        ctx: Context<Withdraw>,
+    /// Create the pool's deposit intent queue (authority only)
+    pub fn init_deposit_intent_queue(ctx: Context<InitDepositIntentQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.deposit_intents;
+        queue.bump = ctx.bumps.deposit_intents;
+        queue.pool = ctx.accounts.ghost_pool.key();
+        queue.head = 0;
+        queue.tail = 0;
+        queue.batch_len = 0;
+        queue.entries = [DepositIntent::default(); DEPOSIT_INTENT_QUEUE_LEN];
+        Ok(())
+    }
+
+    /// Initialize the Ghost Pool
+    /// `pool_id` lets one authority run several pools (e.g. different thresholds or strategies)
+    pub fn initialize_pool(
+        ctx: Context<InitializePool>,
         computation_offset: u64,
-        amount: u64,
-        encrypted_password_hash: [u8; 32],
-        user_pubkey: [u8; 32],
         nonce: u128,
+        investment_threshold: u64,
+        pool_id: u64,
     ) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.bump = ctx.bumps.ghost_pool;
+        pool.authority = ctx.accounts.authority.key();
+        pool.seed_authority = ctx.accounts.authority.key();
+        pool.pending_authority = Pubkey::default();
+        pool.pool_id = pool_id;
+        pool.deposit_mint = ctx.accounts.deposit_mint.key();
+        let decimals = ctx.accounts.deposit_mint.decimals;
+        require!(decimals <= MAX_DEPOSIT_DECIMALS, ErrorCode::UnsupportedMintDecimals);
+        require_supported_mint_extensions(&ctx.accounts.deposit_mint)?;
+        pool.deposit_decimals = decimals;
+        pool.yield_scale = 10u64.pow(decimals as u32 + YIELD_SCALE_EXTRA_DIGITS);
+        pool.vault_bump = ctx.bumps.vault;
+        pool.investment_threshold = investment_threshold;
+        pool.last_investment_time = 0;
+        pool.state_nonce = nonce;
+        // Initialize encrypted_state with zeros (avoid large stack array)
+        for i in 0..POOL_STATE_FIELDS {
+            pool.encrypted_state[i] = [0u8; 32];
+        }
+        pool.total_deposits = 0;
+        pool.total_withdrawals = 0;
+        pool.total_invested = 0;
+        pool.pending_investment_amount = 0;
+        pool.collateral_token_account = Pubkey::default();
+        pool.total_collateral_received = 0;
+        pool.shard_count = 0;
+        pool.next_shard = 0;
+        pool.counter_bucket = 0;
+        pool.counter_epoch_slots = 0;
+        pool.last_counter_epoch_slot = 0;
+        pool.rotator_token_account = Pubkey::default();
+        pool.rotator_bump = 0;
+        pool.rotator_max_balance = 0;
+        pool.rotator_balance = 0;
+        pool.unrecorded_investment_amount = 0;
+        pool.kamino_position_value = 0;
+        pool.legacy_frozen = false;
+        pool.legacy_retired = false;
+        pool.status = PoolStatus::Uninitialized;
+        pool.paused = 0;
+        pool.event_mode = EventMode::Anchor;
+        pool.min_deposit = 0;
+        pool.max_pool_cap = 0;
+        pool.investment_cooldown_secs = 0;
+        pool.pending_payouts = 0;
+        pool.liquidity_buffer_bps = 0;
+        pool.swap_mint = Pubkey::default();
+        pool.swap_program = Pubkey::default();
+        pool.swap_mint_oracle = Pubkey::default();
+        pool.deposit_mint_oracle = Pubkey::default();
+        pool.max_swap_slippage_bps = 0;
+        pool.queued_withdrawals = 0;
+        pool.performance_fee_bps = 0;
+        pool.management_fee_bps = 0;
+        pool.fee_recipient_token_account = Pubkey::default();
+        pool.accrued_fees = 0;
+        pool.last_fee_time = 0;
+        pool.cu_price_micro = 0;
+        pool.early_exit_penalty_bps = 0;
+        pool.early_exit_window_secs = 0;
+        pool.early_exit_penalty_to_fees = false;
+        pool.mxe_key_hash = mxe_key_hash(&ctx.accounts.mxe_account);
+        pool.mxe_rotation_pending = false;
+        pool.withdrawal_cap_per_epoch = 0;
+        pool.debits_in_flight = 0;
+        pool.password_rotations = 0;
+        pool.recovery_inactivity_slots = 0;
+        pool.pending_venue_investments = [0; STRATEGY_VENUES];
+        pool.auto_compound = false;
+        pool.uncompounded_yield = 0;
+        pool.min_slots_between_checks = 0;
+        pool.last_check_slot = 0;
+        pool.state_write_in_flight = Pubkey::default();
+        pool.state_write_slot = 0;
+        pool.queued_deposits = 0;
+        pool.version = GHOST_POOL_VERSION;
+        pool.closing_since = 0;
+        pool.needs_attention = false;
+        pool.lending_program = KAMINO_LENDING_PROGRAM_ID;
+        pool.pending_lending_program = Pubkey::default();
+        pool.lending_program_effective_slot = 0;
+        pool.position_value = 0;
+        pool.position_synced_slot = 0;
+        pool.share_mint = Pubkey::default();
+        pool.referral_share_bps = 0;
+        pool.referral_rewards_paid = 0;
+        pool.state_upgrade_pending = false;
+        pool.max_deposit_per_user = 0;
+        pool.max_relayer_rebate = 0;
+        pool.admin_multisig = Pubkey::default();
+        pool.timelock_duration = MIN_TIMELOCK_SLOTS;
+        pool.tvl_bucket = 0;
+        pool.tvl_epoch_slots = 0;
+        pool.last_tvl_slot = 0;
+        pool.last_stats_slot = 0;
+
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
         let args = ArgBuilder::new()
-            .x25519_pubkey(user_pubkey)
             .plaintext_u128(nonce)
-            .encrypted_u128(encrypted_password_hash)
-            .plaintext_u64(amount)
-            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
-            .account(
-                ctx.accounts.ghost_pool.key(),
-                106, // Offset to encrypted_state
-                416, // 13 * 32 bytes (2 deposits, v4)
-            )
             .build();
 
         queue_computation(
@@ -393,776 +1044,13062 @@ pub mod ghost_pool {
             computation_offset,
             args,
             None,
-            vec![AuthorizeWithdrawalCallback::callback_ix(
+            vec![InitPoolStateCallback::callback_ix(
                 computation_offset,
                 &ctx.accounts.mxe_account,
-                &[
-                    CallbackAccount {
-                        pubkey: ctx.accounts.ghost_pool.key(),
-                        is_writable: true,
-                    },
-                    CallbackAccount {
-                        pubkey: ctx.accounts.vault.key(),
-                        is_writable: true,
-                    },
-                    CallbackAccount {
-                        pubkey: ctx.accounts.user_token_account.key(),
-                        is_writable: true,
-                    },
-                    CallbackAccount {
-                        pubkey: ctx.accounts.token_program.key(),
-                        is_writable: false,
-                    },
-                ],
+                &[CallbackAccount {
+                    pubkey: ctx.accounts.ghost_pool.key(),
+                    is_writable: true,
+                }],
             )?],
-            1,
-            0,
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
         )?;
 
         Ok(())
     }
 
-    #[arcium_callback(encrypted_ix = "authorize_withdrawal")]
-    pub fn authorize_withdrawal_callback(
-        ctx: Context<AuthorizeWithdrawalCallback>,
-        output: SignedComputationOutputs<AuthorizeWithdrawalOutput>,
+    #[arcium_callback(encrypted_ix = "init_pool_state")]
+    pub fn init_pool_state_callback(
+        ctx: Context<InitPoolStateCallback>,
+        output: SignedComputationOutputs<InitPoolStateOutput>,
     ) -> Result<()> {
-        let auth = match output.verify_output(
+        let o = match output.verify_output(
             &ctx.accounts.cluster_account,
             &ctx.accounts.computation_account,
         ) {
-            Ok(AuthorizeWithdrawalOutput { field_0 }) => field_0,
+            Ok(InitPoolStateOutput { field_0 }) => field_0,
+            // Nothing was staged and there's no log yet: the pool just stays Uninitialized
             Err(_) => return Err(ErrorCode::AbortedComputation.into()),
         };
 
-        // Store authorization result temporarily (in a real implementation,
-        // you'd need a separate account to store this between instructions)
-        // For now, we'll just emit an event if authorized
-        if auth.field_0 && auth.field_1 > 0 {
-            let amount = auth.field_1;
-            msg!("Withdrawal authorized for amount: {} at idx: {}", amount, auth.field_2);
-
-            // Get pool info for PDA signer
-            let pool = &mut ctx.accounts.ghost_pool;
-            let pool_key = pool.key();
-            let pool_bump = pool.bump;
-            let authority = pool.authority;
-
-            // Transfer USDC from vault to user
-            let seeds = &[
-                b"ghost_pool",
-                authority.as_ref(),
-                &[pool_bump],
-            ];
-            let signer_seeds = &[&seeds[..]];
-
-            let cpi_accounts = anchor_spl::token::Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.user_token_account.to_account_info(),
-                authority: pool.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-            anchor_spl::token::transfer(cpi_ctx, amount)?;
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
 
-            msg!("Transferred {} USDC to user", amount);
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        // EncData output: only ciphertexts, no nonce (nonce managed by MXE)
+        pool.encrypted_state = o.ciphertexts;
+        // CRITICAL: MXE increments nonce by 1 when re-encrypting outputs
+        // We must update state_nonce to match for future operations
+        pool.state_nonce = pool.state_nonce.wrapping_add(1);
 
-            // Increment withdrawal counter
-            pool.total_withdrawals += 1;
+        let pool_key = pool.key();
+        let authority_key = pool.authority;
+        pool.transition(pool_key, PoolStatus::Active)?;
+        pool.check_invariants(&before, StateWrite::Reencrypted, None);
 
-            emit!(WithdrawalAuthorizedEvent {
-                pool: pool_key,
-                amount,
-                idx: auth.field_2,
-            });
-        } else {
-            return Err(ErrorCode::WithdrawalUnauthorized.into());
-        }
+        emit!(PoolInitializedEvent {
+            pool: pool_key,
+            authority: authority_key,
+        });
 
         Ok(())
     }
 
-    /// Execute Kamino deposit after MPC approval
-    /// Uses Mock Kamino's deposit_reserve_liquidity instruction
-    pub fn invest_in_kamino(ctx: Context<InvestInKamino>) -> Result<()> {
-        let pool = &ctx.accounts.ghost_pool;
-        let amount = pool.pending_investment_amount;
-
-        require!(amount > 0, ErrorCode::NoPendingInvestment);
+    /// User deposits USDC into the pool
+    /// `encrypted_password_hash` is the salted commitment hash(password, user pubkey, salt),
+    /// never the bare password hash, and the entry is bound to the signing `user`: only that
+    /// signer, with the same commitment, can withdraw or transfer it. Reusing one's own
+    /// commitment tops up the existing entry; a deposit the ledger rejects (commitment held by
+    /// another signer, no free slot, or a position past max_deposit_per_user) is refunded to
+    /// `refund_token_account` by the callback. A deposit outside min_deposit, max_deposit_per_user
+    /// or max_pool_cap fails before any tokens move.
+    /// The tokens wait in the pool's escrow until then, swept into the vault only once the
+    /// ledger has recorded the deposit; should the computation abort, `reclaim_escrow` returns them.
+    /// `encrypted_allowed_destinations` are owner fingerprints (see `pubkey_fingerprint`),
+    /// encrypted with the password hash under the same nonce; all zero allows any destination.
+    /// `encrypted_beneficiary`, under the same nonce, is the fingerprint of a signer who may
+    /// recover the deposit once it has been inactive long enough (see recover_deposit), 0 for none.
+    /// `encrypted_referrer`, under its own `referrer_nonce`, is the fingerprint of the signer who
+    /// referred the deposit, 0 for none: they earn a share of the fees booked on it (see
+    /// claim_referral_rewards) without the referral ever being public. Topping up keeps the
+    /// entry's referrer.
+    /// `lock_secs` (up to MAX_DEPOSIT_LOCK_SECS) holds the deposit in the pool for that long: the
+    /// MPC refuses withdrawals, transfers and recovery before it matures. Topping up can only
+    /// extend an existing lock. Passing a `deposit_receipt` account at `receipt_index` leaves an on-chain record of the
+    /// deposit that only holds commitments (see `DepositReceipt`).
+    /// In a pool that issues share tokens, the callback mints the deposit's shares to
+    /// `share_token_account` (see enable_share_tokens)
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],  // Will be interpreted as u128
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        encrypted_referrer: [u8; 32],
+        referrer_nonce: u128,
+        lock_secs: u64,
+        receipt_index: u32,
+        amount_blinding: [u8; 32],
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        // A delegate depositing someone else's tokens goes through deposit_delegated
+        require_keys_eq!(ctx.accounts.user_usdc_token.owner, user, ErrorCode::TokenOwnerMismatch);
+        queue_process_deposit(
+            ctx,
+            user,
+            computation_offset,
+            amount,
+            encrypted_password_hash,
+            user_pubkey,
+            nonce,
+            encrypted_allowed_destinations,
+            encrypted_beneficiary,
+            encrypted_referrer,
+            referrer_nonce,
+            lock_secs,
+            receipt_index,
+            amount_blinding,
+            cu_price_micro,
+        )
+    }
 
-        msg!("Executing Mock Kamino deposit: {} USDC", amount);
+    /// Deposit on behalf of the owner of `user_usdc_token`, for relayers and integrators: the
+    /// signer moves the tokens as the account's SPL token delegate (see `approve`) and pays the
+    /// transaction and rent, while the encrypted inputs are still the owner's own. The entry is
+    /// bound to the owner, who alone can withdraw or transfer it, and the FailedDeposit is
+    /// theirs, as are the refund and share accounts; a `deposit_receipt` stays keyed by the
+    /// signer. Otherwise exactly `deposit`
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_delegated(
+        ctx: Context<Deposit>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        encrypted_referrer: [u8; 32],
+        referrer_nonce: u128,
+        lock_secs: u64,
+        receipt_index: u32,
+        amount_blinding: [u8; 32],
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let source = &ctx.accounts.user_usdc_token;
+        let owner = source.owner;
+        require!(
+            owner != ctx.accounts.user.key() && source.delegate == COption::Some(ctx.accounts.user.key()),
+            ErrorCode::NotTokenDelegate
+        );
+        require!(source.delegated_amount >= amount, ErrorCode::NotTokenDelegate);
+        require_keys_eq!(ctx.accounts.refund_token_account.owner, owner, ErrorCode::NotTokenDelegate);
+        if let Some(share_token_account) = &ctx.accounts.share_token_account {
+            require_keys_eq!(share_token_account.owner, owner, ErrorCode::NotTokenDelegate);
+        }
 
-        // Mock Kamino's deposit_reserve_liquidity discriminator (anchor generated)
-        // sha256("global:deposit_reserve_liquidity")[0..8] = a9c91e7e06cd6644
-        let discriminator: [u8; 8] = [0xa9, 0xc9, 0x1e, 0x7e, 0x06, 0xcd, 0x66, 0x44];
+        queue_process_deposit(
+            ctx,
+            owner,
+            computation_offset,
+            amount,
+            encrypted_password_hash,
+            user_pubkey,
+            nonce,
+            encrypted_allowed_destinations,
+            encrypted_beneficiary,
+            encrypted_referrer,
+            referrer_nonce,
+            lock_secs,
+            receipt_index,
+            amount_blinding,
+            cu_price_micro,
+        )
+    }
 
-        let mut data = discriminator.to_vec();
-        data.extend_from_slice(&amount.to_le_bytes());
+    /// Deposit native SOL into a wSOL pool: wraps `amount` lamports into `user_usdc_token`
+    /// (the user's wSOL account, created beforehand, e.g. idempotently in the same transaction),
+    /// then runs `deposit` as is. An account that held no wSOL before is closed again once the
+    /// deposit has left it, unless it is also the `refund_token_account`
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_sol(
+        ctx: Context<Deposit>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        encrypted_referrer: [u8; 32],
+        referrer_nonce: u128,
+        lock_secs: u64,
+        receipt_index: u32,
+        amount_blinding: [u8; 32],
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(is_native_mint(&ctx.accounts.deposit_mint.key()), ErrorCode::NotNativeMint);
+        let temporary = ctx.accounts.user_usdc_token.amount == 0
+            && ctx.accounts.refund_token_account.key() != ctx.accounts.user_usdc_token.key();
+
+        let user = ctx.accounts.user.to_account_info();
+        let wsol = ctx.accounts.user_usdc_token.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: user.clone(),
+                    to: wsol.clone(),
+                },
+            ),
+            amount,
+        )?;
+        token_interface::sync_native(CpiContext::new(
+            token_program.clone(),
+            token_interface::SyncNative { account: wsol.clone() },
+        ))?;
+        ctx.accounts.user_usdc_token.reload()?;
+
+        deposit(
+            ctx,
+            computation_offset,
+            amount,
+            encrypted_password_hash,
+            user_pubkey,
+            nonce,
+            encrypted_allowed_destinations,
+            encrypted_beneficiary,
+            encrypted_referrer,
+            referrer_nonce,
+            lock_secs,
+            receipt_index,
+            amount_blinding,
+            cu_price_micro,
+        )?;
 
-        // Build account metas matching Mock Kamino's DepositReserveLiquidity struct
-        let accounts = vec![
-            AccountMeta::new(ctx.accounts.vault.key(), true), // owner (signer) - vault PDA signs
-            AccountMeta::new_readonly(ctx.accounts.kamino_lending_market.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.kamino_lending_market_authority.key(), false),
-            AccountMeta::new(ctx.accounts.kamino_reserve.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.reserve_liquidity_mint.key(), false),
-            AccountMeta::new(ctx.accounts.reserve_collateral_mint.key(), false),
-            AccountMeta::new(ctx.accounts.reserve_liquidity_supply.key(), false),
-            AccountMeta::new(ctx.accounts.vault.key(), false), // user_liquidity (our vault is source)
-            AccountMeta::new(ctx.accounts.user_destination_collateral.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
-        ];
+        if temporary {
+            token_interface::close_account(CpiContext::new(
+                token_program,
+                token_interface::CloseAccount {
+                    account: wsol,
+                    destination: user.clone(),
+                    authority: user,
+                },
+            ))?;
+        }
+        Ok(())
+    }
 
-        let ix = Instruction {
-            program_id: KAMINO_LENDING_PROGRAM_ID,
-            accounts,
-            data,
+    #[arcium_callback(encrypted_ix = "process_deposit")]
+    pub fn process_deposit_callback(
+        ctx: Context<ProcessDepositCallback>,
+        output: SignedComputationOutputs<ProcessDepositOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ProcessDepositOutput { field_0 }) => field_0,
+            Err(_) => {
+                let accounts = &mut ctx.accounts;
+                let computation_account = accounts.computation_account.key();
+                // A recredit goes back to PayoutPending as if rejected; a token deposit stays
+                // reclaimable through its FailedDeposit
+                if let Some(pending) = accounts.pending_withdrawal.as_mut() {
+                    pending.transition(PositionStatus::PayoutPending)?;
+                    accounts.ghost_pool.pending_payouts += pending.amount;
+                }
+                fail_deposit(&mut accounts.failed_deposit, &computation_account);
+                accounts.ghost_pool.end_state_write(&computation_account);
+                return accounts.computation_log.record_aborted(&computation_account);
+            }
         };
 
-        // Sign with vault PDA
-        let pool_key = ctx.accounts.ghost_pool.key();
-        let vault_seeds = &[
-            b"vault".as_ref(),
-            pool_key.as_ref(),
-            &[ctx.accounts.ghost_pool.vault_bump],
-        ];
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // Rejected (duplicate commitment, full ledger or per-user cap): the ledger is unchanged, so
+        // hand the funds back instead of writing the state
+        let result = o.field_1;
+        if !result.field_0 {
+            let amount = result.field_3;
+            let before = ctx.accounts.ghost_pool.counters();
+            let accounts = &mut ctx.accounts;
+            if let (Some(escrow), Some(failed_deposit), Some(mint), Some(refund), Some(token_program)) = (
+                &accounts.deposit_escrow,
+                &accounts.failed_deposit,
+                &accounts.deposit_mint,
+                &accounts.refund_token_account,
+                &accounts.token_program,
+            ) {
+                let refund = refund.to_account_info();
+                transfer_from_vault(&accounts.ghost_pool, escrow, mint, &refund, token_program, failed_deposit.amount)?;
+            } else if let (Some(vault), Some(mint), Some(refund), Some(token_program)) = (
+                &accounts.vault,
+                &accounts.deposit_mint,
+                &accounts.refund_token_account,
+                &accounts.token_program,
+            ) {
+                transfer_from_vault(&accounts.ghost_pool, vault, mint, &refund.to_account_info(), token_program, amount)?;
+            } else if let Some(pending) = accounts.pending_withdrawal.as_mut() {
+                pending.transition(PositionStatus::PayoutPending)?;
+                accounts.ghost_pool.pending_payouts += amount;
+            }
+            accounts.ghost_pool.check_invariants(
+                &before,
+                StateWrite::None,
+                accounts.vault.as_ref().map(|v| v.to_account_info()).as_ref(),
+            );
+
+            let reason = if result.field_4 {
+                "over the per-user cap"
+            } else if result.field_2 {
+                "duplicate"
+            } else {
+                "ledger full"
+            };
+            msg!("Deposit of {} rejected ({}), refunded", amount, reason);
+            emit!(DepositRejectedEvent {
+                pool: accounts.ghost_pool.key(),
+                amount,
+                ledger_full: !result.field_2,
+                over_user_cap: result.field_4,
+            });
+            settle_failed_deposit(&accounts.failed_deposit, &accounts.depositor, &accounts.computation_account.key())?;
+            accounts.ghost_pool.end_state_write(&accounts.computation_account.key());
+            accounts.computation_log.record_completed(&accounts.computation_account.key())?;
+            return Ok(());
+        }
 
-        invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.vault.to_account_info(),
-                ctx.accounts.kamino_lending_market.to_account_info(),
-                ctx.accounts.kamino_lending_market_authority.to_account_info(),
-                ctx.accounts.kamino_reserve.to_account_info(),
-                ctx.accounts.reserve_liquidity_mint.to_account_info(),
-                ctx.accounts.reserve_collateral_mint.to_account_info(),
-                ctx.accounts.reserve_liquidity_supply.to_account_info(),
-                ctx.accounts.user_destination_collateral.to_account_info(),
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.kamino_program.to_account_info(),
-            ],
-            &[vault_seeds],
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
         )?;
-
-        // Update pool state
+        let computation_offset = ctx.accounts.computation_log.computation_offset(&ctx.accounts.computation_account.key());
+        // Share pools: priced against the pool's assets before the deposit joins them
+        if let (Some(share_mint), Some(share_account), Some(vault), Some(token_program)) = (
+            &ctx.accounts.share_mint,
+            &ctx.accounts.share_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+        ) {
+            let assets = ctx.accounts.ghost_pool.net_assets(vault.amount);
+            let shares = share_amount(result.field_3, share_mint.supply, assets, false);
+            mint_shares(&ctx.accounts.ghost_pool, share_mint, share_account, token_program, shares)?;
+        }
+        // Credited: only now do the escrowed tokens join the vault
+        if let (Some(escrow), Some(failed_deposit), Some(vault), Some(mint), Some(token_program)) = (
+            &ctx.accounts.deposit_escrow,
+            &ctx.accounts.failed_deposit,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.token_program,
+        ) {
+            let vault = vault.to_account_info();
+            transfer_from_vault(&ctx.accounts.ghost_pool, escrow, mint, &vault, token_program, failed_deposit.amount)?;
+        }
         let pool = &mut ctx.accounts.ghost_pool;
-        pool.total_invested += amount;
-        pool.pending_investment_amount = 0;
-        pool.last_investment_time = Clock::get()?.unix_timestamp;
-        pool.collateral_token_account = ctx.accounts.user_destination_collateral.key();
+        let before = pool.counters();
+        // Two outputs, so the state comes back under a fresh nonce
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        // A top-up isn't a new deposit
+        if pool.counter_bucket == 0 && !result.field_1 {
+            pool.total_deposits += 1;
+        }
+        pool.check_invariants(&before, StateWrite::Fresh, None);
 
-        emit!(InvestmentExecutedEvent {
+        emit_pool_event(pool.event_mode, DepositEvent {
             pool: pool.key(),
-            amount,
+            deposit_count: pool.total_deposits,
+            amount: result.field_3,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
         });
 
-        Ok(())
-    }
-
-    /// Set the collateral token account for receiving Kamino cTokens
-    pub fn set_collateral_account(ctx: Context<SetCollateralAccount>) -> Result<()> {
-        let pool = &mut ctx.accounts.ghost_pool;
-        pool.collateral_token_account = ctx.accounts.collateral_token_account.key();
+        settle_failed_deposit(
+            &ctx.accounts.failed_deposit,
+            &ctx.accounts.depositor,
+            &ctx.accounts.computation_account.key(),
+        )?;
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
 
-        msg!("Collateral token account set: {}", pool.collateral_token_account);
         Ok(())
     }
 
-}
-
-/// Ghost Pool account
-#[account]
-pub struct GhostPool {
-    pub bump: u8,
-    pub authority: Pubkey,
-    pub usdc_mint: Pubkey,
-    pub vault_bump: u8,
+    /// Deposit the pool's secondary mint (e.g. USDT into a USDC pool), swapped into the deposit
+    /// mint in the same instruction. The route (`swap_data` + remaining accounts) is built
+    /// off-chain for the configured swap program; it must land at least the oracle value of
+    /// what it spent, less max_swap_slippage_bps, in the vault, and the ledger is credited with
+    /// what actually landed. No deposit receipt is written. If the ledger rejects the deposit,
+    /// the landed amount is refunded in the deposit mint to `refund_token_account`.
+    /// `lock_secs` and the referrer work as for `deposit`
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_with_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositWithSwap<'info>>,
+        computation_offset: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        encrypted_referrer: [u8; 32],
+        referrer_nonce: u128,
+        lock_secs: u64,
+        swap_data: Vec<u8>,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.ghost_pool.issues_shares(), ErrorCode::SharePoolDeposit);
+        require!(lock_secs <= MAX_DEPOSIT_LOCK_SECS, ErrorCode::InvalidLockDuration);
+        let now = Clock::get()?.unix_timestamp;
+        let price_in = oracle_price(&ctx.accounts.swap_mint_oracle, now)?;
+        let price_out = oracle_price(&ctx.accounts.deposit_mint_oracle, now)?;
+
+        let source_before = ctx.accounts.user_swap_token.amount;
+        let vault_before = ctx.accounts.vault_usdc_token.amount;
+
+        // Signed by the user only: the route can't move anything the pool owns
+        let route = Instruction {
+            program_id: ctx.accounts.swap_program.key(),
+            accounts: ctx
+                .remaining_accounts
+                .iter()
+                .map(|a| AccountMeta {
+                    pubkey: a.key(),
+                    is_signer: a.is_signer,
+                    is_writable: a.is_writable,
+                })
+                .collect(),
+            data: swap_data,
+        };
+        let mut route_infos = ctx.remaining_accounts.to_vec();
+        route_infos.push(ctx.accounts.swap_program.to_account_info());
+        invoke(&route, &route_infos)?;
+
+        ctx.accounts.user_swap_token.reload()?;
+        ctx.accounts.vault_usdc_token.reload()?;
+        let spent = source_before.saturating_sub(ctx.accounts.user_swap_token.amount);
+        let amount = ctx.accounts.vault_usdc_token.amount.saturating_sub(vault_before);
+        require!(spent > 0 && amount > 0, ErrorCode::InvalidSwap);
+
+        let quote = oracle_quote(
+            spent,
+            price_in,
+            ctx.accounts.swap_mint.decimals,
+            price_out,
+            ctx.accounts.deposit_mint.decimals,
+        )?;
+        let min_amount = (quote as u128
+            * (MAX_BPS - ctx.accounts.ghost_pool.max_swap_slippage_bps) as u128
+            / MAX_BPS as u128) as u64;
+        require!(amount >= min_amount, ErrorCode::SwapSlippageExceeded);
+        msg!("Swapped {} into {} (oracle minimum {})", spent, amount, min_amount);
 
-    // Investment settings
-    pub investment_threshold: u64,
-    pub last_investment_time: i64,
+        ctx.accounts.ghost_pool.check_deposit_limits(amount, ctx.accounts.vault_usdc_token.amount)?;
+        let before = ctx.accounts.ghost_pool.counters();
 
-    // Encrypted state (v4: 2 deposits with EncData output)
-    pub state_nonce: u128,
-    pub encrypted_state: [[u8; 32]; 13],  // PoolState with 2 deposits = 13 field elements (416 bytes, fits callback limit)
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
-    // Public stats
-    pub total_deposits: u64,
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_password_hash)
+            .encrypted_u128(encrypted_allowed_destinations[0])
+            .encrypted_u128(encrypted_allowed_destinations[1])
+            .encrypted_u128(encrypted_beneficiary)
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(referrer_nonce)
+            .encrypted_u128(encrypted_referrer)
+            .plaintext_u64(amount)
+            .plaintext_u64(now as u64)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(lock_secs)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(ctx.accounts.ghost_pool.max_deposit_per_user)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::Deposit,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        let [failed_deposit, depositor] = open_failed_deposit(
+            ctx.accounts.failed_deposit.as_mut(),
+            ctx.bumps.failed_deposit,
+            ctx.accounts.ghost_pool.key(),
+            ctx.accounts.user.key(),
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            amount,
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessDepositCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.vault_usdc_token.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_mint.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.refund_token_account.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.token_program.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    failed_deposit,
+                    depositor,
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault_usdc_token.to_account_info()),
+        );
+
+        emit!(DepositSwappedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            swap_mint: ctx.accounts.swap_mint.key(),
+            amount_in: spent,
+            amount_out: amount,
+        });
+
+        Ok(())
+    }
+
+    /// Add to an existing position without taking a new ledger slot
+    /// `encrypted_password_hash` is the position's password commitment and the signer must be
+    /// its owner. Accrued yield is settled into principal, `amount` is added on top and the
+    /// early-exit window restarts. If no position matches, the deposit is refunded to
+    /// `refund_token_account`
+    pub fn top_up_deposit(
+        ctx: Context<TopUpDeposit>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.ghost_pool.issues_shares(), ErrorCode::SharePoolDeposit);
+        let amount = deposit_into_vault(
+            &ctx.accounts.user,
+            &ctx.accounts.user_usdc_token,
+            &mut ctx.accounts.vault_usdc_token,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        ctx.accounts.ghost_pool.check_deposit_limits(amount, ctx.accounts.vault_usdc_token.amount)?;
+        let before = ctx.accounts.ghost_pool.counters();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_password_hash)
+            .plaintext_u64(amount)
+            .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(ctx.accounts.ghost_pool.max_deposit_per_user)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::TopUp,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        let [failed_deposit, depositor] = open_failed_deposit(
+            ctx.accounts.failed_deposit.as_mut(),
+            ctx.bumps.failed_deposit,
+            ctx.accounts.ghost_pool.key(),
+            ctx.accounts.user.key(),
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            amount,
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![TopUpDepositCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.vault_usdc_token.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_mint.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.refund_token_account.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.token_program.key(),
+                        is_writable: false,
+                    },
+                    failed_deposit,
+                    depositor,
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault_usdc_token.to_account_info()),
+        );
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "top_up_deposit")]
+    pub fn top_up_deposit_callback(
+        ctx: Context<TopUpDepositCallback>,
+        output: SignedComputationOutputs<TopUpDepositOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(TopUpDepositOutput { field_0 }) => field_0,
+            Err(_) => {
+                let accounts = &mut ctx.accounts;
+                let computation_account = accounts.computation_account.key();
+                fail_deposit(&mut accounts.failed_deposit, &computation_account);
+                accounts.ghost_pool.end_state_write(&computation_account);
+                return accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        let result = o.field_1;
+        let amount = result.field_3;
+        let accounts = &mut ctx.accounts;
+
+        // No position matched, or the top-up would pass the per-user cap: the ledger is
+        // unchanged, hand the funds back
+        if !result.field_0 {
+            let before = accounts.ghost_pool.counters();
+            transfer_from_vault(
+                &accounts.ghost_pool,
+                &accounts.vault,
+                &accounts.deposit_mint,
+                &accounts.refund_token_account.to_account_info(),
+                &accounts.token_program,
+                amount,
+            )?;
+            accounts.ghost_pool.check_invariants(
+                &before,
+                StateWrite::None,
+                Some(&accounts.vault.to_account_info()),
+            );
+
+            let reason = if result.field_4 { "over the per-user cap" } else { "no matching position" };
+            msg!("Top-up of {} rejected ({}), refunded", amount, reason);
+            emit!(DepositRejectedEvent {
+                pool: accounts.ghost_pool.key(),
+                amount,
+                ledger_full: false,
+                over_user_cap: result.field_4,
+            });
+            settle_failed_deposit(&accounts.failed_deposit, &accounts.depositor, &accounts.computation_account.key())?;
+            accounts.ghost_pool.end_state_write(&accounts.computation_account.key());
+            accounts.computation_log.record_completed(&accounts.computation_account.key())?;
+            return Ok(());
+        }
+
+        accounts.computation_log.check_state_nonce(
+            &accounts.computation_account.key(),
+            accounts.ghost_pool.state_nonce,
+        )?;
+        let pool = &mut accounts.ghost_pool;
+        let before = pool.counters();
+        // Two outputs, so the state comes back under a fresh nonce
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        emit_pool_event(pool.event_mode, DepositToppedUpEvent {
+            pool: pool.key(),
+            amount,
+        });
+
+        settle_failed_deposit(&accounts.failed_deposit, &accounts.depositor, &accounts.computation_account.key())?;
+        accounts.ghost_pool.end_state_write(&accounts.computation_account.key());
+        accounts.computation_log.record_completed(&accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Queue a deposit for the next process_deposit_batch instead of paying for its own
+    /// computation. Takes the same encrypted inputs as `deposit` and moves the tokens into the
+    /// vault now; the ledger is credited when a keeper folds the intent in, and a rejected
+    /// intent is refunded to `refund_token_account` then
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_deposit(
+        ctx: Context<QueueDeposit>,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        encrypted_referrer: [u8; 32],
+        referrer_nonce: u128,
+        lock_secs: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.ghost_pool.issues_shares(), ErrorCode::SharePoolDeposit);
+        require!(lock_secs <= MAX_DEPOSIT_LOCK_SECS, ErrorCode::InvalidLockDuration);
+        let amount = deposit_into_vault(
+            &ctx.accounts.user,
+            &ctx.accounts.user_usdc_token,
+            &mut ctx.accounts.vault_usdc_token,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        ctx.accounts.ghost_pool.check_deposit_limits(amount, ctx.accounts.vault_usdc_token.amount)?;
+        let before = ctx.accounts.ghost_pool.counters();
+
+        let position = ctx.accounts.deposit_intents.push(DepositIntent {
+            user: ctx.accounts.user.key(),
+            refund_token_account: ctx.accounts.refund_token_account.key(),
+            amount,
+            lock_secs,
+            user_pubkey,
+            nonce,
+            encrypted_secret: [
+                encrypted_password_hash,
+                encrypted_allowed_destinations[0],
+                encrypted_allowed_destinations[1],
+                encrypted_beneficiary,
+            ],
+            referrer_nonce,
+            encrypted_referrer,
+        })?;
+        ctx.accounts.ghost_pool.queued_deposits += amount;
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault_usdc_token.to_account_info()),
+        );
+
+        emit!(DepositIntentQueuedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            position,
+        });
+        Ok(())
+    }
+
+    /// Fold up to DEPOSIT_BATCH_SIZE queued deposit intents into the ledger with one
+    /// computation (permissionless, keeper-driven). Registered crankers are credited the
+    /// crank bounty
+    pub fn process_deposit_batch(ctx: Context<ProcessDepositBatch>, computation_offset: u64) -> Result<()> {
+        let queue = &ctx.accounts.deposit_intents;
+        let count = queue.len().min(DEPOSIT_BATCH_SIZE as u64) as usize;
+        require!(count > 0, ErrorCode::NoDepositIntents);
+        // Padding repeats the last real intent; the circuit ignores everything past `count`
+        let intents: [DepositIntent; DEPOSIT_BATCH_SIZE] =
+            core::array::from_fn(|b| *queue.get(b.min(count - 1)));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let clock = Clock::get()?;
+        let mut args = ArgBuilder::new();
+        for intent in &intents {
+            args = args
+                .x25519_pubkey(intent.user_pubkey)
+                .plaintext_u128(intent.nonce)
+                .encrypted_u128(intent.encrypted_secret[0])
+                .encrypted_u128(intent.encrypted_secret[1])
+                .encrypted_u128(intent.encrypted_secret[2])
+                .encrypted_u128(intent.encrypted_secret[3])
+                .x25519_pubkey(intent.user_pubkey)
+                .plaintext_u128(intent.referrer_nonce)
+                .encrypted_u128(intent.encrypted_referrer)
+                .plaintext_u64(intent.amount)
+                .plaintext_u64(intent.lock_secs)
+                .plaintext_u128(pubkey_fingerprint(&intent.user));
+        }
+        let args = args
+            .plaintext_u8(count as u8)
+            .plaintext_u64(clock.unix_timestamp as u64)
+            .plaintext_u64(clock.slot)
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(ctx.accounts.ghost_pool.max_deposit_per_user)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.deposit_intents.batch_len = count as u8;
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::DepositBatch,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.payer.key(),
+        )?;
+
+        // Refund legs for the intents in the batch; a repeated account is only passed once
+        let refund_0 = intents[0].refund_token_account;
+        let refund_1 = if count > 1 && intents[1].refund_token_account != refund_0 {
+            intents[1].refund_token_account
+        } else {
+            crate::ID
+        };
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessDepositBatchCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_intents.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.vault.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_mint.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.token_program.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: refund_0,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: refund_1,
+                        is_writable: refund_1 != crate::ID,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        credit_crank_bounty(
+            &mut ctx.accounts.ghost_pool,
+            ctx.accounts.cranker_registry.as_deref(),
+            ctx.accounts.cranker.as_deref_mut(),
+        );
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_deposit_batch")]
+    pub fn process_deposit_batch_callback(
+        ctx: Context<ProcessDepositBatchCallback>,
+        output: SignedComputationOutputs<ProcessDepositBatchOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ProcessDepositBatchOutput { field_0 }) => field_0,
+            Err(_) => {
+                // The intents stay at the head of the queue for the next batch
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.deposit_intents.batch_len = 0;
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+
+        let accepted = o.field_1.field_0;
+        let merged = o.field_1.field_1;
+        let accounts = &mut ctx.accounts;
+        let before = accounts.ghost_pool.counters();
+        let batch_len = accounts.deposit_intents.batch_len as usize;
+        let mut new_deposits = 0u64;
+
+        for b in 0..batch_len {
+            let intent = accounts.deposit_intents.pop_front();
+            accounts.ghost_pool.queued_deposits =
+                accounts.ghost_pool.queued_deposits.saturating_sub(intent.amount);
+            if accepted[b] {
+                if !merged[b] {
+                    new_deposits += 1;
+                }
+                continue;
+            }
+
+            // Rejected: hand the funds back. A refund account that can no longer take them
+            // leaves them in the vault rather than stalling the queue for everyone behind it
+            let refund = [&accounts.refund_token_account_0, &accounts.refund_token_account_1]
+                .into_iter()
+                .flatten()
+                .find(|a| a.key() == intent.refund_token_account);
+            let refunded = match refund {
+                Some(refund)
+                    if is_payable(refund, &accounts.ghost_pool.deposit_mint, accounts.token_program.key) =>
+                {
+                    transfer_from_vault(
+                        &accounts.ghost_pool,
+                        &accounts.vault,
+                        &accounts.deposit_mint,
+                        &refund.to_account_info(),
+                        &accounts.token_program,
+                        intent.amount,
+                    )?;
+                    true
+                }
+                _ => false,
+            };
+            msg!("Queued deposit of {} rejected, refunded: {}", intent.amount, refunded);
+            emit!(DepositIntentRejectedEvent {
+                pool: accounts.ghost_pool.key(),
+                amount: intent.amount,
+                refunded,
+            });
+        }
+        accounts.deposit_intents.batch_len = 0;
+
+        let pool = &mut accounts.ghost_pool;
+        // Two outputs, so the state comes back under a fresh nonce
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        if pool.counter_bucket == 0 {
+            pool.total_deposits += new_deposits;
+        }
+        pool.check_invariants(&before, StateWrite::Fresh, Some(&accounts.vault.to_account_info()));
+
+        emit!(DepositBatchProcessedEvent {
+            pool: accounts.ghost_pool.key(),
+            processed: batch_len as u8,
+            accepted: accepted[..batch_len].iter().filter(|a| **a).count() as u8,
+        });
+
+        accounts.ghost_pool.end_state_write(&accounts.computation_account.key());
+        accounts.computation_log.record_completed(&accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Check if investment threshold reached and invest in Kamino
+    /// Crank: the authority or a cranker allowed by the pool's CrankerRegistry
+    pub fn check_and_invest(
+        ctx: Context<CheckAndInvest>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        authorize_crank(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.cranker_registry.as_deref(),
+            ctx.accounts.cranker.as_deref(),
+        )?;
+
+        let pool = &ctx.accounts.ghost_pool;
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= pool.last_investment_time + pool.investment_cooldown_secs,
+            ErrorCode::InvestmentCooldown
+        );
+        require!(
+            clock.slot >= pool.last_check_slot.saturating_add(pool.min_slots_between_checks),
+            ErrorCode::CheckTooFrequent
+        );
+
+        // The MPC can't approve more than the vault holds uncommitted, so skip the fee
+        let idle = ctx.accounts.vault.amount
+            .saturating_sub(pool.pending_investment_amount)
+            .saturating_sub(pool.pending_payouts)
+            .saturating_sub(pool.queued_withdrawals)
+            .saturating_sub(pool.queued_deposits);
+        if idle < pool.investment_threshold {
+            msg!("Vault holds {} idle, below the {} threshold; not queued", idle, pool.investment_threshold);
+            return Ok(());
+        }
+        ctx.accounts.ghost_pool.last_check_slot = clock.slot;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let threshold = ctx.accounts.ghost_pool.investment_threshold;
+        let buffer_bps = ctx.accounts.ghost_pool.liquidity_buffer_bps;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u64(threshold)
+            .plaintext_u16(buffer_bps)
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::CheckInvestment,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CheckInvestmentNeededCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        credit_crank_bounty(
+            &mut ctx.accounts.ghost_pool,
+            ctx.accounts.cranker_registry.as_deref(),
+            ctx.accounts.cranker.as_deref_mut(),
+        );
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_investment_needed")]
+    pub fn check_investment_needed_callback(
+        ctx: Context<CheckInvestmentNeededCallback>,
+        output: SignedComputationOutputs<CheckInvestmentNeededOutput>,
+    ) -> Result<()> {
+        let decision = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(CheckInvestmentNeededOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // If should invest, store the pending investment amount and its per-venue split
+        // Actual venue CPIs happen in separate instructions (invest_in_kamino, invest_via_adapter)
+        if decision.field_0 && decision.field_1 > 0 {
+            msg!("Investment approved by MPC: {} USDC split {:?}", decision.field_1, decision.field_2);
+
+            let pool = &mut ctx.accounts.ghost_pool;
+            let before = pool.counters();
+            pool.pending_investment_amount = decision.field_1;
+            pool.pending_venue_investments = decision.field_2;
+            pool.check_invariants(&before, StateWrite::None, None);
+
+            emit!(InvestmentApprovedEvent {
+                pool: pool.key(),
+                amount: decision.field_1,
+            });
+        } else {
+            msg!("Investment not needed at this time");
+        }
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Replace the encrypted target weights check_investment_needed splits investments by
+    /// (authority only). `encrypted_weights` are bps per venue (VENUE_*), encrypted together
+    /// under one nonce by the authority's client; the MPC refuses weights not summing to 10_000
+    pub fn set_strategy_weights(
+        ctx: Context<SetStrategyWeights>,
+        computation_offset: u64,
+        encrypted_weights: [[u8; 32]; STRATEGY_VENUES],
+        authority_pubkey: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(authority_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u16(encrypted_weights[VENUE_KAMINO])
+            .encrypted_u16(encrypted_weights[VENUE_ADAPTER])
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::SetStrategyWeights,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SetStrategyWeightsCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) + nonce doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "set_strategy_weights")]
+    pub fn set_strategy_weights_callback(
+        ctx: Context<SetStrategyWeightsCallback>,
+        output: SignedComputationOutputs<SetStrategyWeightsOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(SetStrategyWeightsOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        require!(o.field_1, ErrorCode::StrategyWeightsRejected);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        emit!(StrategyWeightsUpdatedEvent {
+            pool: pool.key(),
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Withdraw USDC from the pool (with password verification)
+    /// `amount` may be any part of the balance: claim_withdrawal pays exactly the authorized
+    /// amount and process_withdrawal settles the position's yield before debiting it, so the
+    /// remainder keeps accruing from a fresh checkpoint. The next withdrawal from the same
+    /// position can only be authorized once that debit has landed
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let recipient = ctx.accounts.user_token_account.key();
+        require!(recipient != ctx.accounts.ghost_pool.rotator_token_account, ErrorCode::InvalidRotator);
+        queue_withdrawal(ctx, computation_offset, amount, encrypted_password_hash, user_pubkey, nonce, recipient, cu_price_micro)
+    }
+
+    /// Withdraw via the pool's rotator token account
+    /// The callback pays vault -> rotator, a keeper later forwards rotator -> recipient
+    /// in a later slot, so the vault never transfers to the recipient directly.
+    /// `user_token_account` must be the rotator; `recipient` is the final token account,
+    /// passed as `recipient_token_account` so its owner can be checked against the allowlist.
+    pub fn withdraw_via_rotator(
+        ctx: Context<Withdraw>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        recipient: Pubkey,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.ghost_pool;
+        require!(pool.rotator_token_account != Pubkey::default(), ErrorCode::RotatorNotConfigured);
+        require!(
+            ctx.accounts.user_token_account.key() == pool.rotator_token_account,
+            ErrorCode::InvalidRotator
+        );
+        require!(recipient != pool.rotator_token_account, ErrorCode::InvalidRotator);
+        require!(
+            pool.rotator_balance + amount <= pool.rotator_max_balance,
+            ErrorCode::RotatorLimitExceeded
+        );
+        let recipient_account = ctx.accounts.recipient_token_account.as_ref()
+            .ok_or(ErrorCode::InvalidRotator)?;
+        require!(recipient_account.key() == recipient, ErrorCode::InvalidRotator);
+
+        queue_withdrawal(ctx, computation_offset, amount, encrypted_password_hash, user_pubkey, nonce, recipient, cu_price_micro)
+    }
+
+    /// Withdraw with a relayer paying the transaction, the rent and the Arcium fee, for users
+    /// left without SOL. `user` still signs (as a non-paying signer) and the password hash is
+    /// encrypted to the MXE as for `withdraw`, so the relayer sees nothing it couldn't see on
+    /// chain. Pays straight to `user_token_account`, never through the rotator; the relayer is
+    /// compensated by claim_withdrawal_relayed, out of the payout. The pool must allow relaying (see
+    /// set_max_relayer_rebate)
+    pub fn withdraw_relayed(
+        ctx: Context<WithdrawRelayed>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(ctx.accounts.ghost_pool.max_relayer_rebate > 0, ErrorCode::RelayingDisabled);
+        require!(
+            ctx.accounts.user_token_account.key() != ctx.accounts.ghost_pool.rotator_token_account,
+            ErrorCode::InvalidRotator
+        );
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let destination = ctx.accounts.user_token_account.key();
+        let destination_owner = ctx.accounts.user_token_account.owner;
+        let callback_accounts = open_withdrawal(
+            ctx.accounts.ghost_pool.key(),
+            ctx.accounts.computation_log.key(),
+            &mut ctx.accounts.withdrawal_receipt,
+            ctx.bumps.withdrawal_receipt,
+            &mut ctx.accounts.pending_withdrawal,
+            ctx.bumps.pending_withdrawal,
+            ctx.accounts.user.key(),
+            destination,
+            destination,
+            Some(destination_owner),
+            computation_offset,
+        )?;
+        let args = authorize_withdrawal_args(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.user.key(),
+            user_pubkey,
+            nonce,
+            encrypted_password_hash,
+            Some(amount),
+            &destination_owner,
+        )?
+        .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::Withdraw,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AuthorizeWithdrawalCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &callback_accounts,
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    /// Withdraw a whole position without knowing its balance
+    /// authorize_withdrawal_all reveals the full principal plus yield as the authorized
+    /// amount; from there it's the usual claim_withdrawal, whose ledger debit empties the
+    /// slot and deactivates it. Yield recorded between the authorization and the debit
+    /// stays behind in the position
+    pub fn withdraw_all(
+        ctx: Context<WithdrawAll>,
+        computation_offset: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let destination = ctx.accounts.user_token_account.key();
+        require!(destination != ctx.accounts.ghost_pool.rotator_token_account, ErrorCode::InvalidRotator);
+        let destination_owner = ctx.accounts.user_token_account.owner;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let callback_accounts = open_withdrawal(
+            ctx.accounts.ghost_pool.key(),
+            ctx.accounts.computation_log.key(),
+            &mut ctx.accounts.withdrawal_receipt,
+            ctx.bumps.withdrawal_receipt,
+            &mut ctx.accounts.pending_withdrawal,
+            ctx.bumps.pending_withdrawal,
+            ctx.accounts.user.key(),
+            destination,
+            destination,
+            Some(destination_owner),
+            computation_offset,
+        )?;
+        let args = authorize_withdrawal_args(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.user.key(),
+            user_pubkey,
+            nonce,
+            encrypted_password_hash,
+            None,
+            &destination_owner,
+        )?
+        .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::Withdraw,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AuthorizeWithdrawalAllCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &callback_accounts,
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    /// Recover a deposit whose owner has gone quiet, as its designated beneficiary
+    /// recover_deposit matches the signer against the beneficiary stored with the deposit
+    /// and requires ghost_pool.recovery_inactivity_slots without an owner action. The full
+    /// balance is authorized into the beneficiary's PendingWithdrawal, claimed as usual
+    pub fn recover_deposit(
+        ctx: Context<RecoverDeposit>,
+        computation_offset: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let destination = ctx.accounts.user_token_account.key();
+        require!(destination != ctx.accounts.ghost_pool.rotator_token_account, ErrorCode::InvalidRotator);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let callback_accounts = open_withdrawal(
+            ctx.accounts.ghost_pool.key(),
+            ctx.accounts.computation_log.key(),
+            &mut ctx.accounts.withdrawal_receipt,
+            ctx.bumps.withdrawal_receipt,
+            &mut ctx.accounts.pending_withdrawal,
+            ctx.bumps.pending_withdrawal,
+            ctx.accounts.user.key(),
+            destination,
+            destination,
+            None,
+            computation_offset,
+        )?;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(ctx.accounts.ghost_pool.recovery_inactivity_slots)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RecoverDeposit,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RecoverDepositCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &callback_accounts,
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    /// Configure the rotator hot wallet used by withdraw_via_rotator (authority only)
+    pub fn init_rotator(ctx: Context<InitRotator>, max_balance: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.rotator_token_account = ctx.accounts.rotator.key();
+        pool.rotator_bump = ctx.bumps.rotator;
+        pool.rotator_max_balance = max_balance;
+        pool.rotator_balance = 0;
+
+        msg!("Rotator initialized: {} (max balance {})", pool.rotator_token_account, max_balance);
+        Ok(())
+    }
+
+    /// Update the rotator balance cap (authority only)
+    pub fn set_rotator_limit(ctx: Context<SetRotatorLimit>, max_balance: u64) -> Result<()> {
+        ctx.accounts.ghost_pool.rotator_max_balance = max_balance;
+        Ok(())
+    }
+
+    /// Forward a rotator-routed payout to its recipient (second hop)
+    /// Keeper-driven: the keeper picks a randomized delay, the program only enforces a later slot
+    pub fn forward_from_rotator(ctx: Context<ForwardFromRotator>) -> Result<()> {
+        let receipt = &ctx.accounts.withdrawal_receipt;
+        require!(Clock::get()?.slot > receipt.slot, ErrorCode::ForwardTooEarly);
+        let amount = receipt.amount;
+
+        let pool = &ctx.accounts.ghost_pool;
+        let pool_key = pool.key();
+        let pool_id = pool.pool_id.to_le_bytes();
+        let seeds = &[
+            b"ghost_pool",
+            pool.seed_authority.as_ref(),
+            &pool_id,
+            &[pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.rotator.to_account_info(),
+            mint: ctx.accounts.deposit_mint.to_account_info(),
+            to: ctx.accounts.recipient_token_account.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, ctx.accounts.deposit_mint.decimals)?;
+
+        let before = ctx.accounts.ghost_pool.counters();
+        ctx.accounts.ghost_pool.rotator_balance -= amount;
+        ctx.accounts.withdrawal_receipt.forwarded = true;
+        ctx.accounts.ghost_pool.check_invariants(&before, StateWrite::None, None);
+
+        emit!(RotatorForwardedEvent {
+            pool: pool_key,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "authorize_withdrawal")]
+    pub fn authorize_withdrawal_callback(
+        ctx: Context<AuthorizeWithdrawalCallback>,
+        output: SignedComputationOutputs<AuthorizeWithdrawalOutput>,
+    ) -> Result<()> {
+        let auth = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(AuthorizeWithdrawalOutput { field_0 }) => field_0,
+            Err(_) => {
+                // Settled like a rejection, so the request can be cancelled and made again
+                let computation_account = ctx.accounts.computation_account.key();
+                reject_pending_withdrawal(&mut ctx.accounts.pending_withdrawal, WithdrawalFailure::Aborted)?;
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        // Authorization only: the payout happens in claim_withdrawal. The computation must
+        // also have checked the destination this withdrawal pays
+        let bound = auth.field_6 == ctx.accounts.pending_withdrawal.destination_owner;
+        let authorized = auth.field_0 && auth.field_1 > 0 && bound;
+        // Authorized against a balance an earlier withdrawal hasn't been deducted from yet
+        let in_flight = authorized && ctx.accounts.ghost_pool.debits_in_flight & (1 << auth.field_2) != 0;
+        if authorized && !in_flight {
+            let amount = auth.field_1;
+            let penalty = auth.field_4;
+            msg!("Withdrawal authorized for amount: {} at idx: {} (penalty {})", amount, auth.field_2, penalty);
+
+            let pending = &mut ctx.accounts.pending_withdrawal;
+            pending.amount = amount;
+            pending.idx = auth.field_2;
+            pending.expiry_slot = Clock::get()?.slot + PENDING_WITHDRAWAL_TTL_SLOTS;
+            pending.transition(PositionStatus::Authorized)?;
+
+            let receipt = &mut ctx.accounts.withdrawal_receipt;
+            receipt.amount = amount;
+            receipt.idx = auth.field_2;
+            receipt.penalty = penalty;
+            receipt.penalty_to_fees = ctx.accounts.ghost_pool.early_exit_penalty_to_fees;
+
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalAuthorizedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                user: ctx.accounts.pending_withdrawal.user,
+                amount,
+                idx: auth.field_2,
+                computation_offset: ctx.accounts.withdrawal_receipt.computation_offset,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        } else {
+            let reason = if in_flight {
+                WithdrawalFailure::DebitInFlight
+            } else {
+                WithdrawalFailure::from_flags(auth.field_3 && bound, auth.field_5)
+            };
+            reject_pending_withdrawal(&mut ctx.accounts.pending_withdrawal, reason)?;
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                user: ctx.accounts.pending_withdrawal.user,
+                reason,
+                computation_offset: ctx.accounts.withdrawal_receipt.computation_offset,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            ctx.accounts.computation_log.record_failed(&ctx.accounts.computation_account.key())?;
+            return Ok(());
+        }
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "authorize_withdrawal_all")]
+    pub fn authorize_withdrawal_all_callback(
+        ctx: Context<AuthorizeWithdrawalAllCallback>,
+        output: SignedComputationOutputs<AuthorizeWithdrawalAllOutput>,
+    ) -> Result<()> {
+        let auth = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(AuthorizeWithdrawalAllOutput { field_0 }) => field_0,
+            Err(_) => {
+                // Settled like a rejection, so the request can be cancelled and made again
+                let computation_account = ctx.accounts.computation_account.key();
+                reject_pending_withdrawal(&mut ctx.accounts.pending_withdrawal, WithdrawalFailure::Aborted)?;
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        let bound = auth.field_6 == ctx.accounts.pending_withdrawal.destination_owner;
+        let authorized = auth.field_0 && auth.field_1 > 0 && bound;
+        // Authorized against a balance an earlier withdrawal hasn't been deducted from yet
+        let in_flight = authorized && ctx.accounts.ghost_pool.debits_in_flight & (1 << auth.field_2) != 0;
+        if !authorized || in_flight {
+            let reason = if in_flight {
+                WithdrawalFailure::DebitInFlight
+            } else {
+                WithdrawalFailure::from_flags(auth.field_3 && bound, auth.field_5)
+            };
+            reject_pending_withdrawal(&mut ctx.accounts.pending_withdrawal, reason)?;
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                user: ctx.accounts.pending_withdrawal.user,
+                reason,
+                computation_offset: ctx.accounts.withdrawal_receipt.computation_offset,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            ctx.accounts.computation_log.record_failed(&ctx.accounts.computation_account.key())?;
+            return Ok(());
+        }
+
+        // Authorization only, as for authorize_withdrawal: the payout happens in claim_withdrawal
+        let amount = auth.field_1;
+        let penalty = auth.field_4;
+        msg!("Full withdrawal authorized for amount: {} at idx: {} (penalty {})", amount, auth.field_2, penalty);
+
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        pending.amount = amount;
+        pending.idx = auth.field_2;
+        pending.expiry_slot = Clock::get()?.slot + PENDING_WITHDRAWAL_TTL_SLOTS;
+        pending.transition(PositionStatus::Authorized)?;
+
+        let receipt = &mut ctx.accounts.withdrawal_receipt;
+        receipt.amount = amount;
+        receipt.idx = auth.field_2;
+        receipt.penalty = penalty;
+        receipt.penalty_to_fees = ctx.accounts.ghost_pool.early_exit_penalty_to_fees;
+
+        emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalAuthorizedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            user: ctx.accounts.pending_withdrawal.user,
+            amount,
+            idx: auth.field_2,
+            computation_offset: ctx.accounts.withdrawal_receipt.computation_offset,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "recover_deposit")]
+    pub fn recover_deposit_callback(
+        ctx: Context<RecoverDepositCallback>,
+        output: SignedComputationOutputs<RecoverDepositOutput>,
+    ) -> Result<()> {
+        let auth = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(RecoverDepositOutput { field_0 }) => field_0,
+            Err(_) => {
+                // Settled like a rejection, so the request can be cancelled and made again
+                let computation_account = ctx.accounts.computation_account.key();
+                reject_pending_withdrawal(&mut ctx.accounts.pending_withdrawal, WithdrawalFailure::Aborted)?;
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        let authorized = auth.field_0 && auth.field_1 > 0;
+        // Authorized against a balance an earlier withdrawal hasn't been deducted from yet
+        let in_flight = authorized && ctx.accounts.ghost_pool.debits_in_flight & (1 << auth.field_2) != 0;
+        if !authorized || in_flight {
+            let reason = if in_flight {
+                WithdrawalFailure::DebitInFlight
+            } else {
+                WithdrawalFailure::Unauthorized
+            };
+            reject_pending_withdrawal(&mut ctx.accounts.pending_withdrawal, reason)?;
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                user: ctx.accounts.pending_withdrawal.user,
+                reason,
+                computation_offset: ctx.accounts.withdrawal_receipt.computation_offset,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            ctx.accounts.computation_log.record_failed(&ctx.accounts.computation_account.key())?;
+            return Ok(());
+        }
+
+        let amount = auth.field_1;
+        msg!("Recovery authorized for amount: {} at idx: {}", amount, auth.field_2);
+
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        pending.amount = amount;
+        pending.idx = auth.field_2;
+        pending.expiry_slot = Clock::get()?.slot + PENDING_WITHDRAWAL_TTL_SLOTS;
+        pending.transition(PositionStatus::Authorized)?;
+
+        let receipt = &mut ctx.accounts.withdrawal_receipt;
+        receipt.amount = amount;
+        receipt.idx = auth.field_2;
+
+        emit!(DepositRecoveredEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            beneficiary: ctx.accounts.pending_withdrawal.user,
+            amount,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Pay out an authorized withdrawal and queue the ledger update (step 2)
+    /// Transfers vault -> receipt destination, then queues process_withdrawal for the
+    /// (idx, amount) the authorize callback escrowed in the PendingWithdrawal.
+    /// A destination that can't take the payout (closed, frozen) doesn't fail the claim:
+    /// the ledger is still debited and the transfer stays on the PendingWithdrawal for
+    /// retry_payout, so the authorization isn't lost to the expiry.
+    /// In a share pool the payout burns the shares it is worth from `share_token_account`
+    pub fn claim_withdrawal(
+        ctx: Context<ClaimWithdrawal>,
+        computation_offset: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_withdrawal;
+        require!(Clock::get()?.slot <= pending.expiry_slot, ErrorCode::PendingWithdrawalExpired);
+        let amount = pending.amount;
+        let idx = pending.idx;
+        // Two authorizations against one slot: only the first may debit it
+        require!(ctx.accounts.ghost_pool.debits_in_flight & (1 << idx) == 0, ErrorCode::DebitInFlight);
+        let (debit, pool_penalty) = ctx.accounts.withdrawal_receipt.ledger_debit();
+        let before = ctx.accounts.ghost_pool.counters();
+        if ctx.accounts.withdrawal_receipt.penalty_to_fees {
+            ctx.accounts.ghost_pool.accrued_fees += ctx.accounts.withdrawal_receipt.penalty;
+        }
+        ctx.accounts.ghost_pool.debits_in_flight |= 1 << idx;
+
+        let payable = is_payable(
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.ghost_pool.deposit_mint,
+            ctx.accounts.token_program.key,
+        ) && pays_authorized_owner(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.pending_withdrawal,
+            &ctx.accounts.destination_token_account,
+        );
+        if payable {
+            burn_shares(
+                &ctx.accounts.ghost_pool,
+                ctx.accounts.share_mint.as_deref(),
+                ctx.accounts.share_token_account.as_deref(),
+                &ctx.accounts.user,
+                Some(&ctx.accounts.token_program),
+                amount,
+                ctx.accounts.ghost_pool.net_assets(ctx.accounts.vault.amount),
+            )?;
+            pay_out(
+                &mut ctx.accounts.ghost_pool,
+                &ctx.accounts.vault,
+                &ctx.accounts.deposit_mint,
+                &ctx.accounts.destination_token_account,
+                &ctx.accounts.token_program,
+                &mut ctx.accounts.withdrawal_receipt,
+                amount,
+            )?;
+            ctx.accounts.pending_withdrawal.transition(PositionStatus::Claimed)?;
+        } else {
+            let pending = &mut ctx.accounts.pending_withdrawal;
+            pending.payout_attempts = 1;
+            pending.transition(PositionStatus::PayoutPending)?;
+            ctx.accounts.ghost_pool.pending_payouts += amount;
+            msg!("Destination cannot take the payout, kept for retry_payout");
+            emit!(PayoutFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                attempts: 1,
+            });
+        }
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u8(idx)
+            .plaintext_u64(debit)
+            .plaintext_u64(pool_penalty)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::ClaimWithdrawal,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessWithdrawalCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.withdrawal_receipt.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        if payable {
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalClaimedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                amount,
+            });
+            ctx.accounts.pending_withdrawal.close(ctx.accounts.user.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    /// claim_withdrawal for a withdraw_relayed withdrawal, the relayer again paying the
+    /// transaction and Arcium fee with `user` co-signing. `rebate` (at most the pool's
+    /// max_relayer_rebate, and less than the withdrawal) goes to `relayer_token_account` and the
+    /// rest to the destination; the ledger is debited the whole withdrawal. The rebate is paid
+    /// even if the user's payout has to wait for retry_payout
+    pub fn claim_withdrawal_relayed(
+        ctx: Context<ClaimWithdrawalRelayed>,
+        computation_offset: u64,
+        rebate: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_withdrawal;
+        require!(Clock::get()?.slot <= pending.expiry_slot, ErrorCode::PendingWithdrawalExpired);
+        require!(
+            rebate <= ctx.accounts.ghost_pool.max_relayer_rebate && rebate < pending.amount,
+            ErrorCode::RelayerRebateTooHigh
+        );
+        let withdrawn = pending.amount;
+        // The user gets the rest, however the payout goes
+        let amount = withdrawn - rebate;
+        let idx = pending.idx;
+        // Two authorizations against one slot: only the first may debit it
+        require!(ctx.accounts.ghost_pool.debits_in_flight & (1 << idx) == 0, ErrorCode::DebitInFlight);
+        let (debit, pool_penalty) = ctx.accounts.withdrawal_receipt.ledger_debit();
+        let before = ctx.accounts.ghost_pool.counters();
+        if ctx.accounts.withdrawal_receipt.penalty_to_fees {
+            ctx.accounts.ghost_pool.accrued_fees += ctx.accounts.withdrawal_receipt.penalty;
+        }
+        ctx.accounts.ghost_pool.debits_in_flight |= 1 << idx;
+
+        let payable = is_payable(
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.ghost_pool.deposit_mint,
+            ctx.accounts.token_program.key,
+        ) && pays_authorized_owner(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.pending_withdrawal,
+            &ctx.accounts.destination_token_account,
+        );
+        // The rebate's shares burn now, the user's with whichever attempt pays them
+        burn_shares(
+            &ctx.accounts.ghost_pool,
+            ctx.accounts.share_mint.as_deref(),
+            ctx.accounts.share_token_account.as_deref(),
+            &ctx.accounts.user,
+            Some(&ctx.accounts.token_program),
+            if payable { withdrawn } else { rebate },
+            ctx.accounts.ghost_pool.net_assets(ctx.accounts.vault.amount),
+        )?;
+        if rebate > 0 {
+            transfer_from_vault(
+                &ctx.accounts.ghost_pool,
+                &ctx.accounts.vault,
+                &ctx.accounts.deposit_mint,
+                &ctx.accounts.relayer_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                rebate,
+            )?;
+            emit!(RelayerRebatePaidEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                relayer_token_account: ctx.accounts.relayer_token_account.key(),
+                amount: rebate,
+            });
+        }
+        if payable {
+            pay_out(
+                &mut ctx.accounts.ghost_pool,
+                &ctx.accounts.vault,
+                &ctx.accounts.deposit_mint,
+                &ctx.accounts.destination_token_account,
+                &ctx.accounts.token_program,
+                &mut ctx.accounts.withdrawal_receipt,
+                amount,
+            )?;
+            ctx.accounts.pending_withdrawal.transition(PositionStatus::Claimed)?;
+        } else {
+            // retry_payout replays what is left for the user
+            let pending = &mut ctx.accounts.pending_withdrawal;
+            pending.amount = amount;
+            pending.payout_attempts = 1;
+            pending.transition(PositionStatus::PayoutPending)?;
+            ctx.accounts.ghost_pool.pending_payouts += amount;
+            msg!("Destination cannot take the payout, kept for retry_payout");
+            emit!(PayoutFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                attempts: 1,
+            });
+        }
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u8(idx)
+            .plaintext_u64(debit)
+            .plaintext_u64(pool_penalty)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::ClaimWithdrawal,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessWithdrawalCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.withdrawal_receipt.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        if payable {
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalClaimedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                amount,
+            });
+            ctx.accounts.pending_withdrawal.close(ctx.accounts.user.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    /// claim_withdrawal for a wSOL pool that unwraps the payout: once paid, the destination
+    /// (the user's own wSOL account, named at withdraw time) is closed to the user, so its whole
+    /// balance arrives as SOL. A payout left pending for retry_payout keeps the account open
+    pub fn withdraw_sol(
+        ctx: Context<ClaimWithdrawal>,
+        computation_offset: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(is_native_mint(&ctx.accounts.deposit_mint.key()), ErrorCode::NotNativeMint);
+        require!(
+            ctx.accounts.withdrawal_receipt.recipient == ctx.accounts.withdrawal_receipt.destination,
+            ErrorCode::RotatorPayout
+        );
+        let payable = is_payable(
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.ghost_pool.deposit_mint,
+            ctx.accounts.token_program.key,
+        ) && pays_authorized_owner(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.pending_withdrawal,
+            &ctx.accounts.destination_token_account,
+        );
+
+        let user = ctx.accounts.user.to_account_info();
+        let wsol = ctx.accounts.destination_token_account.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        claim_withdrawal(ctx, computation_offset, cu_price_micro)?;
+
+        if payable {
+            token_interface::close_account(CpiContext::new(
+                token_program,
+                token_interface::CloseAccount {
+                    account: wsol,
+                    destination: user.clone(),
+                    authority: user,
+                },
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Queue an authorized withdrawal the vault can't fund right now (funds are in Kamino)
+    /// Debits the ledger like claim_withdrawal, but instead of paying it appends the position
+    /// to the pool's WithdrawalQueue, where it no longer expires. fulfill_withdrawal pays it
+    /// once redemptions have refilled the vault; a share pool burns the shares here, at queue time
+    pub fn request_withdrawal(
+        ctx: Context<RequestWithdrawal>,
+        computation_offset: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_withdrawal;
+        require!(Clock::get()?.slot <= pending.expiry_slot, ErrorCode::PendingWithdrawalExpired);
+        let amount = pending.amount;
+        let idx = pending.idx;
+        // Two authorizations against one slot: only the first may debit it
+        require!(ctx.accounts.ghost_pool.debits_in_flight & (1 << idx) == 0, ErrorCode::DebitInFlight);
+        require!(ctx.accounts.vault.amount < amount, ErrorCode::VaultCanPayWithdrawal);
+        burn_shares(
+            &ctx.accounts.ghost_pool,
+            ctx.accounts.share_mint.as_deref(),
+            ctx.accounts.share_token_account.as_deref(),
+            &ctx.accounts.user,
+            ctx.accounts.token_program.as_ref(),
+            amount,
+            ctx.accounts.ghost_pool.net_assets(ctx.accounts.vault.amount),
+        )?;
+
+        let position = ctx.accounts.withdrawal_queue.push(ctx.accounts.pending_withdrawal.key(), amount)?;
+        ctx.accounts.pending_withdrawal.transition(PositionStatus::Queued)?;
+        let (debit, pool_penalty) = ctx.accounts.withdrawal_receipt.ledger_debit();
+        let before = ctx.accounts.ghost_pool.counters();
+        ctx.accounts.ghost_pool.queued_withdrawals += amount;
+        if ctx.accounts.withdrawal_receipt.penalty_to_fees {
+            ctx.accounts.ghost_pool.accrued_fees += ctx.accounts.withdrawal_receipt.penalty;
+        }
+        ctx.accounts.ghost_pool.debits_in_flight |= 1 << idx;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u8(idx)
+            .plaintext_u64(debit)
+            .plaintext_u64(pool_penalty)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RequestWithdrawal,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessWithdrawalCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.withdrawal_receipt.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(&before, StateWrite::None, None);
+
+        emit!(WithdrawalQueuedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            amount,
+            position,
+        });
+
+        Ok(())
+    }
+
+    /// Pay the withdrawal at the head of the WithdrawalQueue (permissionless, keeper-driven)
+    /// Strict FIFO: only the head can be fulfilled, and only once the vault covers it.
+    /// A head whose destination can't take the payout moves to PayoutPending (retry_payout)
+    /// instead of blocking the queue. Registered crankers are credited the crank bounty
+    pub fn fulfill_withdrawal(ctx: Context<FulfillWithdrawal>) -> Result<()> {
+        let amount = ctx.accounts.pending_withdrawal.amount;
+        require!(ctx.accounts.vault.amount >= amount, ErrorCode::VaultLacksLiquidity);
+
+        ctx.accounts.withdrawal_queue.pop_front();
+        let before = ctx.accounts.ghost_pool.counters();
+        ctx.accounts.ghost_pool.queued_withdrawals =
+            ctx.accounts.ghost_pool.queued_withdrawals.saturating_sub(amount);
+
+        if !(is_payable(
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.ghost_pool.deposit_mint,
+            ctx.accounts.token_program.key,
+        ) && pays_authorized_owner(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.pending_withdrawal,
+            &ctx.accounts.destination_token_account,
+        )) {
+            let pending = &mut ctx.accounts.pending_withdrawal;
+            pending.payout_attempts = 1;
+            pending.transition(PositionStatus::PayoutPending)?;
+            ctx.accounts.ghost_pool.pending_payouts += amount;
+            ctx.accounts.ghost_pool.check_invariants(
+                &before,
+                StateWrite::None,
+                Some(&ctx.accounts.vault.to_account_info()),
+            );
+            msg!("Destination cannot take the payout, kept for retry_payout");
+            emit!(PayoutFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                attempts: 1,
+            });
+            credit_crank_bounty(
+                &mut ctx.accounts.ghost_pool,
+                ctx.accounts.cranker_registry.as_deref(),
+                ctx.accounts.cranker.as_deref_mut(),
+            );
+            return Ok(());
+        }
+
+        pay_out(
+            &mut ctx.accounts.ghost_pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.token_program,
+            &mut ctx.accounts.withdrawal_receipt,
+            amount,
+        )?;
+        ctx.accounts.pending_withdrawal.transition(PositionStatus::Claimed)?;
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalClaimedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            amount,
+        });
+
+        credit_crank_bounty(
+            &mut ctx.accounts.ghost_pool,
+            ctx.accounts.cranker_registry.as_deref(),
+            ctx.accounts.cranker.as_deref_mut(),
+        );
+
+        ctx.accounts.pending_withdrawal.close(ctx.accounts.user.to_account_info())
+    }
+
+    /// Release a PendingWithdrawal that was never claimed (or never authorized) in time
+    /// The receipt stays unpaid and can be closed separately
+    pub fn cancel_pending_withdrawal(ctx: Context<CancelPendingWithdrawal>) -> Result<()> {
+        require!(
+            Clock::get()?.slot > ctx.accounts.pending_withdrawal.expiry_slot,
+            ErrorCode::PendingWithdrawalNotExpired
+        );
+        ctx.accounts.pending_withdrawal.transition(PositionStatus::Cancelled)
+    }
+
+    /// Retry a payout claim_withdrawal could not deliver, up to MAX_PAYOUT_ATTEMPTS in total
+    /// Pays the transfer recorded on the PendingWithdrawal (the ledger was debited at claim
+    /// time). An attempt against a destination that still can't take it is counted rather
+    /// than failing the transaction, so the bound holds. Shares are burned by the attempt that pays
+    pub fn retry_payout(ctx: Context<RetryPayout>) -> Result<()> {
+        let pending = &mut ctx.accounts.pending_withdrawal;
+        require!(pending.payout_attempts < MAX_PAYOUT_ATTEMPTS, ErrorCode::PayoutRetriesExhausted);
+        pending.payout_attempts += 1;
+        let attempts = pending.payout_attempts;
+        let amount = pending.amount;
+
+        if !(is_payable(
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.ghost_pool.deposit_mint,
+            ctx.accounts.token_program.key,
+        ) && pays_authorized_owner(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.pending_withdrawal,
+            &ctx.accounts.destination_token_account,
+        )) {
+            msg!("Payout attempt {} of {} failed", attempts, MAX_PAYOUT_ATTEMPTS);
+            emit!(PayoutFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                attempts,
+            });
+            return Ok(());
+        }
+
+        let before = ctx.accounts.ghost_pool.counters();
+        // The payout is still set aside in pending_payouts: count it back into the assets
+        burn_shares(
+            &ctx.accounts.ghost_pool,
+            ctx.accounts.share_mint.as_deref(),
+            ctx.accounts.share_token_account.as_deref(),
+            &ctx.accounts.user,
+            Some(&ctx.accounts.token_program),
+            amount,
+            ctx.accounts.ghost_pool.net_assets(ctx.accounts.vault.amount).saturating_add(amount),
+        )?;
+        pay_out(
+            &mut ctx.accounts.ghost_pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.token_program,
+            &mut ctx.accounts.withdrawal_receipt,
+            amount,
+        )?;
+        ctx.accounts.ghost_pool.pending_payouts = ctx.accounts.ghost_pool.pending_payouts.saturating_sub(amount);
+        ctx.accounts.pending_withdrawal.transition(PositionStatus::Claimed)?;
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalClaimedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            amount,
+        });
+
+        ctx.accounts.pending_withdrawal.close(ctx.accounts.user.to_account_info())
+    }
+
+    /// Give up on an undeliverable payout and re-credit its amount to the encrypted ledger
+    /// Allowed once every payout attempt is spent and the claim's debit has landed. The
+    /// compensating computation is process_deposit without the token leg (the funds never
+    /// left the vault), under a password hash, allowlist and referrer the user submits afresh
+    pub fn recredit_payout(
+        ctx: Context<RecreditPayout>,
+        computation_offset: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        encrypted_referrer: [u8; 32],
+        referrer_nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        let amount = ctx.accounts.pending_withdrawal.amount;
+        ctx.accounts.pending_withdrawal.transition(PositionStatus::Recredited)?;
+        let before = ctx.accounts.ghost_pool.counters();
+        ctx.accounts.ghost_pool.pending_payouts = ctx.accounts.ghost_pool.pending_payouts.saturating_sub(amount);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_password_hash)
+            .encrypted_u128(encrypted_allowed_destinations[0])
+            .encrypted_u128(encrypted_allowed_destinations[1])
+            .encrypted_u128(encrypted_beneficiary)
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(referrer_nonce)
+            .encrypted_u128(encrypted_referrer)
+            .plaintext_u64(amount)
+            .plaintext_u64(0) // Penalty already taken at claim, no new window
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(0) // No new lock, a merged entry keeps its own
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(0) // Paying the user back isn't a deposit, so no per-user cap
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RecreditPayout,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessDepositCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.pending_withdrawal.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: crate::ID,
+                        is_writable: false,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(&before, StateWrite::None, None);
+
+        emit!(PayoutRecreditedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Deduct a paid withdrawal from the encrypted ledger
+    /// Permissionless retry for claims whose process_withdrawal computation did not land:
+    /// consumes the (idx, amount) recorded on the receipt
+    pub fn finalize_withdrawal(
+        ctx: Context<ProcessWithdrawForQueue>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let receipt = &ctx.accounts.withdrawal_receipt;
+        let (debit, pool_penalty) = receipt.ledger_debit();
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u8(receipt.idx)
+            .plaintext_u64(debit)
+            .plaintext_u64(pool_penalty)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::FinalizeWithdrawal,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessWithdrawalCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.withdrawal_receipt.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_withdrawal")]
+    pub fn process_withdrawal_callback(
+        ctx: Context<ProcessWithdrawalCallback>,
+        output: SignedComputationOutputs<ProcessWithdrawalOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ProcessWithdrawalOutput { field_0 }) => field_0,
+            Err(_) => {
+                // The withdrawal is already paid or queued, so its debit stays in flight for
+                // finalize_withdrawal to retry
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let computation_offset = ctx.accounts.computation_log.computation_offset(&ctx.accounts.computation_account.key());
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.ciphertexts;
+        pool.state_nonce = pool.state_nonce.wrapping_add(1);
+        pool.check_invariants(&before, StateWrite::Reencrypted, None);
+
+        // Checked again here: two finalize computations may have been queued back to back
+        let receipt = &mut ctx.accounts.withdrawal_receipt;
+        require!(!receipt.finalized, ErrorCode::WithdrawalAlreadyFinalized);
+        receipt.finalized = true;
+        pool.debits_in_flight &= !(1 << receipt.idx);
+
+        emit_pool_event(pool.event_mode, WithdrawalCompletedEvent {
+            pool: pool.key(),
+            user: receipt.user,
+            amount: receipt.amount,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Transfer part of one confidential position to another inside the encrypted ledger
+    /// The sender's password authorizes; the recipient supplies its own encrypted password
+    /// hash (with its x25519 pubkey and nonce) out of band. No tokens move, so the only
+    /// public trace is the amount and whether the transfer succeeded, never the two positions
+    pub fn transfer_position(
+        ctx: Context<TransferPosition>,
+        computation_offset: u64,
+        amount: u64,
+        sender_password_hash: [u8; 32],
+        sender_pubkey: [u8; 32],
+        sender_nonce: u128,
+        recipient_password_hash: [u8; 32],
+        recipient_pubkey: [u8; 32],
+        recipient_nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidTransferAmount);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(sender_pubkey)
+            .plaintext_u128(sender_nonce)
+            .encrypted_u128(sender_password_hash)
+            .plaintext_u64(amount)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .x25519_pubkey(recipient_pubkey)
+            .plaintext_u128(recipient_nonce)
+            .encrypted_u128(recipient_password_hash)
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.withdrawal_cap_per_epoch)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::TransferPosition,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![TransferPositionCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) + nonce doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "transfer_position")]
+    pub fn transfer_position_callback(
+        ctx: Context<TransferPositionCallback>,
+        output: SignedComputationOutputs<TransferPositionOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(TransferPositionOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // A rejected transfer leaves the ledger as it was, so there is nothing to write
+        require!(o.field_1, ErrorCode::TransferRejected);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        emit!(PositionTransferredEvent {
+            pool: pool.key(),
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Swap the password commitment guarding the signer's position for a new one
+    /// Both commitments are encrypted together under one nonce. The old one must match an
+    /// active position bound to the signer. The only public trace is the pool's rotation
+    /// counter, never which position rotated
+    pub fn rotate_password(
+        ctx: Context<RotatePassword>,
+        computation_offset: u64,
+        encrypted_old_password_hash: [u8; 32],
+        encrypted_new_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_old_password_hash)
+            .encrypted_u128(encrypted_new_password_hash)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RotatePassword,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RotatePasswordCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) + nonce doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "rotate_password")]
+    pub fn rotate_password_callback(
+        ctx: Context<RotatePasswordCallback>,
+        output: SignedComputationOutputs<RotatePasswordOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(RotatePasswordOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // A rejected rotation leaves the ledger as it was, so there is nothing to write
+        require!(o.field_1, ErrorCode::PasswordRotationRejected);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        pool.password_rotations += 1;
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        emit!(PasswordRotatedEvent {
+            pool: pool.key(),
+            rotation_count: pool.password_rotations,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Ask the MPC for a position's current balance (principal plus accrued yield)
+    /// The balance comes back encrypted to `user_pubkey` in the signer's BalanceQueryResult,
+    /// which the client decrypts locally; nothing about it is revealed on-chain. The PDA is
+    /// reused by later queries
+    pub fn query_balance(
+        ctx: Context<QueryBalance>,
+        computation_offset: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let query = &mut ctx.accounts.balance_query;
+        query.bump = ctx.bumps.balance_query;
+        query.pool = ctx.accounts.ghost_pool.key();
+        query.user = ctx.accounts.user.key();
+        query.computation_account = ctx.accounts.computation_account.key();
+        query.slot = 0;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_password_hash)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::QueryBalance,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![GetBalanceCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.balance_query.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "get_balance")]
+    pub fn get_balance_callback(
+        ctx: Context<GetBalanceCallback>,
+        output: SignedComputationOutputs<GetBalanceOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(GetBalanceOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        // A newer query may have reused the PDA while this one was in flight
+        require!(
+            ctx.accounts.balance_query.computation_account == ctx.accounts.computation_account.key(),
+            ErrorCode::StaleBalanceQuery
+        );
+
+        let query = &mut ctx.accounts.balance_query;
+        query.encrypted_balance = o.ciphertexts[0];
+        query.nonce = o.nonce;
+        query.slot = Clock::get()?.slot;
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Configure public counter privacy (authority only)
+    /// bucket = 0 keeps exact per-deposit counters; otherwise total_deposits and
+    /// total_withdrawals are only refreshed by close_counter_epoch, rounded to the nearest `bucket`
+    pub fn set_counter_privacy(
+        ctx: Context<SetCounterPrivacy>,
+        bucket: u16,
+        epoch_slots: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.counter_bucket = bucket;
+        pool.counter_epoch_slots = epoch_slots;
+        pool.last_counter_epoch_slot = Clock::get()?.slot;
+
+        msg!("Counter privacy set: bucket {}, epoch {} slots", bucket, epoch_slots);
+        Ok(())
+    }
+
+    /// Close the current counter epoch and publish the bucketed deposit and withdrawal counts
+    /// Permissionless, but at most once per `counter_epoch_slots`
+    pub fn close_counter_epoch(
+        ctx: Context<CloseCounterEpoch>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(pool.counter_bucket > 0, ErrorCode::CountersNotBucketed);
+
+        let slot = Clock::get()?.slot;
+        require!(
+            slot >= pool.last_counter_epoch_slot + pool.counter_epoch_slots,
+            ErrorCode::CounterEpochNotOver
+        );
+        pool.last_counter_epoch_slot = slot;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u16(ctx.accounts.ghost_pool.counter_bucket)
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RevealDepositCount,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.payer.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealDepositCountCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_deposit_count")]
+    pub fn reveal_deposit_count_callback(
+        ctx: Context<RevealDepositCountCallback>,
+        output: SignedComputationOutputs<RevealDepositCountOutput>,
+    ) -> Result<()> {
+        let totals = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(RevealDepositCountOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // State is only read, so the nonce does not advance
+        // Rounding can land below a count published exactly before bucketing was turned on,
+        // which stays as it is until the rounded lifetime count passes it
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.total_deposits = pool.total_deposits.max(totals.field_0);
+        pool.total_withdrawals = pool.total_withdrawals.max(totals.field_1);
+        pool.check_invariants(&before, StateWrite::None, None);
+
+        emit!(CounterEpochClosedEvent {
+            pool: pool.key(),
+            bucketed_deposits: totals.field_0,
+            bucketed_withdrawals: totals.field_1,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Configure soft TVL disclosure (authority only)
+    /// bucket = 0 turns publish_tvl and publish_stats off; otherwise they reveal amounts rounded
+    /// to a multiple of `bucket` (in base units of the deposit mint), each at most once per
+    /// `epoch_slots`
+    pub fn set_tvl_disclosure(ctx: Context<UpdatePoolConfig>, bucket: u64, epoch_slots: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.tvl_bucket = bucket;
+        pool.tvl_epoch_slots = epoch_slots;
+        pool.last_tvl_slot = Clock::get()?.slot;
+        pool.last_stats_slot = pool.last_tvl_slot;
+
+        msg!("TVL disclosure set: bucket {}, epoch {} slots", bucket, epoch_slots);
+        Ok(())
+    }
+
+    /// Publish the noise-padded, bucketed TVL
+    /// Permissionless, but at most once per `tvl_epoch_slots`: each publication is a fresh
+    /// noisy sample, and averaging many of them would wear the noise away
+    pub fn publish_tvl(ctx: Context<PublishTvl>, computation_offset: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(pool.tvl_bucket > 0, ErrorCode::TvlDisclosureDisabled);
+
+        let slot = Clock::get()?.slot;
+        require!(
+            slot >= pool.last_tvl_slot + pool.tvl_epoch_slots,
+            ErrorCode::TvlEpochNotOver
+        );
+        pool.last_tvl_slot = slot;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u64(ctx.accounts.ghost_pool.tvl_bucket)
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::PublishTvl,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.payer.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![PublishTvlCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "publish_tvl")]
+    pub fn publish_tvl_callback(
+        ctx: Context<PublishTvlCallback>,
+        output: SignedComputationOutputs<PublishTvlOutput>,
+    ) -> Result<()> {
+        let tvl = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(PublishTvlOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // State is only read, so the nonce does not advance
+        let pool = &ctx.accounts.ghost_pool;
+        emit!(TvlPublishedEvent {
+            pool: pool.key(),
+            tvl,
+            bucket: pool.tvl_bucket,
+            slot: Clock::get()?.slot,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Publish aggregate pool stats: the depositor count (rounded like the public counters),
+    /// and the median position and unclaimed yield rounded to `tvl_bucket`
+    /// Permissionless, but at most once per `tvl_epoch_slots`
+    pub fn publish_stats(ctx: Context<PublishStats>, computation_offset: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(pool.tvl_bucket > 0, ErrorCode::TvlDisclosureDisabled);
+
+        let slot = Clock::get()?.slot;
+        require!(
+            slot >= pool.last_stats_slot + pool.tvl_epoch_slots,
+            ErrorCode::TvlEpochNotOver
+        );
+        pool.last_stats_slot = slot;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u16(ctx.accounts.ghost_pool.counter_bucket.max(1))
+            .plaintext_u64(ctx.accounts.ghost_pool.tvl_bucket)
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::PublishStats,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.payer.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ComputePoolStatsCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "compute_pool_stats")]
+    pub fn compute_pool_stats_callback(
+        ctx: Context<ComputePoolStatsCallback>,
+        output: SignedComputationOutputs<ComputePoolStatsOutput>,
+    ) -> Result<()> {
+        let stats = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ComputePoolStatsOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // State is only read, so the nonce does not advance
+        let pool = &ctx.accounts.ghost_pool;
+        emit!(PoolStatsEvent {
+            pool: pool.key(),
+            active_depositors: stats.field_0,
+            median_principal: stats.field_1,
+            unclaimed_yield: stats.field_2,
+            bucket: pool.tvl_bucket,
+            slot: Clock::get()?.slot,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Create the next deposit shard (authority only)
+    /// Shards extend the ledger beyond the MAX_DEPOSITS slots held in the pool itself
+    pub fn init_deposit_shard(
+        ctx: Context<InitDepositShard>,
+        computation_offset: u64,
+        shard_index: u16,
+        nonce: u128,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(shard_index == pool.shard_count, ErrorCode::InvalidShardIndex);
+        pool.shard_count += 1;
+
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.bump = ctx.bumps.deposit_shard;
+        shard.pool = pool.key();
+        shard.shard_index = shard_index;
+        shard.state_nonce = nonce;
+        shard.encrypted_state = [[0u8; 32]; SHARD_STATE_FIELDS];
+        shard.slots_used = 0;
+        shard.state_upgrade_pending = false;
+        shard.debits_in_flight = 0;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(nonce)
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::InitShard,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![InitShardStateCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_shard.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // ShardState (30 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "init_shard_state")]
+    pub fn init_shard_state_callback(
+        ctx: Context<InitShardStateCallback>,
+        output: SignedComputationOutputs<InitShardStateOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(InitShardStateOutput { field_0 }) => field_0,
+            Err(_) => return ctx.accounts.computation_log.record_aborted(&ctx.accounts.computation_account.key()),
+        };
+
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.encrypted_state = o.ciphertexts;
+        shard.state_nonce = shard.state_nonce.wrapping_add(1);
+
+        emit!(ShardInitializedEvent {
+            pool: shard.pool,
+            shard: shard.key(),
+            shard_index: shard.shard_index,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Deposit USDC into the pool's current shard
+    /// Deposits are routed to `ghost_pool.next_shard`, which advances once the shard is full.
+    /// `lock_secs` locks the deposit as for `deposit`
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_to_shard(
+        ctx: Context<DepositToShard>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+        encrypted_beneficiary: [u8; 32],
+        lock_secs: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.ghost_pool.issues_shares(), ErrorCode::SharePoolDeposit);
+        require!(lock_secs <= MAX_DEPOSIT_LOCK_SECS, ErrorCode::InvalidLockDuration);
+        let amount = deposit_into_vault(
+            &ctx.accounts.user,
+            &ctx.accounts.user_usdc_token,
+            &mut ctx.accounts.vault_usdc_token,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        ctx.accounts.ghost_pool.check_deposit_limits(amount, ctx.accounts.vault_usdc_token.amount)?;
+        let before = ctx.accounts.ghost_pool.counters();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        // Reserve a slot now so concurrent deposits can't oversubscribe the shard
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.slots_used += 1;
+        if shard.slots_used >= SHARD_SIZE {
+            ctx.accounts.ghost_pool.next_shard += 1;
+        }
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_password_hash)
+            .encrypted_u128(encrypted_allowed_destinations[0])
+            .encrypted_u128(encrypted_allowed_destinations[1])
+            .encrypted_u128(encrypted_beneficiary)
+            .plaintext_u64(amount)
+            .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(lock_secs)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u128(ctx.accounts.deposit_shard.state_nonce)
+            .shard_state(&ctx.accounts.deposit_shard)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::DepositShard,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        let [failed_deposit, depositor] = open_failed_deposit(
+            ctx.accounts.failed_deposit.as_mut(),
+            ctx.bumps.failed_deposit,
+            ctx.accounts.ghost_pool.key(),
+            ctx.accounts.user.key(),
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            amount,
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessDepositShardCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_shard.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    failed_deposit,
+                    depositor,
+                ],
+            )?],
+            3, // Pool state + shard state (70 FE)
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault_usdc_token.to_account_info()),
+        );
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_deposit_shard")]
+    pub fn process_deposit_shard_callback(
+        ctx: Context<ProcessDepositShardCallback>,
+        output: SignedComputationOutputs<ProcessDepositShardOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ProcessDepositShardOutput { field_0 }) => field_0,
+            Err(_) => {
+                // The shard never took the slot reserved at queue time; the tokens stay
+                // reclaimable through the deposit's FailedDeposit
+                let accounts = &mut ctx.accounts;
+                let computation_account = accounts.computation_account.key();
+                accounts.deposit_shard.slots_used = accounts.deposit_shard.slots_used.saturating_sub(1);
+                fail_deposit(&mut accounts.failed_deposit, &computation_account);
+                accounts.ghost_pool.end_state_write(&computation_account);
+                return accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let computation_offset = ctx.accounts.computation_log.computation_offset(&ctx.accounts.computation_account.key());
+        // Two MXE outputs, so each carries its own nonce back
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        if pool.counter_bucket == 0 {
+            pool.total_deposits += 1;
+        }
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.encrypted_state = o.field_1.ciphertexts;
+        shard.state_nonce = o.field_1.nonce;
+
+        emit_pool_event(pool.event_mode, DepositEvent {
+            pool: pool.key(),
+            deposit_count: pool.total_deposits,
+            amount: 0,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        settle_failed_deposit(
+            &ctx.accounts.failed_deposit,
+            &ctx.accounts.depositor,
+            &ctx.accounts.computation_account.key(),
+        )?;
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Withdraw USDC against a deposit held in a shard (with password verification)
+    /// The caller names the shard their deposit was routed to, which narrows the
+    /// anonymity set to that shard's depositors. The callback pays straight away; the shard
+    /// entry is debited by finalize_shard_withdrawal (step 2)
+    pub fn withdraw_from_shard(
+        ctx: Context<WithdrawFromShard>,
+        computation_offset: u64,
+        amount: u64,
+        encrypted_password_hash: [u8; 32],
+        user_pubkey: [u8; 32],
+        nonce: u128,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let receipt = &mut ctx.accounts.withdrawal_receipt;
+        receipt.bump = ctx.bumps.withdrawal_receipt;
+        receipt.pool = ctx.accounts.ghost_pool.key();
+        receipt.user = ctx.accounts.user.key();
+        receipt.destination = ctx.accounts.user_token_account.key();
+        receipt.recipient = ctx.accounts.user_token_account.key();
+        receipt.forwarded = false;
+        receipt.amount = 0;
+        receipt.slot = 0;
+        receipt.computation_offset = computation_offset;
+        receipt.paid = false;
+        receipt.idx = 0;
+        receipt.from_shard = true;
+        receipt.finalized = false;
+        receipt.penalty = 0;
+        receipt.penalty_to_fees = false;
+        receipt.shard_index = ctx.accounts.deposit_shard.shard_index;
+
+        let args = ArgBuilder::new()
+            .x25519_pubkey(user_pubkey)
+            .plaintext_u128(nonce)
+            .encrypted_u128(encrypted_password_hash)
+            .plaintext_u64(amount)
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user_token_account.owner))
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.user.key()))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+            .plaintext_u16(ctx.accounts.ghost_pool.early_exit_penalty_bps)
+            .plaintext_u64(ctx.accounts.ghost_pool.early_exit_window_secs as u64)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.withdrawal_cap_per_epoch)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u128(ctx.accounts.deposit_shard.state_nonce)
+            .shard_state(&ctx.accounts.deposit_shard)?
+            .build();
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::WithdrawShard,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AuthorizeWithdrawalShardCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_shard.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.vault.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_mint.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.user_token_account.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.token_program.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.withdrawal_receipt.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            1,
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "authorize_withdrawal_shard")]
+    pub fn authorize_withdrawal_shard_callback(
+        ctx: Context<AuthorizeWithdrawalShardCallback>,
+        output: SignedComputationOutputs<AuthorizeWithdrawalShardOutput>,
+    ) -> Result<()> {
+        let auth = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(AuthorizeWithdrawalShardOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        // Paid straight from here, so the authorization must be for this account's owner
+        let bound = auth.field_6 == pubkey_fingerprint(&ctx.accounts.user_token_account.owner);
+        if !(auth.field_0 && auth.field_1 > 0 && bound) {
+            // The receipt stays unpaid; there's no position to settle
+            let reason = WithdrawalFailure::from_flags(auth.field_3 && bound, auth.field_5);
+            msg!("Shard withdrawal rejected: {:?}", reason);
+            emit_pool_event(ctx.accounts.ghost_pool.event_mode, WithdrawalFailedEvent {
+                pool: ctx.accounts.ghost_pool.key(),
+                user: ctx.accounts.withdrawal_receipt.user,
+                reason,
+                computation_offset: ctx.accounts.withdrawal_receipt.computation_offset,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            ctx.accounts.computation_log.record_failed(&ctx.accounts.computation_account.key())?;
+            return Ok(());
+        }
+
+        // Authorized against a balance an earlier payout hasn't been deducted from yet
+        require!(
+            ctx.accounts.deposit_shard.debits_in_flight & (1 << auth.field_2) == 0,
+            ErrorCode::DebitInFlight
+        );
+        let amount = auth.field_1;
+        let penalty = auth.field_4;
+        msg!("Shard withdrawal authorized for amount: {} at idx: {} (penalty {})", amount, auth.field_2, penalty);
+        let before = ctx.accounts.ghost_pool.counters();
+
+        transfer_from_vault(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        if pool.counter_bucket == 0 {
+            pool.total_withdrawals += 1;
+        }
+        // A pool-bound penalty is shared out by the debit, see WithdrawalReceipt::ledger_debit
+        if pool.early_exit_penalty_to_fees {
+            pool.accrued_fees += penalty;
+        }
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+        // Paid, so until finalize_shard_withdrawal lands the entry's balance is stale
+        ctx.accounts.deposit_shard.debits_in_flight |= 1 << auth.field_2;
+
+        let receipt = &mut ctx.accounts.withdrawal_receipt;
+        receipt.amount = amount;
+        receipt.slot = Clock::get()?.slot;
+        receipt.paid = true;
+        receipt.idx = auth.field_2;
+        receipt.penalty = penalty;
+        receipt.penalty_to_fees = pool.early_exit_penalty_to_fees;
+
+        emit_pool_event(pool.event_mode, WithdrawalAuthorizedEvent {
+            pool: pool.key(),
+            user: receipt.user,
+            amount,
+            idx: auth.field_2,
+            computation_offset: receipt.computation_offset,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Deduct a paid shard withdrawal from the encrypted ledger (step 2 of withdraw_from_shard)
+    /// Permissionless, like finalize_withdrawal: consumes the (idx, amount) the authorize
+    /// callback recorded on the receipt. Until it lands the entry takes no other withdrawal
+    pub fn finalize_shard_withdrawal(
+        ctx: Context<FinalizeShardWithdrawal>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let receipt = &ctx.accounts.withdrawal_receipt;
+        let (debit, pool_penalty) = receipt.ledger_debit();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u128(ctx.accounts.deposit_shard.state_nonce)
+            .shard_state(&ctx.accounts.deposit_shard)?
+            .plaintext_u8(receipt.idx)
+            .plaintext_u64(debit)
+            .plaintext_u64(pool_penalty)
+            .plaintext_u64(Clock::get()?.slot)
+            .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::FinalizeWithdrawal,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.user.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ProcessWithdrawalShardCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_shard.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.withdrawal_receipt.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            3, // Pool state + shard state (70 FE)
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_withdrawal_shard")]
+    pub fn process_withdrawal_shard_callback(
+        ctx: Context<ProcessWithdrawalShardCallback>,
+        output: SignedComputationOutputs<ProcessWithdrawalShardOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ProcessWithdrawalShardOutput { field_0 }) => field_0,
+            Err(_) => {
+                // The withdrawal is already paid, so its debit stays in flight for
+                // finalize_shard_withdrawal to retry
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let computation_offset = ctx.accounts.computation_log.computation_offset(&ctx.accounts.computation_account.key());
+        // Two MXE outputs, so each carries its own nonce back
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.encrypted_state = o.field_1.ciphertexts;
+        shard.state_nonce = o.field_1.nonce;
+
+        // Checked again here: two finalize computations may have been queued back to back
+        let receipt = &mut ctx.accounts.withdrawal_receipt;
+        require!(!receipt.finalized, ErrorCode::WithdrawalAlreadyFinalized);
+        receipt.finalized = true;
+        shard.debits_in_flight &= !(1 << receipt.idx);
+
+        emit_pool_event(pool.event_mode, WithdrawalCompletedEvent {
+            pool: pool.key(),
+            user: receipt.user,
+            amount: receipt.amount,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Stop new deposits into the legacy pool-level slots (authority only)
+    /// Withdrawals and in-flight callbacks keep working until the slots are migrated or drained
+    pub fn freeze_legacy_pool(ctx: Context<FreezeLegacyPool>) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.legacy_frozen = true;
+
+        emit!(LegacyPoolFrozenEvent {
+            pool: pool.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Move one legacy deposit slot into the shard currently accepting deposits (authority only)
+    /// Run once per slot after `freeze_legacy_pool`; the callback retires the legacy
+    /// layout once every slot is empty
+    pub fn migrate_legacy_deposit(
+        ctx: Context<MigrateLegacyDeposit>,
+        computation_offset: u64,
+        idx: u8,
+    ) -> Result<()> {
+        require!(idx < MAX_DEPOSITS, ErrorCode::InvalidLegacySlot);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        // Reserve the destination slot, released again if the legacy slot was empty
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.slots_used += 1;
+        if shard.slots_used >= SHARD_SIZE {
+            ctx.accounts.ghost_pool.next_shard += 1;
+        }
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u128(ctx.accounts.deposit_shard.state_nonce)
+            .shard_state(&ctx.accounts.deposit_shard)?
+            .plaintext_u8(idx)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::MigrateLegacy,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![MigrateLegacySlotCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_shard.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            3, // Pool state + shard state, same as deposit_to_shard
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "migrate_legacy_slot")]
+    pub fn migrate_legacy_slot_callback(
+        ctx: Context<MigrateLegacySlotCallback>,
+        output: SignedComputationOutputs<MigrateLegacySlotOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(MigrateLegacySlotOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        let moved = o.field_2;
+        let legacy_empty = o.field_3;
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        if legacy_empty {
+            pool.legacy_retired = true;
+        }
+        pool.check_invariants(&before, StateWrite::Fresh, None);
+
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.encrypted_state = o.field_1.ciphertexts;
+        shard.state_nonce = o.field_1.nonce;
+        if !moved {
+            shard.slots_used = shard.slots_used.saturating_sub(1);
+        }
+
+        emit!(LegacyDepositMigratedEvent {
+            pool: pool.key(),
+            shard_index: shard.shard_index,
+            moved,
+            legacy_retired: pool.legacy_retired,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Execute Kamino deposit after MPC approval
+    /// Uses deposit_reserve_liquidity on Mock Kamino, or KLend with the `kamino-mainnet` feature
+    /// Reverts with SlippageExceeded if the reserve mints fewer than `min_collateral_out` cTokens
+    pub fn invest_in_kamino(ctx: Context<InvestInKamino>, min_collateral_out: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &ctx.accounts.ghost_pool;
+        let amount = pool.pending_venue_investments[VENUE_KAMINO];
+
+        require!(amount > 0, ErrorCode::NoPendingInvestment);
+
+        msg!("Executing Kamino deposit: {} USDC", amount);
+
+        let vault_before = ctx.accounts.vault.amount;
+        let collateral_before = ctx.accounts.user_destination_collateral.amount;
+
+        // Sign with vault PDA
+        let pool_key = ctx.accounts.ghost_pool.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            pool_key.as_ref(),
+            &[ctx.accounts.ghost_pool.vault_bump],
+        ];
+        ctx.accounts.reserve_cpi_accounts().deposit_liquidity(amount, &[vault_seeds])?;
+
+        // Book what the CPI actually did, not what it was asked to do
+        ctx.accounts.vault.reload()?;
+        ctx.accounts.user_destination_collateral.reload()?;
+        let collateral_received = ctx.accounts.user_destination_collateral.amount
+            .saturating_sub(collateral_before);
+        require_settled(vault_before, ctx.accounts.vault.amount, amount, collateral_received)?;
+        require!(collateral_received >= min_collateral_out, ErrorCode::SlippageExceeded);
+        msg!("Received {} cTokens", collateral_received);
+
+        // Update pool state
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.book_investment(VENUE_KAMINO, amount, collateral_received, ctx.accounts.user_destination_collateral.key())?;
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+
+        emit!(InvestmentExecutedEvent {
+            pool: pool.key(),
+            amount,
+            collateral_received,
+            total_collateral_received: pool.total_collateral_received,
+        });
+
+        Ok(())
+    }
+
+    /// Redeem cTokens from Kamino back into the vault (authority only)
+    /// Replenishes vault liquidity so withdrawals don't stall when most USDC is invested
+    /// Reverts with SlippageExceeded if the vault receives less than `min_liquidity_out`
+    pub fn redeem_from_kamino(
+        ctx: Context<RedeemFromKamino>,
+        collateral_amount: u64,
+        min_liquidity_out: u64,
+    ) -> Result<()> {
+        require!(collateral_amount > 0, ErrorCode::InvalidRedeemAmount);
+        let collateral_before = ctx.accounts.collateral_token_account.amount;
+        require!(collateral_amount <= collateral_before, ErrorCode::InsufficientCollateral);
+
+        msg!("Redeeming {} cTokens from Kamino", collateral_amount);
+
+        // Sign with vault PDA
+        let pool_key = ctx.accounts.ghost_pool.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            pool_key.as_ref(),
+            &[ctx.accounts.ghost_pool.vault_bump],
+        ];
+
+        let vault_before = ctx.accounts.vault.amount;
+        ctx.accounts.reserve_cpi_accounts().redeem_collateral(collateral_amount, &[vault_seeds])?;
+
+        ctx.accounts.vault.reload()?;
+        let liquidity_amount = ctx.accounts.vault.amount.saturating_sub(vault_before);
+        require!(liquidity_amount >= min_liquidity_out, ErrorCode::SlippageExceeded);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.book_redemption(collateral_amount, collateral_before, liquidity_amount);
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+
+        emit!(RedemptionExecutedEvent {
+            pool: pool.key(),
+            collateral_amount,
+            liquidity_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Reinvest realized yield from the vault back into Kamino (authority only, auto_compound)
+    /// Run after harvest_yield's record_yield has landed, in the same keeper run. Only idle
+    /// vault funds are used, so compounding never competes with approved investments or
+    /// owed payouts
+    pub fn compound(ctx: Context<CompoundYield>) -> Result<()> {
+        let pool = &ctx.accounts.ghost_pool;
+        let idle = ctx.accounts.vault.amount
+            .saturating_sub(pool.pending_investment_amount)
+            .saturating_sub(pool.pending_payouts)
+            .saturating_sub(pool.queued_withdrawals)
+            .saturating_sub(pool.queued_deposits);
+        let amount = pool.uncompounded_yield.min(idle);
+        require!(amount > 0, ErrorCode::NothingToCompound);
+
+        msg!("Compounding {} USDC of yield into Kamino", amount);
+
+        let collateral_before = ctx.accounts.collateral_token_account.amount;
+
+        // Sign with vault PDA
+        let pool_key = ctx.accounts.ghost_pool.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            pool_key.as_ref(),
+            &[ctx.accounts.ghost_pool.vault_bump],
+        ];
+        ctx.accounts.reserve_cpi_accounts().deposit_liquidity(amount, &[vault_seeds])?;
+
+        ctx.accounts.collateral_token_account.reload()?;
+        let collateral_received = ctx.accounts.collateral_token_account.amount
+            .saturating_sub(collateral_before);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.book_compound(amount, collateral_received);
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+
+        emit!(CompoundedEvent {
+            pool: pool.key(),
+            amount,
+            new_total_invested: pool.total_invested,
+        });
+
+        Ok(())
+    }
+
+    /// Register the pool's yield venue (authority only)
+    /// `reserve` is the venue account the adapter invests into (Kamino/Solend reserve) and must
+    /// belong to the venue's program. The venue can only be switched while nothing is invested.
+    /// Kamino markets hold a reserve per mint, so the reserve must be the deposit mint's, and
+    /// the adapter goes to the pool's lending_program
+    pub fn set_yield_adapter(ctx: Context<SetYieldAdapter>, venue: YieldVenue) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(ctx.accounts.ghost_pool.total_collateral_received == 0, ErrorCode::YieldAdapterActive);
+        let mut program_id = venue.program_id().ok_or(ErrorCode::YieldVenueUnsupported)?;
+        if matches!(venue, YieldVenue::Mock | YieldVenue::Kamino) {
+            program_id = ctx.accounts.ghost_pool.lending_program;
+        }
+        require_keys_eq!(*ctx.accounts.reserve.owner, program_id, ErrorCode::InvalidReserve);
+        if matches!(venue, YieldVenue::Mock | YieldVenue::Kamino) {
+            require_keys_eq!(
+                reserve_pubkey(&ctx.accounts.reserve, reserve_layout::LIQUIDITY_MINT)?,
+                ctx.accounts.ghost_pool.deposit_mint,
+                ErrorCode::InvalidReserve
+            );
+        }
+
+        let adapter = &mut ctx.accounts.yield_adapter;
+        adapter.bump = ctx.bumps.yield_adapter;
+        adapter.pool = ctx.accounts.ghost_pool.key();
+        adapter.venue = venue;
+        adapter.program_id = program_id;
+        adapter.reserve = ctx.accounts.reserve.key();
+
+        msg!("Yield adapter set to {:?} reserve {}", venue, adapter.reserve);
+        Ok(())
+    }
+
+    /// Invest the adapter venue's share of the MPC-approved amount through the registered yield
+    /// adapter. Same bookkeeping as invest_in_kamino; the venue's accounts are passed as remaining
+    /// accounts in the order documented on `AdapterCpi`
+    pub fn invest_via_adapter<'info>(
+        ctx: Context<'_, '_, 'info, 'info, InvestViaAdapter<'info>>,
+    ) -> Result<()> {
+        let amount = ctx.accounts.ghost_pool.pending_venue_investments[VENUE_ADAPTER];
+        msg!("Investing {} USDC via {:?}", amount, ctx.accounts.yield_adapter.venue);
+
+        let vault_before = ctx.accounts.vault.amount;
+        let collateral_before = ctx.accounts.collateral_token_account.amount;
+        let pool_key = ctx.accounts.ghost_pool.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            pool_key.as_ref(),
+            &[ctx.accounts.ghost_pool.vault_bump],
+        ];
+        ctx.accounts.adapter_cpi(ctx.remaining_accounts).deposit(amount, &[vault_seeds])?;
+
+        ctx.accounts.vault.reload()?;
+        ctx.accounts.collateral_token_account.reload()?;
+        let collateral_received = ctx.accounts.collateral_token_account.amount
+            .saturating_sub(collateral_before);
+        require_settled(vault_before, ctx.accounts.vault.amount, amount, collateral_received)?;
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.book_investment(VENUE_ADAPTER, amount, collateral_received, ctx.accounts.collateral_token_account.key())?;
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+
+        emit!(InvestmentExecutedEvent {
+            pool: pool.key(),
+            amount,
+            collateral_received,
+            total_collateral_received: pool.total_collateral_received,
+        });
+
+        Ok(())
+    }
+
+    /// Redeem collateral from the registered yield adapter back into the vault (authority only)
+    /// Same bookkeeping as redeem_from_kamino
+    pub fn divest_via_adapter<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DivestViaAdapter<'info>>,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        require!(collateral_amount > 0, ErrorCode::InvalidRedeemAmount);
+        let collateral_before = ctx.accounts.collateral_token_account.amount;
+        require!(collateral_amount <= collateral_before, ErrorCode::InsufficientCollateral);
+
+        msg!("Redeeming {} cTokens via {:?}", collateral_amount, ctx.accounts.yield_adapter.venue);
+
+        let pool_key = ctx.accounts.ghost_pool.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            pool_key.as_ref(),
+            &[ctx.accounts.ghost_pool.vault_bump],
+        ];
+        let vault_before = ctx.accounts.vault.amount;
+        ctx.accounts.adapter_cpi(ctx.remaining_accounts).withdraw(collateral_amount, &[vault_seeds])?;
+
+        ctx.accounts.vault.reload()?;
+        let liquidity_amount = ctx.accounts.vault.amount.saturating_sub(vault_before);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.book_redemption(collateral_amount, collateral_before, liquidity_amount);
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+
+        emit!(RedemptionExecutedEvent {
+            pool: pool.key(),
+            collateral_amount,
+            liquidity_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Record an executed Kamino investment in the encrypted state (authority only)
+    /// Consumes `unrecorded_investment_amount`, so each executed investment is recorded once
+    pub fn record_investment_mpc(
+        ctx: Context<RecordInvestmentMpc>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        let amount = pool.unrecorded_investment_amount;
+        require!(amount > 0, ErrorCode::NoUnrecordedInvestment);
+        pool.unrecorded_investment_amount = 0;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u64(amount)
+            .build();
+
+        ctx.accounts.pending_computation.open(
+            ctx.bumps.pending_computation,
+            ctx.accounts.ghost_pool.key(),
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            OpKind::RecordInvestment,
+            ctx.accounts.authority.key(),
+            ProvisionalState::UnrecordedInvestment { amount },
+        )?;
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RecordInvestment,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RecordInvestmentCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.pending_computation.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.authority.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "record_investment")]
+    pub fn record_investment_callback(
+        ctx: Context<RecordInvestmentCallback>,
+        output: SignedComputationOutputs<RecordInvestmentOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(RecordInvestmentOutput { field_0 }) => field_0,
+            Err(_) => {
+                // As for expire_computation; the PendingComputation closes with the callback
+                let accounts = &mut ctx.accounts;
+                let computation_account = accounts.computation_account.key();
+                accounts.pending_computation.provisional.roll_back(&mut accounts.ghost_pool);
+                accounts.ghost_pool.end_state_write(&computation_account);
+                return accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pending = &ctx.accounts.pending_computation;
+        let amount = match pending.provisional {
+            ProvisionalState::UnrecordedInvestment { amount } => amount,
+            _ => 0,
+        };
+        let computation_offset = pending.computation_offset;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.ciphertexts;
+        pool.state_nonce = pool.state_nonce.wrapping_add(1);
+        pool.check_invariants(&before, StateWrite::Reencrypted, None);
+
+        emit!(InvestmentRecordedEvent {
+            pool: pool.key(),
+            amount,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Harvest realized Kamino yield into the encrypted state
+    /// Values the pool's cTokens at the reserve exchange rate and records the gain
+    /// since the last harvest via the record_yield circuit.
+    /// Crank: the authority or a cranker allowed by the pool's CrankerRegistry
+    pub fn harvest_yield(
+        ctx: Context<HarvestYield>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        authorize_crank(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.cranker_registry.as_deref(),
+            ctx.accounts.cranker.as_deref(),
+        )?;
+
+        let exchange_rate = reserve_exchange_rate(&ctx.accounts.kamino_reserve)?;
+        let current_value = collateral_value(ctx.accounts.collateral_token_account.amount, exchange_rate);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(!pool.charges_fees(), ErrorCode::FeesConfigured);
+        let yield_amount = current_value.saturating_sub(pool.kamino_position_value);
+        require!(yield_amount > 0, ErrorCode::NoYieldToHarvest);
+        pool.kamino_position_value = current_value;
+
+        msg!("Harvesting {} USDC of yield (rate {})", yield_amount, exchange_rate);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        ctx.accounts.pending_computation.open(
+            ctx.bumps.pending_computation,
+            ctx.accounts.ghost_pool.key(),
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            OpKind::RecordYield,
+            ctx.accounts.authority.key(),
+            ProvisionalState::Harvest {
+                yield_amount,
+                fee_amount: 0,
+                prev_fee_time: ctx.accounts.ghost_pool.last_fee_time,
+            },
+        )?;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u64(yield_amount)
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RecordYield,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RecordYieldCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.pending_computation.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.authority.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        emit!(YieldHarvestedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            yield_amount,
+            exchange_rate,
+            fee_amount: 0,
+        });
+
+        credit_crank_bounty(
+            &mut ctx.accounts.ghost_pool,
+            ctx.accounts.cranker_registry.as_deref(),
+            ctx.accounts.cranker.as_deref_mut(),
+        );
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "record_yield")]
+    pub fn record_yield_callback(
+        ctx: Context<RecordYieldCallback>,
+        output: SignedComputationOutputs<RecordYieldOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(RecordYieldOutput { field_0 }) => field_0,
+            Err(_) => {
+                // As for expire_computation; the PendingComputation closes with the callback
+                let accounts = &mut ctx.accounts;
+                let computation_account = accounts.computation_account.key();
+                accounts.pending_computation.provisional.roll_back(&mut accounts.ghost_pool);
+                accounts.ghost_pool.end_state_write(&computation_account);
+                return accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pending = &ctx.accounts.pending_computation;
+        let (yield_amount, fee_amount) = match pending.provisional {
+            ProvisionalState::Harvest { yield_amount, fee_amount, .. } => (yield_amount, fee_amount),
+            _ => (0, 0),
+        };
+        let computation_offset = pending.computation_offset;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.ciphertexts;
+        pool.state_nonce = pool.state_nonce.wrapping_add(1);
+        pool.check_invariants(&before, StateWrite::Reencrypted, None);
+
+        emit!(YieldRecordedEvent {
+            pool: pool.key(),
+            position_value: pool.kamino_position_value,
+            yield_amount,
+            fee_amount,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Harvest yield for a pool that charges fees (authority only)
+    /// Same valuation as harvest_yield; the performance fee and the management fee accrued
+    /// since the last harvest are booked to accrued_fees and kept out of the yield index
+    /// by the record_yield_with_fees circuit
+    pub fn harvest_yield_with_fees(
+        ctx: Context<HarvestYieldWithFees>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let exchange_rate = reserve_exchange_rate(&ctx.accounts.kamino_reserve)?;
+        let current_value = collateral_value(ctx.accounts.collateral_token_account.amount, exchange_rate);
+        let now = Clock::get()?.unix_timestamp;
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let yield_amount = current_value.saturating_sub(pool.kamino_position_value);
+        require!(yield_amount > 0, ErrorCode::NoYieldToHarvest);
+        let management_fee = pool.management_fee(now);
+        let fee_amount = pool.yield_fee(yield_amount, management_fee);
+        let prev_fee_time = pool.last_fee_time;
+        pool.kamino_position_value = current_value;
+        pool.accrued_fees += fee_amount;
+        pool.last_fee_time = now;
+
+        msg!("Harvesting {} USDC of yield, {} in fees (rate {})", yield_amount, fee_amount, exchange_rate);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        ctx.accounts.pending_computation.open(
+            ctx.bumps.pending_computation,
+            ctx.accounts.ghost_pool.key(),
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            OpKind::RecordYield,
+            ctx.accounts.authority.key(),
+            ProvisionalState::Harvest {
+                yield_amount,
+                fee_amount,
+                prev_fee_time,
+            },
+        )?;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .plaintext_u64(yield_amount)
+            .plaintext_u16(ctx.accounts.ghost_pool.performance_fee_bps)
+            .plaintext_u64(management_fee)
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::RecordYield,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.authority.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RecordYieldWithFeesCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.pending_computation.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.authority.key(),
+                        is_writable: true,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price_micro,
+        )?;
+
+        emit!(YieldHarvestedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            yield_amount,
+            exchange_rate,
+            fee_amount,
+        });
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "record_yield_with_fees")]
+    pub fn record_yield_with_fees_callback(
+        ctx: Context<RecordYieldWithFeesCallback>,
+        output: SignedComputationOutputs<RecordYieldWithFeesOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(RecordYieldWithFeesOutput { field_0 }) => field_0,
+            Err(_) => {
+                // As for expire_computation; the PendingComputation closes with the callback
+                let accounts = &mut ctx.accounts;
+                let computation_account = accounts.computation_account.key();
+                accounts.pending_computation.provisional.roll_back(&mut accounts.ghost_pool);
+                accounts.ghost_pool.end_state_write(&computation_account);
+                return accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+        let pending = &ctx.accounts.pending_computation;
+        let (yield_amount, fee_amount) = match pending.provisional {
+            ProvisionalState::Harvest { yield_amount, fee_amount, .. } => (yield_amount, fee_amount),
+            _ => (0, 0),
+        };
+        let computation_offset = pending.computation_offset;
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.encrypted_state = o.ciphertexts;
+        pool.state_nonce = pool.state_nonce.wrapping_add(1);
+        pool.check_invariants(&before, StateWrite::Reencrypted, None);
+
+        emit!(YieldRecordedEvent {
+            pool: pool.key(),
+            position_value: pool.kamino_position_value,
+            yield_amount,
+            fee_amount,
+            computation_offset,
+            state_nonce_before: before.state_nonce,
+            state_nonce_after: pool.state_nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        ctx.accounts.ghost_pool.end_state_write(&ctx.accounts.computation_account.key());
+        ctx.accounts.computation_log.record_completed(&ctx.accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Roll back a PendingComputation whose callback hasn't landed within
+    /// COMPUTATION_TIMEOUT_SLOTS (permissionless). Restores the provisional pool state the
+    /// queueing instruction staged, so the work can be queued again; closing the account
+    /// makes a late callback fail instead of applying it twice
+    pub fn expire_computation(ctx: Context<ExpireComputation>, computation_offset: u64) -> Result<()> {
+        let pending = &ctx.accounts.pending_computation;
+        require!(
+            Clock::get()?.slot >= pending.queue_slot.saturating_add(COMPUTATION_TIMEOUT_SLOTS),
+            ErrorCode::ComputationNotExpired
+        );
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pending.provisional.roll_back(pool);
+        pool.end_state_write(&pending.computation_account);
+        pool.check_invariants(&before, StateWrite::None, None);
+        ctx.accounts.computation_log.record_failed(&pending.computation_account)?;
+
+        msg!("Computation {} expired and rolled back", computation_offset);
+        emit!(ComputationExpiredEvent {
+            pool: pool.key(),
+            computation_offset,
+            kind: pending.kind,
+        });
+        Ok(())
+    }
+
+    /// Take back the tokens of a deposit whose computation aborted, or whose callback hasn't
+    /// landed within COMPUTATION_TIMEOUT_SLOTS (depositor only). Closes the FailedDeposit, so
+    /// a late callback fails rather than crediting tokens that have left the vault
+    pub fn reclaim_failed_deposit(ctx: Context<ReclaimFailedDeposit>, computation_offset: u64) -> Result<()> {
+        let failed_deposit = &ctx.accounts.failed_deposit;
+        failed_deposit.require_reclaimable()?;
+
+        let before = ctx.accounts.ghost_pool.counters();
+        transfer_from_vault(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            failed_deposit.amount,
+        )?;
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        msg!("Deposit of {} from computation {} reclaimed", failed_deposit.amount, computation_offset);
+        emit!(DepositReclaimedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            user: ctx.accounts.user.key(),
+            amount: failed_deposit.amount,
+            computation_offset,
+        });
+        Ok(())
+    }
+
+    /// Take back a `deposit` still held in the pool's escrow: its computation aborted, or its
+    /// callback is overdue, e.g. because the refund to `refund_token_account` kept failing
+    /// (depositor only). The tokens never reached the vault, so no pool accounting changes
+    pub fn reclaim_escrow(ctx: Context<ReclaimEscrow>, computation_offset: u64) -> Result<()> {
+        let failed_deposit = &ctx.accounts.failed_deposit;
+        failed_deposit.require_reclaimable()?;
+
+        transfer_from_vault(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.deposit_escrow,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            failed_deposit.amount,
+        )?;
+
+        msg!("Escrowed deposit of {} from computation {} reclaimed", failed_deposit.amount, computation_offset);
+        emit!(DepositReclaimedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            user: ctx.accounts.user.key(),
+            amount: failed_deposit.amount,
+            computation_offset,
+        });
+        Ok(())
+    }
+
+    /// Check the pool's public books against its token balances (permissionless) and emit
+    /// a ReconciliationEvent. The ledger's balances are encrypted and total_deposits is a
+    /// count, so what can be checked in the clear is: the vault covers everything earmarked
+    /// in it (approved investment, undelivered and queued withdrawals, queued deposits,
+    /// accrued fees), and the Kamino position is worth at least the principal put in.
+    /// With `mark`, the authority records the result in `needs_attention`, set while the
+    /// books don't balance and cleared by a balanced run
+    pub fn reconcile(ctx: Context<Reconcile>, mark: bool) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        let vault_balance = ctx.accounts.vault.amount;
+        let earmarked = pool
+            .pending_investment_amount
+            .saturating_add(pool.pending_payouts)
+            .saturating_add(pool.queued_withdrawals)
+            .saturating_add(pool.queued_deposits)
+            .saturating_add(pool.accrued_fees);
+        let vault_delta = vault_balance as i64 - earmarked as i64;
+        let position_delta = pool.kamino_position_value as i64 - pool.total_invested as i64;
+        let balanced = vault_delta >= 0 && position_delta >= 0;
+
+        if mark {
+            require_keys_eq!(ctx.accounts.caller.key(), pool.authority, ErrorCode::Unauthorized);
+            pool.needs_attention = !balanced;
+        }
+
+        msg!(
+            "Reconciled: vault {} against {} earmarked, position {} against {} invested{}",
+            vault_balance,
+            earmarked,
+            pool.kamino_position_value,
+            pool.total_invested,
+            if balanced { "" } else { " (out of balance)" }
+        );
+        emit!(ReconciliationEvent {
+            pool: pool.key(),
+            vault_balance,
+            earmarked,
+            vault_delta,
+            kamino_position_value: pool.kamino_position_value,
+            total_invested: pool.total_invested,
+            position_delta,
+            balanced,
+            needs_attention: pool.needs_attention,
+        });
+        Ok(())
+    }
+
+    /// Value the pool's cTokens at the reserve's current exchange rate (permissionless) into
+    /// `position_value` and emit a PositionSyncedEvent. Unlike harvest_yield this books
+    /// nothing: kamino_position_value still moves only when yield is recorded, so the gap
+    /// between the two is the yield not yet harvested
+    pub fn sync_position(ctx: Context<SyncPosition>) -> Result<()> {
+        let collateral_amount = ctx.accounts.collateral_token_account.amount;
+        let exchange_rate = reserve_exchange_rate(&ctx.accounts.kamino_reserve)?;
+        let position_value = collateral_value(collateral_amount, exchange_rate);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.position_value = position_value;
+        pool.position_synced_slot = Clock::get()?.slot;
+
+        emit!(PositionSyncedEvent {
+            pool: pool.key(),
+            collateral_amount,
+            exchange_rate,
+            position_value,
+            kamino_position_value: pool.kamino_position_value,
+        });
+        Ok(())
+    }
+
+    /// Point state_nonce back at the nonce the pool ciphertexts are encrypted under
+    /// (authority only, everything paused). Recovery for a desync the callback nonce check
+    /// couldn't catch, e.g. an overwritten log entry. `expected` must be the current value,
+    /// so a stale resync can't be replayed
+    pub fn resync_state_nonce(ctx: Context<SetPoolPause>, expected: u128, nonce: u128) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(pool.paused & PAUSE_ALL == PAUSE_ALL, ErrorCode::InvalidPoolStatus);
+        require!(pool.state_nonce == expected, ErrorCode::NonceMismatch);
+        pool.state_nonce = nonce;
+        emit!(StateNonceResyncedEvent {
+            pool: pool.key(),
+            previous_nonce: expected,
+            nonce,
+        });
+        Ok(())
+    }
+
+    /// Halt deposits, withdrawals and/or investment for incident response (authority only)
+    /// `flags` is a mask of PAUSE_* bits, added to those already set
+    pub fn pause_pool(ctx: Context<SetPoolPause>, flags: u8) -> Result<()> {
+        require!(flags != 0 && flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.paused |= flags;
+        emit!(PoolPauseChangedEvent {
+            pool: pool.key(),
+            paused: pool.paused,
+        });
+        Ok(())
+    }
+
+    /// Clear PAUSE_* bits set by pause_pool (authority only)
+    pub fn unpause_pool(ctx: Context<SetPoolPause>, flags: u8) -> Result<()> {
+        require!(flags != 0 && flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.paused &= !flags;
+        emit!(PoolPauseChangedEvent {
+            pool: pool.key(),
+            paused: pool.paused,
+        });
+        Ok(())
+    }
+
+    /// Require a quorum of `members` to co-sign every privileged instruction (authority, plus the
+    /// current quorum once one is set). Pause and unpause stay single-key for incident response.
+    /// Empty `members` turns the multisig off
+    pub fn set_admin_multisig(ctx: Context<SetAdminMultisig>, threshold: u8, members: Vec<Pubkey>) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(members.len() <= MAX_ADMIN_MULTISIG_MEMBERS, ErrorCode::InvalidAdminMultisig);
+        require!(
+            members.is_empty() || (threshold >= 1 && threshold as usize <= members.len()),
+            ErrorCode::InvalidAdminMultisig
+        );
+        for (i, member) in members.iter().enumerate() {
+            require!(
+                *member != Pubkey::default() && !members[..i].contains(member),
+                ErrorCode::InvalidAdminMultisig
+            );
+        }
+
+        let multisig = &mut ctx.accounts.admin_multisig;
+        multisig.bump = ctx.bumps.admin_multisig;
+        multisig.pool = ctx.accounts.ghost_pool.key();
+        multisig.threshold = if members.is_empty() { 0 } else { threshold };
+        multisig.member_count = members.len() as u8;
+        multisig.members = [Pubkey::default(); MAX_ADMIN_MULTISIG_MEMBERS];
+        multisig.members[..members.len()].copy_from_slice(&members);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.admin_multisig = if members.is_empty() { Pubkey::default() } else { multisig.key() };
+
+        emit!(AdminMultisigUpdatedEvent {
+            pool: pool.key(),
+            admin_multisig: pool.admin_multisig,
+            threshold: multisig.threshold,
+            member_count: multisig.member_count,
+        });
+        Ok(())
+    }
+
+    /// Start handing the pool over to `new_authority` (authority only)
+    /// Nothing changes until the new key signs accept_authority; proposing
+    /// Pubkey::default() withdraws a pending proposal
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        ctx.accounts.ghost_pool.pending_authority = new_authority;
+        msg!("Authority handover proposed to {}", new_authority);
+        Ok(())
+    }
+
+    /// Complete a handover started by propose_authority (signed by the proposed key)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        let previous_authority = pool.authority;
+        pool.authority = ctx.accounts.new_authority.key();
+        pool.pending_authority = Pubkey::default();
+
+        emit!(AuthorityTransferredEvent {
+            pool: pool.key(),
+            previous_authority,
+            new_authority: pool.authority,
+        });
+        Ok(())
+    }
+
+    /// Start moving the pool to another deployment of the lending program (authority only),
+    /// e.g. a redeployed Mock Kamino or a KLend fork at a different address. The CPIs this build
+    /// makes don't change, so the new program must speak the same interface.
+    /// apply_lending_program takes it live after the pool's timelock_duration, giving
+    /// depositors time to exit; proposing Pubkey::default() withdraws a pending proposal
+    pub fn propose_lending_program(ctx: Context<UpdateLendingProgram>, lending_program: Pubkey) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.pending_lending_program = lending_program;
+        pool.lending_program_effective_slot = if lending_program == Pubkey::default() {
+            0
+        } else {
+            Clock::get()?.slot + pool.timelock_slots()
+        };
+
+        emit!(LendingProgramProposedEvent {
+            pool: pool.key(),
+            lending_program,
+            effective_slot: pool.lending_program_effective_slot,
+        });
+        Ok(())
+    }
+
+    /// Switch to the lending program proposed by propose_lending_program once its timelock has
+    /// run, or straight away on a pool nobody has deposited into yet (authority only). Nothing
+    /// may be invested with the current program, Kamino or cash; the yield adapter and cash
+    /// strategy point at its reserves and have to be set again
+    pub fn apply_lending_program(ctx: Context<UpdateLendingProgram>) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(pool.pending_lending_program != Pubkey::default(), ErrorCode::NoPendingLendingProgram);
+        require!(
+            pool.total_deposits == 0 || Clock::get()?.slot >= pool.lending_program_effective_slot,
+            ErrorCode::LendingProgramTimelocked
+        );
+        require!(
+            pool.total_collateral_received == 0 && pool.cash_principal == 0,
+            ErrorCode::LendingPositionOpen
+        );
+
+        let previous_lending_program = pool.lending_program;
+        pool.lending_program = pool.pending_lending_program;
+        pool.pending_lending_program = Pubkey::default();
+        pool.lending_program_effective_slot = 0;
+
+        emit!(LendingProgramChangedEvent {
+            pool: pool.key(),
+            previous_lending_program,
+            lending_program: pool.lending_program,
+        });
+        Ok(())
+    }
+
+    /// Change the investment and deposit parameters (authority only)
+    /// `min_deposit`, `max_deposit_per_user` and `max_pool_cap` are in base units of the deposit
+    /// mint, 0 = no limit. The per-user cap bounds the sum of a signer's positions, top-ups and
+    /// settled yield included, and is checked inside the MPC against the hidden balances; the pool
+    /// cap applies to vault + Kamino position + cash principal after a deposit lands.
+    /// `liquidity_buffer_bps` of total deposits is never offered to check_and_invest.
+    /// `withdrawal_cap_per_epoch` bounds what a single deposit can withdraw or transfer out
+    /// per WITHDRAWAL_EPOCH_SLOTS, enforced inside the MPC; 0 = no cap.
+    /// `min_slots_between_checks` rate-limits check_and_invest so cranks can't burn MPC fees
+    pub fn update_pool_config(
+        ctx: Context<UpdatePoolConfig>,
+        investment_threshold: u64,
+        min_deposit: u64,
+        max_deposit_per_user: u64,
+        max_pool_cap: u64,
+        investment_cooldown_secs: i64,
+        liquidity_buffer_bps: u16,
+        withdrawal_cap_per_epoch: u64,
+        min_slots_between_checks: u64,
+    ) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(investment_threshold > 0, ErrorCode::InvalidPoolConfig);
+        require!(min_slots_between_checks <= MAX_SLOTS_BETWEEN_CHECKS, ErrorCode::InvalidPoolConfig);
+        require!(liquidity_buffer_bps <= MAX_BPS, ErrorCode::InvalidPoolConfig);
+        require!(
+            (0..=MAX_INVESTMENT_COOLDOWN_SECS).contains(&investment_cooldown_secs),
+            ErrorCode::InvalidPoolConfig
+        );
+        if max_pool_cap > 0 {
+            require!(
+                min_deposit <= max_pool_cap && investment_threshold <= max_pool_cap,
+                ErrorCode::InvalidPoolConfig
+            );
+        }
+        if max_deposit_per_user > 0 {
+            require!(min_deposit <= max_deposit_per_user, ErrorCode::InvalidPoolConfig);
+        }
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.investment_threshold = investment_threshold;
+        pool.min_deposit = min_deposit;
+        pool.max_deposit_per_user = max_deposit_per_user;
+        pool.max_pool_cap = max_pool_cap;
+        pool.investment_cooldown_secs = investment_cooldown_secs;
+        pool.liquidity_buffer_bps = liquidity_buffer_bps;
+        pool.withdrawal_cap_per_epoch = withdrawal_cap_per_epoch;
+        pool.min_slots_between_checks = min_slots_between_checks;
+
+        emit!(PoolConfigUpdatedEvent {
+            pool: pool.key(),
+            investment_threshold,
+            min_deposit,
+            max_deposit_per_user,
+            max_pool_cap,
+            investment_cooldown_secs,
+            liquidity_buffer_bps,
+            withdrawal_cap_per_epoch,
+            min_slots_between_checks,
+        });
+        Ok(())
+    }
+
+    /// Set the default Arcium callback priority fee, in micro-lamports per CU (authority only)
+    /// Cranks always queue at this price; user instructions take an optional
+    /// `cu_price_micro` to pay more (or less) for their own computation
+    pub fn set_cu_price(ctx: Context<UpdatePoolConfig>, cu_price_micro: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        ctx.accounts.ghost_pool.cu_price_micro = cu_price_micro;
+        msg!("Default callback priority fee set to {} micro-lamports/CU", cu_price_micro);
+        Ok(())
+    }
+
+    /// Let designated beneficiaries recover deposits after `inactivity_slots` without an owner
+    /// action (authority only). 0 turns recovery off; otherwise at least MIN_RECOVERY_INACTIVITY_SLOTS
+    pub fn set_recovery_period(ctx: Context<UpdatePoolConfig>, inactivity_slots: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(
+            inactivity_slots == 0 || inactivity_slots >= MIN_RECOVERY_INACTIVITY_SLOTS,
+            ErrorCode::InvalidPoolConfig
+        );
+        ctx.accounts.ghost_pool.recovery_inactivity_slots = inactivity_slots;
+        msg!("Recovery inactivity period set to {} slots", inactivity_slots);
+        Ok(())
+    }
+
+    /// Set how long proposed admin actions wait before they can run (authority only), in slots.
+    /// At least MIN_TIMELOCK_SLOTS, and once the pool holds deposits it can only grow
+    pub fn set_timelock_duration(ctx: Context<UpdatePoolConfig>, timelock_duration: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(
+            timelock_duration >= MIN_TIMELOCK_SLOTS
+                && (pool.total_deposits == 0 || timelock_duration >= pool.timelock_slots()),
+            ErrorCode::InvalidPoolConfig
+        );
+        pool.timelock_duration = timelock_duration;
+        msg!("Admin timelock set to {} slots", timelock_duration);
+        Ok(())
+    }
+
+    /// Cap the rebate a relayer may take out of a withdrawal it pays for (authority only), in
+    /// base units of the deposit mint. 0 turns withdraw_relayed off
+    pub fn set_max_relayer_rebate(ctx: Context<UpdatePoolConfig>, max_relayer_rebate: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        ctx.accounts.ghost_pool.max_relayer_rebate = max_relayer_rebate;
+        msg!("Relayer rebate capped at {}", max_relayer_rebate);
+        Ok(())
+    }
+
+    /// Let compound reinvest redeemed yield into Kamino (authority only)
+    pub fn set_auto_compound(ctx: Context<UpdatePoolConfig>, enabled: bool) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        ctx.accounts.ghost_pool.auto_compound = enabled;
+        msg!("Auto-compound {}", if enabled { "enabled" } else { "disabled" });
+        Ok(())
+    }
+
+    /// Charge an early-exit penalty on withdrawals soon after a deposit (authority only)
+    /// `penalty_bps` of the requested amount right after the deposit, decaying linearly to 0
+    /// at `window_secs`. The ledger is debited the full amount and the penalty stays in the
+    /// vault, either shared out to the remaining depositors or, with `to_fees`, booked to
+    /// accrued_fees for collect_fees. A 0 bps penalty turns it off
+    pub fn set_early_exit_penalty(
+        ctx: Context<UpdatePoolConfig>,
+        penalty_bps: u16,
+        window_secs: i64,
+        to_fees: bool,
+    ) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(penalty_bps <= MAX_EARLY_EXIT_PENALTY_BPS, ErrorCode::InvalidPoolConfig);
+        require!((0..=MAX_EARLY_EXIT_WINDOW_SECS).contains(&window_secs), ErrorCode::InvalidPoolConfig);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(
+            !to_fees || pool.fee_recipient_token_account != Pubkey::default(),
+            ErrorCode::InvalidFeeRecipient
+        );
+        pool.early_exit_penalty_bps = penalty_bps;
+        pool.early_exit_window_secs = window_secs;
+        pool.early_exit_penalty_to_fees = to_fees;
+        msg!(
+            "Early-exit penalty set: {} bps over {}s, to {}",
+            penalty_bps,
+            window_secs,
+            if to_fees { "fees" } else { "depositors" }
+        );
+        Ok(())
+    }
+
+    /// Accept the MXE's current x25519 key after a cluster rotation (authority only)
+    /// Callbacks flag the pool when the key no longer matches mxe_key_hash, which holds
+    /// deposits, withdrawals and transfers whose inputs are encrypted client-side. Call
+    /// this once integrators have been told to refetch the MXE public key
+    pub fn acknowledge_cluster_rotation(ctx: Context<AcknowledgeClusterRotation>) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        let previous = pool.mxe_key_hash;
+        pool.mxe_key_hash = mxe_key_hash(&ctx.accounts.mxe_account);
+        pool.mxe_rotation_pending = false;
+
+        emit!(ClusterRotationAcknowledgedEvent {
+            pool: pool.key(),
+            previous_key_hash: previous,
+            mxe_key_hash: pool.mxe_key_hash,
+        });
+        Ok(())
+    }
+
+    /// Accept a secondary mint through deposit_with_swap (authority only)
+    /// Swaps run against `swap_program` and must land within `max_swap_slippage_bps` of the
+    /// price implied by the two Pyth feeds
+    pub fn set_swap_config(ctx: Context<SetSwapConfig>, max_swap_slippage_bps: u16) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(max_swap_slippage_bps <= MAX_SWAP_SLIPPAGE_BPS, ErrorCode::InvalidPoolConfig);
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.swap_mint = ctx.accounts.swap_mint.key();
+        pool.swap_program = ctx.accounts.swap_program.key();
+        pool.swap_mint_oracle = ctx.accounts.swap_mint_oracle.key();
+        pool.deposit_mint_oracle = ctx.accounts.deposit_mint_oracle.key();
+        pool.max_swap_slippage_bps = max_swap_slippage_bps;
+        msg!("Swap deposits enabled for mint {}", pool.swap_mint);
+        Ok(())
+    }
+
+    /// Propose new protocol fees and where they are paid (authority only). set_fee_config
+    /// applies them once the pool's timelock_duration has run; cancel_action withdraws them
+    pub fn propose_fee_config(
+        ctx: Context<ProposeFeeConfig>,
+        performance_fee_bps: u16,
+        management_fee_bps: u16,
+    ) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(
+            performance_fee_bps <= MAX_PERFORMANCE_FEE_BPS && management_fee_bps <= MAX_MANAGEMENT_FEE_BPS,
+            ErrorCode::InvalidPoolConfig
+        );
+
+        let action = &mut ctx.accounts.pending_action;
+        action.bump = ctx.bumps.pending_action;
+        action.target = ctx.accounts.ghost_pool.key();
+        action.proposer = ctx.accounts.authority.key();
+        action.eta_slot = Clock::get()?.slot + ctx.accounts.ghost_pool.timelock_slots();
+        action.action = AdminAction::FeeConfig {
+            performance_fee_bps,
+            management_fee_bps,
+            fee_recipient_token_account: ctx.accounts.fee_recipient_token_account.key(),
+        };
+
+        emit!(AdminActionProposedEvent {
+            target: action.target,
+            kind: action.action.kind(),
+            eta_slot: action.eta_slot,
+        });
+        Ok(())
+    }
+
+    /// Apply the fees proposed by propose_fee_config once their timelock has run, or straight
+    /// away on a pool nobody has deposited into yet (authority only)
+    /// Management fee accrual restarts now; fees already booked stay collectable.
+    /// With both fees at 0 the pool harvests through harvest_yield again
+    pub fn set_fee_config(ctx: Context<SetFeeConfig>) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let AdminAction::FeeConfig { performance_fee_bps, management_fee_bps, fee_recipient_token_account } =
+            ctx.accounts.pending_action.action.clone()
+        else {
+            return err!(ErrorCode::AdminActionMismatch);
+        };
+        require_keys_eq!(
+            ctx.accounts.fee_recipient_token_account.key(),
+            fee_recipient_token_account,
+            ErrorCode::AdminActionMismatch
+        );
+        require!(
+            ctx.accounts.ghost_pool.total_deposits == 0
+                || Clock::get()?.slot >= ctx.accounts.pending_action.eta_slot,
+            ErrorCode::AdminActionTimelocked
+        );
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.performance_fee_bps = performance_fee_bps;
+        pool.management_fee_bps = management_fee_bps;
+        pool.fee_recipient_token_account = ctx.accounts.fee_recipient_token_account.key();
+        pool.last_fee_time = Clock::get()?.unix_timestamp;
+        msg!(
+            "Fees set: {} bps performance, {} bps management, paid to {}",
+            performance_fee_bps,
+            management_fee_bps,
+            pool.fee_recipient_token_account
+        );
+        Ok(())
+    }
+
+    /// Withdraw a timelocked admin action before it runs (its proposer only)
+    pub fn cancel_action(ctx: Context<CancelAdminAction>) -> Result<()> {
+        let action = &ctx.accounts.pending_action;
+        emit!(AdminActionCancelledEvent {
+            target: action.target,
+            kind: action.action.kind(),
+        });
+        Ok(())
+    }
+
+    /// Pay booked fees from the vault to the fee recipient (authority only)
+    /// Only idle vault funds are used, so fees never compete with approved investments or
+    /// owed payouts; redeem from Kamino first if the vault is short
+    pub fn collect_fees(ctx: Context<CollectFees>, amount: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &ctx.accounts.ghost_pool;
+        require!(amount > 0 && amount <= pool.accrued_fees, ErrorCode::InvalidFeeAmount);
+        let idle = ctx.accounts.vault.amount
+            .saturating_sub(pool.pending_investment_amount)
+            .saturating_sub(pool.pending_payouts)
+            .saturating_sub(pool.queued_withdrawals)
+            .saturating_sub(pool.queued_deposits);
+        require!(amount <= idle, ErrorCode::InsufficientIdleFunds);
+
+        let before = pool.counters();
+        transfer_from_vault(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.fee_recipient_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        ctx.accounts.ghost_pool.accrued_fees -= amount;
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        emit!(FeesCollectedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            amount,
+            recipient: ctx.accounts.fee_recipient_token_account.key(),
+        });
+        Ok(())
+    }
+
+    /// Set the referrers' share of the pool's fees, in bps of the fees booked on each referred
+    /// deposit (authority only, 0 disables referrals). Claims are valued at the share in
+    /// force when they are made
+    pub fn set_referral_share(ctx: Context<SetReferralShare>, referral_share_bps: u16) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        require!(referral_share_bps <= MAX_BPS, ErrorCode::InvalidPoolConfig);
+        ctx.accounts.ghost_pool.referral_share_bps = referral_share_bps;
+        msg!("Referral share set to {} bps", referral_share_bps);
+        Ok(())
+    }
+
+    /// Pay the signer the referral rewards earned by the deposits that named them as referrer
+    /// The MPC sums referral_share_bps of the fees booked on each such deposit since it was
+    /// made, last topped up or last claimed for, and reveals only the total; which deposits
+    /// were referred, and by whom, stays encrypted. Rewards come out of accrued_fees and idle
+    /// vault funds; when those can't cover them the claim is dropped and can be retried
+    pub fn claim_referral_rewards(
+        ctx: Context<ClaimReferralRewards>,
+        computation_offset: u64,
+        cu_price_micro: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let args = ArgBuilder::new()
+            .plaintext_u128(pubkey_fingerprint(&ctx.accounts.referrer.key()))
+            .plaintext_u16(ctx.accounts.ghost_pool.referral_share_bps)
+            .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+            .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+            .pool_state(&ctx.accounts.ghost_pool)?
+            .build();
+
+        ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+        ctx.accounts.computation_log.record_queued(
+            OpKind::ClaimReferral,
+            computation_offset,
+            ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+            &ctx.accounts.referrer.key(),
+        )?;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ClaimReferralRewardsCallback::callback_ix(
+                computation_offset,
+                &ctx.accounts.mxe_account,
+                &[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.ghost_pool.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_log.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.vault.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.deposit_mint.key(),
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.referrer_token_account.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.token_program.key(),
+                        is_writable: false,
+                    },
+                ],
+            )?],
+            2, // PoolState (40 FE) + nonce doesn't fit a single callback tx
+            ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "claim_referral_rewards")]
+    pub fn claim_referral_rewards_callback(
+        ctx: Context<ClaimReferralRewardsCallback>,
+        output: SignedComputationOutputs<ClaimReferralRewardsOutput>,
+    ) -> Result<()> {
+        let o = match output.verify_output(
+            &ctx.accounts.cluster_account,
+            &ctx.accounts.computation_account,
+        ) {
+            Ok(ClaimReferralRewardsOutput { field_0 }) => field_0,
+            Err(_) => {
+                let computation_account = ctx.accounts.computation_account.key();
+                ctx.accounts.ghost_pool.end_state_write(&computation_account);
+                return ctx.accounts.computation_log.record_aborted(&computation_account);
+            }
+        };
+
+        observe_mxe_key(&mut ctx.accounts.ghost_pool, &ctx.accounts.mxe_account);
+
+        ctx.accounts.computation_log.check_state_nonce(
+            &ctx.accounts.computation_account.key(),
+            ctx.accounts.ghost_pool.state_nonce,
+        )?;
+
+        let reward = o.field_1;
+        let accounts = &mut ctx.accounts;
+        let idle = accounts.vault.amount
+            .saturating_sub(accounts.ghost_pool.pending_investment_amount)
+            .saturating_sub(accounts.ghost_pool.pending_payouts)
+            .saturating_sub(accounts.ghost_pool.queued_withdrawals)
+            .saturating_sub(accounts.ghost_pool.queued_deposits);
+        if reward > accounts.ghost_pool.accrued_fees || reward > idle {
+            // Dropping the new state keeps the deposits' checkpoints where they were, so
+            // nothing is lost: the same rewards are claimable once the vault can pay them
+            msg!("Referral rewards of {} exceed the fees the vault can pay now", reward);
+            let computation_account = accounts.computation_account.key();
+            accounts.ghost_pool.end_state_write(&computation_account);
+            return accounts.computation_log.record_aborted(&computation_account);
+        }
+
+        let before = accounts.ghost_pool.counters();
+        if reward > 0 {
+            transfer_from_vault(
+                &accounts.ghost_pool,
+                &accounts.vault,
+                &accounts.deposit_mint,
+                &accounts.referrer_token_account.to_account_info(),
+                &accounts.token_program,
+                reward,
+            )?;
+        }
+
+        let pool = &mut accounts.ghost_pool;
+        pool.accrued_fees -= reward;
+        pool.referral_rewards_paid += reward;
+        // Two outputs, so the state comes back under a fresh nonce
+        pool.encrypted_state = o.field_0.ciphertexts;
+        pool.state_nonce = o.field_0.nonce;
+        pool.check_invariants(&before, StateWrite::Fresh, Some(&accounts.vault.to_account_info()));
+
+        emit!(ReferralRewardsClaimedEvent {
+            pool: accounts.ghost_pool.key(),
+            referrer_token_account: accounts.referrer_token_account.key(),
+            amount: reward,
+        });
+
+        accounts.ghost_pool.end_state_write(&accounts.computation_account.key());
+        accounts.computation_log.record_completed(&accounts.computation_account.key())?;
+
+        Ok(())
+    }
+
+    /// Configure crank permissioning (authority only, creates the registry on first use)
+    /// `permissionless` lets anyone call check_and_invest and harvest_yield; `bounty` is
+    /// credited to a registered cranker per successful crank, out of accrued_fees
+    pub fn set_cranker_config(ctx: Context<SetCrankerConfig>, permissionless: bool, bounty: u64) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let registry = &mut ctx.accounts.cranker_registry;
+        registry.bump = ctx.bumps.cranker_registry;
+        registry.pool = ctx.accounts.ghost_pool.key();
+        registry.permissionless = permissionless;
+        registry.bounty = bounty;
+        msg!("Crank config: permissionless {}, bounty {}", permissionless, bounty);
+        Ok(())
+    }
+
+    /// Whitelist a keeper for the crank instructions (authority only)
+    pub fn register_cranker(ctx: Context<RegisterCranker>, keeper: Pubkey) -> Result<()> {
+        let cranker = &mut ctx.accounts.cranker;
+        cranker.bump = ctx.bumps.cranker;
+        cranker.pool = ctx.accounts.ghost_pool.key();
+        cranker.keeper = keeper;
+        cranker.bounty_owed = 0;
+        cranker.cranks = 0;
+        ctx.accounts.cranker_registry.cranker_count += 1;
+        msg!("Cranker {} registered", keeper);
+        Ok(())
+    }
+
+    /// Remove a keeper from the whitelist (authority only)
+    /// Any unclaimed bounty goes back to accrued_fees
+    pub fn remove_cranker(ctx: Context<RemoveCranker>) -> Result<()> {
+        ctx.accounts.ghost_pool.accrued_fees += ctx.accounts.cranker.bounty_owed;
+        ctx.accounts.cranker_registry.cranker_count -= 1;
+        msg!("Cranker {} removed", ctx.accounts.cranker.keeper);
+        Ok(())
+    }
+
+    /// Pay a registered cranker its credited bounty from the vault
+    /// Only idle vault funds are used, same as collect_fees
+    pub fn claim_crank_bounty(ctx: Context<ClaimCrankBounty>) -> Result<()> {
+        let amount = ctx.accounts.cranker.bounty_owed;
+        require!(amount > 0, ErrorCode::NoCrankBounty);
+        let pool = &ctx.accounts.ghost_pool;
+        let idle = ctx.accounts.vault.amount
+            .saturating_sub(pool.pending_investment_amount)
+            .saturating_sub(pool.pending_payouts)
+            .saturating_sub(pool.queued_withdrawals)
+            .saturating_sub(pool.queued_deposits);
+        require!(amount <= idle, ErrorCode::InsufficientIdleFunds);
+
+        let before = pool.counters();
+        transfer_from_vault(
+            &ctx.accounts.ghost_pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.deposit_mint,
+            &ctx.accounts.keeper_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        ctx.accounts.cranker.bounty_owed = 0;
+        ctx.accounts.ghost_pool.check_invariants(
+            &before,
+            StateWrite::None,
+            Some(&ctx.accounts.vault.to_account_info()),
+        );
+
+        emit!(CrankBountyClaimedEvent {
+            pool: ctx.accounts.ghost_pool.key(),
+            keeper: ctx.accounts.keeper.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Choose how the pool's deposit and withdrawal events are encoded (authority only)
+    pub fn set_event_mode(ctx: Context<SetEventMode>, mode: EventMode) -> Result<()> {
+        ctx.accounts.ghost_pool.event_mode = mode;
+        msg!("Event mode set to {:?}", mode);
+        Ok(())
+    }
+
+    /// Make the pool issue transferable share tokens (authority only, before anything is
+    /// deposited; there is no way back). `deposit` then mints shares in proportion to what
+    /// the deposit adds to the pool's assets, and a withdrawal burns the shares its payout is
+    /// worth on top of the MPC password check, so holding the shares is part of the claim.
+    /// Balances stay encrypted: the shares only reveal amounts that are public anyway.
+    /// Share pools take deposits through deposit / deposit_sol only
+    pub fn enable_share_tokens(ctx: Context<EnableShareTokens>) -> Result<()> {
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(
+            !pool.issues_shares()
+                && pool.net_assets(ctx.accounts.vault.amount) == 0
+                && pool.total_invested == 0
+                && pool.state_write_in_flight == Pubkey::default(),
+            ErrorCode::ShareTokensUnavailable
+        );
+        pool.share_mint = ctx.accounts.share_mint.key();
+
+        emit!(ShareTokensEnabledEvent {
+            pool: pool.key(),
+            share_mint: pool.share_mint,
+        });
+        Ok(())
+    }
+
+    /// Move the pool along its lifecycle (authority only, see `PoolStatus`)
+    pub fn set_pool_status(ctx: Context<SetPoolStatus>, status: PoolStatus) -> Result<()> {
+        // Only the init callback may activate an uninitialized pool
+        require!(
+            ctx.accounts.ghost_pool.status.is_initialized(),
+            ErrorCode::InvalidStatusTransition
+        );
+        let pool_key = ctx.accounts.ghost_pool.key();
+        ctx.accounts.ghost_pool.transition(pool_key, status)
+    }
+
+    /// Decommission a Closing pool (authority only): sweep the vault and the strategy's
+    /// collateral to the authority, close both token accounts and reclaim the pool's rent.
+    /// Every deposit must have been withdrawn, unless `force` is set and the pool has been
+    /// Closing for POOL_CLOSE_GRACE_SECS
+    pub fn close_pool(ctx: Context<ClosePool>, force: bool) -> Result<()> {
+        let pool = &ctx.accounts.ghost_pool;
+        require!(pool.state_write_in_flight == Pubkey::default(), ErrorCode::StateWriteInFlight);
+        require!(pool.cash_principal == 0, ErrorCode::CashStrategyActive);
+        // Bucketed counters aren't exact, so only an explicit force can close such a pool
+        let drained = pool.counter_bucket == 0 && pool.total_deposits == pool.total_withdrawals;
+        if !drained {
+            require!(force, ErrorCode::PoolNotDrained);
+            require!(
+                Clock::get()?.unix_timestamp >= pool.closing_since.saturating_add(POOL_CLOSE_GRACE_SECS),
+                ErrorCode::CloseGracePeriodActive
+            );
+        }
+
+        // The collateral account belongs to the vault PDA, the vault to the pool
+        let pool_key = pool.key();
+        let vault_seeds = &[b"vault".as_ref(), pool_key.as_ref(), &[pool.vault_bump]];
+        let mut collateral_swept = 0;
+        if pool.collateral_token_account != Pubkey::default() {
+            let (Some(collateral), Some(destination), Some(collateral_token_program)) = (
+                &ctx.accounts.collateral_token_account,
+                &ctx.accounts.authority_collateral_account,
+                &ctx.accounts.collateral_token_program,
+            ) else {
+                return Err(ErrorCode::MissingCollateralAccounts.into());
+            };
+            collateral_swept = collateral.amount;
+            if collateral_swept > 0 {
+                anchor_spl::token::transfer(
+                    CpiContext::new_with_signer(
+                        collateral_token_program.to_account_info(),
+                        anchor_spl::token::Transfer {
+                            from: collateral.to_account_info(),
+                            to: destination.to_account_info(),
+                            authority: ctx.accounts.vault.to_account_info(),
+                        },
+                        &[vault_seeds],
+                    ),
+                    collateral_swept,
+                )?;
+            }
+            anchor_spl::token::close_account(CpiContext::new_with_signer(
+                collateral_token_program.to_account_info(),
+                anchor_spl::token::CloseAccount {
+                    account: collateral.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+                &[vault_seeds],
+            ))?;
+        }
+
+        let vault_swept = ctx.accounts.vault.amount;
+        if vault_swept > 0 {
+            transfer_from_vault(
+                &ctx.accounts.ghost_pool,
+                &ctx.accounts.vault,
+                &ctx.accounts.deposit_mint,
+                &ctx.accounts.authority_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                vault_swept,
+            )?;
+        }
+        let pool_id = pool.pool_id.to_le_bytes();
+        let pool_seeds = &[
+            b"ghost_pool".as_ref(),
+            pool.seed_authority.as_ref(),
+            &pool_id,
+            &[pool.bump],
+        ];
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account: ctx.accounts.vault.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: ctx.accounts.ghost_pool.to_account_info(),
+            },
+            &[pool_seeds],
+        ))?;
+
+        msg!("Pool closed: swept {} from the vault and {} collateral", vault_swept, collateral_swept);
+        emit!(PoolClosedEvent {
+            pool: pool_key,
+            vault_swept,
+            collateral_swept,
+            forced: !drained,
+        });
+        // The pool account itself is closed to the authority by `close = authority`
+        Ok(())
+    }
+
+    /// Configure the cash strategy: an instant-liquidity Mock Kamino reserve for idle vault funds
+    /// (authority only). Can only be switched while nothing is swept into the current one
+    pub fn set_cash_strategy(ctx: Context<SetCashStrategy>) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        require!(pool.cash_principal == 0, ErrorCode::CashStrategyActive);
+        pool.cash_reserve = ctx.accounts.cash_reserve.key();
+        pool.cash_collateral_account = ctx.accounts.cash_collateral_account.key();
+        msg!("Cash strategy set to reserve {}", pool.cash_reserve);
+        Ok(())
+    }
+
+    /// Sweep idle vault USDC into the cash strategy (authority only)
+    /// USDC approved for the main strategy or owed to undelivered or queued payouts stays in the vault,
+    /// and the sweep is rejected
+    /// unless the reserve can pay the whole cash position back immediately
+    pub fn sweep_to_cash(ctx: Context<SweepToCash>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidSweepAmount);
+        let idle = ctx.accounts.vault.amount
+            .saturating_sub(ctx.accounts.ghost_pool.pending_investment_amount)
+            .saturating_sub(ctx.accounts.ghost_pool.pending_payouts)
+            .saturating_sub(ctx.accounts.ghost_pool.queued_withdrawals)
+            .saturating_sub(ctx.accounts.ghost_pool.queued_deposits);
+        require!(amount <= idle, ErrorCode::InsufficientIdleFunds);
+
+        msg!("Sweeping {} idle USDC into the cash strategy", amount);
+
+        let collateral_before = ctx.accounts.cash_collateral_account.amount;
+        let pool = &ctx.accounts.ghost_pool;
+        let pool_id = pool.pool_id.to_le_bytes();
+        let seeds = &[b"ghost_pool".as_ref(), pool.seed_authority.as_ref(), &pool_id, &[pool.bump]];
+        ctx.accounts.reserve_cpi_accounts().deposit_liquidity(amount, &[seeds])?;
+
+        ctx.accounts.cash_collateral_account.reload()?;
+        ctx.accounts.cash_liquidity_supply.reload()?;
+        let collateral_amount = ctx.accounts.cash_collateral_account.amount
+            .saturating_sub(collateral_before);
+
+        // Instant-liquidity invariant: the whole cash position must be redeemable right now
+        let exchange_rate = reserve_exchange_rate(&ctx.accounts.cash_reserve)?;
+        let total_liquidity = reserve_available_liquidity(&ctx.accounts.cash_reserve)?;
+        let position_value = collateral_value(ctx.accounts.cash_collateral_account.amount, exchange_rate);
+        require!(
+            position_value <= total_liquidity.min(ctx.accounts.cash_liquidity_supply.amount),
+            ErrorCode::CashStrategyIlliquid
+        );
+
+        let pool = &mut ctx.accounts.ghost_pool;
+        let before = pool.counters();
+        pool.cash_principal += amount;
+        pool.check_invariants(&before, StateWrite::None, Some(&ctx.accounts.vault.to_account_info()));
+
+        emit!(CashSweptEvent {
+            pool: pool.key(),
+            amount,
+            collateral_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Redeem just enough of the cash strategy to cover the caller's authorized withdrawal
+    /// Meant to be placed ahead of claim_withdrawal in the same transaction; a no-op when
+    /// the vault already covers the payout
+    pub fn redeem_cash_for_withdrawal(ctx: Context<RedeemCashForWithdrawal>) -> Result<()> {
+        let shortfall = ctx.accounts.pending_withdrawal.amount
+            .saturating_sub(ctx.accounts.vault.amount);
+        if shortfall == 0 {
+            return Ok(());
+        }
+
+        // collateral = ceil(shortfall * 1e6 / exchange_rate), so the payout covers the shortfall
+        let exchange_rate = reserve_exchange_rate(&ctx.accounts.cash_reserve)?;
+        require!(exchange_rate > 0, ErrorCode::InvalidReserve);
+        let needed = (shortfall as u128 * 1_000_000).div_ceil(exchange_rate as u128) as u64;
+        let collateral_amount = needed.min(ctx.accounts.cash_collateral_account.amount);
+        require!(collateral_amount > 0, ErrorCode::InsufficientCollateral);
+
+        let cpi = ctx.accounts.reserve_cpi_accounts();
+        redeem_cash(
+            &mut ctx.accounts.ghost_pool,
+            &mut ctx.accounts.vault,
+            &mut ctx.accounts.cash_collateral_account,
+            cpi,
+            collateral_amount,
+        )
+    }
+
+    /// Pull funds back out of the cash strategy into the vault (authority only)
+    pub fn redeem_from_cash(ctx: Context<RedeemFromCash>, collateral_amount: u64) -> Result<()> {
+        require!(collateral_amount > 0, ErrorCode::InvalidRedeemAmount);
+        require!(
+            collateral_amount <= ctx.accounts.cash_collateral_account.amount,
+            ErrorCode::InsufficientCollateral
+        );
+
+        let cpi = ctx.accounts.reserve_cpi_accounts();
+        redeem_cash(
+            &mut ctx.accounts.ghost_pool,
+            &mut ctx.accounts.vault,
+            &mut ctx.accounts.cash_collateral_account,
+            cpi,
+            collateral_amount,
+        )
+    }
+
+    /// Set the collateral token account for receiving Kamino cTokens
+    pub fn set_collateral_account(ctx: Context<SetCollateralAccount>) -> Result<()> {
+        require_admin_quorum(&ctx.accounts.ghost_pool, ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.ghost_pool;
+        pool.collateral_token_account = ctx.accounts.collateral_token_account.key();
+
+        msg!("Collateral token account set: {}", pool.collateral_token_account);
+        Ok(())
+    }
+
+    /// Close a withdrawal receipt and return its rent to the user
+    pub fn close_withdrawal_receipt(_ctx: Context<CloseWithdrawalReceipt>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Close a deposit receipt once its deposit has been withdrawn in full
+    /// The user opens the amount commitment and points at a later paid withdrawal covering it
+    pub fn close_receipt(
+        ctx: Context<CloseReceipt>,
+        amount: u64,
+        amount_blinding: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            amount_commitment(amount, &amount_blinding) == ctx.accounts.deposit_receipt.amount_commitment,
+            ErrorCode::InvalidReceiptOpening
+        );
+        require!(
+            ctx.accounts.withdrawal_receipt.amount >= amount,
+            ErrorCode::DepositNotWithdrawn
+        );
+        Ok(())
+    }
+
+}
+
+/// Ghost Pool account
+#[account]
+pub struct GhostPool {
+    pub bump: u8,
+    pub authority: Pubkey,                   // May be a PDA (e.g. a Squads vault) signing through CPI
+    pub deposit_mint: Pubkey,                // Any SPL or Token-2022 mint (USDC, PYUSD, wSOL, ...); Kamino/cash paths need SPL Token
+    pub vault_bump: u8,
+
+    // Investment settings
+    pub investment_threshold: u64,
+    pub last_investment_time: i64,
+
+    // Encrypted state (v12: 2 signer-bound deposits with allowlists, caps, locks and referrers; weights, fee index)
+    pub state_nonce: u128,
+    pub encrypted_state: [[u8; 32]; POOL_STATE_FIELDS],  // PoolState, 2 deposits = 40 FE (1280 bytes, two callback txs)
+
+    // Public stats
+    pub total_deposits: u64,
     pub total_withdrawals: u64,
     pub total_invested: u64,
 
-    // Kamino integration
-    pub pending_investment_amount: u64,      // Amount approved by MPC for investment
-    pub collateral_token_account: Pubkey,    // Kamino collateral token account (cTokens)
-    pub total_collateral_received: u64,      // Total cTokens received from Kamino
+    // Kamino integration
+    pub pending_investment_amount: u64,      // Amount approved by MPC for investment
+    pub collateral_token_account: Pubkey,    // Kamino collateral token account (cTokens)
+    pub total_collateral_received: u64,      // Total cTokens received from Kamino
+
+    // Sharded ledger
+    pub shard_count: u16,                    // Number of initialized DepositShard accounts
+    pub next_shard: u16,                     // Shard currently accepting deposits
+
+    // Counter privacy (bucket 0 = exact public counters)
+    pub counter_bucket: u16,                 // Round published counters to this multiple
+    pub counter_epoch_slots: u64,            // Minimum slots between counter publications
+    pub last_counter_epoch_slot: u64,
+
+    // Withdrawal rotator (default pubkey = disabled)
+    pub rotator_token_account: Pubkey,       // Pool-owned hot wallet for two-hop payouts
+    pub rotator_bump: u8,
+    pub rotator_max_balance: u64,            // Cap on funds in transit
+    pub rotator_balance: u64,                // Paid into the rotator, not yet forwarded
+
+    pub unrecorded_investment_amount: u64,   // Invested in Kamino, not yet recorded in encrypted_state
+    pub kamino_position_value: u64,          // USDC value of cTokens as of the last harvest
+
+    // Legacy sunset: frozen = no new pool-level deposits, retired = legacy slots empty
+    pub legacy_frozen: bool,
+    pub legacy_retired: bool,
+
+    // Cash strategy (default pubkey = disabled): idle funds in an instant-liquidity reserve
+    pub cash_reserve: Pubkey,
+    pub cash_collateral_account: Pubkey,     // Pool-owned cToken account for the cash reserve
+    pub cash_principal: u64,                 // USDC swept in, not yet redeemed
+    pub cash_yield_realized: u64,            // Cash yield paid back to the vault (kept apart from harvest_yield)
+
+    pub pool_id: u64,                        // Distinguishes pools run by the same authority (PDA seed)
+    pub status: PoolStatus,                  // Lifecycle gate for every non-callback instruction
+
+    pub deposit_decimals: u8,                // Decimals of deposit_mint
+    pub yield_scale: u64,                    // Yield index precision, 10^(decimals + 3), passed to the circuits
+
+    pub paused: u8,                          // PAUSE_* circuit breaker bits
+
+    pub pending_authority: Pubkey,           // Proposed by propose_authority, default = none
+    pub seed_authority: Pubkey,              // Authority at creation; PDA seed, survives handovers
+
+    pub event_mode: EventMode,               // Encoding of the deposit/withdrawal events
+
+    // Set by update_pool_config (0 = no limit)
+    pub min_deposit: u64,                    // Smallest credited deposit
+    pub max_pool_cap: u64,                   // Ceiling on vault + Kamino position + cash principal
+    pub investment_cooldown_secs: i64,       // Minimum time between Kamino investments
+
+    pub pending_payouts: u64,                // Debited from the ledger, not yet delivered (PayoutPending)
+    pub liquidity_buffer_bps: u16,           // Share of total deposits kept out of investment (set by update_pool_config)
+
+    // Set by set_swap_config (swap_mint default = deposit_with_swap disabled)
+    pub swap_mint: Pubkey,                   // Secondary mint swapped into deposit_mint on deposit
+    pub swap_program: Pubkey,                // Program the off-chain route is executed against
+    pub swap_mint_oracle: Pubkey,            // Pyth PriceUpdateV2 for swap_mint
+    pub deposit_mint_oracle: Pubkey,         // Pyth PriceUpdateV2 for deposit_mint
+    pub max_swap_slippage_bps: u16,          // Allowed shortfall against the oracle quote
+
+    pub queued_withdrawals: u64,             // Debited from the ledger, waiting in the WithdrawalQueue
+
+    // Protocol fees, set by set_fee_config and taken out of harvested yield
+    pub performance_fee_bps: u16,            // Share of each harvest's gross yield
+    pub management_fee_bps: u16,             // Per year, on the Kamino position
+    pub fee_recipient_token_account: Pubkey,
+    pub accrued_fees: u64,                   // Booked at harvest, not yet paid by collect_fees
+    pub last_fee_time: i64,                  // Management fee accrues from here
+
+    pub cu_price_micro: u64,                 // Default Arcium callback priority fee, see cu_price
+
+    // Early-exit penalty, see set_early_exit_penalty
+    pub early_exit_penalty_bps: u16,         // Charged on a withdrawal right after the deposit
+    pub early_exit_window_secs: i64,         // Penalty decays linearly to 0 over this window
+    pub early_exit_penalty_to_fees: bool,    // Penalties go to accrued_fees, else to the remaining depositors
+
+    // MXE key clients encrypt to, see observe_mxe_key
+    pub mxe_key_hash: [u8; 32],              // sha256 of the MXE x25519 key last acknowledged
+    pub mxe_rotation_pending: bool,          // A callback saw a different key; client-encrypted flows wait for the ack
+
+    pub withdrawal_cap_per_epoch: u64,       // Most one deposit may withdraw or transfer out per WITHDRAWAL_EPOCH_SLOTS, 0 = uncapped
+
+    pub debits_in_flight: u8,                // Bit per ledger slot whose process_withdrawal hasn't landed yet
+    pub password_rotations: u64,             // Completed rotate_password calls, the only public trace of them
+    pub recovery_inactivity_slots: u64,      // Owner silence before a beneficiary may recover_deposit, 0 = off
+    pub pending_venue_investments: [u64; STRATEGY_VENUES], // pending_investment_amount split per venue (VENUE_*)
+
+    // Auto-compounding, see compound
+    pub auto_compound: bool,                 // Set by set_auto_compound
+    pub uncompounded_yield: u64,             // Redeemed yield back in the vault, not yet reinvested
+
+    pub min_slots_between_checks: u64,       // Set by update_pool_config, 0 = no gating
+    pub last_check_slot: u64,                // Slot of the last queued check_investment_needed
+
+    // One pool state write in flight at a time, see begin_state_write
+    pub state_write_in_flight: Pubkey,       // Computation account of the pending write, default = none
+    pub state_write_slot: u64,               // Slot it was queued in
+
+    pub queued_deposits: u64,                // In the vault, waiting in the DepositIntentQueue
+
+    pub version: u8,                         // GHOST_POOL_VERSION the account was last migrated to
+
+    pub closing_since: i64,                  // When the pool entered Closing (0 = never)
+
+    pub needs_attention: bool,               // Set by reconcile (authority) while the public books don't balance
+
+    // Lending program the Kamino and cash paths CPI into, see propose_lending_program
+    pub lending_program: Pubkey,
+    pub pending_lending_program: Pubkey,     // Default = none
+    pub lending_program_effective_slot: u64, // First slot apply_lending_program accepts the pending one
+
+    pub position_value: u64,                 // USDC value of the cTokens as of sync_position
+    pub position_synced_slot: u64,           // Slot of the last sync_position (0 = never)
+
+    pub share_mint: Pubkey,                  // Set by enable_share_tokens, default = no share tokens
+
+    pub referral_share_bps: u16,             // Of the fees booked on referred deposits, see set_referral_share
+    pub referral_rewards_paid: u64,          // Lifetime total paid by claim_referral_rewards
+
+    pub state_upgrade_pending: bool,         // encrypted_state still holds the v11 PoolState, see upgrade_pool_state
+
+    pub max_deposit_per_user: u64,           // Ceiling on one signer's positions combined, MPC-enforced (0 = no cap)
+
+    pub max_relayer_rebate: u64,             // Most a relayer may take per relayed withdrawal (0 = no relaying)
+
+    pub admin_multisig: Pubkey,              // AdminMultisig co-signing privileged instructions (default = none)
+
+    pub timelock_duration: u64,              // Slots proposed admin actions wait, see timelock_slots
+
+    pub tvl_bucket: u64,                     // publish_tvl rounds to multiples of this (0 = no disclosure)
+    pub tvl_epoch_slots: u64,                // Minimum slots between publish_tvl calls
+    pub last_tvl_slot: u64,
+    pub last_stats_slot: u64,
+}
+
+/// Extra slice of the encrypted deposit ledger, chained off a GhostPool
+#[account]
+pub struct DepositShard {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub shard_index: u16,
+
+    // Encrypted ShardState (SHARD_SIZE deposits, 30 field elements)
+    pub state_nonce: u128,
+    pub encrypted_state: [[u8; 32]; SHARD_STATE_FIELDS],
+
+    pub slots_used: u8,               // Slots reserved by deposits (public, used for routing)
+    pub state_upgrade_pending: bool,  // encrypted_state still holds the v11 ShardState, see upgrade_shard_state
+    pub debits_in_flight: u8,         // Bit per shard slot paid out whose process_withdrawal_shard hasn't landed yet
+}
+
+impl DepositShard {
+    /// Byte offset of `encrypted_state`: discriminator, bump, pool, shard_index and state_nonce
+    pub const ENCRYPTED_STATE_OFFSET: u32 = (8 + 1 + 32 + 2 + 16) as u32;
+    pub const ENCRYPTED_STATE_LEN: u32 = (SHARD_STATE_FIELDS * 32) as u32;
+    pub const LEGACY_ENCRYPTED_STATE_LEN: u32 = (LEGACY_SHARD_STATE_FIELDS * 32) as u32;
+
+    pub const SPACE: usize = 8 + 1 + 32 + 2 + 16 + (32 * SHARD_STATE_FIELDS) + 1 + 1 + 1;
+    /// Shards created before the v12 ShardState: the v11 blob and slots_used
+    pub const LEGACY_SPACE: usize = 8 + 1 + 32 + 2 + 16 + (32 * LEGACY_SHARD_STATE_FIELDS) + 1;
+}
+
+/// Ring buffer of the pool's most recently queued computations
+/// Single account for keepers and indexers to poll for liveness and latency
+#[account]
+pub struct ComputationLog {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub head: u16,                    // Index the next entry is written to
+    pub total_queued: u64,
+    pub entries: [ComputationLogEntry; COMPUTATION_LOG_LEN],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ComputationLogEntry {
+    pub kind: OpKind,
+    pub computation_offset: u64,
+    pub computation_account: Pubkey,  // Matched by callbacks, which don't see the offset
+    pub state_nonce: u128,            // Pool state_nonce the computation consumed
+    pub requester_hash: [u8; 8],      // Truncated sha256 of the requester, not the key itself
+    pub queue_slot: u64,
+    pub completion_slot: u64,         // 0 while pending
+    pub succeeded: bool,              // Aborted or rejected callbacks never complete
+}
+
+/// Tracks one queued computation that staged provisional pool state, keyed by offset
+/// Closed by its callback; otherwise expire_computation rolls the state back
+#[account]
+pub struct PendingComputation {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub computation_offset: u64,
+    pub computation_account: Pubkey,
+    pub kind: OpKind,
+    pub requester: Pubkey,            // Paid the rent, refunded on close
+    pub queue_slot: u64,
+    pub provisional: ProvisionalState,
+}
+
+/// Public pool state a queueing instruction changed ahead of its callback
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub enum ProvisionalState {
+    #[default]
+    None,
+    UnrecordedInvestment { amount: u64 },  // Consumed from unrecorded_investment_amount
+    Harvest {
+        yield_amount: u64,                 // Added to kamino_position_value
+        fee_amount: u64,                   // Booked to accrued_fees
+        prev_fee_time: i64,
+    },
+}
+
+impl ProvisionalState {
+    /// Undo the staged change, for a computation that expired or aborted
+    fn roll_back(self, pool: &mut GhostPool) {
+        match self {
+            ProvisionalState::None => {}
+            ProvisionalState::UnrecordedInvestment { amount } => {
+                pool.unrecorded_investment_amount += amount;
+            }
+            ProvisionalState::Harvest { yield_amount, fee_amount, prev_fee_time } => {
+                pool.kamino_position_value = pool.kamino_position_value.saturating_sub(yield_amount);
+                pool.accrued_fees = pool.accrued_fees.saturating_sub(fee_amount);
+                pool.last_fee_time = prev_fee_time;
+            }
+        }
+    }
+}
+
+impl PendingComputation {
+    #[allow(clippy::too_many_arguments)]
+    fn open(
+        &mut self,
+        bump: u8,
+        pool: Pubkey,
+        computation_offset: u64,
+        computation_account: Pubkey,
+        kind: OpKind,
+        requester: Pubkey,
+        provisional: ProvisionalState,
+    ) -> Result<()> {
+        self.bump = bump;
+        self.pool = pool;
+        self.computation_offset = computation_offset;
+        self.computation_account = computation_account;
+        self.kind = kind;
+        self.requester = requester;
+        self.queue_slot = Clock::get()?.slot;
+        self.provisional = provisional;
+        Ok(())
+    }
+}
+
+/// Tokens a deposit moved into the vault (or, for `deposit`, its escrow) ahead of its
+/// computation, keyed by offset. Closed by the callback once the ledger credits or refunds
+/// them. If the computation aborts, or its callback hasn't landed within
+/// COMPUTATION_TIMEOUT_SLOTS, the depositor takes them back with reclaim_failed_deposit
+/// (reclaim_escrow when escrowed), which closes the account so a late callback fails
+#[account]
+pub struct FailedDeposit {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub user: Pubkey,                 // Depositor: paid the rent, reclaims the tokens
+    pub computation_offset: u64,
+    pub computation_account: Pubkey,
+    pub amount: u64,                  // Tokens held for the deposit, net of transfer fees
+    pub queue_slot: u64,
+    pub failed: bool,                 // Set by the callback of an aborted computation
+    pub escrowed: bool,               // Tokens held in the deposit's escrow, not the vault
+}
+
+impl FailedDeposit {
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 8 + 32 + 8 + 8 + 1 + 1;
+
+    /// The computation aborted, or its callback is overdue
+    fn require_reclaimable(&self) -> Result<()> {
+        require!(
+            self.failed || Clock::get()?.slot >= self.queue_slot.saturating_add(COMPUTATION_TIMEOUT_SLOTS),
+            ErrorCode::DepositNotReclaimable
+        );
+        Ok(())
+    }
+}
+
+/// FIFO of deposits waiting to be folded into the ledger by process_deposit_batch
+/// Tokens are already in the vault; entries hold the depositor's encrypted inputs
+#[account]
+pub struct DepositIntentQueue {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub head: u64,                    // Sequence number of the next intent to fold in
+    pub tail: u64,                    // Sequence number the next intent gets
+    pub batch_len: u8,                // Intents at the head covered by the batch in flight
+    pub entries: [DepositIntent; DEPOSIT_INTENT_QUEUE_LEN],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct DepositIntent {
+    pub user: Pubkey,                 // Depositing signer, bound as the entry's owner
+    pub refund_token_account: Pubkey,
+    pub amount: u64,
+    pub lock_secs: u64,
+    pub user_pubkey: [u8; 32],        // Depositor's x25519 key for the secret below
+    pub nonce: u128,
+    pub encrypted_secret: [[u8; 32]; 4], // DepositSecret: password hash, allowed destinations, beneficiary
+    pub referrer_nonce: u128,
+    pub encrypted_referrer: [u8; 32], // Referrer fingerprint under user_pubkey, 0 for none
+}
+
+impl DepositIntentQueue {
+    /// Append an intent, returning its sequence number
+    fn push(&mut self, intent: DepositIntent) -> Result<u64> {
+        require!(
+            self.tail - self.head < DEPOSIT_INTENT_QUEUE_LEN as u64,
+            ErrorCode::DepositIntentQueueFull
+        );
+        let seq = self.tail;
+        self.entries[(seq % DEPOSIT_INTENT_QUEUE_LEN as u64) as usize] = intent;
+        self.tail += 1;
+        Ok(seq)
+    }
+
+    fn len(&self) -> u64 {
+        self.tail - self.head
+    }
+
+    /// The intent `i` places behind the head
+    fn get(&self, i: usize) -> &DepositIntent {
+        &self.entries[((self.head + i as u64) % DEPOSIT_INTENT_QUEUE_LEN as u64) as usize]
+    }
+
+    fn pop_front(&mut self) -> DepositIntent {
+        let idx = (self.head % DEPOSIT_INTENT_QUEUE_LEN as u64) as usize;
+        let intent = self.entries[idx];
+        self.entries[idx] = DepositIntent::default();
+        self.head += 1;
+        intent
+    }
+}
+
+/// FIFO of withdrawals the vault couldn't fund when they were claimed
+/// Entries are paid strictly in order by fulfill_withdrawal as the vault is replenished
+#[account]
+pub struct WithdrawalQueue {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub head: u64,                    // Sequence number of the next entry to fulfill
+    pub tail: u64,                    // Sequence number the next request gets
+    pub entries: [WithdrawalQueueEntry; WITHDRAWAL_QUEUE_LEN],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct WithdrawalQueueEntry {
+    pub pending_withdrawal: Pubkey,
+    pub amount: u64,
+}
+
+impl WithdrawalQueue {
+    /// Append a withdrawal, returning its sequence number
+    fn push(&mut self, pending_withdrawal: Pubkey, amount: u64) -> Result<u64> {
+        require!(
+            self.tail - self.head < WITHDRAWAL_QUEUE_LEN as u64,
+            ErrorCode::WithdrawalQueueFull
+        );
+        let seq = self.tail;
+        self.entries[(seq % WITHDRAWAL_QUEUE_LEN as u64) as usize] = WithdrawalQueueEntry {
+            pending_withdrawal,
+            amount,
+        };
+        self.tail += 1;
+        Ok(seq)
+    }
+
+    fn front(&self) -> Option<&WithdrawalQueueEntry> {
+        (self.head < self.tail).then(|| &self.entries[(self.head % WITHDRAWAL_QUEUE_LEN as u64) as usize])
+    }
+
+    fn pop_front(&mut self) {
+        self.entries[(self.head % WITHDRAWAL_QUEUE_LEN as u64) as usize] = WithdrawalQueueEntry::default();
+        self.head += 1;
+    }
+}
+
+/// Circuit breaker bits in `GhostPool::paused`, set by pause_pool and cleared by unpause_pool
+pub const PAUSE_DEPOSITS: u8 = 1 << 0;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 1;
+pub const PAUSE_INVESTMENT: u8 = 1 << 2;
+pub const PAUSE_ALL: u8 = PAUSE_DEPOSITS | PAUSE_WITHDRAWALS | PAUSE_INVESTMENT;
+
+/// Encoding of the per-position events, stored on `GhostPool::event_mode`
+///
+/// `Anchor` logs the regular `#[event]` (discriminator + borsh, pool key included).
+/// `Packed` logs `PACKED_EVENT_MAGIC || tag || fields` instead: no discriminator and
+/// no pool key, which the indexer takes from the transaction's ghost_pool account.
+/// Pool-level and admin events are always Anchor events.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum EventMode {
+    #[default]
+    Anchor,
+    Packed,
+}
+
+/// Prefix of every packed event, so indexers can tell it from an Anchor discriminator
+pub const PACKED_EVENT_MAGIC: [u8; 2] = *b"gP";
+
+/// Events that have a packed encoding (see `EventMode::Packed`)
+pub trait PackedEvent: anchor_lang::Event {
+    const TAG: u8;
+    /// Fields after the pool key, little-endian
+    fn pack(&self, out: &mut Vec<u8>);
+}
+
+impl PackedEvent for DepositEvent {
+    const TAG: u8 = 1;
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.deposit_count.to_le_bytes());
+        out.extend_from_slice(&self.amount.to_le_bytes());
+        out.extend_from_slice(&self.computation_offset.to_le_bytes());
+        out.extend_from_slice(&self.state_nonce_before.to_le_bytes());
+        out.extend_from_slice(&self.state_nonce_after.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+}
+
+impl PackedEvent for WithdrawalAuthorizedEvent {
+    const TAG: u8 = 2;
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.user.as_ref());
+        out.extend_from_slice(&self.amount.to_le_bytes());
+        out.push(self.idx);
+        out.extend_from_slice(&self.computation_offset.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+}
+
+impl PackedEvent for WithdrawalClaimedEvent {
+    const TAG: u8 = 3;
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.amount.to_le_bytes());
+    }
+}
+
+impl PackedEvent for WithdrawalCompletedEvent {
+    const TAG: u8 = 4;
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.user.as_ref());
+        out.extend_from_slice(&self.amount.to_le_bytes());
+        out.extend_from_slice(&self.computation_offset.to_le_bytes());
+        out.extend_from_slice(&self.state_nonce_before.to_le_bytes());
+        out.extend_from_slice(&self.state_nonce_after.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+}
+
+impl PackedEvent for WithdrawalFailedEvent {
+    const TAG: u8 = 5;
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.user.as_ref());
+        out.push(self.reason as u8);
+        out.extend_from_slice(&self.computation_offset.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+}
+
+/// Why the authorize circuit turned a withdrawal down, carried by `WithdrawalFailedEvent`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WithdrawalFailure {
+    Unauthorized,
+    DestinationNotAllowed,
+    DepositLocked,
+    Aborted,                          // The authorize computation aborted
+    DebitInFlight,                    // An earlier withdrawal from the slot hasn't been deducted yet
+}
+
+impl WithdrawalFailure {
+    /// From the authorize circuits' destination-allowed and unlocked flags
+    fn from_flags(destination_allowed: bool, unlocked: bool) -> Self {
+        if !destination_allowed {
+            WithdrawalFailure::DestinationNotAllowed
+        } else if !unlocked {
+            WithdrawalFailure::DepositLocked
+        } else {
+            WithdrawalFailure::Unauthorized
+        }
+    }
+}
+
+/// Pool lifecycle, stored on `GhostPool::status`
+///
+/// ```text
+/// Uninitialized --(init_pool_state callback)--> Active
+/// Active <--> Degraded <--> Paused <--> Active
+/// Active | Degraded | Paused --> Closing   (terminal)
+/// Closing --(close_pool)--> account closed
+/// ```
+///
+/// | Status        | Deposits | Withdrawals | Investment |
+/// |---------------|----------|-------------|------------|
+/// | Uninitialized | no       | no          | no         |
+/// | Active        | yes      | yes         | yes        |
+/// | Degraded      | no       | yes         | no         |
+/// | Paused        | no       | no          | no         |
+/// | Closing       | no       | yes         | no         |
+///
+/// Callbacks are never gated, so computations queued before a transition still land.
+/// Strategy redemptions, harvests and ledger repair only need an initialized pool.
+/// `GhostPool::paused` can additionally halt one column at a time without a transition.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum PoolStatus {
+    #[default]
+    Uninitialized,
+    Active,
+    Degraded,
+    Paused,
+    Closing,
+}
+
+impl PoolStatus {
+    /// Edges of the lifecycle diagram; Uninitialized -> Active belongs to the init callback
+    pub fn can_transition_to(self, to: PoolStatus) -> bool {
+        use PoolStatus::*;
+        matches!(
+            (self, to),
+            (Uninitialized, Active)
+                | (Active, Degraded | Paused | Closing)
+                | (Degraded, Active | Paused | Closing)
+                | (Paused, Active | Degraded | Closing)
+        )
+    }
+
+    pub fn is_initialized(self) -> bool {
+        self != PoolStatus::Uninitialized
+    }
+
+    pub fn accepts_deposits(self) -> bool {
+        self == PoolStatus::Active
+    }
+
+    pub fn accepts_withdrawals(self) -> bool {
+        matches!(self, PoolStatus::Active | PoolStatus::Degraded | PoolStatus::Closing)
+    }
+
+    pub fn accepts_investment(self) -> bool {
+        self == PoolStatus::Active
+    }
+}
+
+/// Withdrawal position lifecycle, stored on `PendingWithdrawal::status`
+///
+/// ```text
+/// Requested --(authorize callback)--> Authorized --(claim_withdrawal)--> Claimed
+/// Requested | Authorized --(cancel_pending_withdrawal, after expiry)--> Cancelled
+/// Requested --(authorize callback, rejected)--> expires at once, see WithdrawalFailedEvent
+/// Authorized --(claim_withdrawal, destination not payable)--> PayoutPending
+/// PayoutPending --(retry_payout)--> Claimed
+/// PayoutPending --(recredit_payout, attempts spent)--> Recredited
+/// Authorized --(request_withdrawal, vault short)--> Queued
+/// Queued --(fulfill_withdrawal)--> Claimed | PayoutPending
+/// ```
+///
+/// Claimed, Cancelled and Recredited are terminal: the account is closed in the same
+/// instruction, so they only ever appear in `PositionStatusChangedEvent`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum PositionStatus {
+    #[default]
+    Requested,
+    Authorized,
+    Claimed,
+    Cancelled,
+    PayoutPending,
+    Recredited,
+    Queued,
+}
+
+impl PositionStatus {
+    pub fn can_transition_to(self, to: PositionStatus) -> bool {
+        use PositionStatus::*;
+        matches!(
+            (self, to),
+            (Requested, Authorized)
+                | (Authorized | PayoutPending, Claimed)
+                | (Requested | Authorized, Cancelled)
+                | (Authorized, PayoutPending)
+                | (PayoutPending, Recredited)
+                | (Recredited, PayoutPending)
+                | (Authorized, Queued)
+                | (Queued, Claimed | PayoutPending)
+        )
+    }
+}
+
+impl GhostPool {
+    /// Byte offset of `encrypted_state` in the account data: the discriminator plus every
+    /// field declared ahead of it. Handed to the MXE, which reads the PoolState from there
+    pub const ENCRYPTED_STATE_OFFSET: u32 = (8 // discriminator
+        + size_of::<u8>() // bump
+        + size_of::<Pubkey>() // authority
+        + size_of::<Pubkey>() // deposit_mint
+        + size_of::<u8>() // vault_bump
+        + size_of::<u64>() // investment_threshold
+        + size_of::<i64>() // last_investment_time
+        + size_of::<u128>()) as u32; // state_nonce
+    pub const ENCRYPTED_STATE_LEN: u32 = (POOL_STATE_FIELDS * 32) as u32;
+    /// Length of the v11 PoolState version 0 and 1 pools hold at the same offset
+    pub const LEGACY_ENCRYPTED_STATE_LEN: u32 = (LEGACY_POOL_STATE_FIELDS * 32) as u32;
+
+    /// Account size for the current layout
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 1 + 8 + 8 + 16 + (32 * POOL_STATE_FIELDS) + 8 + 8 + 8 + 8 + 32 + 8 + 2 + 2 + 2 + 8 + 8 + 32 + 1 + 8 + 8 + 8 + 8 + 1 + 1 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 8 + 1 + 32 + 32 + 1 + 8 + 8 + 8 + 8 + 2 + 32 + 32 + 32 + 32 + 2 + 8 + 2 + 2 + 32 + 8 + 8 + 8 + 2 + 8 + 1 + 32 + 1 + 8 + 1 + 8 + 8 + (8 * STRATEGY_VENUES) + 1 + 8 + 8 + 8 + 32 + 8 + 8 + 1 + 8 + 1 + 32 + 32 + 8 + 8 + 8 + 32 + 2 + 8 + 1 + 8 + 8 + 32 + 8 + 8 + 8 + 8 + 8;  // v5: + Kamino fields + shards + counter privacy + rotator + investment record + harvest + legacy sunset + cash strategy + pool id + status + mint decimals + pause flags + authority handover + event mode + pool config + pending payouts + liquidity buffer + swap config + withdrawal queue + fees + priority fee + early-exit penalty + cluster rotation + withdrawal cap + debits in flight + password rotations + recovery period + venue split + auto compound + check gating + write sequencing + deposit intents + layout version + close grace + reconciliation flag + lending program + synced position + share mint + referrals + state upgrade + per-user cap + relayer rebate + admin multisig + timelock + TVL disclosure + pool stats
+
+    /// Largest account written under version 1: the v11 blob, and every field through share_mint
+    pub const V1_SPACE: usize = Self::SPACE
+        - (Self::ENCRYPTED_STATE_LEN - Self::LEGACY_ENCRYPTED_STATE_LEN) as usize
+        - (2 + 8 + 1 + 8 + 8 + 32 + 8 + 8 + 8 + 8 + 8);
+
+    /// Where `encrypted_state` lives for this account's version. A migration that moves the
+    /// blob bumps GHOST_POOL_VERSION and adds the new location here. Pools that haven't been
+    /// migrated yet, or whose blob still waits for upgrade_pool_state, are refused by every computation
+    fn encrypted_state_location(&self) -> Result<(u32, u32)> {
+        match self.version {
+            GHOST_POOL_VERSION if self.state_upgrade_pending => Err(ErrorCode::StateUpgradePending.into()),
+            GHOST_POOL_VERSION => Ok((Self::ENCRYPTED_STATE_OFFSET, Self::ENCRYPTED_STATE_LEN)),
+            v if v < GHOST_POOL_VERSION => Err(ErrorCode::PoolNotMigrated.into()),
+            _ => Err(ErrorCode::UnsupportedStateLayout.into()),
+        }
+    }
+
+    pub fn is_paused(&self, flags: u8) -> bool {
+        self.paused & flags != 0
+    }
+
+    /// Slots between proposing an admin action and running it; pools migrated from before
+    /// timelock_duration existed read 0 there and get the minimum
+    pub fn timelock_slots(&self) -> u64 {
+        self.timelock_duration.max(MIN_TIMELOCK_SLOTS)
+    }
+
+    /// Whether deposits mint share tokens, see enable_share_tokens
+    pub fn issues_shares(&self) -> bool {
+        self.share_mint != Pubkey::default()
+    }
+
+    /// What the pool holds for its depositors (the share supply's backing): vault, Kamino
+    /// position and cash principal, less what the vault holds for others or owes already
+    fn net_assets(&self, vault_balance: u64) -> u64 {
+        vault_balance
+            .saturating_add(self.kamino_position_value)
+            .saturating_add(self.cash_principal)
+            .saturating_sub(self.pending_payouts)
+            .saturating_sub(self.queued_withdrawals)
+            .saturating_sub(self.queued_deposits)
+            .saturating_sub(self.accrued_fees)
+    }
+
+    fn counters(&self) -> PoolCounters {
+        PoolCounters {
+            total_deposits: self.total_deposits,
+            total_withdrawals: self.total_withdrawals,
+            state_nonce: self.state_nonce,
+        }
+    }
+
+    /// Debug-build invariants, checked at the end of every instruction that moves funds,
+    /// public counters or the encrypted state. Compiled out of release builds, so deployed
+    /// programs pay no CU for them
+    fn check_invariants(&self, before: &PoolCounters, write: StateWrite, vault: Option<&AccountInfo>) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        debug_assert!(
+            self.total_withdrawals >= before.total_withdrawals,
+            "total_withdrawals went backwards"
+        );
+        debug_assert!(
+            self.total_deposits >= before.total_deposits,
+            "total_deposits went backwards"
+        );
+        match write {
+            StateWrite::None => debug_assert!(
+                self.state_nonce == before.state_nonce,
+                "state_nonce moved without a state write"
+            ),
+            StateWrite::Reencrypted => debug_assert!(
+                self.state_nonce == before.state_nonce.wrapping_add(1),
+                "state_nonce out of step with the state write"
+            ),
+            StateWrite::Fresh => {}
+        }
+        if let Some(vault) = vault {
+            debug_assert!(
+                token_amount(vault) >= self.pending_payouts,
+                "vault holds less than the undelivered payouts"
+            );
+        }
+    }
+
+    /// Priority fee (micro-lamports per CU) for a queued computation's callback txs:
+    /// the caller's `requested` override if any, else the pool default.
+    /// Cranks always pass None
+    fn cu_price(&self, requested: Option<u64>) -> u64 {
+        requested.unwrap_or(self.cu_price_micro)
+    }
+
+    fn charges_fees(&self) -> bool {
+        self.performance_fee_bps > 0 || self.management_fee_bps > 0
+    }
+
+    /// Management fee accrued on the Kamino position since the last fee checkpoint
+    fn management_fee(&self, now: i64) -> u64 {
+        if self.last_fee_time == 0 || now <= self.last_fee_time {
+            return 0;
+        }
+        let elapsed = (now - self.last_fee_time) as u128;
+        (self.kamino_position_value as u128 * self.management_fee_bps as u128 * elapsed
+            / (MAX_BPS as u128 * SECONDS_PER_YEAR)) as u64
+    }
+
+    /// Fee kept out of `yield_amount`: the performance fee plus `management_fee`, capped at
+    /// the yield (must match record_yield_with_fees in encrypted-ixs)
+    fn yield_fee(&self, yield_amount: u64, management_fee: u64) -> u64 {
+        let performance_fee = (yield_amount as u128 * self.performance_fee_bps as u128 / MAX_BPS as u128) as u64;
+        performance_fee.saturating_add(management_fee).min(yield_amount)
+    }
+
+    /// Enforce min_deposit, max_deposit_per_user and max_pool_cap on a deposit, `vault_balance`
+    /// being the vault's balance with the deposit in it. The MPC holds everything the depositor
+    /// has in the ledger, not just the deposit, to max_deposit_per_user
+    fn check_deposit_limits(&self, amount: u64, vault_balance: u64) -> Result<()> {
+        require!(amount >= self.min_deposit, ErrorCode::DepositBelowMinimum);
+        require!(
+            self.max_deposit_per_user == 0 || amount <= self.max_deposit_per_user,
+            ErrorCode::DepositAboveUserCap
+        );
+        if self.max_pool_cap > 0 {
+            let pool_value = vault_balance
+                .saturating_add(self.kamino_position_value)
+                .saturating_add(self.cash_principal);
+            require!(pool_value <= self.max_pool_cap, ErrorCode::PoolCapExceeded);
+        }
+        Ok(())
+    }
+
+    /// Claim the pool's single state write slot for a computation about to be queued
+    /// Computations reading the same encrypted_state would otherwise race, the later
+    /// callback dropping the earlier one's effects. A write whose callback never landed
+    /// stops blocking after STATE_WRITE_TIMEOUT_SLOTS
+    fn begin_state_write(&mut self, computation_account: Pubkey) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        require!(
+            self.state_write_in_flight == Pubkey::default()
+                || slot >= self.state_write_slot.saturating_add(STATE_WRITE_TIMEOUT_SLOTS),
+            ErrorCode::StateWriteInFlight
+        );
+        self.state_write_in_flight = computation_account;
+        self.state_write_slot = slot;
+        Ok(())
+    }
+
+    /// Release the slot once the computation's callback has landed, written or rejected
+    fn end_state_write(&mut self, computation_account: &Pubkey) {
+        if self.state_write_in_flight == *computation_account {
+            self.state_write_in_flight = Pubkey::default();
+        }
+    }
+
+    /// Book an executed strategy investment of `venue`'s pending share
+    fn book_investment(
+        &mut self,
+        venue: usize,
+        amount: u64,
+        collateral_received: u64,
+        collateral_account: Pubkey,
+    ) -> Result<()> {
+        self.total_invested += amount;
+        self.total_collateral_received += collateral_received;
+        self.pending_investment_amount = self.pending_investment_amount.saturating_sub(amount);
+        self.pending_venue_investments[venue] = 0;
+        self.unrecorded_investment_amount += amount;
+        self.kamino_position_value += amount;
+        self.last_investment_time = Clock::get()?.unix_timestamp;
+        self.collateral_token_account = collateral_account;
+        Ok(())
+    }
+
+    /// Retire the redeemed share of principal; the rest of the payout is yield
+    fn book_redemption(&mut self, collateral_amount: u64, collateral_before: u64, liquidity_amount: u64) {
+        let principal = (self.total_invested as u128 * collateral_amount as u128
+            / collateral_before as u128) as u64;
+        self.total_invested = self.total_invested.saturating_sub(principal);
+        self.total_collateral_received = self.total_collateral_received.saturating_sub(collateral_amount);
+        self.kamino_position_value = self.kamino_position_value.saturating_sub(liquidity_amount);
+        self.uncompounded_yield += liquidity_amount.saturating_sub(principal);
+    }
+
+    /// Book yield reinvested by compound: it was already recorded by record_yield, so it joins
+    /// the principal without going through record_investment
+    fn book_compound(&mut self, amount: u64, collateral_received: u64) {
+        self.total_invested += amount;
+        self.total_collateral_received += collateral_received;
+        self.kamino_position_value += amount;
+        self.uncompounded_yield = self.uncompounded_yield.saturating_sub(amount);
+    }
+
+    /// Move the pool along its lifecycle, emitting the transition
+    fn transition(&mut self, pool: Pubkey, to: PoolStatus) -> Result<()> {
+        require!(self.status.can_transition_to(to), ErrorCode::InvalidStatusTransition);
+        emit!(PoolStatusChangedEvent {
+            pool,
+            from: self.status,
+            to,
+        });
+        if to == PoolStatus::Closing {
+            self.closing_since = Clock::get()?.unix_timestamp;
+        }
+        self.status = to;
+        Ok(())
+    }
+}
+
+impl WithdrawalReceipt {
+    /// process_withdrawal's (amount, pool_penalty): the payout plus its early-exit penalty
+    /// leave the ledger, and the penalty is shared out again unless it went to fees
+    fn ledger_debit(&self) -> (u64, u64) {
+        let pool_penalty = if self.penalty_to_fees { 0 } else { self.penalty };
+        (self.amount + self.penalty, pool_penalty)
+    }
+}
+
+impl PendingWithdrawal {
+    /// Move the position along its lifecycle, emitting the transition
+    fn transition(&mut self, to: PositionStatus) -> Result<()> {
+        require!(self.status.can_transition_to(to), ErrorCode::InvalidStatusTransition);
+        emit!(PositionStatusChangedEvent {
+            pool: self.pool,
+            user: self.user,
+            from: self.status,
+            to,
+        });
+        self.status = to;
+        Ok(())
+    }
+}
+
+/// Public pool counters, snapshotted before an instruction for `GhostPool::check_invariants`
+struct PoolCounters {
+    total_deposits: u64,
+    total_withdrawals: u64,
+    state_nonce: u128,
+}
+
+// Borsh writes these fixed-size fields at their in-memory size, so the sums above match the
+// serialized account. Pinned so a field added ahead of the blob fails the build instead of
+// feeding the MXE the wrong bytes; deliberate moves go through GHOST_POOL_VERSION
+const _: () = assert!(GhostPool::ENCRYPTED_STATE_OFFSET == 106);
+const _: () = assert!(GhostPool::ENCRYPTED_STATE_LEN as usize == size_of::<[[u8; 32]; POOL_STATE_FIELDS]>());
+const _: () = assert!(DepositShard::ENCRYPTED_STATE_OFFSET == 59);
+
+/// Pass a pool's (or shard's) `encrypted_state` to a computation by reference
+trait PoolStateArg: Sized {
+    fn pool_state(self, pool: &Account<GhostPool>) -> Result<Self>;
+    fn shard_state(self, shard: &Account<DepositShard>) -> Result<Self>;
+}
+
+impl PoolStateArg for ArgBuilder {
+    fn pool_state(self, pool: &Account<GhostPool>) -> Result<Self> {
+        let (offset, len) = pool.encrypted_state_location()?;
+        Ok(self.account(pool.key(), offset, len))
+    }
+
+    fn shard_state(self, shard: &Account<DepositShard>) -> Result<Self> {
+        require!(!shard.state_upgrade_pending, ErrorCode::StateUpgradePending);
+        Ok(self.account(shard.key(), DepositShard::ENCRYPTED_STATE_OFFSET, DepositShard::ENCRYPTED_STATE_LEN))
+    }
+}
+
+/// How an instruction changed `encrypted_state`, which fixes how far `state_nonce` may move
+#[derive(Clone, Copy)]
+enum StateWrite {
+    None,
+    /// Single EncData output: the MXE re-encrypts under state_nonce + 1
+    Reencrypted,
+    /// Multi-output circuit: the MXE returns the new nonce
+    Fresh,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum OpKind {
+    #[default]
+    None,
+    Deposit,
+    CheckInvestment,
+    Withdraw,
+    FinalizeWithdrawal,
+    RecordInvestment,
+    RevealDepositCount,
+    InitShard,
+    DepositShard,
+    WithdrawShard,
+    RecordYield,
+    MigrateLegacy,
+    ClaimWithdrawal,
+    RecreditPayout,
+    RequestWithdrawal,
+    TransferPosition,
+    TopUp,
+    QueryBalance,
+    RotatePassword,
+    RecoverDeposit,
+    SetStrategyWeights,
+    DepositBatch,
+    ClaimReferral,
+    UpgradeState,
+    PublishTvl,
+    PublishStats,
+}
+
+impl ComputationLog {
+    fn record_queued(
+        &mut self,
+        kind: OpKind,
+        computation_offset: u64,
+        computation_account: Pubkey,
+        state_nonce: u128,
+        requester: &Pubkey,
+    ) -> Result<()> {
+        let mut requester_hash = [0u8; 8];
+        requester_hash.copy_from_slice(&hash(requester.as_ref()).to_bytes()[..8]);
+
+        let head = self.head as usize;
+        self.entries[head] = ComputationLogEntry {
+            kind,
+            computation_offset,
+            computation_account,
+            state_nonce,
+            requester_hash,
+            queue_slot: Clock::get()?.slot,
+            completion_slot: 0,
+            succeeded: false,
+        };
+        self.head = ((head + 1) % COMPUTATION_LOG_LEN) as u16;
+        self.total_queued += 1;
+
+        emit!(ComputationQueuedEvent {
+            pool: self.pool,
+            kind,
+            computation_offset,
+            state_nonce,
+            slot: self.entries[head].queue_slot,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Offset a pending computation was queued under, for callbacks' events
+    /// (0 if its entry was already overwritten)
+    fn computation_offset(&self, computation_account: &Pubkey) -> u64 {
+        self.entries
+            .iter()
+            .find(|e| e.completion_slot == 0 && e.computation_account == *computation_account)
+            .map_or(0, |e| e.computation_offset)
+    }
+
+    /// Mark the entry for a computation as done; no-op if it was already overwritten
+    fn record_completed(&mut self, computation_account: &Pubkey) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|e| e.completion_slot == 0 && e.computation_account == *computation_account)
+        {
+            entry.completion_slot = slot;
+            entry.succeeded = true;
+        }
+        Ok(())
+    }
+
+    /// Refuse a pool state write from a computation that consumed a different state_nonce
+    /// than the pool holds now: another write landed in between, so applying this one would
+    /// drop it and leave state_nonce out of step with the ciphertexts. Entries already
+    /// overwritten in the ring can't be checked
+    fn check_state_nonce(&self, computation_account: &Pubkey, state_nonce: u128) -> Result<()> {
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .find(|e| e.completion_slot == 0 && e.computation_account == *computation_account)
+        {
+            require!(entry.state_nonce == state_nonce, ErrorCode::NonceMismatch);
+        }
+        Ok(())
+    }
+
+    /// Mark the entry for an expired or rejected computation as done without success
+    fn record_failed(&mut self, computation_account: &Pubkey) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|e| e.completion_slot == 0 && e.computation_account == *computation_account)
+        {
+            entry.completion_slot = slot;
+        }
+        Ok(())
+    }
+
+    /// Record an aborted computation as failed and emit ComputationFailedEvent, once its
+    /// callback has undone whatever the queueing instruction staged
+    /// (kind None and offset 0 if the entry was already overwritten)
+    fn record_aborted(&mut self, computation_account: &Pubkey) -> Result<()> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.completion_slot == 0 && e.computation_account == *computation_account)
+            .copied()
+            .unwrap_or_default();
+        self.record_failed(computation_account)?;
+
+        msg!("Computation {} aborted", entry.computation_offset);
+        emit!(ComputationFailedEvent {
+            pool: self.pool,
+            kind: entry.kind,
+            computation_offset: entry.computation_offset,
+        });
+        Ok(())
+    }
+}
+
+/// On-chain proof of a withdrawal payout (for integrators that can't consume events)
+#[account]
+pub struct WithdrawalReceipt {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub user: Pubkey,                 // Signer of the withdrawal, may close the receipt
+    pub destination: Pubkey,          // Token account that received the payout
+    pub recipient: Pubkey,            // Final recipient (differs from destination when routed via rotator)
+    pub amount: u64,
+    pub slot: u64,                    // Slot the payout landed in (0 until paid)
+    pub computation_offset: u64,
+    pub paid: bool,
+    pub idx: u8,                      // Ledger slot revealed by the authorize callback
+    pub from_shard: bool,             // Paid from the DepositShard at shard_index
+    pub finalized: bool,              // Encrypted ledger updated (finalize_withdrawal, or finalize_shard_withdrawal)
+    pub forwarded: bool,              // Rotator hop completed
+    pub penalty: u64,                 // Early-exit penalty kept back from `amount` (debited on top of it)
+    pub penalty_to_fees: bool,        // Penalty goes to accrued_fees instead of the remaining depositors
+    pub shard_index: u16,             // Shard a from_shard withdrawal debits
+}
+
+/// Optional per-user record of a deposit, enumerable by `receipt_index`
+/// Holds commitments only: the password and amount stay out of public state
+#[account]
+pub struct DepositReceipt {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub receipt_index: u32,
+    pub password_commitment: [u8; 32],  // sha256 over the encrypted password hash and nonce
+    pub amount_commitment: [u8; 32],    // sha256 over the amount and a user-chosen blinding
+    pub deposited_at: i64,
+    pub deposit_slot: u64,
+}
+
+/// Latest query_balance answer for a (pool, user), encrypted to the querying client
+#[account]
+pub struct BalanceQueryResult {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub computation_account: Pubkey,  // Query the result belongs to
+    pub encrypted_balance: [u8; 32],  // Enc<Shared, u64> ciphertext under the client's x25519 key
+    pub nonce: u128,
+    pub slot: u64,                    // Slot the result landed in (0 while the query is pending)
+}
+
+/// Lending venue a `YieldAdapter` dispatches to
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum YieldVenue {
+    Mock,      // Mock Kamino (devnet builds)
+    Kamino,    // KLend (`kamino-mainnet` builds)
+    MarginFi,  // Positions live in a MarginFi account, not a cToken: no adapter arm yet
+    Solend,
+}
+
+impl YieldVenue {
+    /// Lending program the venue's CPIs go to, None if this build has no arm for it
+    /// (Mock and Kamino adapters are registered against the pool's lending_program instead)
+    pub fn program_id(self) -> Option<Pubkey> {
+        match self {
+            YieldVenue::Mock => (!cfg!(feature = "kamino-mainnet")).then_some(KAMINO_LENDING_PROGRAM_ID),
+            YieldVenue::Kamino => cfg!(feature = "kamino-mainnet").then_some(KAMINO_LENDING_PROGRAM_ID),
+            YieldVenue::MarginFi => None,
+            YieldVenue::Solend => Some(SOLEND_PROGRAM_ID),
+        }
+    }
+}
+
+/// The pool's registered yield venue, used by invest_via_adapter / divest_via_adapter
+#[account]
+pub struct YieldAdapter {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub venue: YieldVenue,
+    pub program_id: Pubkey,  // Lending program, pinned from `venue` at registration
+    pub reserve: Pubkey,     // Reserve the pool invests into
+}
+
+/// Crank permissioning for check_and_invest, harvest_yield and fulfill_withdrawal
+#[account]
+pub struct CrankerRegistry {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub permissionless: bool,  // Anyone may crank; only registered crankers earn the bounty
+    pub bounty: u64,           // Credited per successful crank, out of accrued_fees
+    pub cranker_count: u16,
+}
+
+/// Co-signers required on top of the authority for privileged instructions, set by
+/// set_admin_multisig. Members sign as remaining accounts after this account
+#[account]
+pub struct AdminMultisig {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub threshold: u8,                         // Distinct members that must sign
+    pub member_count: u8,
+    pub members: [Pubkey; MAX_ADMIN_MULTISIG_MEMBERS],
+}
+
+impl AdminMultisig {
+    pub const SPACE: usize = 8 + 1 + 32 + 1 + 1 + 32 * MAX_ADMIN_MULTISIG_MEMBERS;
+
+    pub fn members(&self) -> &[Pubkey] {
+        &self.members[..self.member_count as usize]
+    }
+}
+
+/// A keeper whitelisted by register_cranker, one per (pool, keeper)
+#[account]
+pub struct Cranker {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub keeper: Pubkey,
+    pub bounty_owed: u64,      // Credited, not yet paid by claim_crank_bounty
+    pub cranks: u64,           // Successful cranks credited so far
+}
+
+/// Off-chain circuit sources the init_*_comp_def instructions read, one per program
+#[account]
+pub struct CircuitRegistry {
+    pub bump: u8,
+    pub authority: Pubkey,     // Program upgrade authority at creation; signs circuit upgrades
+    pub count: u8,             // Pins in use, from the front of `entries`
+    pub entries: [CircuitPin; CIRCUIT_REGISTRY_LEN],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct CircuitPin {
+    pub name: [u8; 32],                  // Circuit name, zero-padded; its comp def offset derives from it
+    pub url: [u8; MAX_CIRCUIT_URL_LEN],  // Zero-padded
+    pub hash: [u8; 32],                  // circuit_hash! of the build the URL serves
+    pub version: u16,                    // 1 when first pinned, bumped by each upgrade_circuit
+}
+
+impl CircuitRegistry {
+    pub const SPACE: usize = 8 + 1 + 32 + 1 + CIRCUIT_REGISTRY_LEN * (32 + MAX_CIRCUIT_URL_LEN + 32 + 2);
+
+    /// Source for `circuit`, None if it isn't pinned (the circuit is then uploaded on-chain)
+    pub fn source(&self, circuit: &str) -> Option<CircuitSource> {
+        let name = circuit_name(circuit).ok()?;
+        let pin = self.entries[..self.count as usize].iter().find(|pin| pin.name == name)?;
+        let len = pin.url.iter().position(|b| *b == 0).unwrap_or(MAX_CIRCUIT_URL_LEN);
+        Some(CircuitSource::OffChain(OffChainCircuitSource {
+            source: String::from_utf8_lossy(&pin.url[..len]).into_owned(),
+            hash: pin.hash,
+        }))
+    }
+
+    /// Pin `circuit` to (url, hash), returning the pin's version
+    fn pin(&mut self, circuit: &str, url: &str, hash: [u8; 32]) -> Result<u16> {
+        let name = circuit_name(circuit)?;
+        require!(
+            !url.is_empty() && url.len() <= MAX_CIRCUIT_URL_LEN && !url.contains('\0'),
+            ErrorCode::InvalidCircuitUrl
+        );
+        let idx = match self.entries[..self.count as usize].iter().position(|pin| pin.name == name) {
+            Some(idx) => idx,
+            None => {
+                require!((self.count as usize) < CIRCUIT_REGISTRY_LEN, ErrorCode::CircuitRegistryFull);
+                self.count += 1;
+                self.count as usize - 1
+            }
+        };
+        let pin = &mut self.entries[idx];
+        pin.name = name;
+        pin.url = [0; MAX_CIRCUIT_URL_LEN];
+        pin.url[..url.len()].copy_from_slice(url.as_bytes());
+        pin.hash = hash;
+        pin.version += 1;
+        Ok(pin.version)
+    }
+}
+
+/// Registry key of a circuit: its name, zero-padded to 32 bytes
+fn circuit_name(circuit: &str) -> Result<[u8; 32]> {
+    require!(!circuit.is_empty() && circuit.len() <= 32, ErrorCode::InvalidCircuitName);
+    let mut name = [0u8; 32];
+    name[..circuit.len()].copy_from_slice(circuit.as_bytes());
+    Ok(name)
+}
+
+/// A privileged change held back by the timelock, one per (target, kind)
+#[account]
+pub struct PendingAdminAction {
+    pub bump: u8,
+    pub target: Pubkey,        // GhostPool, or the CircuitRegistry for circuit pins
+    pub proposer: Pubkey,      // Authority that proposed it; signs cancel_action
+    pub eta_slot: u64,         // First slot the action may run
+    pub action: AdminAction,
+}
+
+impl PendingAdminAction {
+    // Sized for the largest variant, CircuitPin
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 8 + 1 + (4 + 32) + (4 + MAX_CIRCUIT_URL_LEN) + 32;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum AdminAction {
+    FeeConfig { performance_fee_bps: u16, management_fee_bps: u16, fee_recipient_token_account: Pubkey },
+    CircuitPin { circuit: String, url: String, hash: [u8; 32] },
+}
+
+impl AdminAction {
+    pub fn kind(&self) -> AdminActionKind {
+        match self {
+            Self::FeeConfig { .. } => AdminActionKind::FeeConfig,
+            Self::CircuitPin { .. } => AdminActionKind::CircuitPin,
+        }
+    }
+}
+
+/// PendingAdminAction seed and event tag for each AdminAction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdminActionKind {
+    FeeConfig,
+    CircuitPin,
+}
+
+/// Escrow between authorize_withdrawal and claim_withdrawal, one per (pool, user)
+#[account]
+pub struct PendingWithdrawal {
+    pub bump: u8,
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub receipt: Pubkey,              // WithdrawalReceipt paid out on claim
+    pub amount: u64,                  // Authorized amount (0 until the callback lands)
+    pub idx: u8,                      // Ledger slot revealed by the authorize callback
+    pub expiry_slot: u64,             // Claimable until this slot, cancellable after
+    pub status: PositionStatus,
+    pub destination: Pubkey,          // Payout token account (the transfer retry_payout replays)
+    pub payout_attempts: u8,          // Undelivered payouts so far, capped at MAX_PAYOUT_ATTEMPTS
+    pub destination_owner: u128,      // Fingerprint of the owner the MPC authorized paying (0 = unbound)
+}
+
+#[queue_computation_accounts("init_pool_state", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, nonce: u128, investment_threshold: u64, pool_id: u64)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = GhostPool::SPACE,
+        seeds = [b"ghost_pool", authority.key().as_ref(), &pool_id.to_le_bytes()],
+        bump,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Vault PDA to hold the deposit mint
+    #[account(
+        init,
+        payer = authority,
+        token::mint = deposit_mint,
+        token::authority = ghost_pool,
+        token::token_program = token_program,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_POOL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("init_pool_state")]
+#[derive(Accounts)]
+pub struct InitPoolStateCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_POOL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+#[queue_computation_accounts("process_deposit", user)]
+#[derive(Accounts)]
+#[instruction(
+    computation_offset: u64,
+    amount: u64,
+    encrypted_password_hash: [u8; 32],
+    user_pubkey: [u8; 32],
+    nonce: u128,
+    encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+    encrypted_beneficiary: [u8; 32],
+    encrypted_referrer: [u8; 32],
+    referrer_nonce: u128,
+    lock_secs: u64,
+    receipt_index: u32,
+)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+
+    /// The signer's own account, or (deposit_delegated) one it is the delegate of
+    #[account(
+        mut,
+        constraint = user_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = user_usdc_token.owner == user.key()
+            || user_usdc_token.delegate == COption::Some(user.key()) @ ErrorCode::TokenOwnerMismatch,
+    )]
+    pub user_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+        constraint = vault_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = vault_usdc_token.owner == ghost_pool.key() @ ErrorCode::InvalidVault,
+    )]
+    pub vault_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Where the deposit is refunded if the ledger rejects it (usually `user_usdc_token`)
+    #[account(
+        mut,
+        constraint = refund_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub refund_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    /// Optional deposit receipt (omit to leave no per-user artifact)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 4 + 32 + 32 + 8 + 8,
+        seeds = [b"receipt", ghost_pool.key().as_ref(), user.key().as_ref(), &receipt_index.to_le_bytes()],
+        bump,
+    )]
+    pub deposit_receipt: Option<Box<Account<'info, DepositReceipt>>>,
+
+    /// Record of the escrowed deposit, reclaimable through it should the computation abort
+    #[account(
+        init,
+        payer = user,
+        space = FailedDeposit::SPACE,
+        seeds = [b"failed_deposit", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub failed_deposit: Box<Account<'info, FailedDeposit>>,
+
+    /// Holds deposits until their callback sweeps them into the vault (or refunds them)
+    #[account(
+        init_if_needed,
+        payer = user,
+        token::mint = deposit_mint,
+        token::authority = ghost_pool,
+        token::token_program = token_program,
+        seeds = [b"escrow", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub deposit_escrow: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Where the callback mints the deposit's shares (share pools only)
+    #[account(constraint = share_token_account.mint == ghost_pool.share_mint @ ErrorCode::InvalidMint)]
+    pub share_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+
+    // Arcium accounts...
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[derive(Accounts)]
+pub struct QueueDeposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"deposit_intents", ghost_pool.key().as_ref()],
+        bump = deposit_intents.bump,
+    )]
+    pub deposit_intents: Box<Account<'info, DepositIntentQueue>>,
+
+    #[account(
+        mut,
+        constraint = user_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = user_usdc_token.owner == user.key() @ ErrorCode::TokenOwnerMismatch,
+    )]
+    pub user_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+        constraint = vault_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = vault_usdc_token.owner == ghost_pool.key() @ ErrorCode::InvalidVault,
+    )]
+    pub vault_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Where the deposit is refunded if the ledger rejects it (usually `user_usdc_token`)
+    #[account(
+        constraint = refund_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub refund_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[queue_computation_accounts("process_deposit_batch", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ProcessDepositBatch<'info> {
+    /// Keeper or authority; pays the computation
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"deposit_intents", ghost_pool.key().as_ref()],
+        bump = deposit_intents.bump,
+    )]
+    pub deposit_intents: Box<Account<'info, DepositIntentQueue>>,
+    #[account(
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump = cranker_registry.bump,
+    )]
+    pub cranker_registry: Option<Box<Account<'info, CrankerRegistry>>>,
+    /// Payer's registration, credited the crank bounty
+    #[account(
+        mut,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), payer.key().as_ref()],
+        bump = cranker.bump,
+    )]
+    pub cranker: Option<Box<Account<'info, Cranker>>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_DEPOSIT_BATCH))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_deposit_batch")]
+#[derive(Accounts)]
+pub struct ProcessDepositBatchCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_DEPOSIT_BATCH))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"deposit_intents", ghost_pool.key().as_ref()],
+        bump = deposit_intents.bump,
+    )]
+    pub deposit_intents: Box<Account<'info, DepositIntentQueue>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Refund legs for rejected intents
+    /// CHECK: matched against each intent's recorded refund account, deserialized by is_payable
+    #[account(mut)]
+    pub refund_token_account_0: Option<UncheckedAccount<'info>>,
+    /// CHECK: matched against each intent's recorded refund account, deserialized by is_payable
+    #[account(mut)]
+    pub refund_token_account_1: Option<UncheckedAccount<'info>>,
+}
+
+#[callback_accounts("process_deposit")]
+#[derive(Accounts)]
+pub struct ProcessDepositCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Refund leg for a rejected token deposit (None for recredit_payout)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
+    #[account(mut)]
+    pub refund_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Recredited position handed back to PayoutPending if recredit_payout is rejected
+    #[account(mut)]
+    pub pending_withdrawal: Option<Box<Account<'info, PendingWithdrawal>>>,
+    /// The deposit's FailedDeposit: closed once its tokens are credited or refunded, marked failed on abort
+    #[account(mut)]
+    pub failed_deposit: Option<Box<Account<'info, FailedDeposit>>>,
+    /// CHECK: rent refund for failed_deposit, checked against its user
+    #[account(mut)]
+    pub depositor: Option<UncheckedAccount<'info>>,
+    /// The pool's deposit escrow (`deposit` only): failed_deposit.amount of it is swept into the
+    /// vault once credited, else refunded
+    #[account(mut)]
+    pub deposit_escrow: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    /// Share pools (`deposit` only): the credited deposit's shares are minted to share_token_account
+    #[account(mut, address = ghost_pool.share_mint @ ErrorCode::InvalidMint)]
+    pub share_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
+    #[account(mut)]
+    pub share_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+}
+
+#[queue_computation_accounts("process_deposit", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct DepositWithSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+        constraint = ghost_pool.swap_mint != Pubkey::default() @ ErrorCode::SwapNotConfigured,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+
+    /// Source of the swap; what it loses is what gets priced
+    #[account(
+        mut,
+        constraint = user_swap_token.mint == ghost_pool.swap_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_swap_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+        constraint = vault_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = vault_usdc_token.owner == ghost_pool.key() @ ErrorCode::InvalidVault,
+    )]
+    pub vault_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.swap_mint @ ErrorCode::InvalidMint)]
+    pub swap_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Deposit-mint account the swapped amount is refunded to if the ledger rejects the deposit
+    #[account(
+        mut,
+        constraint = refund_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub refund_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    /// Optional failed-deposit record (see FailedDeposit): omit it and an aborted computation keeps the tokens
+    #[account(
+        init,
+        payer = user,
+        space = FailedDeposit::SPACE,
+        seeds = [b"failed_deposit", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub failed_deposit: Option<Box<Account<'info, FailedDeposit>>>,
+
+    #[account(address = ghost_pool.swap_mint_oracle @ ErrorCode::InvalidOracle)]
+    /// CHECK: swap_mint_oracle
+    pub swap_mint_oracle: UncheckedAccount<'info>,
+
+    #[account(address = ghost_pool.deposit_mint_oracle @ ErrorCode::InvalidOracle)]
+    /// CHECK: deposit_mint_oracle
+    pub deposit_mint_oracle: UncheckedAccount<'info>,
+
+    #[account(address = ghost_pool.swap_program @ ErrorCode::SwapNotConfigured)]
+    /// CHECK: swap_program
+    pub swap_program: UncheckedAccount<'info>,
+
+    // Arcium accounts...
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[queue_computation_accounts("top_up_deposit", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct TopUpDeposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+
+    #[account(
+        mut,
+        constraint = user_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = user_usdc_token.owner == user.key() @ ErrorCode::TokenOwnerMismatch,
+    )]
+    pub user_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+        constraint = vault_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = vault_usdc_token.owner == ghost_pool.key() @ ErrorCode::InvalidVault,
+    )]
+    pub vault_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Where the top-up is refunded if no position matches (usually `user_usdc_token`)
+    #[account(
+        mut,
+        constraint = refund_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub refund_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    /// Optional failed-deposit record (see FailedDeposit): omit it and an aborted computation keeps the tokens
+    #[account(
+        init,
+        payer = user,
+        space = FailedDeposit::SPACE,
+        seeds = [b"failed_deposit", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub failed_deposit: Option<Box<Account<'info, FailedDeposit>>>,
+
+    // Arcium accounts...
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_TOP_UP_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("top_up_deposit")]
+#[derive(Accounts)]
+pub struct TopUpDepositCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_TOP_UP_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(mut)]
+    pub refund_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// The deposit's FailedDeposit: closed once its tokens are credited or refunded, marked failed on abort
+    #[account(mut)]
+    pub failed_deposit: Option<Box<Account<'info, FailedDeposit>>>,
+    /// CHECK: rent refund for failed_deposit, checked against its user
+    #[account(mut)]
+    pub depositor: Option<UncheckedAccount<'info>>,
+}
+
+// Similar structs for CheckAndInvest, Withdraw, etc.
+// (Abbreviated for brevity - you can generate these following the same pattern)
+
+#[queue_computation_accounts("check_investment_needed", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CheckAndInvest<'info> {
+    /// Pool authority or a cranker allowed by cranker_registry (see authorize_crank)
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = ghost_pool.status.accepts_investment() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_INVESTMENT) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump = cranker_registry.bump,
+    )]
+    pub cranker_registry: Option<Box<Account<'info, CrankerRegistry>>>,
+    /// Signer's registration, credited the crank bounty
+    #[account(
+        mut,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), authority.key().as_ref()],
+        bump = cranker.bump,
+    )]
+    pub cranker: Option<Box<Account<'info, Cranker>>>,
+    /// Pool vault, read to skip checks that can't reach the threshold
+    #[account(
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    // ... (same Arcium accounts as above)
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_INVESTMENT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("check_investment_needed")]
+#[derive(Accounts)]
+pub struct CheckInvestmentNeededCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_INVESTMENT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("authorize_withdrawal", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_retired @ ErrorCode::LegacyPoolRetired,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Destination for the claimed payout. Its owner isn't tied to the signer: the MPC checks it
+    /// against the deposit's encrypted allowlist, and withdraw_via_rotator points it at the rotator
+    #[account(constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Final recipient of a rotator-routed withdrawal (withdraw_via_rotator only)
+    pub recipient_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    /// Receipt filled in once the payout is claimed
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 1 + 2,
+        seeds = [b"withdrawal_receipt", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Escrow written by the authorize callback, consumed by claim_withdrawal
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 8 + 1 + 8 + 1 + 32 + 1 + 16,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    // ... Arcium accounts
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[queue_computation_accounts("authorize_withdrawal", relayer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct WithdrawRelayed<'info> {
+    /// Pays the transaction, the rent and the Arcium fee
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    /// Owner of the position, signing without paying
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_retired @ ErrorCode::LegacyPoolRetired,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// User's token account (destination for the claimed payout)
+    #[account(constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Receipt filled in once the payout is claimed
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 1 + 2,
+        seeds = [b"withdrawal_receipt", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Escrow written by the authorize callback, consumed by claim_withdrawal
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + 1 + 32 + 32 + 32 + 8 + 1 + 8 + 1 + 32 + 1 + 16,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    // ... Arcium accounts
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = relayer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("authorize_withdrawal")]
+#[derive(Accounts)]
+pub struct AuthorizeWithdrawalCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = pending_withdrawal.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("authorize_withdrawal_all", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct WithdrawAll<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_retired @ ErrorCode::LegacyPoolRetired,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// User's token account (destination for the claimed payout)
+    #[account(constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Receipt filled in once the payout is claimed
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 1 + 2,
+        seeds = [b"withdrawal_receipt", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Escrow written by the authorize callback, consumed by claim_withdrawal
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 8 + 1 + 8 + 1 + 32 + 1 + 16,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    // ... Arcium accounts
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL_ALL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("authorize_withdrawal_all")]
+#[derive(Accounts)]
+pub struct AuthorizeWithdrawalAllCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL_ALL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = pending_withdrawal.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("recover_deposit", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RecoverDeposit<'info> {
+    /// The beneficiary
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_retired @ ErrorCode::LegacyPoolRetired,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = ghost_pool.recovery_inactivity_slots > 0 @ ErrorCode::RecoveryDisabled,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Beneficiary's token account (destination for the claimed payout)
+    #[account(constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Receipt filled in once the payout is claimed
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 1 + 2,
+        seeds = [b"withdrawal_receipt", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Escrow written by the recover callback, consumed by claim_withdrawal
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 8 + 1 + 8 + 1 + 32 + 1 + 16,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    // ... Arcium accounts
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECOVER_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("recover_deposit")]
+#[derive(Accounts)]
+pub struct RecoverDepositCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECOVER_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = pending_withdrawal.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("init_shard_state", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, shard_index: u16)]
+pub struct InitDepositShard<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init,
+        payer = authority,
+        space = DepositShard::SPACE,
+        seeds = [b"shard", ghost_pool.key().as_ref(), &shard_index.to_le_bytes()],
+        bump,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("init_shard_state")]
+#[derive(Accounts)]
+pub struct InitShardStateCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", deposit_shard.pool.as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("process_deposit_shard", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct DepositToShard<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_deposits() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_DEPOSITS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Shard currently accepting deposits
+    #[account(
+        mut,
+        seeds = [b"shard", ghost_pool.key().as_ref(), &ghost_pool.next_shard.to_le_bytes()],
+        bump = deposit_shard.bump,
+        constraint = deposit_shard.slots_used < SHARD_SIZE @ ErrorCode::ShardFull,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        mut,
+        constraint = user_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = user_usdc_token.owner == user.key() @ ErrorCode::TokenOwnerMismatch,
+    )]
+    pub user_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+        constraint = vault_usdc_token.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+        constraint = vault_usdc_token.owner == ghost_pool.key() @ ErrorCode::InvalidVault,
+    )]
+    pub vault_usdc_token: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Optional failed-deposit record (see FailedDeposit): omit it and an aborted computation keeps the tokens
+    #[account(
+        init,
+        payer = user,
+        space = FailedDeposit::SPACE,
+        seeds = [b"failed_deposit", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub failed_deposit: Option<Box<Account<'info, FailedDeposit>>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_deposit_shard")]
+#[derive(Accounts)]
+pub struct ProcessDepositShardCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = deposit_shard.pool == ghost_pool.key() @ ErrorCode::InvalidShardIndex,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// The deposit's FailedDeposit: closed once its tokens are credited or refunded, marked failed on abort
+    #[account(mut)]
+    pub failed_deposit: Option<Box<Account<'info, FailedDeposit>>>,
+    /// CHECK: rent refund for failed_deposit, checked against its user
+    #[account(mut)]
+    pub depositor: Option<UncheckedAccount<'info>>,
+}
+
+#[queue_computation_accounts("authorize_withdrawal_shard", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct WithdrawFromShard<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        seeds = [b"shard", ghost_pool.key().as_ref(), &deposit_shard.shard_index.to_le_bytes()],
+        bump = deposit_shard.bump,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    /// Vault token account (source for withdrawal)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// User's token account (destination for withdrawal)
+    #[account(
+        mut,
+        constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Receipt filled in by the callback once the payout is made
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 1 + 2,
+        seeds = [b"withdrawal_receipt", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("authorize_withdrawal_shard")]
+#[derive(Accounts)]
+pub struct AuthorizeWithdrawalShardCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = deposit_shard.pool == ghost_pool.key() @ ErrorCode::InvalidShardIndex,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(mut)]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(mut)]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        mut,
+        constraint = withdrawal_receipt.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.destination == user_token_account.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.shard_index == deposit_shard.shard_index @ ErrorCode::InvalidShardIndex,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("process_withdrawal_shard", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct FinalizeShardWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        constraint = withdrawal_receipt.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.paid @ ErrorCode::WithdrawalNotPaid,
+        constraint = withdrawal_receipt.from_shard @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.finalized @ ErrorCode::WithdrawalAlreadyFinalized,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// The shard the receipt was paid from
+    #[account(
+        seeds = [b"shard", ghost_pool.key().as_ref(), &withdrawal_receipt.shard_index.to_le_bytes()],
+        bump = deposit_shard.bump,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_withdrawal_shard")]
+#[derive(Accounts)]
+pub struct ProcessWithdrawalShardCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL_SHARD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = deposit_shard.pool == ghost_pool.key() @ ErrorCode::InvalidShardIndex,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        mut,
+        constraint = withdrawal_receipt.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.shard_index == deposit_shard.shard_index @ ErrorCode::InvalidShardIndex,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[derive(Accounts)]
+pub struct SetCounterPrivacy<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+#[queue_computation_accounts("reveal_deposit_count", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CloseCounterEpoch<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_DEPOSIT_COUNT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("reveal_deposit_count")]
+#[derive(Accounts)]
+pub struct RevealDepositCountCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_DEPOSIT_COUNT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("publish_tvl", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct PublishTvl<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PUBLISH_TVL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("publish_tvl")]
+#[derive(Accounts)]
+pub struct PublishTvlCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PUBLISH_TVL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("compute_pool_stats", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct PublishStats<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_POOL_STATS))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("compute_pool_stats")]
+#[derive(Accounts)]
+pub struct ComputePoolStatsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_POOL_STATS))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+/// Accounts for creating the computation log
+#[derive(Accounts)]
+pub struct InitComputationLog<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 2 + 8 + COMPUTATION_LOG_LEN * (1 + 8 + 32 + 16 + 8 + 8 + 8 + 1),
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("transfer_position", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_TRANSFER_POSITION))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("transfer_position")]
+#[derive(Accounts)]
+pub struct TransferPositionCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_TRANSFER_POSITION))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("set_strategy_weights", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SetStrategyWeights<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_STRATEGY_WEIGHTS))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("set_strategy_weights")]
+#[derive(Accounts)]
+pub struct SetStrategyWeightsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SET_STRATEGY_WEIGHTS))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("rotate_password", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RotatePassword<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ROTATE_PASSWORD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("rotate_password")]
+#[derive(Accounts)]
+pub struct RotatePasswordCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ROTATE_PASSWORD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("get_balance", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct QueryBalance<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 32 + 16 + 8,
+        seeds = [b"balance_query", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub balance_query: Box<Account<'info, BalanceQueryResult>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_GET_BALANCE))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("get_balance")]
+#[derive(Accounts)]
+pub struct GetBalanceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_GET_BALANCE))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"balance_query", ghost_pool.key().as_ref(), balance_query.user.as_ref()],
+        bump = balance_query.bump,
+    )]
+    pub balance_query: Box<Account<'info, BalanceQueryResult>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+/// Accounts for creating the withdrawal queue
+#[derive(Accounts)]
+pub struct InitWithdrawalQueue<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + 8 + WITHDRAWAL_QUEUE_LEN * (32 + 8),
+        seeds = [b"withdrawal_queue", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: may predate the current GhostPool layout; discriminator and authority are
+    /// checked by hand in migrate_pool
+    #[account(mut, owner = crate::ID)]
+    pub ghost_pool: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("upgrade_pool_state", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct UpgradePoolState<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.state_upgrade_pending @ ErrorCode::NoStateUpgradePending,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_UPGRADE_POOL_STATE))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("upgrade_pool_state")]
+#[derive(Accounts)]
+pub struct UpgradePoolStateCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_UPGRADE_POOL_STATE))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("upgrade_shard_state", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, shard_index: u16)]
+pub struct UpgradeShardState<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// CHECK: may predate the current DepositShard layout; resized and deserialized by hand
+    /// in upgrade_shard_state
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"shard", ghost_pool.key().as_ref(), &shard_index.to_le_bytes()],
+        bump,
+    )]
+    pub deposit_shard: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_UPGRADE_SHARD_STATE))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("upgrade_shard_state")]
+#[derive(Accounts)]
+pub struct UpgradeShardStateCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_UPGRADE_SHARD_STATE))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = deposit_shard.pool == ghost_pool.key() @ ErrorCode::InvalidShardIndex,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[derive(Accounts)]
+pub struct InitDepositIntentQueue<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + 8 + 1 + DEPOSIT_INTENT_QUEUE_LEN * (32 + 32 + 8 + 8 + 32 + 16 + 32 * 4 + 16 + 32),
+        seeds = [b"deposit_intents", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub deposit_intents: Box<Account<'info, DepositIntentQueue>>,
+    pub system_program: Program<'info, System>,
+}
+
+// Init comp def structs
+#[init_computation_definition_accounts("init_pool_state", payer)]
+#[derive(Accounts)]
+pub struct InitPoolCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_deposit", payer)]
+#[derive(Accounts)]
+pub struct InitDepositCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("check_investment_needed", payer)]
+#[derive(Accounts)]
+pub struct InitCheckInvestmentNeededCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("record_investment", payer)]
+#[derive(Accounts)]
+pub struct InitRecordInvestmentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("record_yield", payer)]
+#[derive(Accounts)]
+pub struct InitRecordYieldCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("authorize_withdrawal", payer)]
+#[derive(Accounts)]
+pub struct InitAuthorizeWithdrawalCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("authorize_withdrawal_all", payer)]
+#[derive(Accounts)]
+pub struct InitAuthorizeWithdrawalAllCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("set_strategy_weights", payer)]
+#[derive(Accounts)]
+pub struct InitSetStrategyWeightsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_deposit_batch", payer)]
+#[derive(Accounts)]
+pub struct InitProcessDepositBatchCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("claim_referral_rewards", payer)]
+#[derive(Accounts)]
+pub struct InitClaimReferralRewardsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("upgrade_pool_state", payer)]
+#[derive(Accounts)]
+pub struct InitUpgradePoolStateCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("upgrade_shard_state", payer)]
+#[derive(Accounts)]
+pub struct InitUpgradeShardStateCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("recover_deposit", payer)]
+#[derive(Accounts)]
+pub struct InitRecoverDepositCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("rotate_password", payer)]
+#[derive(Accounts)]
+pub struct InitRotatePasswordCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("get_balance", payer)]
+#[derive(Accounts)]
+pub struct InitGetBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_withdrawal", payer)]
+#[derive(Accounts)]
+pub struct InitProcessWithdrawalCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("init_shard_state", payer)]
+#[derive(Accounts)]
+pub struct InitShardCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_deposit_shard", payer)]
+#[derive(Accounts)]
+pub struct InitDepositShardCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("authorize_withdrawal_shard", payer)]
+#[derive(Accounts)]
+pub struct InitAuthorizeWithdrawalShardCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_withdrawal_shard", payer)]
+#[derive(Accounts)]
+pub struct InitProcessWithdrawalShardCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("migrate_legacy_slot", payer)]
+#[derive(Accounts)]
+pub struct InitMigrateLegacySlotCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("record_yield_with_fees", payer)]
+#[derive(Accounts)]
+pub struct InitRecordYieldWithFeesCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("top_up_deposit", payer)]
+#[derive(Accounts)]
+pub struct InitTopUpDepositCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("transfer_position", payer)]
+#[derive(Accounts)]
+pub struct InitTransferPositionCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("reveal_deposit_count", payer)]
+#[derive(Accounts)]
+pub struct InitRevealDepositCountCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("publish_tvl", payer)]
+#[derive(Accounts)]
+pub struct InitPublishTvlCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("compute_pool_stats", payer)]
+#[derive(Accounts)]
+pub struct InitComputePoolStatsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: created at its PDA by Arcium's init_computation_definition CPI
+    pub comp_def_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for init_comp_defs; the selected comp def PDAs follow as remaining accounts
+#[derive(Accounts)]
+pub struct InitCompDefs<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(seeds = [b"circuit_registry"], bump = circuit_registry.bump)]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for init_circuit_registry
+#[derive(Accounts)]
+pub struct InitCircuitRegistry<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = CircuitRegistry::SPACE,
+        seeds = [b"circuit_registry"],
+        bump,
+    )]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::GhostPool>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for propose_circuit_upgrade
+#[derive(Accounts)]
+pub struct ProposeCircuitUpgrade<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"circuit_registry"],
+        bump = circuit_registry.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = PendingAdminAction::SPACE,
+        seeds = [b"admin_action", circuit_registry.key().as_ref(), &[AdminActionKind::CircuitPin as u8]],
+        bump,
+    )]
+    pub pending_action: Box<Account<'info, PendingAdminAction>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for upgrade_circuit
+#[derive(Accounts)]
+pub struct UpgradeCircuit<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"circuit_registry"],
+        bump = circuit_registry.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub circuit_registry: Box<Account<'info, CircuitRegistry>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"admin_action", circuit_registry.key().as_ref(), &[AdminActionKind::CircuitPin as u8]],
+        bump = pending_action.bump,
+    )]
+    pub pending_action: Box<Account<'info, PendingAdminAction>>,
+}
+
+/// Accounts for cancel_action
+#[derive(Accounts)]
+pub struct CancelAdminAction<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = authority,
+        constraint = pending_action.proposer == authority.key() @ ErrorCode::Unauthorized,
+    )]
+    pub pending_action: Box<Account<'info, PendingAdminAction>>,
+}
+
+
+#[queue_computation_accounts("process_withdrawal", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ProcessWithdrawForQueue<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        constraint = withdrawal_receipt.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.paid @ ErrorCode::WithdrawalNotPaid,
+        constraint = !withdrawal_receipt.from_shard @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.finalized @ ErrorCode::WithdrawalAlreadyFinalized,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[queue_computation_accounts("process_withdrawal", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ClaimWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = pending_withdrawal.status == PositionStatus::Authorized @ ErrorCode::WithdrawalUnauthorized,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.paid @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Vault token account (source for withdrawal)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Token account named at withdraw time (user account or rotator)
+    /// CHECK: pinned to the receipt destination; may be closed, so it is deserialized by is_payable
+    #[account(
+        mut,
+        address = withdrawal_receipt.destination @ ErrorCode::InvalidReceipt,
+    )]
+    pub destination_token_account: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Share pools: the pool's share mint and the user's share account, burned by the payout
+    #[account(mut, address = ghost_pool.share_mint @ ErrorCode::InvalidMint)]
+    pub share_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
+    #[account(mut)]
+    pub share_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(
+        mut,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[queue_computation_accounts("process_withdrawal", relayer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ClaimWithdrawalRelayed<'info> {
+    /// Pays the transaction and the Arcium fee, for the rebate
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    /// Owner of the position, signing without paying; keeps the PendingWithdrawal's rent
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = pending_withdrawal.status == PositionStatus::Authorized @ ErrorCode::WithdrawalUnauthorized,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.paid @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Vault token account (source for withdrawal)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Token account named at withdraw time (user account or rotator)
+    /// CHECK: pinned to the receipt destination; may be closed, so it is deserialized by is_payable
+    #[account(
+        mut,
+        address = withdrawal_receipt.destination @ ErrorCode::InvalidReceipt,
+    )]
+    pub destination_token_account: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Where the relayer's rebate is paid
+    #[account(
+        mut,
+        constraint = relayer_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Share pools: the pool's share mint and the user's share account, burned by the payout
+    #[account(mut, address = ghost_pool.share_mint @ ErrorCode::InvalidMint)]
+    pub share_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
+    #[account(mut)]
+    pub share_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    #[account(
+        mut,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[queue_computation_accounts("process_withdrawal", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RequestWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"withdrawal_queue", ghost_pool.key().as_ref()],
+        bump = withdrawal_queue.bump,
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+    #[account(
+        mut,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = pending_withdrawal.status == PositionStatus::Authorized @ ErrorCode::WithdrawalUnauthorized,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.paid @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    /// Vault token account, checked to be short of the withdrawal
+    #[account(
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Share pools: the pool's share mint and the user's share account, burned at queue time
+    #[account(mut, address = ghost_pool.share_mint @ ErrorCode::InvalidMint)]
+    pub share_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
+    #[account(mut)]
+    pub share_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(
+        mut,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[derive(Accounts)]
+pub struct FulfillWithdrawal<'info> {
+    /// Keeper or authority; pays nothing but the transaction fee
+    pub fulfiller: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"withdrawal_queue", ghost_pool.key().as_ref()],
+        bump = withdrawal_queue.bump,
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+    #[account(
+        mut,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), pending_withdrawal.user.as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = pending_withdrawal.status == PositionStatus::Queued @ ErrorCode::WithdrawalNotQueued,
+        constraint = withdrawal_queue.front().map(|e| e.pending_withdrawal) == Some(pending_withdrawal.key())
+            @ ErrorCode::NotQueueHead,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.paid @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Payout destination recorded on the PendingWithdrawal
+    /// CHECK: pinned to the recorded destination; may be closed, so it is deserialized by is_payable
+    #[account(
+        mut,
+        address = pending_withdrawal.destination @ ErrorCode::InvalidReceipt,
+    )]
+    pub destination_token_account: UncheckedAccount<'info>,
+    /// Withdrawing user, refunded the PendingWithdrawal rent
+    /// CHECK: pinned to the position's user
+    #[account(
+        mut,
+        address = pending_withdrawal.user @ ErrorCode::Unauthorized,
+    )]
+    pub user: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump = cranker_registry.bump,
+    )]
+    pub cranker_registry: Option<Box<Account<'info, CrankerRegistry>>>,
+    /// Fulfiller's registration, credited the crank bounty
+    #[account(
+        mut,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), fulfiller.key().as_ref()],
+        bump = cranker.bump,
+    )]
+    pub cranker: Option<Box<Account<'info, Cranker>>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPendingWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        close = user,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+}
+
+#[derive(Accounts)]
+pub struct RetryPayout<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = pending_withdrawal.status == PositionStatus::PayoutPending @ ErrorCode::PayoutNotPending,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    #[account(
+        mut,
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+        constraint = !withdrawal_receipt.paid @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Payout destination recorded on the PendingWithdrawal
+    /// CHECK: pinned to the recorded destination; may be closed, so it is deserialized by is_payable
+    #[account(
+        mut,
+        address = pending_withdrawal.destination @ ErrorCode::InvalidReceipt,
+    )]
+    pub destination_token_account: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Share pools: the pool's share mint and the user's share account, burned by the payout
+    #[account(mut, address = ghost_pool.share_mint @ ErrorCode::InvalidMint)]
+    pub share_mint: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
+    #[account(mut)]
+    pub share_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+}
+
+#[queue_computation_accounts("process_deposit", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RecreditPayout<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = pending_withdrawal.status == PositionStatus::PayoutPending @ ErrorCode::PayoutNotPending,
+        constraint = pending_withdrawal.payout_attempts >= MAX_PAYOUT_ATTEMPTS @ ErrorCode::PayoutRetriesRemaining,
+        close = user,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
+    /// Re-crediting before the claim's debit lands would credit the amount twice
+    #[account(
+        address = pending_withdrawal.receipt @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.finalized @ ErrorCode::WithdrawalNotFinalized,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_withdrawal")]
+#[derive(Accounts)]
+pub struct ProcessWithdrawalCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = withdrawal_receipt.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+#[queue_computation_accounts("record_investment", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RecordInvestmentMpc<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.accepts_investment() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_INVESTMENT) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Provisional state to roll back if the callback never lands (expire_computation)
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + 32 + 1 + 32 + 8 + 1 + 8 + 8 + 8,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECORD_INVESTMENT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("record_investment")]
+#[derive(Accounts)]
+pub struct RecordInvestmentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECORD_INVESTMENT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Closed by the callback, so a computation already expired can't land
+    #[account(
+        mut,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &pending_computation.computation_offset.to_le_bytes()],
+        bump = pending_computation.bump,
+        constraint = pending_computation.computation_account == computation_account.key()
+            @ ErrorCode::InvalidPendingComputation,
+        close = requester,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    /// CHECK: rent refund, pinned to the requester recorded at queue time
+    #[account(mut, address = pending_computation.requester)]
+    pub requester: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("record_yield", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct HarvestYield<'info> {
+    /// Pool authority or a cranker allowed by cranker_registry (see authorize_crank)
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Provisional state to roll back if the callback never lands (expire_computation)
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + 32 + 1 + 32 + 8 + 1 + 8 + 8 + 8,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump = cranker_registry.bump,
+    )]
+    pub cranker_registry: Option<Box<Account<'info, CrankerRegistry>>>,
+    /// Signer's registration, credited the crank bounty
+    #[account(
+        mut,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), authority.key().as_ref()],
+        bump = cranker.bump,
+    )]
+    pub cranker: Option<Box<Account<'info, Cranker>>>,
+    /// Mock Kamino Reserve account (exchange rate source)
+    /// CHECK: owner checked, exchange_rate read at a fixed offset
+    #[account(owner = ghost_pool.lending_program)]
+    pub kamino_reserve: UncheckedAccount<'info>,
+    /// Pool's cToken account
+    #[account(address = ghost_pool.collateral_token_account)]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECORD_YIELD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[queue_computation_accounts("record_yield_with_fees", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct HarvestYieldWithFees<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Provisional state to roll back if the callback never lands (expire_computation)
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + 32 + 1 + 32 + 8 + 1 + 8 + 8 + 8,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    /// Mock Kamino Reserve account (exchange rate source)
+    /// CHECK: owner checked, exchange_rate read at a fixed offset
+    #[account(owner = ghost_pool.lending_program)]
+    pub kamino_reserve: UncheckedAccount<'info>,
+    /// Pool's cToken account
+    #[account(address = ghost_pool.collateral_token_account)]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECORD_YIELD_WITH_FEES))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("record_yield_with_fees")]
+#[derive(Accounts)]
+pub struct RecordYieldWithFeesCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECORD_YIELD_WITH_FEES))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Closed by the callback, so a computation already expired can't land
+    #[account(
+        mut,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &pending_computation.computation_offset.to_le_bytes()],
+        bump = pending_computation.bump,
+        constraint = pending_computation.computation_account == computation_account.key()
+            @ ErrorCode::InvalidPendingComputation,
+        close = requester,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    /// CHECK: rent refund, pinned to the requester recorded at queue time
+    #[account(mut, address = pending_computation.requester)]
+    pub requester: UncheckedAccount<'info>,
+}
+
+#[callback_accounts("record_yield")]
+#[derive(Accounts)]
+pub struct RecordYieldCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_RECORD_YIELD))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Closed by the callback, so a computation already expired can't land
+    #[account(
+        mut,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &pending_computation.computation_offset.to_le_bytes()],
+        bump = pending_computation.bump,
+        constraint = pending_computation.computation_account == computation_account.key()
+            @ ErrorCode::InvalidPendingComputation,
+        close = requester,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    /// CHECK: rent refund, pinned to the requester recorded at queue time
+    #[account(mut, address = pending_computation.requester)]
+    pub requester: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ExpireComputation<'info> {
+    pub caller: Signer<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump = pending_computation.bump,
+        close = requester,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    /// CHECK: rent refund, pinned to the requester recorded at queue time
+    #[account(mut, address = pending_computation.requester)]
+    pub requester: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ReclaimFailedDeposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"failed_deposit", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump = failed_deposit.bump,
+        has_one = user @ ErrorCode::Unauthorized,
+        constraint = !failed_deposit.escrowed @ ErrorCode::DepositEscrowed,
+        close = user,
+    )]
+    pub failed_deposit: Box<Account<'info, FailedDeposit>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ReclaimEscrow<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"failed_deposit", ghost_pool.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump = failed_deposit.bump,
+        has_one = user @ ErrorCode::Unauthorized,
+        constraint = failed_deposit.escrowed @ ErrorCode::DepositNotEscrowed,
+        close = user,
+    )]
+    pub failed_deposit: Box<Account<'info, FailedDeposit>>,
+    #[account(
+        mut,
+        seeds = [b"escrow", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub deposit_escrow: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub user_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeLegacyPool<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = !ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolFrozen,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+#[queue_computation_accounts("migrate_legacy_slot", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct MigrateLegacyDeposit<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.legacy_frozen @ ErrorCode::LegacyPoolNotFrozen,
+        constraint = !ghost_pool.legacy_retired @ ErrorCode::LegacyPoolRetired,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    /// Shard currently accepting deposits
+    #[account(
+        mut,
+        seeds = [b"shard", ghost_pool.key().as_ref(), &ghost_pool.next_shard.to_le_bytes()],
+        bump = deposit_shard.bump,
+        constraint = deposit_shard.slots_used < SHARD_SIZE @ ErrorCode::ShardFull,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: comp
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_MIGRATE_LEGACY_SLOT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("migrate_legacy_slot")]
+#[derive(Accounts)]
+pub struct MigrateLegacySlotCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_MIGRATE_LEGACY_SLOT))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        constraint = deposit_shard.pool == ghost_pool.key() @ ErrorCode::InvalidShardIndex,
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+}
+
+/// Accounts for investing in Kamino after MPC approval
+#[derive(Accounts)]
+pub struct InvestInKamino<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.pending_venue_investments[VENUE_KAMINO] > 0 @ ErrorCode::NoPendingInvestment,
+        constraint = ghost_pool.status.accepts_investment() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_INVESTMENT) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    /// Pool's USDC vault (source of liquidity)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Kamino Lending Market
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub kamino_lending_market: UncheckedAccount<'info>,
+
+    /// Kamino Lending Market Authority PDA
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub kamino_lending_market_authority: UncheckedAccount<'info>,
+
+    /// Kamino Reserve account
+    /// CHECK: owner checked, layout read in ReserveCpi::validate
+    #[account(mut, owner = ghost_pool.lending_program @ ErrorCode::InvalidReserve)]
+    pub kamino_reserve: UncheckedAccount<'info>,
+
+    /// Reserve liquidity mint (USDC)
+    #[account(address = ghost_pool.deposit_mint)]
+    pub reserve_liquidity_mint: Box<Account<'info, Mint>>,
+
+    /// Reserve collateral mint (cToken)
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub reserve_collateral_mint: UncheckedAccount<'info>,
+
+    /// Reserve liquidity supply vault
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub reserve_liquidity_supply: UncheckedAccount<'info>,
+
+    /// Destination for collateral tokens (cTokens)
+    #[account(
+        mut,
+        token::mint = reserve_collateral_mint,
+        token::authority = vault,
+    )]
+    pub user_destination_collateral: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instructions sysvar, required by KLend
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: Kamino Lending program
+    #[account(address = ghost_pool.lending_program @ ErrorCode::InvalidLendingProgram)]
+    pub kamino_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InvestInKamino<'info> {
+    fn reserve_cpi_accounts(&self) -> ReserveCpi<'info> {
+        ReserveCpi {
+            owner: self.vault.to_account_info(),
+            lending_market: self.kamino_lending_market.to_account_info(),
+            lending_market_authority: self.kamino_lending_market_authority.to_account_info(),
+            reserve: self.kamino_reserve.to_account_info(),
+            liquidity_mint: self.reserve_liquidity_mint.to_account_info(),
+            collateral_mint: self.reserve_collateral_mint.to_account_info(),
+            liquidity_supply: self.reserve_liquidity_supply.to_account_info(),
+            user_liquidity: self.vault.to_account_info(),
+            user_collateral: self.user_destination_collateral.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            instructions_sysvar: self.instructions_sysvar.to_account_info(),
+            kamino_program: self.kamino_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts for reinvesting realized yield into the pool's Kamino position
+#[derive(Accounts)]
+pub struct CompoundYield<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        has_one = collateral_token_account @ ErrorCode::InvalidCollateralAccount,
+        constraint = ghost_pool.auto_compound @ ErrorCode::AutoCompoundDisabled,
+        constraint = ghost_pool.status.accepts_investment() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_INVESTMENT) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    /// Pool's USDC vault (source of the yield)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Kamino Lending Market
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub kamino_lending_market: UncheckedAccount<'info>,
+
+    /// Kamino Lending Market Authority PDA
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub kamino_lending_market_authority: UncheckedAccount<'info>,
+
+    /// Kamino Reserve account
+    /// CHECK: owner checked, layout read in ReserveCpi::validate
+    #[account(mut, owner = ghost_pool.lending_program @ ErrorCode::InvalidReserve)]
+    pub kamino_reserve: UncheckedAccount<'info>,
+
+    /// Reserve liquidity mint (USDC)
+    #[account(address = ghost_pool.deposit_mint)]
+    pub reserve_liquidity_mint: Box<Account<'info, Mint>>,
+
+    /// Reserve collateral mint (cToken)
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub reserve_collateral_mint: UncheckedAccount<'info>,
+
+    /// Reserve liquidity supply vault
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub reserve_liquidity_supply: UncheckedAccount<'info>,
+
+    /// Pool's existing cToken account
+    #[account(
+        mut,
+        token::mint = reserve_collateral_mint,
+        token::authority = vault,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instructions sysvar, required by KLend
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: Kamino Lending program
+    #[account(address = ghost_pool.lending_program @ ErrorCode::InvalidLendingProgram)]
+    pub kamino_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CompoundYield<'info> {
+    fn reserve_cpi_accounts(&self) -> ReserveCpi<'info> {
+        ReserveCpi {
+            owner: self.vault.to_account_info(),
+            lending_market: self.kamino_lending_market.to_account_info(),
+            lending_market_authority: self.kamino_lending_market_authority.to_account_info(),
+            reserve: self.kamino_reserve.to_account_info(),
+            liquidity_mint: self.reserve_liquidity_mint.to_account_info(),
+            collateral_mint: self.reserve_collateral_mint.to_account_info(),
+            liquidity_supply: self.reserve_liquidity_supply.to_account_info(),
+            user_liquidity: self.vault.to_account_info(),
+            user_collateral: self.collateral_token_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            instructions_sysvar: self.instructions_sysvar.to_account_info(),
+            kamino_program: self.kamino_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts for redeeming cTokens from Kamino back into the vault
+#[derive(Accounts)]
+pub struct RedeemFromKamino<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    /// Pool's USDC vault (destination of liquidity, owner of the cTokens)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Kamino Lending Market
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub kamino_lending_market: UncheckedAccount<'info>,
+
+    /// Kamino Lending Market Authority PDA
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub kamino_lending_market_authority: UncheckedAccount<'info>,
+
+    /// Kamino Reserve account
+    /// CHECK: owner checked, layout read in ReserveCpi::validate
+    #[account(mut, owner = ghost_pool.lending_program @ ErrorCode::InvalidReserve)]
+    pub kamino_reserve: UncheckedAccount<'info>,
+
+    /// Reserve liquidity mint (USDC)
+    #[account(address = ghost_pool.deposit_mint)]
+    pub reserve_liquidity_mint: Box<Account<'info, Mint>>,
+
+    /// Reserve collateral mint (cToken)
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub reserve_collateral_mint: UncheckedAccount<'info>,
+
+    /// Reserve liquidity supply vault
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub reserve_liquidity_supply: UncheckedAccount<'info>,
+
+    /// Pool's cToken account (source of collateral)
+    #[account(
+        mut,
+        address = ghost_pool.collateral_token_account,
+        token::authority = vault,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instructions sysvar, required by KLend
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: Kamino Lending program
+    #[account(address = ghost_pool.lending_program @ ErrorCode::InvalidLendingProgram)]
+    pub kamino_program: UncheckedAccount<'info>,
+}
+
+impl<'info> RedeemFromKamino<'info> {
+    fn reserve_cpi_accounts(&self) -> ReserveCpi<'info> {
+        ReserveCpi {
+            owner: self.vault.to_account_info(),
+            lending_market: self.kamino_lending_market.to_account_info(),
+            lending_market_authority: self.kamino_lending_market_authority.to_account_info(),
+            reserve: self.kamino_reserve.to_account_info(),
+            liquidity_mint: self.reserve_liquidity_mint.to_account_info(),
+            collateral_mint: self.reserve_collateral_mint.to_account_info(),
+            liquidity_supply: self.reserve_liquidity_supply.to_account_info(),
+            user_liquidity: self.vault.to_account_info(),
+            user_collateral: self.collateral_token_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            instructions_sysvar: self.instructions_sysvar.to_account_info(),
+            kamino_program: self.kamino_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts for registering the pool's yield venue
+#[derive(Accounts)]
+pub struct SetYieldAdapter<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 1 + 32 + 1 + 32 + 32,
+        seeds = [b"yield_adapter", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub yield_adapter: Box<Account<'info, YieldAdapter>>,
+
+    /// Venue reserve
+    /// CHECK: owner checked against the venue's program in set_yield_adapter
+    pub reserve: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for investing through the registered yield adapter
+#[derive(Accounts)]
+pub struct InvestViaAdapter<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.pending_venue_investments[VENUE_ADAPTER] > 0 @ ErrorCode::NoPendingInvestment,
+        constraint = ghost_pool.status.accepts_investment() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_INVESTMENT) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        seeds = [b"yield_adapter", ghost_pool.key().as_ref()],
+        bump = yield_adapter.bump,
+    )]
+    pub yield_adapter: Box<Account<'info, YieldAdapter>>,
+
+    /// Pool's USDC vault (source of liquidity)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Destination for the venue's collateral tokens
+    #[account(mut, token::authority = vault)]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Lending program pinned by the adapter
+    #[account(address = yield_adapter.program_id)]
+    pub venue_program: UncheckedAccount<'info>,
+}
+
+impl<'info> InvestViaAdapter<'info> {
+    fn adapter_cpi<'a>(&'a self, venue_accounts: &'a [AccountInfo<'info>]) -> AdapterCpi<'a, 'info> {
+        AdapterCpi {
+            adapter: &self.yield_adapter,
+            vault: self.vault.to_account_info(),
+            collateral: self.collateral_token_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            venue_program: self.venue_program.to_account_info(),
+            venue_accounts,
+        }
+    }
+}
+
+/// Accounts for redeeming through the registered yield adapter
+#[derive(Accounts)]
+pub struct DivestViaAdapter<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        seeds = [b"yield_adapter", ghost_pool.key().as_ref()],
+        bump = yield_adapter.bump,
+    )]
+    pub yield_adapter: Box<Account<'info, YieldAdapter>>,
+
+    /// Pool's USDC vault (destination of liquidity, owner of the collateral)
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Pool's collateral account (source of collateral)
+    #[account(
+        mut,
+        address = ghost_pool.collateral_token_account,
+        token::authority = vault,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Lending program pinned by the adapter
+    #[account(address = yield_adapter.program_id)]
+    pub venue_program: UncheckedAccount<'info>,
+}
+
+impl<'info> DivestViaAdapter<'info> {
+    fn adapter_cpi<'a>(&'a self, venue_accounts: &'a [AccountInfo<'info>]) -> AdapterCpi<'a, 'info> {
+        AdapterCpi {
+            adapter: &self.yield_adapter,
+            vault: self.vault.to_account_info(),
+            collateral: self.collateral_token_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            venue_program: self.venue_program.to_account_info(),
+            venue_accounts,
+        }
+    }
+}
+
+/// Accounts for changing the pool lifecycle status
+#[derive(Accounts)]
+pub struct SetPoolStatus<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for close_pool
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status == PoolStatus::Closing @ ErrorCode::InvalidPoolStatus,
+        close = authority,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Receives whatever is left in the vault
+    #[account(
+        mut,
+        constraint = authority_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub authority_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Pool's strategy collateral account, required once one has been set
+    #[account(
+        mut,
+        address = ghost_pool.collateral_token_account,
+        token::authority = vault,
+    )]
+    pub collateral_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Receives the residual collateral (cTokens)
+    #[account(mut)]
+    pub authority_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub collateral_token_program: Option<Program<'info, Token>>,
+}
+
+/// Accounts for reconcile
+#[derive(Accounts)]
+pub struct Reconcile<'info> {
+    /// Anyone; must be the pool authority to `mark`
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+}
+
+/// Accounts for sync_position
+#[derive(Accounts)]
+pub struct SyncPosition<'info> {
+    /// Anyone
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    /// Kamino reserve the cTokens were minted by (exchange rate source)
+    /// CHECK: owner checked, collateral mint checked against the cTokens, exchange_rate read at a fixed offset
+    #[account(
+        owner = ghost_pool.lending_program @ ErrorCode::InvalidReserve,
+        constraint = reserve_pubkey(&kamino_reserve, reserve_layout::COLLATERAL_MINT)?
+            == collateral_token_account.mint @ ErrorCode::InvalidReserve,
+    )]
+    pub kamino_reserve: UncheckedAccount<'info>,
+    /// Pool's cToken account
+    #[account(address = ghost_pool.collateral_token_account)]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+}
+
+/// Accounts for pause_pool / unpause_pool
+#[derive(Accounts)]
+pub struct SetPoolPause<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for update_pool_config
+#[derive(Accounts)]
+pub struct UpdatePoolConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for set_swap_config
+#[derive(Accounts)]
+pub struct SetSwapConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(constraint = swap_mint.key() != ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub swap_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// CHECK: any executable; deposit_with_swap only trusts the balances a route leaves behind
+    #[account(executable)]
+    pub swap_program: UncheckedAccount<'info>,
+
+    /// CHECK: owner checked, layout and freshness checked by every deposit_with_swap
+    #[account(owner = PYTH_RECEIVER_PROGRAM_ID)]
+    pub swap_mint_oracle: UncheckedAccount<'info>,
+
+    /// CHECK: owner checked, layout and freshness checked by every deposit_with_swap
+    #[account(owner = PYTH_RECEIVER_PROGRAM_ID)]
+    pub deposit_mint_oracle: UncheckedAccount<'info>,
+}
+
+/// Accounts for acknowledge_cluster_rotation
+#[derive(Accounts)]
+pub struct AcknowledgeClusterRotation<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+}
+
+/// Accounts for propose_fee_config
+#[derive(Accounts)]
+pub struct ProposeFeeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(constraint = fee_recipient_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub fee_recipient_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = PendingAdminAction::SPACE,
+        seeds = [b"admin_action", ghost_pool.key().as_ref(), &[AdminActionKind::FeeConfig as u8]],
+        bump,
+    )]
+    pub pending_action: Box<Account<'info, PendingAdminAction>>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[queue_computation_accounts("init_pool_state", authority)]
+/// Accounts for set_fee_config
+#[derive(Accounts)]
+pub struct SetFeeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(constraint = fee_recipient_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub fee_recipient_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"admin_action", ghost_pool.key().as_ref(), &[AdminActionKind::FeeConfig as u8]],
+        bump = pending_action.bump,
+    )]
+    pub pending_action: Box<Account<'info, PendingAdminAction>>,
+}
+
+/// Accounts for set_referral_share
+#[derive(Accounts)]
+pub struct SetReferralShare<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for collecting protocol fees
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    #[account(
+        mut,
+        address = ghost_pool.fee_recipient_token_account @ ErrorCode::InvalidFeeRecipient,
+    )]
+    pub fee_recipient_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[queue_computation_accounts("claim_referral_rewards", referrer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"ghost_pool", ghost_pool.seed_authority.as_ref(), &ghost_pool.pool_id.to_le_bytes()],
+        bump = ghost_pool.bump,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+        constraint = ghost_pool.referral_share_bps > 0 @ ErrorCode::ReferralsDisabled,
+        constraint = !ghost_pool.mxe_rotation_pending @ ErrorCode::ClusterRotationPending,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Where the rewards are paid, any account of the deposit mint
+    #[account(
+        mut,
+        constraint = referrer_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub referrer_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = referrer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: mempool
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: execpool
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
+    /// CHECK: computation
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_REFERRAL_REWARDS))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Box<Account<'info, FeePool>>,
+    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Box<Account<'info, ClockAccount>>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("claim_referral_rewards")]
+#[derive(Accounts)]
+pub struct ClaimReferralRewardsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_REFERRAL_REWARDS))]
+    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: computation_account, verified against the cluster by verify_output
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
+    pub cluster_account: Box<Account<'info, Cluster>>,
+    #[account(
+        address = ::anchor_lang::solana_program::sysvar::instructions::ID,
+        constraint = invoked_top_level(&instructions_sysvar) @ ErrorCode::WrappedCallback,
+    )]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        mut,
+        seeds = [b"computation_log", ghost_pool.key().as_ref()],
+        bump = computation_log.bump,
+    )]
+    pub computation_log: Box<Account<'info, ComputationLog>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    /// Where the rewards are paid, any account of the deposit mint
+    #[account(
+        mut,
+        constraint = referrer_token_account.mint == ghost_pool.deposit_mint @ ErrorCode::InvalidMint,
+    )]
+    pub referrer_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Accounts for set_cranker_config
+#[derive(Accounts)]
+pub struct SetCrankerConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 1 + 32 + 1 + 8 + 2,
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub cranker_registry: Box<Account<'info, CrankerRegistry>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for register_cranker
+#[derive(Accounts)]
+#[instruction(keeper: Pubkey)]
+pub struct RegisterCranker<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump = cranker_registry.bump,
+    )]
+    pub cranker_registry: Box<Account<'info, CrankerRegistry>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 32 + 8 + 8,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), keeper.as_ref()],
+        bump,
+    )]
+    pub cranker: Box<Account<'info, Cranker>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for remove_cranker
+#[derive(Accounts)]
+pub struct RemoveCranker<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"cranker_registry", ghost_pool.key().as_ref()],
+        bump = cranker_registry.bump,
+    )]
+    pub cranker_registry: Box<Account<'info, CrankerRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), cranker.keeper.as_ref()],
+        bump = cranker.bump,
+        close = authority,
+    )]
+    pub cranker: Box<Account<'info, Cranker>>,
+}
+
+/// Accounts for claim_crank_bounty
+#[derive(Accounts)]
+pub struct ClaimCrankBounty<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(mut)]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"cranker", ghost_pool.key().as_ref(), keeper.key().as_ref()],
+        bump = cranker.bump,
+    )]
+    pub cranker: Box<Account<'info, Cranker>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    #[account(
+        mut,
+        token::mint = deposit_mint,
+        token::authority = keeper,
+        token::token_program = token_program,
+    )]
+    pub keeper_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Accounts for enable_share_tokens
+#[derive(Accounts)]
+pub struct EnableShareTokens<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+    #[account(
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    /// Share mint, same decimals and token program as the deposit mint, minted by the pool
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = ghost_pool.deposit_decimals,
+        mint::authority = ghost_pool,
+        mint::token_program = token_program,
+        seeds = [b"share_mint", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub share_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    #[account(address = *deposit_mint.to_account_info().owner @ ErrorCode::InvalidMint)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for set_event_mode
+#[derive(Accounts)]
+pub struct SetEventMode<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for set_admin_multisig
+/// The current quorum, if any, follows as remaining accounts (see require_admin_quorum)
 #[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct InitializePool<'info> {
+pub struct SetAdminMultisig<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
     #[account(
-        init,
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        init_if_needed,
         payer = authority,
-        space = 8 + 1 + 32 + 32 + 1 + 8 + 8 + 16 + (32 * 13) + 8 + 8 + 8 + 8 + 32 + 8,  // v4: + Kamino fields
-        seeds = [b"ghost_pool", authority.key().as_ref()],
+        space = AdminMultisig::SPACE,
+        seeds = [b"admin_multisig", ghost_pool.key().as_ref()],
         bump,
     )]
+    pub admin_multisig: Box<Account<'info, AdminMultisig>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for propose_authority
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
     pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
 
-    pub usdc_mint: Account<'info, Mint>,
+/// Accounts for accept_authority
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
 
-    /// Vault PDA to hold USDC
     #[account(
-        init,
-        payer = authority,
-        token::mint = usdc_mint,
+        mut,
+        constraint = ghost_pool.pending_authority != Pubkey::default() @ ErrorCode::NoPendingAuthority,
+        constraint = ghost_pool.pending_authority == new_authority.key() @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for propose_lending_program and apply_lending_program
+#[derive(Accounts)]
+pub struct UpdateLendingProgram<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for configuring the cash strategy
+#[derive(Accounts)]
+pub struct SetCashStrategy<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    /// Instant-liquidity Kamino reserve
+    /// CHECK: owner checked, accounts checked against it on every CPI
+    #[account(owner = ghost_pool.lending_program)]
+    pub cash_reserve: UncheckedAccount<'info>,
+
+    /// Pool-owned cToken account, separate from the main strategy's
+    #[account(
         token::authority = ghost_pool,
+        constraint = cash_collateral_account.key() != ghost_pool.collateral_token_account
+            @ ErrorCode::InvalidCollateralAccount,
+    )]
+    pub cash_collateral_account: Box<Account<'info, TokenAccount>>,
+}
+
+/// Accounts for sweeping idle vault funds into the cash strategy
+#[derive(Accounts)]
+pub struct SweepToCash<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.accepts_investment() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_INVESTMENT) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    /// Pool's USDC vault
+    #[account(
+        mut,
         seeds = [b"vault", ghost_pool.key().as_ref()],
-        bump,
+        bump = ghost_pool.vault_bump,
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
 
+    /// Cash reserve's lending market
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub cash_lending_market: UncheckedAccount<'info>,
+
+    /// Cash reserve's lending market authority PDA
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub cash_lending_market_authority: UncheckedAccount<'info>,
+
+    /// Cash strategy reserve
+    /// CHECK: Pinned by set_cash_strategy, owner checked before the CPI
+    #[account(mut, address = ghost_pool.cash_reserve @ ErrorCode::CashStrategyNotSet)]
+    pub cash_reserve: UncheckedAccount<'info>,
+
+    /// Reserve liquidity mint (USDC)
+    #[account(address = ghost_pool.deposit_mint)]
+    pub cash_liquidity_mint: Box<Account<'info, Mint>>,
+
+    /// Cash reserve collateral mint
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub cash_collateral_mint: UncheckedAccount<'info>,
+
+    /// Cash reserve liquidity supply (checked against the reserve before the CPI)
+    #[account(mut)]
+    pub cash_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    /// Pool's cToken account for the cash reserve
+    #[account(mut, address = ghost_pool.cash_collateral_account @ ErrorCode::CashStrategyNotSet)]
+    pub cash_collateral_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instructions sysvar, required by KLend
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: Kamino Lending program
+    #[account(address = ghost_pool.lending_program @ ErrorCode::InvalidLendingProgram)]
+    pub kamino_program: UncheckedAccount<'info>,
+}
+
+impl<'info> SweepToCash<'info> {
+    fn reserve_cpi_accounts(&self) -> ReserveCpi<'info> {
+        ReserveCpi {
+            owner: self.ghost_pool.to_account_info(),
+            lending_market: self.cash_lending_market.to_account_info(),
+            lending_market_authority: self.cash_lending_market_authority.to_account_info(),
+            reserve: self.cash_reserve.to_account_info(),
+            liquidity_mint: self.cash_liquidity_mint.to_account_info(),
+            collateral_mint: self.cash_collateral_mint.to_account_info(),
+            liquidity_supply: self.cash_liquidity_supply.to_account_info(),
+            user_liquidity: self.vault.to_account_info(),
+            user_collateral: self.cash_collateral_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            instructions_sysvar: self.instructions_sysvar.to_account_info(),
+            kamino_program: self.kamino_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts for topping up the vault from the cash strategy ahead of a claim
+#[derive(Accounts)]
+pub struct RedeemCashForWithdrawal<'info> {
+    pub user: Signer<'info>,
+
     #[account(
-        init_if_needed,
-        space = 9,
-        payer = authority,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
     )]
-    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
 
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(
+        seeds = [b"pending_withdrawal", ghost_pool.key().as_ref(), user.key().as_ref()],
+        bump = pending_withdrawal.bump,
+        constraint = matches!(pending_withdrawal.status, PositionStatus::Authorized | PositionStatus::PayoutPending)
+            @ ErrorCode::WithdrawalUnauthorized,
+    )]
+    pub pending_withdrawal: Box<Account<'info, PendingWithdrawal>>,
 
-    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: mempool_account, checked by the arcium program
-    pub mempool_account: UncheckedAccount<'info>,
+    /// Pool's USDC vault
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
 
-    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: executing_pool, checked by the arcium program
-    pub executing_pool: UncheckedAccount<'info>,
+    /// Cash reserve's lending market
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub cash_lending_market: UncheckedAccount<'info>,
 
-    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: computation_account, checked by the arcium program
-    pub computation_account: UncheckedAccount<'info>,
+    /// Cash reserve's lending market authority PDA
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub cash_lending_market_authority: UncheckedAccount<'info>,
 
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_POOL))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    /// Cash strategy reserve
+    /// CHECK: Pinned by set_cash_strategy, owner checked before the CPI
+    #[account(mut, address = ghost_pool.cash_reserve @ ErrorCode::CashStrategyNotSet)]
+    pub cash_reserve: UncheckedAccount<'info>,
 
-    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
+    /// Reserve liquidity mint (USDC)
+    #[account(address = ghost_pool.deposit_mint)]
+    pub cash_liquidity_mint: Box<Account<'info, Mint>>,
 
-    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
-    pub pool_account: Box<Account<'info, FeePool>>,
+    /// Cash reserve collateral mint
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub cash_collateral_mint: UncheckedAccount<'info>,
 
-    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
-    pub clock_account: Box<Account<'info, ClockAccount>>,
+    /// Cash reserve liquidity supply (checked against the reserve before the CPI)
+    #[account(mut)]
+    pub cash_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    /// Pool's cToken account for the cash reserve
+    #[account(mut, address = ghost_pool.cash_collateral_account @ ErrorCode::CashStrategyNotSet)]
+    pub cash_collateral_account: Box<Account<'info, TokenAccount>>,
 
-    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
-    pub arcium_program: Program<'info, Arcium>,
+
+    /// CHECK: instructions sysvar, required by KLend
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: Kamino Lending program
+    #[account(address = ghost_pool.lending_program @ ErrorCode::InvalidLendingProgram)]
+    pub kamino_program: UncheckedAccount<'info>,
 }
 
-#[callback_accounts("init_pool_state")]
+impl<'info> RedeemCashForWithdrawal<'info> {
+    fn reserve_cpi_accounts(&self) -> ReserveCpi<'info> {
+        ReserveCpi {
+            owner: self.ghost_pool.to_account_info(),
+            lending_market: self.cash_lending_market.to_account_info(),
+            lending_market_authority: self.cash_lending_market_authority.to_account_info(),
+            reserve: self.cash_reserve.to_account_info(),
+            liquidity_mint: self.cash_liquidity_mint.to_account_info(),
+            collateral_mint: self.cash_collateral_mint.to_account_info(),
+            liquidity_supply: self.cash_liquidity_supply.to_account_info(),
+            user_liquidity: self.vault.to_account_info(),
+            user_collateral: self.cash_collateral_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            instructions_sysvar: self.instructions_sysvar.to_account_info(),
+            kamino_program: self.kamino_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts for redeeming the cash strategy back into the vault
 #[derive(Accounts)]
-pub struct InitPoolStateCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
+pub struct RedeemFromCash<'info> {
+    pub authority: Signer<'info>,
 
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_POOL))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = ghost_pool.status.is_initialized() @ ErrorCode::InvalidPoolStatus,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
 
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// Pool's USDC vault
+    #[account(
+        mut,
+        seeds = [b"vault", ghost_pool.key().as_ref()],
+        bump = ghost_pool.vault_bump,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Cash reserve's lending market
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub cash_lending_market: UncheckedAccount<'info>,
+
+    /// Cash reserve's lending market authority PDA
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    pub cash_lending_market_authority: UncheckedAccount<'info>,
+
+    /// Cash strategy reserve
+    /// CHECK: Pinned by set_cash_strategy, owner checked before the CPI
+    #[account(mut, address = ghost_pool.cash_reserve @ ErrorCode::CashStrategyNotSet)]
+    pub cash_reserve: UncheckedAccount<'info>,
+
+    /// Reserve liquidity mint (USDC)
+    #[account(address = ghost_pool.deposit_mint)]
+    pub cash_liquidity_mint: Box<Account<'info, Mint>>,
+
+    /// Cash reserve collateral mint
+    /// CHECK: Checked against the reserve before the CPI (ReserveCpi::validate)
+    #[account(mut)]
+    pub cash_collateral_mint: UncheckedAccount<'info>,
+
+    /// Cash reserve liquidity supply (checked against the reserve before the CPI)
+    #[account(mut)]
+    pub cash_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    /// Pool's cToken account for the cash reserve
+    #[account(mut, address = ghost_pool.cash_collateral_account @ ErrorCode::CashStrategyNotSet)]
+    pub cash_collateral_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instructions sysvar, required by KLend
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: Kamino Lending program
+    #[account(address = ghost_pool.lending_program @ ErrorCode::InvalidLendingProgram)]
+    pub kamino_program: UncheckedAccount<'info>,
+}
+
+impl<'info> RedeemFromCash<'info> {
+    fn reserve_cpi_accounts(&self) -> ReserveCpi<'info> {
+        ReserveCpi {
+            owner: self.ghost_pool.to_account_info(),
+            lending_market: self.cash_lending_market.to_account_info(),
+            lending_market_authority: self.cash_lending_market_authority.to_account_info(),
+            reserve: self.cash_reserve.to_account_info(),
+            liquidity_mint: self.cash_liquidity_mint.to_account_info(),
+            collateral_mint: self.cash_collateral_mint.to_account_info(),
+            liquidity_supply: self.cash_liquidity_supply.to_account_info(),
+            user_liquidity: self.vault.to_account_info(),
+            user_collateral: self.cash_collateral_account.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            instructions_sysvar: self.instructions_sysvar.to_account_info(),
+            kamino_program: self.kamino_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts for closing a withdrawal receipt
+#[derive(Accounts)]
+pub struct CloseWithdrawalReceipt<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Routed receipts must stay open until the rotator hop is done, and paid ones until the
+    /// ledger debit has landed: the debit callback writes the receipt
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        constraint = withdrawal_receipt.recipient == withdrawal_receipt.destination
+            || withdrawal_receipt.forwarded
+            || !withdrawal_receipt.paid @ ErrorCode::RotatorHopPending,
+        constraint = !withdrawal_receipt.paid || withdrawal_receipt.finalized @ ErrorCode::WithdrawalNotFinalized,
+        close = user,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+}
+
+/// Accounts for closing a deposit receipt
+#[derive(Accounts)]
+pub struct CloseReceipt<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        close = user,
+    )]
+    pub deposit_receipt: Box<Account<'info, DepositReceipt>>,
+
+    /// Paid withdrawal from the same pool, made after the deposit
+    #[account(
+        has_one = user @ ErrorCode::Unauthorized,
+        constraint = withdrawal_receipt.pool == deposit_receipt.pool @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.paid @ ErrorCode::DepositNotWithdrawn,
+        constraint = withdrawal_receipt.slot > deposit_receipt.deposit_slot @ ErrorCode::DepositNotWithdrawn,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+}
+
+/// Accounts for creating the withdrawal rotator
+#[derive(Accounts)]
+pub struct InitRotator<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        has_one = deposit_mint,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Rotator token account, owned by the pool PDA
+    #[account(
+        init,
+        payer = authority,
+        token::mint = deposit_mint,
+        token::authority = ghost_pool,
+        token::token_program = token_program,
+        seeds = [b"rotator", ghost_pool.key().as_ref()],
+        bump,
+    )]
+    pub rotator: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for updating the rotator cap
+#[derive(Accounts)]
+pub struct SetRotatorLimit<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+}
+
+/// Accounts for the second hop of a rotator-routed withdrawal
+#[derive(Accounts)]
+pub struct ForwardFromRotator<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = ghost_pool.status.accepts_withdrawals() @ ErrorCode::InvalidPoolStatus,
+        constraint = !ghost_pool.is_paused(PAUSE_WITHDRAWALS) @ ErrorCode::PoolPaused,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
+
+    #[account(
+        mut,
+        seeds = [b"rotator", ghost_pool.key().as_ref()],
+        bump = ghost_pool.rotator_bump,
+    )]
+    pub rotator: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = withdrawal_receipt.pool == ghost_pool.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.destination == rotator.key() @ ErrorCode::InvalidReceipt,
+        constraint = withdrawal_receipt.paid @ ErrorCode::WithdrawalNotPaid,
+        constraint = !withdrawal_receipt.forwarded @ ErrorCode::AlreadyForwarded,
+    )]
+    pub withdrawal_receipt: Box<Account<'info, WithdrawalReceipt>>,
+
+    #[account(
+        mut,
+        address = withdrawal_receipt.recipient @ ErrorCode::InvalidReceipt,
+    )]
+    pub recipient_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    #[account(address = ghost_pool.deposit_mint @ ErrorCode::InvalidMint)]
+    pub deposit_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Accounts for setting the collateral token account
+#[derive(Accounts)]
+pub struct SetCollateralAccount<'info> {
+    pub authority: Signer<'info>,
 
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub ghost_pool: Box<Account<'info, GhostPool>>,
 
-    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
+    /// Collateral token account (owned by vault PDA)
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+}
 
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
+// Events
+#[event]
+pub struct PoolInitializedEvent {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+}
 
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
+#[event]
+pub struct ShardInitializedEvent {
+    pub pool: Pubkey,
+    pub shard: Pubkey,
+    pub shard_index: u16,
 }
 
-#[queue_computation_accounts("process_deposit", user)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct Deposit<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+#[event]
+pub struct CounterEpochClosedEvent {
+    pub pool: Pubkey,
+    pub bucketed_deposits: u64,
+    pub bucketed_withdrawals: u64,
+}
 
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
+#[event]
+pub struct TvlPublishedEvent {
+    pub pool: Pubkey,
+    pub tvl: u64,
+    pub bucket: u64,
+    pub slot: u64,
+}
 
-    #[account(mut)]
-    pub user_usdc_token: Box<Account<'info, TokenAccount>>,
+/// Median and unclaimed yield cover the ledger slots held in the pool itself, not its shards
+#[event]
+pub struct PoolStatsEvent {
+    pub pool: Pubkey,
+    pub active_depositors: u16,
+    pub median_principal: u64,
+    pub unclaimed_yield: u64,
+    pub bucket: u64,
+    pub slot: u64,
+}
 
-    #[account(mut)]
-    pub vault_usdc_token: Box<Account<'info, TokenAccount>>,
+#[event]
+pub struct RotatorForwardedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+}
 
-    pub usdc_mint: Account<'info, Mint>,
+#[event]
+pub struct InvestmentRecordedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,                         // Executed investment booked into the ledger
+    pub computation_offset: u64,
+    pub state_nonce_before: u128,
+    pub state_nonce_after: u128,
+    pub timestamp: i64,
+}
 
-    // Arcium accounts...
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = user,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
+#[event]
+pub struct YieldHarvestedEvent {
+    pub pool: Pubkey,
+    pub yield_amount: u64,                   // Gross, before fees
+    pub exchange_rate: u64,
+    pub fee_amount: u64,
+}
 
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
+#[event]
+pub struct FeesCollectedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+}
 
-    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: mempool_account
-    pub mempool_account: UncheckedAccount<'info>,
+#[event]
+pub struct StateNonceResyncedEvent {
+    pub pool: Pubkey,
+    pub previous_nonce: u128,
+    pub nonce: u128,
+}
 
-    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: executing_pool
-    pub executing_pool: UncheckedAccount<'info>,
+#[event]
+pub struct ComputationExpiredEvent {
+    pub pool: Pubkey,
+    pub computation_offset: u64,
+    pub kind: OpKind,
+}
 
-    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
+/// The MPC aborted a computation; its callback undid what had been staged for it
+#[event]
+pub struct ComputationFailedEvent {
+    pub pool: Pubkey,
+    pub kind: OpKind,
+    pub computation_offset: u64,
+}
 
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
+/// A depositor took back the tokens of a deposit the ledger never credited
+#[event]
+pub struct DepositReclaimedEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub computation_offset: u64,
+}
 
-    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
+#[event]
+pub struct YieldRecordedEvent {
+    pub pool: Pubkey,
+    pub position_value: u64,
+    pub yield_amount: u64,                   // Gross, before fees
+    pub fee_amount: u64,
+    pub computation_offset: u64,
+    pub state_nonce_before: u128,
+    pub state_nonce_after: u128,
+    pub timestamp: i64,
+}
 
-    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
-    pub pool_account: Box<Account<'info, FeePool>>,
+#[event]
+pub struct LegacyPoolFrozenEvent {
+    pub pool: Pubkey,
+    pub slot: u64,
+}
 
-    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
-    pub clock_account: Box<Account<'info, ClockAccount>>,
+#[event]
+pub struct LegacyDepositMigratedEvent {
+    pub pool: Pubkey,
+    pub shard_index: u16,
+    pub moved: bool,
+    pub legacy_retired: bool,
+}
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct PositionTransferredEvent {
+    pub pool: Pubkey,
 }
 
-#[callback_accounts("process_deposit")]
-#[derive(Accounts)]
-pub struct ProcessDepositCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_DEPOSIT))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
+/// A beneficiary was authorized to withdraw a dormant deposit
+#[event]
+pub struct DepositRecoveredEvent {
+    pub pool: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
 }
 
-// Similar structs for CheckAndInvest, Withdraw, etc.
-// (Abbreviated for brevity - you can generate these following the same pattern)
+#[event]
+pub struct CrankBountyCreditedEvent {
+    pub pool: Pubkey,
+    pub keeper: Pubkey,
+    pub amount: u64,
+}
 
-#[queue_computation_accounts("check_investment_needed", authority)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct CheckAndInvest<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
-    // ... (same Arcium accounts as above)
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = authority,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: mempool
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: execpool
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: comp
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_INVESTMENT))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
-    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
-    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
-    pub pool_account: Box<Account<'info, FeePool>>,
-    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
-    pub clock_account: Box<Account<'info, ClockAccount>>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct CrankBountyClaimedEvent {
+    pub pool: Pubkey,
+    pub keeper: Pubkey,
+    pub amount: u64,
 }
 
-#[callback_accounts("check_investment_needed")]
-#[derive(Accounts)]
-pub struct CheckInvestmentNeededCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_INVESTMENT))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    /// CHECK: computation
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
+#[event]
+pub struct CompoundedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub new_total_invested: u64,
 }
 
-#[queue_computation_accounts("authorize_withdrawal", user)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct Withdraw<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
-    /// Vault token account (source for withdrawal)
-    #[account(
-        mut,
-        seeds = [b"vault", ghost_pool.key().as_ref()],
-        bump = ghost_pool.vault_bump,
-    )]
-    pub vault: Account<'info, TokenAccount>,
-    /// User's token account (destination for withdrawal)
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
-    // ... Arcium accounts
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = user,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: mempool
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: execpool
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: comp
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
-    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
-    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
-    pub pool_account: Box<Account<'info, FeePool>>,
-    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
-    pub clock_account: Box<Account<'info, ClockAccount>>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct StrategyWeightsUpdatedEvent {
+    pub pool: Pubkey,
+}
+
+#[event]
+pub struct PasswordRotatedEvent {
+    pub pool: Pubkey,
+    pub rotation_count: u64,
 }
 
-#[callback_accounts("authorize_withdrawal")]
-#[derive(Accounts)]
-pub struct AuthorizeWithdrawalCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_AUTHORIZE_WITHDRAWAL))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    /// CHECK: computation
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
-    /// Vault token account (source)
-    #[account(mut)]
-    pub vault: Account<'info, TokenAccount>,
-    /// User's token account (destination)
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct DepositEvent {
+    pub pool: Pubkey,
+    pub deposit_count: u64,
+    pub amount: u64,                         // Credited amount (0 for shard deposits, whose output doesn't carry it)
+    pub computation_offset: u64,             // 0 if the computation log entry was already overwritten
+    pub state_nonce_before: u128,
+    pub state_nonce_after: u128,
+    pub timestamp: i64,
 }
 
-// Init comp def structs
-#[init_computation_definition_accounts("init_pool_state", payer)]
-#[derive(Accounts)]
-pub struct InitPoolCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def_account
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PoolClosedEvent {
+    pub pool: Pubkey,
+    pub vault_swept: u64,
+    pub collateral_swept: u64,
+    pub forced: bool,                        // Closed with deposits still on the books
 }
 
-#[init_computation_definition_accounts("process_deposit", payer)]
-#[derive(Accounts)]
-pub struct InitDepositCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+/// Outcome of a reconcile run; a negative delta is a shortfall
+#[event]
+pub struct ShareTokensEnabledEvent {
+    pub pool: Pubkey,
+    pub share_mint: Pubkey,
 }
 
-#[init_computation_definition_accounts("check_investment_needed", payer)]
-#[derive(Accounts)]
-pub struct InitCheckInvestmentNeededCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PositionSyncedEvent {
+    pub pool: Pubkey,
+    pub collateral_amount: u64,
+    pub exchange_rate: u64,
+    pub position_value: u64,
+    pub kamino_position_value: u64,          // As booked by the last harvest
 }
 
-#[init_computation_definition_accounts("record_investment", payer)]
-#[derive(Accounts)]
-pub struct InitRecordInvestmentCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ReconciliationEvent {
+    pub pool: Pubkey,
+    pub vault_balance: u64,
+    pub earmarked: u64,
+    pub vault_delta: i64,
+    pub kamino_position_value: u64,
+    pub total_invested: u64,
+    pub position_delta: i64,
+    pub balanced: bool,
+    pub needs_attention: bool,
 }
 
-#[init_computation_definition_accounts("record_yield", payer)]
-#[derive(Accounts)]
-pub struct InitRecordYieldCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct LendingProgramProposedEvent {
+    pub pool: Pubkey,
+    pub lending_program: Pubkey,             // Default = proposal withdrawn
+    pub effective_slot: u64,
 }
 
-#[init_computation_definition_accounts("authorize_withdrawal", payer)]
-#[derive(Accounts)]
-pub struct InitAuthorizeWithdrawalCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct LendingProgramChangedEvent {
+    pub pool: Pubkey,
+    pub previous_lending_program: Pubkey,
+    pub lending_program: Pubkey,
 }
 
-#[init_computation_definition_accounts("process_withdrawal", payer)]
-#[derive(Accounts)]
-pub struct InitProcessWithdrawalCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PoolMigratedEvent {
+    pub pool: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
 }
 
+/// A pool's or shard's encrypted state re-encrypted into the current layout
+#[event]
+pub struct StateUpgradedEvent {
+    pub pool: Pubkey,
+    pub account: Pubkey,                     // The pool itself or one of its shards
+}
 
-#[queue_computation_accounts("process_withdrawal", user)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct ProcessWithdrawForQueue<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut)]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
-    #[account(
-        mut,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, ArciumSignerAccount>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut, address = derive_mempool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: mempool
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(mut, address = derive_execpool_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: execpool
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(mut, address = derive_comp_pda!(computation_offset, mxe_account, ErrorCode::ClusterNotSet))]
-    /// CHECK: computation
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_WITHDRAWAL))]
-    pub comp_def_account: Box<Account<'info, ComputationDefinitionAccount>>,
-    #[account(mut, address = derive_cluster_pda!(mxe_account, ErrorCode::ClusterNotSet))]
-    pub cluster_account: Box<Account<'info, Cluster>>,
-    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
-    pub pool_account: Box<Account<'info, FeePool>>,
-    #[account(mut, address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
-    pub clock_account: Box<Account<'info, ClockAccount>>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct ReferralRewardsClaimedEvent {
+    pub pool: Pubkey,
+    pub referrer_token_account: Pubkey,
+    pub amount: u64,
 }
 
-/// Accounts for investing in Mock Kamino after MPC approval
-#[derive(Accounts)]
-pub struct InvestInKamino<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+#[event]
+pub struct DepositIntentQueuedEvent {
+    pub pool: Pubkey,
+    pub position: u64,                       // Queue sequence number, folded in order
+}
 
-    #[account(
-        mut,
-        has_one = authority @ ErrorCode::Unauthorized,
-        constraint = ghost_pool.pending_investment_amount > 0 @ ErrorCode::NoPendingInvestment,
-    )]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
+#[event]
+pub struct DepositIntentRejectedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub refunded: bool,                      // False when the refund account could no longer take it
+}
 
-    /// Pool's USDC vault (source of liquidity)
-    #[account(
-        mut,
-        seeds = [b"vault", ghost_pool.key().as_ref()],
-        bump = ghost_pool.vault_bump,
-    )]
-    pub vault: Box<Account<'info, TokenAccount>>,
+#[event]
+pub struct DepositBatchProcessedEvent {
+    pub pool: Pubkey,
+    pub processed: u8,
+    pub accepted: u8,
+}
 
-    /// Mock Kamino Lending Market
-    /// CHECK: Validated by Mock Kamino program
-    pub kamino_lending_market: UncheckedAccount<'info>,
+/// process_deposit rejected the deposit and `amount` went back to where it came from
+#[event]
+pub struct DepositRejectedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub ledger_full: bool,                   // Else the password commitment belonged to another signer, or matched no position on a top-up
+    pub over_user_cap: bool,                 // The depositor's positions would have passed max_deposit_per_user
+}
 
-    /// Mock Kamino Lending Market Authority PDA
-    /// CHECK: Validated by Mock Kamino program
-    pub kamino_lending_market_authority: UncheckedAccount<'info>,
+#[event]
+pub struct DepositToppedUpEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+}
 
-    /// Mock Kamino Reserve account
-    /// CHECK: Validated by Mock Kamino program
-    #[account(mut)]
-    pub kamino_reserve: UncheckedAccount<'info>,
+#[event]
+pub struct DepositSwappedEvent {
+    pub pool: Pubkey,
+    pub swap_mint: Pubkey,
+    pub amount_in: u64,                      // Swap mint spent
+    pub amount_out: u64,                     // Deposit mint credited
+}
 
-    /// Reserve liquidity mint (USDC)
-    pub reserve_liquidity_mint: Box<Account<'info, Mint>>,
+#[event]
+pub struct InvestmentApprovedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+}
 
-    /// Reserve collateral mint (cToken)
-    /// CHECK: Validated by Mock Kamino program
-    #[account(mut)]
-    pub reserve_collateral_mint: UncheckedAccount<'info>,
+#[event]
+pub struct InvestmentExecutedEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+    /// cTokens the deposit minted into the collateral account
+    pub collateral_received: u64,
+    pub total_collateral_received: u64,
+}
 
-    /// Reserve liquidity supply vault
-    /// CHECK: Validated by Mock Kamino program
-    #[account(mut)]
-    pub reserve_liquidity_supply: UncheckedAccount<'info>,
+/// set_admin_multisig changed the co-signers of privileged instructions
+#[event]
+pub struct AdminMultisigUpdatedEvent {
+    pub pool: Pubkey,
+    pub admin_multisig: Pubkey,  // Default when turned off
+    pub threshold: u8,
+    pub member_count: u8,
+}
 
-    /// Destination for collateral tokens (cTokens)
-    #[account(mut)]
-    pub user_destination_collateral: Box<Account<'info, TokenAccount>>,
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub pool: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
 
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct PoolConfigUpdatedEvent {
+    pub pool: Pubkey,
+    pub investment_threshold: u64,
+    pub min_deposit: u64,
+    pub max_deposit_per_user: u64,
+    pub max_pool_cap: u64,
+    pub investment_cooldown_secs: i64,
+    pub liquidity_buffer_bps: u16,
+    pub withdrawal_cap_per_epoch: u64,
+    pub min_slots_between_checks: u64,
+}
 
-    /// CHECK: Mock Kamino Lending program
-    #[account(address = KAMINO_LENDING_PROGRAM_ID)]
-    pub kamino_program: UncheckedAccount<'info>,
+#[event]
+pub struct PoolPauseChangedEvent {
+    pub pool: Pubkey,
+    pub paused: u8,
+}
 
-    pub system_program: Program<'info, System>,
+/// The MXE key changed under the pool: refetch the MXE public key before encrypting
+#[event]
+pub struct ClusterRotationDetectedEvent {
+    pub pool: Pubkey,
+    pub expected_key_hash: [u8; 32],
+    pub observed_key_hash: [u8; 32],
+}
+
+#[event]
+pub struct ClusterRotationAcknowledgedEvent {
+    pub pool: Pubkey,
+    pub previous_key_hash: [u8; 32],
+    pub mxe_key_hash: [u8; 32],
+}
+
+#[event]
+pub struct PoolStatusChangedEvent {
+    pub pool: Pubkey,
+    pub from: PoolStatus,
+    pub to: PoolStatus,
+}
+
+#[event]
+pub struct PositionStatusChangedEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub from: PositionStatus,
+    pub to: PositionStatus,
+}
+
+#[event]
+pub struct CashSweptEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub collateral_amount: u64,
 }
 
-/// Accounts for setting the collateral token account
-#[derive(Accounts)]
-pub struct SetCollateralAccount<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+#[event]
+pub struct CashRedeemedEvent {
+    pub pool: Pubkey,
+    pub collateral_amount: u64,
+    pub liquidity_amount: u64,
+    pub yield_amount: u64,
+}
 
-    #[account(
-        mut,
-        has_one = authority @ ErrorCode::Unauthorized,
-    )]
-    pub ghost_pool: Box<Account<'info, GhostPool>>,
+#[event]
+pub struct RedemptionExecutedEvent {
+    pub pool: Pubkey,
+    pub collateral_amount: u64,
+    pub liquidity_amount: u64,
+}
 
-    /// Collateral token account (owned by vault PDA)
-    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+#[event]
+pub struct WithdrawalAuthorizedEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub idx: u8,
+    pub computation_offset: u64,
+    pub timestamp: i64,
 }
 
-// Events
 #[event]
-pub struct PoolInitializedEvent {
+pub struct WithdrawalFailedEvent {
     pub pool: Pubkey,
-    pub authority: Pubkey,
+    pub user: Pubkey,
+    pub reason: WithdrawalFailure,
+    pub computation_offset: u64,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct DepositEvent {
+pub struct WithdrawalClaimedEvent {
     pub pool: Pubkey,
-    pub deposit_count: u64,
+    pub amount: u64,
 }
 
+/// claim_withdrawal_relayed paid the relayer `amount` out of a withdrawal
 #[event]
-pub struct InvestmentApprovedEvent {
+pub struct RelayerRebatePaidEvent {
     pub pool: Pubkey,
+    pub relayer_token_account: Pubkey,
     pub amount: u64,
 }
 
 #[event]
-pub struct InvestmentExecutedEvent {
+pub struct WithdrawalQueuedEvent {
     pub pool: Pubkey,
     pub amount: u64,
+    pub position: u64,                       // Queue sequence number, fulfilled in order
 }
 
 #[event]
-pub struct WithdrawalAuthorizedEvent {
+pub struct PayoutFailedEvent {
+    pub pool: Pubkey,
+    pub attempts: u8,
+}
+
+#[event]
+pub struct PayoutRecreditedEvent {
     pub pool: Pubkey,
     pub amount: u64,
-    pub idx: u8,
 }
 
 #[event]
 pub struct WithdrawalCompletedEvent {
     pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub computation_offset: u64,
+    pub state_nonce_before: u128,
+    pub state_nonce_after: u128,
+    pub timestamp: i64,
+}
+
+/// A computation was queued; the requester is left out, as in the ComputationLog
+#[event]
+pub struct ComputationQueuedEvent {
+    pub pool: Pubkey,
+    pub kind: OpKind,
+    pub computation_offset: u64,
+    pub state_nonce: u128,                   // Pool state_nonce the computation consumes
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+/// A timelocked admin action was proposed; it can run from `eta_slot`
+#[event]
+pub struct AdminActionProposedEvent {
+    pub target: Pubkey,  // GhostPool, or the CircuitRegistry
+    pub kind: AdminActionKind,
+    pub eta_slot: u64,
+}
+
+#[event]
+pub struct AdminActionCancelledEvent {
+    pub target: Pubkey,
+    pub kind: AdminActionKind,
+}
+
+#[event]
+pub struct CircuitUpgradedEvent {
+    pub circuit: String,
+    pub url: String,
+    pub hash: [u8; 32],
+    pub version: u16,
 }
 
 // Error codes
@@ -1178,4 +14115,1567 @@ pub enum ErrorCode {
     NoPendingInvestment,
     #[msg("Unauthorized - only pool authority can call this")]
     Unauthorized,
+    #[msg("Withdrawal receipt does not match this withdrawal")]
+    InvalidReceipt,
+    #[msg("Shard index must match the pool's next shard")]
+    InvalidShardIndex,
+    #[msg("Deposit shard has no free slots")]
+    ShardFull,
+    #[msg("Public counters are not bucketed for this pool")]
+    CountersNotBucketed,
+    #[msg("Counter epoch has not ended yet")]
+    CounterEpochNotOver,
+    #[msg("Withdrawal has not been paid out")]
+    WithdrawalNotPaid,
+    #[msg("Withdrawal already deducted from the encrypted ledger")]
+    WithdrawalAlreadyFinalized,
+    #[msg("An earlier withdrawal from this position hasn't been deducted from the ledger yet")]
+    DebitInFlight,
+    #[msg("Balance query was superseded by a newer one")]
+    StaleBalanceQuery,
+    #[msg("Withdrawal rotator is not configured")]
+    RotatorNotConfigured,
+    #[msg("Destination is not the pool's rotator")]
+    InvalidRotator,
+    #[msg("Rotator balance cap exceeded")]
+    RotatorLimitExceeded,
+    #[msg("Rotator payout can only be forwarded in a later slot")]
+    ForwardTooEarly,
+    #[msg("Rotator payout already forwarded")]
+    AlreadyForwarded,
+    #[msg("Rotator hop still pending for this receipt")]
+    RotatorHopPending,
+    #[msg("No executed investment waiting to be recorded")]
+    NoUnrecordedInvestment,
+    #[msg("Kamino reserve account data is invalid")]
+    InvalidReserve,
+    #[msg("No yield accrued since the last harvest")]
+    NoYieldToHarvest,
+    #[msg("Redeem amount must be greater than zero")]
+    InvalidRedeemAmount,
+    #[msg("Not enough cTokens in the collateral account")]
+    InsufficientCollateral,
+    #[msg("Legacy pool is frozen, deposit into a shard instead")]
+    LegacyPoolFrozen,
+    #[msg("Legacy pool must be frozen before migrating")]
+    LegacyPoolNotFrozen,
+    #[msg("Legacy pool slots are retired")]
+    LegacyPoolRetired,
+    #[msg("Invalid legacy deposit slot")]
+    InvalidLegacySlot,
+    #[msg("Pending withdrawal has expired")]
+    PendingWithdrawalExpired,
+    #[msg("Pending withdrawal has not expired yet")]
+    PendingWithdrawalNotExpired,
+    #[msg("Destination owner is not on the deposit's allowlist")]
+    DestinationNotAllowed,
+    #[msg("No cash strategy configured for this pool")]
+    CashStrategyNotSet,
+    #[msg("Cash strategy still holds funds")]
+    CashStrategyActive,
+    #[msg("Cash strategy reserve cannot redeem the whole position instantly")]
+    CashStrategyIlliquid,
+    #[msg("Sweep amount must be positive")]
+    InvalidSweepAmount,
+    #[msg("Sweep exceeds the vault's idle funds")]
+    InsufficientIdleFunds,
+    #[msg("Cash collateral account must differ from the main strategy's")]
+    InvalidCollateralAccount,
+    #[msg("Amount and blinding do not open the receipt's amount commitment")]
+    InvalidReceiptOpening,
+    #[msg("Deposit has not been withdrawn in full")]
+    DepositNotWithdrawn,
+    #[msg("Instruction not allowed in the pool's current status")]
+    InvalidPoolStatus,
+    #[msg("Status transition not allowed by the lifecycle")]
+    InvalidStatusTransition,
+    #[msg("Token account mint does not match the pool's deposit mint")]
+    InvalidMint,
+    #[msg("Deposit mint has too many decimals")]
+    UnsupportedMintDecimals,
+    #[msg("No undelivered payout is pending")]
+    PayoutNotPending,
+    #[msg("Payout attempts exhausted, re-credit the withdrawal instead")]
+    PayoutRetriesExhausted,
+    #[msg("Payout attempts remain, retry the payout first")]
+    PayoutRetriesRemaining,
+    #[msg("Withdrawal has not been deducted from the ledger yet")]
+    WithdrawalNotFinalized,
+    #[msg("Deposit mint uses an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    #[msg("Transfer fees consumed the whole deposit")]
+    DepositConsumedByFees,
+    #[msg("Callback must be a top-level instruction, not invoked via CPI")]
+    WrappedCallback,
+    #[msg("Operation paused by the pool authority")]
+    PoolPaused,
+    #[msg("Pause flags must be a non-empty mask of PAUSE_* bits")]
+    InvalidPauseFlags,
+    #[msg("No authority handover has been proposed")]
+    NoPendingAuthority,
+    #[msg("Pool config values are out of range")]
+    InvalidPoolConfig,
+    #[msg("Deposit is below the pool's minimum")]
+    DepositBelowMinimum,
+    #[msg("Deposit would take the pool over its cap")]
+    PoolCapExceeded,
+    #[msg("Investment cooldown has not elapsed")]
+    InvestmentCooldown,
+    #[msg("Swap deposits are not configured for this pool")]
+    SwapNotConfigured,
+    #[msg("Oracle account is not a fully verified price update")]
+    InvalidOracle,
+    #[msg("Oracle price is too old")]
+    StaleOracle,
+    #[msg("Swap route did not move the expected tokens")]
+    InvalidSwap,
+    #[msg("Swap output is below the oracle slippage bound")]
+    SwapSlippageExceeded,
+    #[msg("Withdrawal queue is full")]
+    WithdrawalQueueFull,
+    #[msg("Vault can pay this withdrawal, claim it instead")]
+    VaultCanPayWithdrawal,
+    #[msg("Vault cannot cover the queued withdrawal yet")]
+    VaultLacksLiquidity,
+    #[msg("Withdrawal is not queued")]
+    WithdrawalNotQueued,
+    #[msg("Only the withdrawal at the head of the queue can be fulfilled")]
+    NotQueueHead,
+    #[msg("Transfer amount must be positive")]
+    InvalidTransferAmount,
+    #[msg("Transfer rejected: unknown sender or recipient, restricted or locked sender, or insufficient balance")]
+    TransferRejected,
+    #[msg("Password rotation rejected: no matching position, or the new password is already in use")]
+    PasswordRotationRejected,
+    #[msg("Deposit recovery is not enabled for this pool")]
+    RecoveryDisabled,
+    #[msg("No dormant deposit names the signer as beneficiary")]
+    RecoveryUnauthorized,
+    #[msg("Pool charges fees, harvest with harvest_yield_with_fees")]
+    FeesConfigured,
+    #[msg("Fee amount must be positive and within the accrued fees")]
+    InvalidFeeAmount,
+    #[msg("Token account is not the pool's fee recipient")]
+    InvalidFeeRecipient,
+    #[msg("MXE key rotated, waiting for acknowledge_cluster_rotation")]
+    ClusterRotationPending,
+    #[msg("Deposit lock exceeds MAX_DEPOSIT_LOCK_SECS")]
+    InvalidLockDuration,
+    #[msg("Deposit is still locked")]
+    DepositLocked,
+    #[msg("No adapter arm for this yield venue in this build")]
+    YieldVenueUnsupported,
+    #[msg("Yield adapter can only be switched while nothing is invested")]
+    YieldAdapterActive,
+    #[msg("Strategy weights must sum to 10000 bps")]
+    StrategyWeightsRejected,
+    #[msg("Auto-compounding is disabled for this pool")]
+    AutoCompoundDisabled,
+    #[msg("No realized yield idle in the vault to compound")]
+    NothingToCompound,
+    #[msg("No crank bounty owed")]
+    NoCrankBounty,
+    #[msg("check_and_invest called before min_slots_between_checks elapsed")]
+    CheckTooFrequent,
+    #[msg("Pending computation does not belong to this computation")]
+    InvalidPendingComputation,
+    #[msg("Computation has not reached COMPUTATION_TIMEOUT_SLOTS yet")]
+    ComputationNotExpired,
+    #[msg("Pool state_nonce does not match the nonce this computation consumed")]
+    NonceMismatch,
+    #[msg("Another pool state computation is in flight, retry after its callback")]
+    StateWriteInFlight,
+    #[msg("Deposit intent queue is full")]
+    DepositIntentQueueFull,
+    #[msg("No queued deposit intents to process")]
+    NoDepositIntents,
+    #[msg("Pool account layout version is not supported by this program")]
+    UnsupportedStateLayout,
+    #[msg("Pool account is on an older layout; run migrate_pool first")]
+    PoolNotMigrated,
+    #[msg("Account is not a GhostPool")]
+    InvalidPoolAccount,
+    #[msg("Pool still has deposits outstanding")]
+    PoolNotDrained,
+    #[msg("Pool has not been closing long enough to force it shut")]
+    CloseGracePeriodActive,
+    #[msg("Pool has a collateral account; pass it with a destination and the token program")]
+    MissingCollateralAccounts,
+    #[msg("Circuit name must be 1 to 32 bytes")]
+    InvalidCircuitName,
+    #[msg("Circuit URL must be 1 to 128 bytes")]
+    InvalidCircuitUrl,
+    #[msg("Circuit registry is full")]
+    CircuitRegistryFull,
+    #[msg("Comp def mask selects no circuit, an unknown circuit, or doesn't match the accounts passed")]
+    InvalidCompDefMask,
+    #[msg("Deposit's computation has neither aborted nor timed out")]
+    DepositNotReclaimable,
+    #[msg("Deposit is held in escrow: reclaim it with reclaim_escrow")]
+    DepositEscrowed,
+    #[msg("Deposit is held in the vault: reclaim it with reclaim_failed_deposit")]
+    DepositNotEscrowed,
+    #[msg("Pool's deposit mint is not wrapped SOL")]
+    NotNativeMint,
+    #[msg("Withdrawal is routed through the rotator: claim it with claim_withdrawal")]
+    RotatorPayout,
+    #[msg("Lending program account is not the pool's lending_program")]
+    InvalidLendingProgram,
+    #[msg("No lending program change has been proposed")]
+    NoPendingLendingProgram,
+    #[msg("Lending program change is still timelocked")]
+    LendingProgramTimelocked,
+    #[msg("Lending program can only be changed while nothing is invested through it")]
+    LendingPositionOpen,
+    #[msg("Share tokens can only be enabled once, before anything is deposited")]
+    ShareTokensUnavailable,
+    #[msg("Pool issues share tokens: pass the share mint and your share token account")]
+    ShareAccountRequired,
+    #[msg("Pool issues share tokens: deposit through deposit or deposit_sol")]
+    SharePoolDeposit,
+    #[msg("Encrypted state is still in the previous layout: run upgrade_pool_state / upgrade_shard_state")]
+    StateUpgradePending,
+    #[msg("Encrypted state is already in the current layout")]
+    NoStateUpgradePending,
+    #[msg("Pool pays no referral rewards")]
+    ReferralsDisabled,
+    #[msg("Deposit is above the pool's per-user cap")]
+    DepositAboveUserCap,
+    #[msg("Signer isn't the delegate for this amount, or the refund or share account isn't the owner's")]
+    NotTokenDelegate,
+    #[msg("Pool doesn't allow relayed withdrawals")]
+    RelayingDisabled,
+    #[msg("Relayer rebate is above the pool's cap or takes the whole withdrawal")]
+    RelayerRebateTooHigh,
+    #[msg("Admin multisig needs 1..=threshold..=members distinct, non-default members")]
+    InvalidAdminMultisig,
+    #[msg("Not enough admin multisig members signed")]
+    AdminQuorumNotMet,
+    #[msg("Admin action is still timelocked")]
+    AdminActionTimelocked,
+    #[msg("Pending admin action doesn't match this instruction")]
+    AdminActionMismatch,
+    #[msg("Token account isn't owned by the signer")]
+    TokenOwnerMismatch,
+    #[msg("Vault isn't the pool's deposit-mint token account")]
+    InvalidVault,
+    #[msg("TVL disclosure is off for this pool")]
+    TvlDisclosureDisabled,
+    #[msg("TVL epoch has not ended yet")]
+    TvlEpochNotOver,
+    #[msg("Kamino returned less than the requested minimum")]
+    SlippageExceeded,
+    #[msg("Venue deposit didn't exchange the approved amount for collateral")]
+    InvestmentNotSettled,
+}
+
+/// Escrow a deposit and queue process_deposit for it, the entry bound to `owner`; shared by
+/// deposit and deposit_delegated
+#[allow(clippy::too_many_arguments)]
+fn queue_process_deposit(
+    ctx: Context<Deposit>,
+    owner: Pubkey,
+    computation_offset: u64,
+    amount: u64,
+    encrypted_password_hash: [u8; 32],
+    user_pubkey: [u8; 32],
+    nonce: u128,
+    encrypted_allowed_destinations: [[u8; 32]; MAX_ALLOWED_DESTINATIONS],
+    encrypted_beneficiary: [u8; 32],
+    encrypted_referrer: [u8; 32],
+    referrer_nonce: u128,
+    lock_secs: u64,
+    receipt_index: u32,
+    amount_blinding: [u8; 32],
+    cu_price_micro: Option<u64>,
+) -> Result<()> {
+    require!(lock_secs <= MAX_DEPOSIT_LOCK_SECS, ErrorCode::InvalidLockDuration);
+    require!(
+        !ctx.accounts.ghost_pool.issues_shares() || ctx.accounts.share_token_account.is_some(),
+        ErrorCode::ShareAccountRequired
+    );
+    // Turn a deposit away before any tokens move; the credited amount is checked again below
+    ctx.accounts
+        .ghost_pool
+        .check_deposit_limits(amount, ctx.accounts.vault_usdc_token.amount.saturating_add(amount))?;
+    let escrowed = deposit_into_vault(
+        &ctx.accounts.user,
+        &ctx.accounts.user_usdc_token,
+        &mut ctx.accounts.deposit_escrow,
+        &ctx.accounts.deposit_mint,
+        &ctx.accounts.token_program,
+        amount,
+    )?;
+    // The sweep into the vault pays any transfer fee again: credit what will land
+    let amount = net_of_transfer_fee(&ctx.accounts.deposit_mint, escrowed)?;
+    ctx.accounts
+        .ghost_pool
+        .check_deposit_limits(amount, ctx.accounts.vault_usdc_token.amount.saturating_add(amount))?;
+    let before = ctx.accounts.ghost_pool.counters();
+
+    if let Some(receipt) = ctx.accounts.deposit_receipt.as_mut() {
+        let clock = Clock::get()?;
+        receipt.bump = ctx.bumps.deposit_receipt.unwrap();
+        receipt.pool = ctx.accounts.ghost_pool.key();
+        receipt.user = ctx.accounts.user.key();
+        receipt.receipt_index = receipt_index;
+        receipt.password_commitment = password_commitment(&encrypted_password_hash, nonce);
+        receipt.amount_commitment = amount_commitment(amount, &amount_blinding);
+        receipt.deposited_at = clock.unix_timestamp;
+        receipt.deposit_slot = clock.slot;
+    }
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    // Queue MPC computation
+    let args = ArgBuilder::new()
+        .x25519_pubkey(user_pubkey)
+        .plaintext_u128(nonce)
+        .encrypted_u128(encrypted_password_hash)
+        .encrypted_u128(encrypted_allowed_destinations[0])
+        .encrypted_u128(encrypted_allowed_destinations[1])
+        .encrypted_u128(encrypted_beneficiary)
+        .x25519_pubkey(user_pubkey)
+        .plaintext_u128(referrer_nonce)
+        .encrypted_u128(encrypted_referrer)
+        .plaintext_u64(amount)
+        .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+        .plaintext_u64(Clock::get()?.slot)
+        .plaintext_u64(lock_secs)
+        .plaintext_u128(pubkey_fingerprint(&owner))
+        .plaintext_u64(ctx.accounts.ghost_pool.yield_scale)
+        .plaintext_u64(ctx.accounts.ghost_pool.max_deposit_per_user)
+        .plaintext_u128(ctx.accounts.ghost_pool.state_nonce)
+        .pool_state(&ctx.accounts.ghost_pool)?
+        .build();
+
+    ctx.accounts.ghost_pool.begin_state_write(ctx.accounts.computation_account.key())?;
+
+    ctx.accounts.computation_log.record_queued(
+        OpKind::Deposit,
+        computation_offset,
+        ctx.accounts.computation_account.key(),
+        ctx.accounts.ghost_pool.state_nonce,
+        &ctx.accounts.user.key(),
+    )?;
+
+    let [failed_deposit, depositor] = open_failed_deposit(
+        Some(&mut ctx.accounts.failed_deposit),
+        Some(ctx.bumps.failed_deposit),
+        ctx.accounts.ghost_pool.key(),
+        owner,
+        computation_offset,
+        ctx.accounts.computation_account.key(),
+        escrowed,
+    )?;
+    ctx.accounts.failed_deposit.escrowed = true;
+
+    // Share pools: the callback mints to the user's share account
+    let mut callback_accounts = vec![
+        CallbackAccount {
+            pubkey: ctx.accounts.ghost_pool.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.computation_log.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.vault_usdc_token.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.deposit_mint.key(),
+            is_writable: false,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.refund_token_account.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: ctx.accounts.token_program.key(),
+            is_writable: false,
+        },
+        CallbackAccount {
+            pubkey: crate::ID,
+            is_writable: false,
+        },
+        failed_deposit,
+        depositor,
+        CallbackAccount {
+            pubkey: ctx.accounts.deposit_escrow.key(),
+            is_writable: true,
+        },
+    ];
+    if let Some(share_token_account) = &ctx.accounts.share_token_account {
+        callback_accounts.push(CallbackAccount {
+            pubkey: ctx.accounts.ghost_pool.share_mint,
+            is_writable: true,
+        });
+        callback_accounts.push(CallbackAccount {
+            pubkey: share_token_account.key(),
+            is_writable: true,
+        });
+    }
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        args,
+        None,
+        vec![ProcessDepositCallback::callback_ix(
+            computation_offset,
+            &ctx.accounts.mxe_account,
+            &callback_accounts,
+        )?],
+        2, // PoolState (40 FE) doesn't fit a single callback tx
+        ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+    )?;
+
+    ctx.accounts.ghost_pool.check_invariants(
+        &before,
+        StateWrite::None,
+        Some(&ctx.accounts.vault_usdc_token.to_account_info()),
+    );
+
+    Ok(())
+}
+
+/// Queue authorize_withdrawal; shared by withdraw and withdraw_via_rotator
+fn queue_withdrawal(
+    ctx: Context<Withdraw>,
+    computation_offset: u64,
+    amount: u64,
+    encrypted_password_hash: [u8; 32],
+    user_pubkey: [u8; 32],
+    nonce: u128,
+    recipient: Pubkey,
+    cu_price_micro: Option<u64>,
+) -> Result<()> {
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    // Allowlist is checked against the final recipient's owner; routed withdrawals
+    // have already matched recipient_token_account to `recipient`
+    let destination_owner = if recipient == ctx.accounts.user_token_account.key() {
+        ctx.accounts.user_token_account.owner
+    } else {
+        ctx.accounts.recipient_token_account.as_ref().ok_or(ErrorCode::InvalidRotator)?.owner
+    };
+
+    let callback_accounts = open_withdrawal(
+        ctx.accounts.ghost_pool.key(),
+        ctx.accounts.computation_log.key(),
+        &mut ctx.accounts.withdrawal_receipt,
+        ctx.bumps.withdrawal_receipt,
+        &mut ctx.accounts.pending_withdrawal,
+        ctx.bumps.pending_withdrawal,
+        ctx.accounts.user.key(),
+        ctx.accounts.user_token_account.key(),
+        recipient,
+        Some(destination_owner),
+        computation_offset,
+    )?;
+    let args = authorize_withdrawal_args(
+        &ctx.accounts.ghost_pool,
+        &ctx.accounts.user.key(),
+        user_pubkey,
+        nonce,
+        encrypted_password_hash,
+        Some(amount),
+        &destination_owner,
+    )?
+    .build();
+
+    ctx.accounts.computation_log.record_queued(
+        OpKind::Withdraw,
+        computation_offset,
+        ctx.accounts.computation_account.key(),
+        ctx.accounts.ghost_pool.state_nonce,
+        &ctx.accounts.user.key(),
+    )?;
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        args,
+        None,
+        vec![AuthorizeWithdrawalCallback::callback_ix(
+            computation_offset,
+            &ctx.accounts.mxe_account,
+            &callback_accounts,
+        )?],
+        1,
+        ctx.accounts.ghost_pool.cu_price(cu_price_micro),
+    )?;
+
+    Ok(())
+}
+
+/// Fill in the receipt and PendingWithdrawal a withdrawal request creates up front (callbacks
+/// have no payer) and return the accounts its authorize callback writes. `destination_owner`
+/// is the owner the circuit must have checked the payout against; None for a recovery, whose
+/// circuit checks the signer as the deposit's beneficiary instead
+#[allow(clippy::too_many_arguments)]
+fn open_withdrawal<'info>(
+    pool: Pubkey,
+    computation_log: Pubkey,
+    receipt: &mut Account<'info, WithdrawalReceipt>,
+    receipt_bump: u8,
+    pending: &mut Account<'info, PendingWithdrawal>,
+    pending_bump: u8,
+    user: Pubkey,
+    destination: Pubkey,
+    recipient: Pubkey,
+    destination_owner: Option<Pubkey>,
+    computation_offset: u64,
+) -> Result<[CallbackAccount; 4]> {
+    receipt.bump = receipt_bump;
+    receipt.pool = pool;
+    receipt.user = user;
+    receipt.destination = destination;
+    receipt.recipient = recipient;
+    receipt.forwarded = false;
+    receipt.amount = 0;
+    receipt.slot = 0;
+    receipt.computation_offset = computation_offset;
+    receipt.paid = false;
+    receipt.idx = 0;
+    receipt.from_shard = false;
+    receipt.finalized = false;
+    receipt.penalty = 0;
+    receipt.penalty_to_fees = false;
+    receipt.shard_index = 0;
+
+    pending.bump = pending_bump;
+    pending.pool = pool;
+    pending.user = user;
+    pending.receipt = receipt.key();
+    pending.amount = 0;
+    pending.idx = 0;
+    pending.expiry_slot = Clock::get()?.slot + PENDING_WITHDRAWAL_TTL_SLOTS;
+    pending.status = PositionStatus::Requested;
+    pending.destination = destination;
+    pending.payout_attempts = 0;
+    pending.destination_owner = destination_owner.map_or(0, |owner| pubkey_fingerprint(&owner));
+
+    Ok([
+        CallbackAccount {
+            pubkey: pool,
+            is_writable: false,
+        },
+        CallbackAccount {
+            pubkey: pending.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: receipt.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: computation_log,
+            is_writable: true,
+        },
+    ])
+}
+
+/// Arguments of authorize_withdrawal, for a withdrawal of `amount` paid to a token account
+/// owned by `destination_owner`, or of authorize_withdrawal_all when there's no amount
+fn authorize_withdrawal_args(
+    pool: &Account<GhostPool>,
+    user: &Pubkey,
+    user_pubkey: [u8; 32],
+    nonce: u128,
+    encrypted_password_hash: [u8; 32],
+    amount: Option<u64>,
+    destination_owner: &Pubkey,
+) -> Result<ArgBuilder> {
+    let args = ArgBuilder::new()
+        .x25519_pubkey(user_pubkey)
+        .plaintext_u128(nonce)
+        .encrypted_u128(encrypted_password_hash);
+    let args = match amount {
+        Some(amount) => args.plaintext_u64(amount),
+        None => args,
+    };
+    args.plaintext_u128(pubkey_fingerprint(destination_owner))
+        .plaintext_u128(pubkey_fingerprint(user))
+        .plaintext_u64(pool.yield_scale)
+        .plaintext_u64(Clock::get()?.unix_timestamp as u64)
+        .plaintext_u16(pool.early_exit_penalty_bps)
+        .plaintext_u64(pool.early_exit_window_secs as u64)
+        .plaintext_u64(withdrawal_epoch_start(Clock::get()?.slot))
+        .plaintext_u64(pool.withdrawal_cap_per_epoch)
+        .plaintext_u128(pool.state_nonce)
+        .pool_state(pool)
+}
+
+/// sha256 of the MXE's x25519 key (zero while the MXE has no keys yet)
+fn mxe_key_hash(mxe: &MXEAccount) -> [u8; 32] {
+    match &mxe.utility_pubkeys {
+        SetUnset::Set(keys) => hash(&keys.x25519_pubkey).to_bytes(),
+        SetUnset::Unset(..) => [0u8; 32],
+    }
+}
+
+/// Compare the MXE key a callback ran under with the pool's acknowledged one
+/// A mismatch flags the pool once and emits ClusterRotationDetectedEvent; pools created
+/// before the key was tracked adopt the first key they see
+fn observe_mxe_key(pool: &mut Account<GhostPool>, mxe: &MXEAccount) {
+    let observed = mxe_key_hash(mxe);
+    if pool.mxe_key_hash == [0u8; 32] {
+        pool.mxe_key_hash = observed;
+    } else if observed != pool.mxe_key_hash && !pool.mxe_rotation_pending {
+        pool.mxe_rotation_pending = true;
+        msg!("MXE key changed, client-encrypted flows held until acknowledge_cluster_rotation");
+        emit!(ClusterRotationDetectedEvent {
+            pool: pool.key(),
+            expected_key_hash: pool.mxe_key_hash,
+            observed_key_hash: observed,
+        });
+    }
+}
+
+/// First slot of the withdrawal cap epoch `slot` falls in
+fn withdrawal_epoch_start(slot: u64) -> u64 {
+    slot - slot % WITHDRAWAL_EPOCH_SLOTS
+}
+
+/// Ledger fingerprint of a pubkey: first 16 bytes, little-endian
+/// Used for destination allowlists and beneficiaries (clients compute the same value when
+/// encrypting `allowed_destinations` and `beneficiary`) and for binding a deposit to its signer
+pub fn pubkey_fingerprint(owner: &Pubkey) -> u128 {
+    u128::from_le_bytes(owner.to_bytes()[..16].try_into().unwrap())
+}
+
+/// Gate for the authority-or-cranker instructions: the authority, a registered cranker, or anyone if permissionless
+fn authorize_crank(
+    pool: &GhostPool,
+    signer: &Pubkey,
+    registry: Option<&Account<CrankerRegistry>>,
+    cranker: Option<&Account<Cranker>>,
+) -> Result<()> {
+    let allowed = *signer == pool.authority
+        || cranker.is_some()
+        || registry.is_some_and(|r| r.permissionless);
+    require!(allowed, ErrorCode::Unauthorized);
+    Ok(())
+}
+
+/// Check the admin multisig's quorum on a privileged instruction; a no-op until
+/// set_admin_multisig sets one. `remaining_accounts` holds the AdminMultisig followed by the
+/// members signing, so the authority itself may be a PDA (a Squads vault) signing through CPI
+fn require_admin_quorum(pool: &GhostPool, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    if pool.admin_multisig == Pubkey::default() {
+        return Ok(());
+    }
+    let (multisig_info, signers) = remaining_accounts.split_first().ok_or(ErrorCode::AdminQuorumNotMet)?;
+    require_keys_eq!(multisig_info.key(), pool.admin_multisig, ErrorCode::InvalidAdminMultisig);
+    require_keys_eq!(*multisig_info.owner, crate::ID, ErrorCode::InvalidAdminMultisig);
+    let multisig = AdminMultisig::try_deserialize(&mut &multisig_info.try_borrow_data()?[..])?;
+
+    let mut signed: Vec<Pubkey> = Vec::with_capacity(multisig.member_count as usize);
+    for account in signers.iter().filter(|a| a.is_signer && multisig.members().contains(a.key)) {
+        if !signed.contains(account.key) {
+            signed.push(*account.key);
+        }
+    }
+    require!(signed.len() >= multisig.threshold as usize, ErrorCode::AdminQuorumNotMet);
+    Ok(())
+}
+
+/// Credit the registry's bounty for a successful crank to a registered cranker
+/// Booked out of accrued_fees so depositors never fund it; paid by claim_crank_bounty
+fn credit_crank_bounty(
+    pool: &mut GhostPool,
+    registry: Option<&Account<CrankerRegistry>>,
+    cranker: Option<&mut Account<Cranker>>,
+) {
+    let (Some(registry), Some(cranker)) = (registry, cranker) else {
+        return;
+    };
+    let amount = registry.bounty.min(pool.accrued_fees);
+    pool.accrued_fees -= amount;
+    cranker.bounty_owed += amount;
+    cranker.cranks += 1;
+    emit!(CrankBountyCreditedEvent {
+        pool: cranker.pool,
+        keeper: cranker.keeper,
+        amount,
+    });
+}
+
+/// Grow a program-owned account to `len`, topping its lamports up to rent exemption from `payer`
+fn resize_with_rent<'info>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    len: usize,
+) -> Result<()> {
+    let shortfall = Rent::get()?.minimum_balance(len).saturating_sub(info.lamports());
+    if shortfall > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    info.resize(len)?;
+    Ok(())
+}
+
+/// Transfer USDC out of the pool vault, signed by the pool PDA
+fn transfer_from_vault<'info>(
+    pool: &Account<'info, GhostPool>,
+    vault: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    destination: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    let pool_id = pool.pool_id.to_le_bytes();
+    let seeds = &[
+        b"ghost_pool",
+        pool.seed_authority.as_ref(),
+        &pool_id,
+        &[pool.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_accounts = TransferChecked {
+        from: vault.to_account_info(),
+        mint: mint.to_account_info(),
+        to: destination.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+    transfer_checked(cpi_ctx, amount, mint.decimals)
+}
+
+/// Move a deposit from the user into the vault and return what actually landed
+/// Token-2022 transfer fees are withheld outside the vault's `amount`, so the encrypted
+/// ledger is credited with the net rather than the requested amount
+fn deposit_into_vault<'info>(
+    user: &Signer<'info>,
+    from: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    vault: &mut InterfaceAccount<'info, token_interface::TokenAccount>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<u64> {
+    let vault_before = vault.amount;
+
+    let cpi_accounts = TransferChecked {
+        from: from.to_account_info(),
+        mint: mint.to_account_info(),
+        to: vault.to_account_info(),
+        authority: user.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    transfer_checked(cpi_ctx, amount, mint.decimals)?;
+
+    vault.reload()?;
+    let received = vault.amount.saturating_sub(vault_before);
+    require!(received > 0, ErrorCode::DepositConsumedByFees);
+    if received != amount {
+        msg!("Deposit of {} credited as {} after transfer fees", amount, received);
+    }
+    Ok(received)
+}
+
+/// What a transfer of `amount` leaves after the mint's Token-2022 transfer fee for this epoch
+/// (all of it for mints without one)
+fn net_of_transfer_fee(mint: &InterfaceAccount<token_interface::Mint>, amount: u64) -> Result<u64> {
+    let info = mint.to_account_info();
+    let data = info.try_borrow_data()?;
+    let Ok(state) = StateWithExtensions::<anchor_spl::token_2022::spl_token_2022::state::Mint>::unpack(&data) else {
+        return Ok(amount);
+    };
+    let Ok(config) = state.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+    let fee = config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ErrorCode::DepositConsumedByFees)?;
+    let net = amount.saturating_sub(fee);
+    require!(net > 0, ErrorCode::DepositConsumedByFees);
+    Ok(net)
+}
+
+/// Check that a venue deposit of `amount` took exactly that out of the vault (from
+/// `vault_before`) and minted some collateral for it
+fn require_settled(vault_before: u64, vault_after: u64, amount: u64, collateral_received: u64) -> Result<()> {
+    require!(
+        vault_before.checked_sub(vault_after) == Some(amount) && collateral_received > 0,
+        ErrorCode::InvestmentNotSettled
+    );
+    Ok(())
+}
+
+/// Reject Token-2022 mints with extensions outside ALLOWED_MINT_EXTENSIONS
+/// (classic SPL Token mints have none)
+fn require_supported_mint_extensions(mint: &InterfaceAccount<token_interface::Mint>) -> Result<()> {
+    let info = mint.to_account_info();
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<anchor_spl::token_2022::spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        require!(
+            ALLOWED_MINT_EXTENSIONS.contains(&extension),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+/// Open a deposit's FailedDeposit if one was passed, returning the callback accounts that let
+/// the callback settle it (program ID placeholders without one)
+fn open_failed_deposit<'info>(
+    failed_deposit: Option<&mut Box<Account<'info, FailedDeposit>>>,
+    bump: Option<u8>,
+    pool: Pubkey,
+    user: Pubkey,
+    computation_offset: u64,
+    computation_account: Pubkey,
+    amount: u64,
+) -> Result<[CallbackAccount; 2]> {
+    let (Some(failed_deposit), Some(bump)) = (failed_deposit, bump) else {
+        return Ok([
+            CallbackAccount {
+                pubkey: crate::ID,
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: crate::ID,
+                is_writable: false,
+            },
+        ]);
+    };
+    failed_deposit.bump = bump;
+    failed_deposit.pool = pool;
+    failed_deposit.user = user;
+    failed_deposit.computation_offset = computation_offset;
+    failed_deposit.computation_account = computation_account;
+    failed_deposit.amount = amount;
+    failed_deposit.queue_slot = Clock::get()?.slot;
+    failed_deposit.failed = false;
+    failed_deposit.escrowed = false;
+    Ok([
+        CallbackAccount {
+            pubkey: failed_deposit.key(),
+            is_writable: true,
+        },
+        CallbackAccount {
+            pubkey: user,
+            is_writable: true,
+        },
+    ])
+}
+
+/// Close a deposit's FailedDeposit once its callback has credited or refunded the tokens,
+/// handing the rent back to the depositor
+fn settle_failed_deposit<'info>(
+    failed_deposit: &Option<Box<Account<'info, FailedDeposit>>>,
+    depositor: &Option<UncheckedAccount<'info>>,
+    computation_account: &Pubkey,
+) -> Result<()> {
+    let Some(failed_deposit) = failed_deposit else {
+        return Ok(());
+    };
+    let depositor = depositor.as_ref().ok_or(ErrorCode::Unauthorized)?;
+    require_keys_eq!(depositor.key(), failed_deposit.user, ErrorCode::Unauthorized);
+    require_keys_eq!(failed_deposit.computation_account, *computation_account, ErrorCode::Unauthorized);
+    failed_deposit.close(depositor.to_account_info())
+}
+
+/// Leave an aborted deposit's FailedDeposit open for reclaim_failed_deposit
+fn fail_deposit(failed_deposit: &mut Option<Box<Account<FailedDeposit>>>, computation_account: &Pubkey) {
+    if let Some(failed_deposit) = failed_deposit {
+        if failed_deposit.computation_account == *computation_account {
+            failed_deposit.failed = true;
+        }
+    }
+}
+
+/// Settle a withdrawal request the authorize circuit turned down: it can be cancelled
+/// straight away instead of after PENDING_WITHDRAWAL_TTL_SLOTS
+fn reject_pending_withdrawal(pending: &mut PendingWithdrawal, reason: WithdrawalFailure) -> Result<()> {
+    msg!("Withdrawal rejected: {:?}", reason);
+    pending.expiry_slot = Clock::get()?.slot;
+    Ok(())
+}
+
+/// Pay a pool-level withdrawal out of the vault and stamp its receipt
+fn pay_out<'info>(
+    pool: &mut Account<'info, GhostPool>,
+    vault: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    destination: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    receipt: &mut WithdrawalReceipt,
+    amount: u64,
+) -> Result<()> {
+    transfer_from_vault(pool, vault, mint, destination, token_program, amount)?;
+
+    msg!("Transferred {} USDC to user", amount);
+
+    // Increment withdrawal counter (frozen while counters are bucketed)
+    if pool.counter_bucket == 0 {
+        pool.total_withdrawals += 1;
+    }
+
+    // Rotator-routed payouts stay on the pool's books until forwarded
+    if receipt.recipient != receipt.destination {
+        require!(
+            pool.rotator_balance + amount <= pool.rotator_max_balance,
+            ErrorCode::RotatorLimitExceeded
+        );
+        pool.rotator_balance += amount;
+    }
+
+    // Record proof of payout for accounting systems that can't consume events
+    receipt.slot = Clock::get()?.slot;
+    receipt.paid = true;
+    Ok(())
+}
+
+/// Shares `amount` is worth when the pool holds `assets` against `supply` shares: 1:1 for
+/// the first deposit, rounded down when minting and up when burning, in the pool's favour
+fn share_amount(amount: u64, supply: u64, assets: u64, round_up: bool) -> u64 {
+    if supply == 0 || assets == 0 {
+        return amount;
+    }
+    let value = amount as u128 * supply as u128;
+    let shares = if round_up { value.div_ceil(assets as u128) } else { value / assets as u128 };
+    shares.min(u64::MAX as u128) as u64
+}
+
+/// Mint `shares` of the pool's share mint to `destination`, signed by the pool PDA
+fn mint_shares<'info>(
+    pool: &Account<'info, GhostPool>,
+    share_mint: &InterfaceAccount<'info, token_interface::Mint>,
+    destination: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    shares: u64,
+) -> Result<()> {
+    let pool_id = pool.pool_id.to_le_bytes();
+    let seeds = &[
+        b"ghost_pool",
+        pool.seed_authority.as_ref(),
+        &pool_id,
+        &[pool.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_accounts = token_interface::MintTo {
+        mint: share_mint.to_account_info(),
+        to: destination.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+    token_interface::mint_to(
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds),
+        shares,
+    )?;
+    msg!("Minted {} shares", shares);
+    Ok(())
+}
+
+/// Burn the shares a withdrawal of `amount` is worth from the user's share account, in a
+/// share pool; `assets` is the pool's net_assets with `amount` still in them
+fn burn_shares<'info>(
+    pool: &GhostPool,
+    share_mint: Option<&InterfaceAccount<'info, token_interface::Mint>>,
+    share_account: Option<&InterfaceAccount<'info, token_interface::TokenAccount>>,
+    user: &Signer<'info>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+    amount: u64,
+    assets: u64,
+) -> Result<()> {
+    if !pool.issues_shares() {
+        return Ok(());
+    }
+    let (Some(share_mint), Some(share_account), Some(token_program)) = (share_mint, share_account, token_program)
+    else {
+        return err!(ErrorCode::ShareAccountRequired);
+    };
+    let shares = share_amount(amount, share_mint.supply, assets, true);
+    let cpi_accounts = token_interface::Burn {
+        mint: share_mint.to_account_info(),
+        from: share_account.to_account_info(),
+        authority: user.to_account_info(),
+    };
+    token_interface::burn(CpiContext::new(token_program.to_account_info(), cpi_accounts), shares)?;
+    msg!("Burned {} shares", shares);
+    Ok(())
+}
+
+/// Wrapped SOL, under either token program
+fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == anchor_spl::token::spl_token::native_mint::ID
+        || *mint == anchor_spl::token_2022::spl_token_2022::native_mint::ID
+}
+
+/// Whether a token account of `token_program` can take a payout in `mint` right now
+/// A failed transfer CPI aborts the whole instruction, so payouts check up front
+fn is_payable(account: &AccountInfo, mint: &Pubkey, token_program: &Pubkey) -> bool {
+    if account.owner != token_program {
+        return false;
+    }
+    let Ok(data) = account.try_borrow_data() else {
+        return false;
+    };
+    match token_interface::TokenAccount::try_deserialize(&mut &data[..]) {
+        Ok(token_account) => token_account.mint == *mint && !token_account.is_frozen(),
+        Err(_) => false,
+    }
+}
+
+/// Whether `destination` is still owned by the owner the withdrawal was authorized for
+/// Token accounts can change owner between authorize and payout, so a payout to one that
+/// did waits for retry_payout like any undeliverable one. Rotator payouts were authorized
+/// for the final recipient and recovery payouts aren't bound, so neither is checked here
+fn pays_authorized_owner(pool: &GhostPool, pending: &PendingWithdrawal, destination: &AccountInfo) -> bool {
+    if pending.destination_owner == 0 || destination.key() == pool.rotator_token_account {
+        return true;
+    }
+    let Ok(data) = destination.try_borrow_data() else {
+        return false;
+    };
+    match token_interface::TokenAccount::try_deserialize(&mut &data[..]) {
+        Ok(token_account) => pubkey_fingerprint(&token_account.owner) == pending.destination_owner,
+        Err(_) => false,
+    }
+}
+
+/// Whether the running instruction is a top-level ghost_pool instruction
+/// `#[arcium_callback]` checks that Arcium's callback_computation precedes a callback, but
+/// not who invoked it: a program placed after callback_computation could CPI into the
+/// callback and run its own logic around the state write. Arcium submits callbacks as
+/// top-level instructions, so guarding the callback contexts with this rejects any wrapper
+fn invoked_top_level(instructions_sysvar: &AccountInfo) -> bool {
+    if get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT {
+        return false;
+    }
+    let Ok(index) = load_current_index_checked(instructions_sysvar) else {
+        return false;
+    };
+    load_instruction_at_checked(index as usize, instructions_sysvar)
+        .is_ok_and(|ix| ix.program_id == crate::ID)
+}
+
+/// Emit `event` in the pool's `EventMode`
+fn emit_pool_event<E: PackedEvent>(mode: EventMode, event: E) {
+    match mode {
+        EventMode::Anchor => emit!(event),
+        EventMode::Packed => {
+            let mut data = Vec::with_capacity(96);
+            data.extend_from_slice(&PACKED_EVENT_MAGIC);
+            data.push(E::TAG);
+            event.pack(&mut data);
+            sol_log_data(&[&data]);
+        }
+    }
+}
+
+/// Balance of an SPL or Token-2022 token account, read from the raw data so it reflects
+/// transfers made earlier in the instruction (base layout: mint, owner, amount)
+fn token_amount(account: &AccountInfo) -> u64 {
+    account
+        .try_borrow_data()
+        .ok()
+        .and_then(|data| data.get(64..72).map(|b| u64::from_le_bytes(b.try_into().unwrap())))
+        .unwrap_or(0)
+}
+
+/// Commitment to the password hash as it was submitted to MPC (ciphertext + nonce)
+fn password_commitment(encrypted_password_hash: &[u8; 32], nonce: u128) -> [u8; 32] {
+    hashv(&[b"ghost-pool/receipt/password".as_ref(), encrypted_password_hash, &nonce.to_le_bytes()]).to_bytes()
+}
+
+/// Hiding commitment to a deposit amount
+pub fn amount_commitment(amount: u64, blinding: &[u8; 32]) -> [u8; 32] {
+    hashv(&[b"ghost-pool/receipt/amount".as_ref(), &amount.to_le_bytes(), blinding]).to_bytes()
+}
+
+/// Read a u64 field of a Kamino Reserve account at a fixed offset
+fn reserve_u64(reserve: &AccountInfo, offset: usize) -> Result<u64> {
+    let data = reserve.try_borrow_data()?;
+    let bytes = data.get(offset..offset + 8).ok_or(ErrorCode::InvalidReserve)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Read a pubkey field of a Kamino Reserve account at a fixed offset
+fn reserve_pubkey(reserve: &AccountInfo, offset: usize) -> Result<Pubkey> {
+    let data = reserve.try_borrow_data()?;
+    let bytes = data.get(offset..offset + 32).ok_or(ErrorCode::InvalidReserve)?;
+    Ok(Pubkey::new_from_array(bytes.try_into().unwrap()))
+}
+
+/// Reserve exchange rate, liquidity per cToken * 1e6
+fn reserve_exchange_rate(reserve: &AccountInfo) -> Result<u64> {
+    exchange_rate_in(&reserve.try_borrow_data()?).ok_or_else(|| ErrorCode::InvalidReserve.into())
+}
+
+/// Exchange rate in a reserve account's data, liquidity per cToken * 1e6
+/// None if the data is too short for the layout. Public so off-chain callers quote the rate
+/// the program books at
+#[cfg(not(feature = "kamino-mainnet"))]
+pub fn exchange_rate_in(data: &[u8]) -> Option<u64> {
+    let bytes = data.get(reserve_layout::EXCHANGE_RATE..reserve_layout::EXCHANGE_RATE + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Exchange rate in a reserve account's data, liquidity per cToken * 1e6
+/// KLend stores no rate: it is total liquidity (available + borrowed - unclaimed fees)
+/// over the collateral mint supply, 1:1 while the reserve is empty. None if the data is too
+/// short for the layout or the rate doesn't fit a u64
+#[cfg(feature = "kamino-mainnet")]
+pub fn exchange_rate_in(data: &[u8]) -> Option<u64> {
+    use reserve_layout::*;
+    let u64_at = |offset: usize| Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().unwrap()));
+    let u128_at = |offset: usize| Some(u128::from_le_bytes(data.get(offset..offset + 16)?.try_into().unwrap()));
+    let fees_sf = u128_at(ACCUMULATED_PROTOCOL_FEES_SF)?
+        .saturating_add(u128_at(ACCUMULATED_REFERRER_FEES_SF)?)
+        .saturating_add(u128_at(PENDING_REFERRER_FEES_SF)?);
+    let total_liquidity = (u64_at(AVAILABLE_AMOUNT)? as u128)
+        .saturating_add(u128_at(BORROWED_AMOUNT_SF)? >> FRACTION_BITS)
+        .saturating_sub(fees_sf >> FRACTION_BITS);
+    let collateral_supply = u64_at(COLLATERAL_MINT_TOTAL_SUPPLY)?;
+    if collateral_supply == 0 {
+        return Some(1_000_000);
+    }
+    u64::try_from(total_liquidity * 1_000_000 / collateral_supply as u128).ok()
+}
+
+/// Liquidity the reserve can pay out right now (the mock's total less what is borrowed)
+#[cfg(not(feature = "kamino-mainnet"))]
+fn reserve_available_liquidity(reserve: &AccountInfo) -> Result<u64> {
+    let total_liquidity = reserve_u64(reserve, reserve_layout::TOTAL_LIQUIDITY)?;
+    Ok(total_liquidity.saturating_sub(reserve_u64(reserve, reserve_layout::BORROWED_LIQUIDITY)?))
+}
+
+/// Liquidity the reserve can pay out right now (KLend's un-borrowed liquidity)
+#[cfg(feature = "kamino-mainnet")]
+fn reserve_available_liquidity(reserve: &AccountInfo) -> Result<u64> {
+    reserve_u64(reserve, reserve_layout::AVAILABLE_AMOUNT)
+}
+
+/// Price and exponent from a fully verified Pyth PriceUpdateV2 account,
+/// rejected if older than MAX_ORACLE_AGE_SECS
+fn oracle_price(oracle: &AccountInfo, now: i64) -> Result<(u64, i32)> {
+    require_keys_eq!(*oracle.owner, PYTH_RECEIVER_PROGRAM_ID, ErrorCode::InvalidOracle);
+    let data = oracle.try_borrow_data()?;
+    require!(
+        data.get(PYTH_VERIFICATION_LEVEL_OFFSET) == Some(&PYTH_VERIFICATION_FULL),
+        ErrorCode::InvalidOracle
+    );
+    let field = |offset: usize, len: usize| data.get(offset..offset + len).ok_or(ErrorCode::InvalidOracle);
+    let price = i64::from_le_bytes(field(PYTH_PRICE_OFFSET, 8)?.try_into().unwrap());
+    let exponent = i32::from_le_bytes(field(PYTH_EXPONENT_OFFSET, 4)?.try_into().unwrap());
+    let publish_time = i64::from_le_bytes(field(PYTH_PUBLISH_TIME_OFFSET, 8)?.try_into().unwrap());
+    require!(price > 0, ErrorCode::InvalidOracle);
+    require!(now - publish_time <= MAX_ORACLE_AGE_SECS, ErrorCode::StaleOracle);
+    Ok((price as u64, exponent))
+}
+
+/// Base units of the output mint that `amount_in` base units of the input mint are worth
+/// at the given (price, exponent) pairs
+fn oracle_quote(
+    amount_in: u64,
+    (price_in, expo_in): (u64, i32),
+    decimals_in: u8,
+    (price_out, expo_out): (u64, i32),
+    decimals_out: u8,
+) -> Result<u64> {
+    // out = in * price_in * 10^expo_in / (price_out * 10^expo_out) * 10^(decimals_out - decimals_in)
+    let scale = expo_in - expo_out + decimals_out as i32 - decimals_in as i32;
+    let factor = 10u128.checked_pow(scale.unsigned_abs()).ok_or(ErrorCode::InvalidOracle)?;
+    let mut numerator = amount_in as u128 * price_in as u128;
+    let mut denominator = price_out as u128;
+    if scale >= 0 {
+        numerator = numerator.checked_mul(factor).ok_or(ErrorCode::InvalidOracle)?;
+    } else {
+        denominator = denominator.checked_mul(factor).ok_or(ErrorCode::InvalidOracle)?;
+    }
+    Ok(u64::try_from(numerator / denominator).map_err(|_| ErrorCode::InvalidOracle)?)
+}
+
+/// USDC value of `collateral` cTokens at `exchange_rate` (liquidity per cToken * 1e6)
+fn collateral_value(collateral: u64, exchange_rate: u64) -> u64 {
+    (collateral as u128 * exchange_rate as u128 / 1_000_000) as u64
+}
+
+/// Accounts for a Kamino deposit/redeem CPI
+/// Mock Kamino is called through its typed `cpi` module; KLend, which has no crate to link,
+/// gets the same instruction names (hence discriminators), its accounts ordered per
+/// instruction with separate collateral/liquidity token programs and the instructions sysvar
+struct ReserveCpi<'info> {
+    owner: AccountInfo<'info>,
+    lending_market: AccountInfo<'info>,
+    lending_market_authority: AccountInfo<'info>,
+    reserve: AccountInfo<'info>,
+    liquidity_mint: AccountInfo<'info>,
+    collateral_mint: AccountInfo<'info>,
+    liquidity_supply: AccountInfo<'info>,
+    user_liquidity: AccountInfo<'info>,
+    user_collateral: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    #[cfg_attr(not(feature = "kamino-mainnet"), allow(dead_code))] // KLend only
+    instructions_sysvar: AccountInfo<'info>,
+    kamino_program: AccountInfo<'info>,
+}
+
+impl ReserveCpi<'_> {
+    /// Deposit `amount` of liquidity from user_liquidity, minting cTokens to user_collateral
+    fn deposit_liquidity(&self, amount: u64, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        self.validate()?;
+        #[cfg(not(feature = "kamino-mainnet"))]
+        {
+            self.refresh()?;
+            let accounts = mock_kamino::cpi::accounts::DepositReserveLiquidity {
+                owner: self.owner.clone(),
+                lending_market: self.lending_market.clone(),
+                lending_market_authority: self.lending_market_authority.clone(),
+                reserve: self.reserve.clone(),
+                liquidity_mint: self.liquidity_mint.clone(),
+                collateral_mint: self.collateral_mint.clone(),
+                reserve_liquidity_supply: self.liquidity_supply.clone(),
+                user_liquidity: self.user_liquidity.clone(),
+                user_collateral: self.user_collateral.clone(),
+                token_program: self.token_program.clone(),
+            };
+            mock_kamino::cpi::deposit_reserve_liquidity(
+                CpiContext::new_with_signer(self.kamino_program.clone(), accounts, signer_seeds),
+                amount,
+            )
+        }
+        #[cfg(feature = "kamino-mainnet")]
+        {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.owner.key(), true),
+                AccountMeta::new(self.reserve.key(), false),
+                AccountMeta::new_readonly(self.lending_market.key(), false),
+                AccountMeta::new_readonly(self.lending_market_authority.key(), false),
+                AccountMeta::new_readonly(self.liquidity_mint.key(), false),
+                AccountMeta::new(self.liquidity_supply.key(), false),
+                AccountMeta::new(self.collateral_mint.key(), false),
+                AccountMeta::new(self.user_liquidity.key(), false),
+                AccountMeta::new(self.user_collateral.key(), false),
+                AccountMeta::new_readonly(self.token_program.key(), false), // collateral token program
+                AccountMeta::new_readonly(self.token_program.key(), false), // liquidity token program
+                AccountMeta::new_readonly(self.instructions_sysvar.key(), false),
+            ];
+            let discriminator = mock_kamino::instruction::DepositReserveLiquidity::DISCRIMINATOR;
+            self.invoke(discriminator, amount, accounts, signer_seeds)
+        }
+    }
+
+    /// Redeem `amount` cTokens from user_collateral, paying liquidity to user_liquidity
+    fn redeem_collateral(&self, amount: u64, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        self.validate()?;
+        #[cfg(not(feature = "kamino-mainnet"))]
+        {
+            self.refresh()?;
+            let accounts = mock_kamino::cpi::accounts::RedeemReserveCollateral {
+                owner: self.owner.clone(),
+                lending_market: self.lending_market.clone(),
+                lending_market_authority: self.lending_market_authority.clone(),
+                reserve: self.reserve.clone(),
+                liquidity_mint: self.liquidity_mint.clone(),
+                collateral_mint: self.collateral_mint.clone(),
+                reserve_liquidity_supply: self.liquidity_supply.clone(),
+                user_liquidity: self.user_liquidity.clone(),
+                user_collateral: self.user_collateral.clone(),
+                token_program: self.token_program.clone(),
+            };
+            mock_kamino::cpi::redeem_reserve_collateral(
+                CpiContext::new_with_signer(self.kamino_program.clone(), accounts, signer_seeds),
+                amount,
+            )
+        }
+        #[cfg(feature = "kamino-mainnet")]
+        {
+            let accounts = vec![
+                AccountMeta::new_readonly(self.owner.key(), true),
+                AccountMeta::new_readonly(self.lending_market.key(), false),
+                AccountMeta::new(self.reserve.key(), false),
+                AccountMeta::new_readonly(self.lending_market_authority.key(), false),
+                AccountMeta::new_readonly(self.liquidity_mint.key(), false),
+                AccountMeta::new(self.collateral_mint.key(), false),
+                AccountMeta::new(self.liquidity_supply.key(), false),
+                AccountMeta::new(self.user_collateral.key(), false),
+                AccountMeta::new(self.user_liquidity.key(), false),
+                AccountMeta::new_readonly(self.token_program.key(), false), // collateral token program
+                AccountMeta::new_readonly(self.token_program.key(), false), // liquidity token program
+                AccountMeta::new_readonly(self.instructions_sysvar.key(), false),
+            ];
+            let discriminator = mock_kamino::instruction::RedeemReserveCollateral::DISCRIMINATOR;
+            self.invoke(discriminator, amount, accounts, signer_seeds)
+        }
+    }
+
+    /// Mock Kamino's refresh_reserve, which each deposit/redeem needs earlier in the same slot
+    /// KLend's refresh also reads the reserve's oracles, which these accounts don't carry, so
+    /// on mainnet it goes in the transaction ahead of the ghost_pool instruction instead
+    #[cfg(not(feature = "kamino-mainnet"))]
+    fn refresh(&self) -> Result<()> {
+        let accounts = mock_kamino::cpi::accounts::RefreshReserve {
+            reserve: self.reserve.clone(),
+            lending_market: self.lending_market.clone(),
+        };
+        mock_kamino::cpi::refresh_reserve(CpiContext::new(self.kamino_program.clone(), accounts))
+    }
+
+    /// Check the reserve accounts against the reserve itself before handing them to Kamino:
+    /// the reserve and market belong to the lending program, the mints and supply vault are
+    /// the ones the reserve records, and the authority is the market's PDA
+    fn validate(&self) -> Result<()> {
+        require_keys_eq!(*self.reserve.owner, self.kamino_program.key(), ErrorCode::InvalidReserve);
+        require_keys_eq!(*self.lending_market.owner, self.kamino_program.key(), ErrorCode::InvalidReserve);
+        require_keys_eq!(
+            reserve_pubkey(&self.reserve, reserve_layout::LENDING_MARKET)?,
+            self.lending_market.key(),
+            ErrorCode::InvalidReserve
+        );
+        require_keys_eq!(
+            reserve_pubkey(&self.reserve, reserve_layout::LIQUIDITY_MINT)?,
+            self.liquidity_mint.key(),
+            ErrorCode::InvalidReserve
+        );
+        require_keys_eq!(
+            reserve_pubkey(&self.reserve, reserve_layout::COLLATERAL_MINT)?,
+            self.collateral_mint.key(),
+            ErrorCode::InvalidReserve
+        );
+        require_keys_eq!(
+            reserve_pubkey(&self.reserve, reserve_layout::LIQUIDITY_SUPPLY)?,
+            self.liquidity_supply.key(),
+            ErrorCode::InvalidReserve
+        );
+        let (authority, _) = Pubkey::find_program_address(
+            &[LENDING_MARKET_AUTHORITY_SEED, self.lending_market.key().as_ref()],
+            self.kamino_program.key,
+        );
+        require_keys_eq!(authority, self.lending_market_authority.key(), ErrorCode::InvalidReserve);
+        Ok(())
+    }
+
+    /// KLend CPI with `accounts` and the data of Mock Kamino's instruction of the same name
+    #[cfg(feature = "kamino-mainnet")]
+    fn invoke(
+        &self,
+        discriminator: &[u8],
+        amount: u64,
+        accounts: Vec<AccountMeta>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let mut data = discriminator.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+
+        let ix = Instruction {
+            program_id: self.kamino_program.key(),
+            accounts,
+            data,
+        };
+
+        invoke_signed(
+            &ix,
+            &[
+                self.owner.clone(),
+                self.lending_market.clone(),
+                self.lending_market_authority.clone(),
+                self.reserve.clone(),
+                self.liquidity_mint.clone(),
+                self.collateral_mint.clone(),
+                self.liquidity_supply.clone(),
+                self.user_liquidity.clone(),
+                self.user_collateral.clone(),
+                self.token_program.clone(),
+                self.instructions_sysvar.clone(),
+                self.kamino_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+/// One yield-adapter CPI, dispatched on the registered venue. The vault both supplies the
+/// liquidity and owns the collateral, and signs as the venue's user. Venue accounts come
+/// from remaining accounts, reserve always third:
+/// - Mock / Kamino: lending_market, lending_market_authority, reserve, liquidity_mint,
+///   collateral_mint, liquidity_supply, instructions_sysvar
+/// - Solend: lending_market, lending_market_authority, reserve, collateral_mint,
+///   liquidity_supply, clock sysvar
+///
+/// Adding a venue means adding its arm to `deposit` and `withdraw`
+struct AdapterCpi<'a, 'info> {
+    adapter: &'a YieldAdapter,
+    vault: AccountInfo<'info>,
+    collateral: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    venue_program: AccountInfo<'info>,
+    venue_accounts: &'a [AccountInfo<'info>],
+}
+
+impl<'info> AdapterCpi<'_, 'info> {
+    fn deposit(&self, amount: u64, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        match self.adapter.venue {
+            YieldVenue::Mock | YieldVenue::Kamino => {
+                self.reserve_cpi()?.deposit_liquidity(amount, signer_seeds)
+            }
+            YieldVenue::Solend => self.token_lending(
+                TOKEN_LENDING_DEPOSIT_RESERVE_LIQUIDITY,
+                amount,
+                &self.vault,
+                &self.collateral,
+                signer_seeds,
+            ),
+            YieldVenue::MarginFi => Err(ErrorCode::YieldVenueUnsupported.into()),
+        }
+    }
+
+    fn withdraw(&self, collateral_amount: u64, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        match self.adapter.venue {
+            YieldVenue::Mock | YieldVenue::Kamino => {
+                self.reserve_cpi()?.redeem_collateral(collateral_amount, signer_seeds)
+            }
+            YieldVenue::Solend => self.token_lending(
+                TOKEN_LENDING_REDEEM_RESERVE_COLLATERAL,
+                collateral_amount,
+                &self.collateral,
+                &self.vault,
+                signer_seeds,
+            ),
+            YieldVenue::MarginFi => Err(ErrorCode::YieldVenueUnsupported.into()),
+        }
+    }
+
+    /// The `count` venue accounts, with the reserve (third) pinned to the adapter's
+    fn venue_accounts(&self, count: usize) -> Result<&[AccountInfo<'info>]> {
+        require!(self.venue_accounts.len() >= count, ErrorCode::InvalidReserve);
+        require_keys_eq!(self.venue_accounts[2].key(), self.adapter.reserve, ErrorCode::InvalidReserve);
+        Ok(&self.venue_accounts[..count])
+    }
+
+    fn reserve_cpi(&self) -> Result<ReserveCpi<'info>> {
+        let accounts = self.venue_accounts(7)?;
+        Ok(ReserveCpi {
+            owner: self.vault.clone(),
+            lending_market: accounts[0].clone(),
+            lending_market_authority: accounts[1].clone(),
+            reserve: accounts[2].clone(),
+            liquidity_mint: accounts[3].clone(),
+            collateral_mint: accounts[4].clone(),
+            liquidity_supply: accounts[5].clone(),
+            user_liquidity: self.vault.clone(),
+            user_collateral: self.collateral.clone(),
+            token_program: self.token_program.clone(),
+            instructions_sysvar: accounts[6].clone(),
+            kamino_program: self.venue_program.clone(),
+        })
+    }
+
+    /// SPL token-lending deposit/redeem: `source` is debited, `destination` credited
+    fn token_lending(
+        &self,
+        tag: u8,
+        amount: u64,
+        source: &AccountInfo<'info>,
+        destination: &AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let accounts = self.venue_accounts(6)?;
+        let (lending_market, lending_market_authority, reserve) = (&accounts[0], &accounts[1], &accounts[2]);
+        let (collateral_mint, liquidity_supply, clock) = (&accounts[3], &accounts[4], &accounts[5]);
+
+        // Deposit moves liquidity into the supply and mints collateral; redeem the reverse
+        let (first, second) = if tag == TOKEN_LENDING_DEPOSIT_RESERVE_LIQUIDITY {
+            (liquidity_supply, collateral_mint)
+        } else {
+            (collateral_mint, liquidity_supply)
+        };
+
+        let mut data = vec![tag];
+        data.extend_from_slice(&amount.to_le_bytes());
+
+        let ix = Instruction {
+            program_id: self.adapter.program_id,
+            accounts: vec![
+                AccountMeta::new(source.key(), false),
+                AccountMeta::new(destination.key(), false),
+                AccountMeta::new(reserve.key(), false),
+                AccountMeta::new(first.key(), false),
+                AccountMeta::new(second.key(), false),
+                AccountMeta::new_readonly(lending_market.key(), false),
+                AccountMeta::new_readonly(lending_market_authority.key(), false),
+                AccountMeta::new_readonly(self.vault.key(), true), // user_transfer_authority
+                AccountMeta::new_readonly(clock.key(), false),
+                AccountMeta::new_readonly(self.token_program.key(), false),
+            ],
+            data,
+        };
+
+        invoke_signed(
+            &ix,
+            &[
+                source.clone(),
+                destination.clone(),
+                reserve.clone(),
+                first.clone(),
+                second.clone(),
+                lending_market.clone(),
+                lending_market_authority.clone(),
+                self.vault.clone(),
+                clock.clone(),
+                self.token_program.clone(),
+                self.venue_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+/// Redeem cash-strategy cTokens into the vault, signed by the pool PDA
+/// Retires principal pro rata and books the rest of the payout as cash yield
+fn redeem_cash<'info>(
+    pool: &mut Account<'info, GhostPool>,
+    vault: &mut Account<'info, TokenAccount>,
+    cash_collateral: &mut Account<'info, TokenAccount>,
+    cpi: ReserveCpi<'info>,
+    collateral_amount: u64,
+) -> Result<()> {
+    let collateral_before = cash_collateral.amount;
+    let vault_before = vault.amount;
+
+    msg!("Redeeming {} cTokens from the cash strategy", collateral_amount);
+
+    let pool_id = pool.pool_id.to_le_bytes();
+    let seeds = &[b"ghost_pool".as_ref(), pool.seed_authority.as_ref(), &pool_id, &[pool.bump]];
+    cpi.redeem_collateral(collateral_amount, &[seeds])?;
+
+    vault.reload()?;
+    cash_collateral.reload()?;
+    let liquidity_amount = vault.amount.saturating_sub(vault_before);
+
+    let before = pool.counters();
+    let principal = (pool.cash_principal as u128 * collateral_amount as u128
+        / collateral_before as u128) as u64;
+    let yield_amount = liquidity_amount.saturating_sub(principal);
+    pool.cash_principal = pool.cash_principal.saturating_sub(principal);
+    pool.cash_yield_realized += yield_amount;
+    pool.check_invariants(&before, StateWrite::None, Some(&vault.to_account_info()));
+
+    emit!(CashRedeemedEvent {
+        pool: pool.key(),
+        collateral_amount,
+        liquidity_amount,
+        yield_amount,
+    });
+
+    Ok(())
 }
@@ -1,10 +1,35 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Burn, Transfer};
 
 declare_id!("F7rKnHPcXGW3tEeuvMvaTdd9j1B79uL9tFFE3fwetNng");
 
+/// Current Reserve account version; migrate_reserve upgrades older reserves to it
+pub const RESERVE_VERSION: u8 = 5;
+
+/// Borrow rate new and migrated reserves start with at any utilization (10% APR, in basis
+/// points); update_reserve_config sets a curve
+pub const DEFAULT_BORROW_RATE_BPS: u64 = 1_000;
+
+/// Scale of Reserve::cumulative_borrow_rate and Obligation::cumulative_borrow_rate
+pub const BORROW_RATE_SCALE: u128 = 1_000_000_000_000;
+
+/// ~2 slots/second
+const SLOTS_PER_YEAR: u64 = 63_000_000;
+
+/// Reserves a MarketOverview tracks per lending market
+pub const MAX_MARKET_RESERVES: usize = 8;
+
+/// ReserveConfig::pause_flags bits
+pub const RESERVE_PAUSE_DEPOSITS: u8 = 1 << 0;
+pub const RESERVE_PAUSE_REDEEMS: u8 = 1 << 1;
+pub const RESERVE_PAUSE_BORROWS: u8 = 1 << 2;
+
 /// Mock Kamino Lending Program
-/// Simulates Kamino's deposit/withdraw flow with cToken issuance and mock yield
+/// Simulates Kamino's deposit/withdraw flow with cToken issuance and mock yield, plus
+/// borrows whose interest accrues to depositors, so reserves can run short of liquidity.
+/// Like KLend, every reserve operation needs a refresh_reserve earlier in the same slot,
+/// and leaves the reserve stale for the next one
 #[program]
 pub mod mock_kamino {
     use super::*;
@@ -20,6 +45,8 @@ pub mod mock_kamino {
     }
 
     /// Initialize a new reserve (e.g., USDC reserve)
+    /// A market holds one reserve per liquidity mint, each with its own collateral mint, and
+    /// lists them in its MarketOverview
     pub fn init_reserve(
         ctx: Context<InitReserve>,
         initial_exchange_rate: u64, // e.g., 1_000_000 = 1:1
@@ -35,11 +62,62 @@ pub mod mock_kamino {
         reserve.total_liquidity = 0;
         reserve.total_collateral = 0;
         reserve.yield_rate_bps = 500; // 5% APY in basis points (for mock)
+        reserve.version = RESERVE_VERSION;
+        reserve.borrowed_liquidity = 0;
+        reserve.base_borrow_rate_bps = DEFAULT_BORROW_RATE_BPS;
+        reserve.cumulative_borrow_rate = BORROW_RATE_SCALE;
+        reserve.borrow_rate_slope_bps = 0;
+        reserve.deposit_cap = 0;
+        reserve.last_refresh_slot = 0;
+
+        let overview = &mut ctx.accounts.market_overview;
+        if overview.lending_market == Pubkey::default() {
+            overview.bump = ctx.bumps.market_overview;
+            overview.lending_market = ctx.accounts.lending_market.key();
+        }
+        overview.record(reserve)?;
+        overview.last_update_slot = reserve.last_update_slot;
 
         msg!("Reserve initialized for mint: {}", ctx.accounts.liquidity_mint.key());
         Ok(())
     }
 
+    /// Copy the current liquidity, borrows and exchange rate of the market's reserves (passed
+    /// as remaining accounts) into its MarketOverview, listing any it doesn't know yet
+    /// Permissionless; the overview is created on first use for markets that predate it
+    pub fn refresh_market_overview<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefreshMarketOverview<'info>>,
+    ) -> Result<()> {
+        let market_key = ctx.accounts.lending_market.key();
+        let overview = &mut ctx.accounts.market_overview;
+        if overview.lending_market == Pubkey::default() {
+            overview.bump = ctx.bumps.market_overview;
+            overview.lending_market = market_key;
+        }
+
+        for info in ctx.remaining_accounts {
+            let reserve = Account::<Reserve>::try_from(info)?;
+            require_keys_eq!(reserve.lending_market, market_key, ErrorCode::ReserveNotInMarket);
+            overview.record(&reserve)?;
+        }
+        overview.last_update_slot = Clock::get()?.slot;
+
+        msg!("Market overview: {} reserves", overview.reserve_count);
+        Ok(())
+    }
+
+    /// Accrue the reserve's yield and borrow interest up to now and mark it fresh for this slot
+    /// This matches Kamino's `refresh_reserve` instruction (the mock has no oracles to read)
+    pub fn refresh_reserve(ctx: Context<RefreshReserve>) -> Result<()> {
+        let reserve = &mut ctx.accounts.reserve;
+        let slot = Clock::get()?.slot;
+        reserve.accrue(slot)?;
+        reserve.last_refresh_slot = slot;
+
+        msg!("Reserve refreshed (rate: {})", reserve.exchange_rate);
+        Ok(())
+    }
+
     /// Deposit liquidity and receive collateral tokens (cTokens)
     /// This matches Kamino's `deposit_reserve_liquidity` instruction
     pub fn deposit_reserve_liquidity(
@@ -47,33 +125,21 @@ pub mod mock_kamino {
         liquidity_amount: u64,
     ) -> Result<()> {
         let reserve = &mut ctx.accounts.reserve;
+        let slot = Clock::get()?.slot;
+        reserve.require_fresh(slot)?;
+        reserve.require_open(slot, RESERVE_PAUSE_DEPOSITS)?;
 
-        // Update exchange rate based on time passed (mock yield accrual)
-        let current_slot = Clock::get()?.slot;
-        let slots_passed = current_slot.saturating_sub(reserve.last_update_slot);
-
-        // Mock yield: increase exchange rate by ~5% APY
-        // Assuming ~2 slots/second, ~63M slots/year
-        // 5% APY = 5e-8 per slot approximately
-        if slots_passed > 0 && reserve.total_collateral > 0 {
-            let yield_factor = 1_000_000u64 + (slots_passed * reserve.yield_rate_bps / 63_000_000);
-            reserve.exchange_rate = reserve.exchange_rate
-                .checked_mul(yield_factor)
-                .unwrap()
-                .checked_div(1_000_000)
-                .unwrap();
-        }
-        reserve.last_update_slot = current_slot;
-
-        // Calculate collateral to mint based on exchange rate
-        // collateral = liquidity * 1e6 / exchange_rate
-        let collateral_amount = liquidity_amount
-            .checked_mul(1_000_000)
-            .unwrap()
-            .checked_div(reserve.exchange_rate)
-            .unwrap();
+        let collateral_amount = reserve.collateral_for(liquidity_amount)?;
 
         require!(collateral_amount > 0, ErrorCode::ZeroCollateral);
+        let total_liquidity = reserve
+            .total_liquidity
+            .checked_add(liquidity_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            reserve.deposit_cap == 0 || total_liquidity <= reserve.deposit_cap,
+            ErrorCode::DepositCapExceeded
+        );
 
         // Transfer liquidity from user to reserve supply
         let cpi_accounts = Transfer {
@@ -108,8 +174,10 @@ pub mod mock_kamino {
         )?;
 
         // Update reserve state
-        reserve.total_liquidity = reserve.total_liquidity.checked_add(liquidity_amount).unwrap();
-        reserve.total_collateral = reserve.total_collateral.checked_add(collateral_amount).unwrap();
+        reserve.total_liquidity = total_liquidity;
+        reserve.total_collateral =
+            reserve.total_collateral.checked_add(collateral_amount).ok_or(ErrorCode::MathOverflow)?;
+        reserve.mark_stale();
 
         msg!(
             "Deposited {} liquidity, minted {} cTokens (rate: {})",
@@ -135,34 +203,19 @@ pub mod mock_kamino {
         collateral_amount: u64,
     ) -> Result<()> {
         let reserve = &mut ctx.accounts.reserve;
+        let slot = Clock::get()?.slot;
+        reserve.require_fresh(slot)?;
+        reserve.require_open(slot, RESERVE_PAUSE_REDEEMS)?;
 
-        // Update exchange rate based on time passed (mock yield accrual)
-        let current_slot = Clock::get()?.slot;
-        let slots_passed = current_slot.saturating_sub(reserve.last_update_slot);
-
-        if slots_passed > 0 && reserve.total_collateral > 0 {
-            let yield_factor = 1_000_000u64 + (slots_passed * reserve.yield_rate_bps / 63_000_000);
-            reserve.exchange_rate = reserve.exchange_rate
-                .checked_mul(yield_factor)
-                .unwrap()
-                .checked_div(1_000_000)
-                .unwrap();
-        }
-        reserve.last_update_slot = current_slot;
-
-        // Calculate liquidity to return based on exchange rate
-        // liquidity = collateral * exchange_rate / 1e6
-        let liquidity_amount = collateral_amount
-            .checked_mul(reserve.exchange_rate)
-            .unwrap()
-            .checked_div(1_000_000)
-            .unwrap();
+        let liquidity_amount = reserve.liquidity_for(collateral_amount)?;
 
         require!(liquidity_amount > 0, ErrorCode::ZeroLiquidity);
+        // Borrowed liquidity isn't in the supply vault until it is repaid
         require!(
-            liquidity_amount <= reserve.total_liquidity,
+            liquidity_amount <= reserve.available_liquidity(),
             ErrorCode::InsufficientLiquidity
         );
+        reserve.record_withdrawal(slot, liquidity_amount)?;
 
         // Burn collateral tokens from user
         let burn_accounts = Burn {
@@ -199,8 +252,11 @@ pub mod mock_kamino {
         )?;
 
         // Update reserve state
-        reserve.total_liquidity = reserve.total_liquidity.checked_sub(liquidity_amount).unwrap();
-        reserve.total_collateral = reserve.total_collateral.checked_sub(collateral_amount).unwrap();
+        reserve.total_liquidity =
+            reserve.total_liquidity.checked_sub(liquidity_amount).ok_or(ErrorCode::MathOverflow)?;
+        reserve.total_collateral =
+            reserve.total_collateral.checked_sub(collateral_amount).ok_or(ErrorCode::MathOverflow)?;
+        reserve.mark_stale();
 
         msg!(
             "Redeemed {} cTokens for {} liquidity (rate: {})",
@@ -219,11 +275,36 @@ pub mod mock_kamino {
         Ok(())
     }
 
-    /// Admin function to manually accrue yield (for testing)
-    pub fn accrue_yield(ctx: Context<AccrueYield>, additional_liquidity: u64) -> Result<()> {
+    /// Borrow liquidity from the reserve's supply against `owner`'s obligation
+    /// Mirrors Kamino's `borrow_obligation_liquidity`, minus collateral and health checks:
+    /// only the market authority borrows, to drive utilization in tests. The debt accrues
+    /// interest at the reserve's borrow rate, which raises the exchange rate for depositors
+    pub fn borrow_obligation_liquidity(
+        ctx: Context<BorrowObligationLiquidity>,
+        liquidity_amount: u64,
+    ) -> Result<()> {
         let reserve = &mut ctx.accounts.reserve;
+        let slot = Clock::get()?.slot;
+        reserve.require_fresh(slot)?;
+        reserve.require_open(slot, RESERVE_PAUSE_BORROWS)?;
+
+        require!(liquidity_amount > 0, ErrorCode::ZeroLiquidity);
+        require!(
+            liquidity_amount <= reserve.available_liquidity(),
+            ErrorCode::InsufficientLiquidity
+        );
+
+        let obligation = &mut ctx.accounts.obligation;
+        if obligation.reserve == Pubkey::default() {
+            obligation.bump = ctx.bumps.obligation;
+            obligation.reserve = reserve.key();
+            obligation.owner = ctx.accounts.owner.key();
+            obligation.cumulative_borrow_rate = reserve.cumulative_borrow_rate;
+        }
+        obligation.refresh(reserve)?;
+        obligation.borrowed_amount =
+            obligation.borrowed_amount.checked_add(liquidity_amount).ok_or(ErrorCode::MathOverflow)?;
 
-        // Mint additional liquidity to the reserve (simulating yield from borrowers)
         let market_key = ctx.accounts.lending_market.key();
         let seeds = &[
             b"lending_market_authority",
@@ -232,17 +313,206 @@ pub mod mock_kamino {
         ];
         let signer_seeds = &[&seeds[..]];
 
+        let transfer_accounts = Transfer {
+            from: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+            to: ctx.accounts.user_liquidity.to_account_info(),
+            authority: ctx.accounts.lending_market_authority.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            liquidity_amount,
+        )?;
+
+        reserve.borrowed_liquidity =
+            reserve.borrowed_liquidity.checked_add(liquidity_amount).ok_or(ErrorCode::MathOverflow)?;
+        reserve.mark_stale();
+
+        msg!(
+            "Borrowed {} liquidity ({} borrowed of {})",
+            liquidity_amount,
+            reserve.borrowed_liquidity,
+            reserve.total_liquidity
+        );
+
+        emit!(BorrowEvent {
+            reserve: reserve.key(),
+            obligation: obligation.key(),
+            liquidity_amount,
+            borrowed_liquidity: reserve.borrowed_liquidity,
+            exchange_rate: reserve.exchange_rate,
+        });
+
+        Ok(())
+    }
+
+    /// Repay an obligation's debt, interest included; u64::MAX repays all of it
+    /// This matches Kamino's `repay_obligation_liquidity` instruction
+    pub fn repay_obligation_liquidity(
+        ctx: Context<RepayObligationLiquidity>,
+        liquidity_amount: u64,
+    ) -> Result<()> {
+        let reserve = &mut ctx.accounts.reserve;
+        let slot = Clock::get()?.slot;
+        reserve.require_fresh(slot)?;
+        // Repaying is never paused, but injected failures apply
+        reserve.require_open(slot, 0)?;
+
+        let obligation = &mut ctx.accounts.obligation;
+        obligation.refresh(reserve)?;
+        let repay_amount = liquidity_amount.min(obligation.borrowed_amount);
+        require!(repay_amount > 0, ErrorCode::ZeroLiquidity);
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_liquidity.to_account_info(),
+            to: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+            repay_amount,
+        )?;
+
+        obligation.borrowed_amount -= repay_amount;
+        // Per-obligation rounding can leave the reserve's total a unit off; never below zero
+        reserve.borrowed_liquidity = reserve.borrowed_liquidity.saturating_sub(repay_amount);
+        reserve.mark_stale();
+
+        msg!(
+            "Repaid {} liquidity ({} still owed, {} borrowed of {})",
+            repay_amount,
+            obligation.borrowed_amount,
+            reserve.borrowed_liquidity,
+            reserve.total_liquidity
+        );
+
+        emit!(RepayEvent {
+            reserve: reserve.key(),
+            obligation: obligation.key(),
+            liquidity_amount: repay_amount,
+            borrowed_liquidity: reserve.borrowed_liquidity,
+            exchange_rate: reserve.exchange_rate,
+        });
+
+        Ok(())
+    }
+
+    /// Set a reserve's mock yield rate, deposit cap and borrow rate curve (market authority only)
+    /// Accrues at the old rates first, so a change applies from this slot on
+    pub fn update_reserve_config(
+        ctx: Context<UpdateReserveConfig>,
+        yield_rate_bps: u64,
+        deposit_cap: u64,
+        base_borrow_rate_bps: u64,
+        borrow_rate_slope_bps: u64,
+    ) -> Result<()> {
+        let reserve = &mut ctx.accounts.reserve;
+        reserve.accrue(Clock::get()?.slot)?;
+
+        reserve.yield_rate_bps = yield_rate_bps;
+        reserve.deposit_cap = deposit_cap;
+        reserve.base_borrow_rate_bps = base_borrow_rate_bps;
+        reserve.borrow_rate_slope_bps = borrow_rate_slope_bps;
+
+        msg!(
+            "Reserve config: yield {} bps, cap {}, borrow rate {} + {} bps x utilization",
+            yield_rate_bps,
+            deposit_cap,
+            base_borrow_rate_bps,
+            borrow_rate_slope_bps
+        );
+
+        emit!(ReserveConfigUpdatedEvent {
+            reserve: reserve.key(),
+            yield_rate_bps,
+            deposit_cap,
+            base_borrow_rate_bps,
+            borrow_rate_slope_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Set a reserve's pauses, per-slot withdrawal cap and injected failures (market authority
+    /// only), for tests of how ghost_pool copes with a venue that rejects or rations redemptions
+    pub fn update_reserve_limits(ctx: Context<UpdateReserveConfig>, config: ReserveConfig) -> Result<()> {
+        let reserve = &mut ctx.accounts.reserve;
+        reserve.config = config;
+
+        msg!(
+            "Reserve limits: paused {:#04b}, {} per slot, failing until slot {}",
+            config.pause_flags,
+            config.max_withdraw_per_slot,
+            config.fail_until_slot
+        );
+
+        emit!(ReserveLimitsUpdatedEvent {
+            reserve: reserve.key(),
+            pause_flags: config.pause_flags,
+            max_withdraw_per_slot: config.max_withdraw_per_slot,
+            fail_until_slot: config.fail_until_slot,
+        });
+
+        Ok(())
+    }
+
+    /// Admin function to manually accrue yield (for testing)
+    /// Grow a reserve created under an older layout to the current one (market authority only)
+    pub fn migrate_reserve(ctx: Context<MigrateReserve>) -> Result<()> {
+        let info = ctx.accounts.reserve.to_account_info();
+        let from_len = info.data_len();
+        if from_len < Reserve::SPACE {
+            let shortfall = Rent::get()?
+                .minimum_balance(Reserve::SPACE)
+                .saturating_sub(info.lamports());
+            if shortfall > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: info.clone(),
+                        },
+                    ),
+                    shortfall,
+                )?;
+            }
+            info.resize(Reserve::SPACE)?;
+        }
+
+        let mut reserve = Reserve::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(reserve.version <= RESERVE_VERSION, ErrorCode::UnsupportedReserveVersion);
+        // 0 -> 1: the version byte itself is the only addition
+        // 1 -> 2: borrow state, starting with nothing borrowed
+        if reserve.version < 2 {
+            reserve.base_borrow_rate_bps = DEFAULT_BORROW_RATE_BPS;
+            reserve.cumulative_borrow_rate = BORROW_RATE_SCALE;
+        }
+        // 2 -> 3: flat borrow rate (no slope) and no deposit cap, both zero after the resize
+        // 3 -> 4: last_refresh_slot, zero (stale) after the resize
+        // 4 -> 5: ReserveConfig and the withdrawal window, zero (no limits) after the resize
+        reserve.version = RESERVE_VERSION;
+        reserve.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!("Reserve migrated to v{} ({} -> {} bytes)", RESERVE_VERSION, from_len, Reserve::SPACE);
+        Ok(())
+    }
+
+    pub fn accrue_yield(ctx: Context<AccrueYield>, additional_liquidity: u64) -> Result<()> {
+        let reserve = &mut ctx.accounts.reserve;
+
         // For testing, we just increase the exchange rate directly
         // In reality, yield comes from borrower interest payments
         let old_rate = reserve.exchange_rate;
-        let rate_increase = additional_liquidity
-            .checked_mul(1_000_000)
-            .unwrap()
-            .checked_div(reserve.total_collateral.max(1))
-            .unwrap();
+        let rate_increase = mul_div(additional_liquidity, 1_000_000, reserve.total_collateral.max(1) as u128)?;
 
-        reserve.exchange_rate = reserve.exchange_rate.checked_add(rate_increase).unwrap();
-        reserve.total_liquidity = reserve.total_liquidity.checked_add(additional_liquidity).unwrap();
+        reserve.exchange_rate =
+            reserve.exchange_rate.checked_add(rate_increase).ok_or(ErrorCode::MathOverflow)?;
+        reserve.total_liquidity =
+            reserve.total_liquidity.checked_add(additional_liquidity).ok_or(ErrorCode::MathOverflow)?;
 
         msg!(
             "Accrued yield: {} liquidity, rate {} -> {}",
@@ -255,6 +525,16 @@ pub mod mock_kamino {
     }
 }
 
+/// `value * numerator / denominator` through u128, failing with MathOverflow rather than
+/// panicking when the product or the u64 result doesn't fit (or `denominator` is zero)
+pub fn mul_div(value: u64, numerator: u128, denominator: u128) -> Result<u64> {
+    let quotient = (value as u128)
+        .checked_mul(numerator)
+        .and_then(|product| product.checked_div(denominator))
+        .ok_or(ErrorCode::MathOverflow)?;
+    u64::try_from(quotient).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
 // ============ Accounts ============
 
 #[account]
@@ -264,6 +544,7 @@ pub struct LendingMarket {
 }
 
 #[account]
+#[derive(Default)]
 pub struct Reserve {
     pub bump: u8,
     pub lending_market: Pubkey,
@@ -275,6 +556,212 @@ pub struct Reserve {
     pub total_liquidity: u64,
     pub total_collateral: u64,
     pub yield_rate_bps: u64,         // Annual yield in basis points
+    pub version: u8,                 // RESERVE_VERSION the account was last migrated to
+    pub borrowed_liquidity: u64,     // Outstanding borrows, interest included (part of total_liquidity)
+    pub base_borrow_rate_bps: u64,   // Annual borrow interest at 0% utilization, in basis points
+    pub cumulative_borrow_rate: u128, // Interest index, BORROW_RATE_SCALE at creation
+    pub borrow_rate_slope_bps: u64,  // Added to the base rate at 100% utilization
+    pub deposit_cap: u64,            // Max total_liquidity deposits can bring it to (0 = uncapped)
+    pub last_refresh_slot: u64,      // Slot of the last refresh_reserve, 0 once an operation used it
+    pub config: ReserveConfig,
+    pub withdraw_window_slot: u64,   // Slot withdrawn_in_window counts redemptions for
+    pub withdrawn_in_window: u64,
+}
+
+/// Test knobs for a reserve that misbehaves (all zero: none)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct ReserveConfig {
+    /// RESERVE_PAUSE_* bits
+    pub pause_flags: u8,
+    /// Liquidity redemptions may pay out per slot (0 = uncapped)
+    pub max_withdraw_per_slot: u64,
+    /// Every operation fails with InjectedFailure before this slot. A rejected operation rolls
+    /// back with its transaction, so a per-operation countdown could never move; tests pick
+    /// how many slots of retries should fail instead
+    pub fail_until_slot: u64,
+}
+
+impl ReserveConfig {
+    pub const SPACE: usize = 1 + 8 + 8;
+}
+
+impl Reserve {
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 16 + 8 + 8 + 8
+        + ReserveConfig::SPACE + 8 + 8;
+
+    /// Reject an operation the config pauses (`pause_flag`) or fails on purpose
+    pub fn require_open(&self, slot: u64, pause_flag: u8) -> Result<()> {
+        require!(self.config.pause_flags & pause_flag == 0, ErrorCode::ReservePaused);
+        require!(slot >= self.config.fail_until_slot, ErrorCode::InjectedFailure);
+        Ok(())
+    }
+
+    /// Count a redemption paying out `liquidity_amount` against the per-slot cap
+    pub fn record_withdrawal(&mut self, slot: u64, liquidity_amount: u64) -> Result<()> {
+        if self.withdraw_window_slot != slot {
+            self.withdraw_window_slot = slot;
+            self.withdrawn_in_window = 0;
+        }
+        self.withdrawn_in_window =
+            self.withdrawn_in_window.checked_add(liquidity_amount).ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            self.config.max_withdraw_per_slot == 0 || self.withdrawn_in_window <= self.config.max_withdraw_per_slot,
+            ErrorCode::WithdrawCapExceeded
+        );
+        Ok(())
+    }
+
+    /// Operations need a refresh_reserve earlier in the same slot, as KLend does
+    pub fn require_fresh(&self, slot: u64) -> Result<()> {
+        require!(self.last_refresh_slot == slot && slot > 0, ErrorCode::ReserveStale);
+        Ok(())
+    }
+
+    /// An operation changed the reserve: the next one needs another refresh_reserve
+    pub fn mark_stale(&mut self) {
+        self.last_refresh_slot = 0;
+    }
+
+    /// Share of the liquidity lent out, in basis points
+    pub fn utilization_bps(&self) -> u64 {
+        if self.total_liquidity == 0 {
+            return 0;
+        }
+        (self.borrowed_liquidity as u128 * 10_000 / self.total_liquidity as u128).min(10_000) as u64
+    }
+
+    /// Annual borrow rate at the current utilization: base + slope * utilization
+    pub fn borrow_rate_bps(&self) -> u128 {
+        self.base_borrow_rate_bps as u128
+            + self.borrow_rate_slope_bps as u128 * self.utilization_bps() as u128 / 10_000
+    }
+
+    /// cTokens `liquidity_amount` deposited mints: liquidity * 1e6 / exchange_rate
+    pub fn collateral_for(&self, liquidity_amount: u64) -> Result<u64> {
+        mul_div(liquidity_amount, 1_000_000, self.exchange_rate as u128)
+    }
+
+    /// Liquidity `collateral_amount` cTokens redeem for: collateral * exchange_rate / 1e6
+    pub fn liquidity_for(&self, collateral_amount: u64) -> Result<u64> {
+        mul_div(collateral_amount, self.exchange_rate as u128, 1_000_000)
+    }
+
+    /// Liquidity sitting in the supply vault, i.e. not lent out
+    pub fn available_liquidity(&self) -> u64 {
+        self.total_liquidity.saturating_sub(self.borrowed_liquidity)
+    }
+
+    /// Bring the exchange rate up to `slot`: the mock yield (yield_rate_bps), then interest on
+    /// outstanding borrows, which is added to the debt and shared by the cToken holders
+    pub fn accrue(&mut self, slot: u64) -> Result<()> {
+        let slots_passed = slot.saturating_sub(self.last_update_slot);
+
+        // Mock yield: increase exchange rate by yield_rate_bps a year (5% APY by default)
+        // Assuming ~2 slots/second, ~63M slots/year
+        // 5% APY = 5e-8 per slot approximately
+        if slots_passed > 0 && self.total_collateral > 0 {
+            // u128: slots_passed * yield_rate_bps overflows u64 after a long enough gap
+            let yield_factor =
+                1_000_000 + slots_passed as u128 * self.yield_rate_bps as u128 / SLOTS_PER_YEAR as u128;
+            self.exchange_rate = mul_div(self.exchange_rate, yield_factor, 1_000_000)?;
+        }
+
+        if slots_passed > 0 && self.borrowed_liquidity > 0 {
+            // Simple interest over the elapsed slots, compounding at each accrual
+            let rate = self
+                .borrow_rate_bps()
+                .checked_mul(slots_passed as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let per_year = 10_000 * SLOTS_PER_YEAR as u128;
+            let interest = mul_div(self.borrowed_liquidity, rate, per_year)?;
+            let index_growth =
+                self.cumulative_borrow_rate.checked_mul(rate).ok_or(ErrorCode::MathOverflow)? / per_year;
+            self.cumulative_borrow_rate =
+                self.cumulative_borrow_rate.checked_add(index_growth).ok_or(ErrorCode::MathOverflow)?;
+            self.borrowed_liquidity =
+                self.borrowed_liquidity.checked_add(interest).ok_or(ErrorCode::MathOverflow)?;
+            self.total_liquidity =
+                self.total_liquidity.checked_add(interest).ok_or(ErrorCode::MathOverflow)?;
+            let rate_increase = mul_div(interest, 1_000_000, self.total_collateral.max(1) as u128)?;
+            self.exchange_rate =
+                self.exchange_rate.checked_add(rate_increase).ok_or(ErrorCode::MathOverflow)?;
+        }
+        self.last_update_slot = slot;
+        Ok(())
+    }
+}
+
+/// Per-reserve figures of one market, as of each reserve's last refresh_market_overview
+#[account]
+pub struct MarketOverview {
+    pub bump: u8,
+    pub lending_market: Pubkey,
+    pub reserve_count: u8,
+    pub reserves: [ReserveSummary; MAX_MARKET_RESERVES],  // First reserve_count are in use
+    pub last_update_slot: u64,
+}
+
+impl MarketOverview {
+    pub const SPACE: usize = 8 + 1 + 32 + 1 + ReserveSummary::SPACE * MAX_MARKET_RESERVES + 8;
+
+    /// Update `reserve`'s entry, adding it if it's new
+    pub fn record(&mut self, reserve: &Account<Reserve>) -> Result<()> {
+        let count = self.reserve_count as usize;
+        let index = match self.reserves[..count].iter().position(|entry| entry.reserve == reserve.key()) {
+            Some(index) => index,
+            None => {
+                require!(count < MAX_MARKET_RESERVES, ErrorCode::MarketFull);
+                self.reserve_count += 1;
+                count
+            }
+        };
+        self.reserves[index] = ReserveSummary {
+            reserve: reserve.key(),
+            liquidity_mint: reserve.liquidity_mint,
+            total_liquidity: reserve.total_liquidity,
+            borrowed_liquidity: reserve.borrowed_liquidity,
+            exchange_rate: reserve.exchange_rate,
+        };
+        Ok(())
+    }
+}
+
+/// One reserve's TVL (total_liquidity, in its own mint) and rate in a MarketOverview
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct ReserveSummary {
+    pub reserve: Pubkey,
+    pub liquidity_mint: Pubkey,
+    pub total_liquidity: u64,
+    pub borrowed_liquidity: u64,
+    pub exchange_rate: u64,
+}
+
+impl ReserveSummary {
+    pub const SPACE: usize = 32 + 32 + 8 + 8 + 8;
+}
+
+/// A borrower's debt to one reserve
+#[account]
+pub struct Obligation {
+    pub bump: u8,
+    pub reserve: Pubkey,
+    pub owner: Pubkey,
+    pub borrowed_amount: u64,         // Debt as of cumulative_borrow_rate, interest included
+    pub cumulative_borrow_rate: u128, // Reserve's interest index when borrowed_amount was last updated
+}
+
+impl Obligation {
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 8 + 16;
+
+    /// Roll the debt forward to the reserve's interest index
+    pub fn refresh(&mut self, reserve: &Reserve) -> Result<()> {
+        if self.cumulative_borrow_rate > 0 {
+            self.borrowed_amount =
+                mul_div(self.borrowed_amount, reserve.cumulative_borrow_rate, self.cumulative_borrow_rate)?;
+        }
+        self.cumulative_borrow_rate = reserve.cumulative_borrow_rate;
+        Ok(())
+    }
 }
 
 // ============ Contexts ============
@@ -318,7 +805,13 @@ pub struct InitReserve<'info> {
     pub liquidity_mint: Account<'info, Mint>,
 
     /// The collateral token mint (cToken) - must be created beforehand with market authority as mint authority
-    #[account(mut)]
+    /// Unminted, so no other reserve can already be issuing it
+    #[account(
+        mut,
+        constraint = collateral_mint.mint_authority == COption::Some(lending_market_authority.key())
+            @ ErrorCode::InvalidCollateralMint,
+        constraint = collateral_mint.supply == 0 @ ErrorCode::InvalidCollateralMint,
+    )]
     pub collateral_mint: Account<'info, Mint>,
 
     /// Token account to hold reserve liquidity
@@ -335,16 +828,56 @@ pub struct InitReserve<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8,
+        space = Reserve::SPACE,
         seeds = [b"reserve", lending_market.key().as_ref(), liquidity_mint.key().as_ref()],
         bump,
     )]
     pub reserve: Account<'info, Reserve>,
 
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = MarketOverview::SPACE,
+        seeds = [b"market_overview", lending_market.key().as_ref()],
+        bump,
+    )]
+    pub market_overview: Box<Account<'info, MarketOverview>>,
+
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct RefreshMarketOverview<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub lending_market: Account<'info, LendingMarket>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MarketOverview::SPACE,
+        seeds = [b"market_overview", lending_market.key().as_ref()],
+        bump,
+    )]
+    pub market_overview: Box<Account<'info, MarketOverview>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshReserve<'info> {
+    #[account(
+        mut,
+        has_one = lending_market,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
+    )]
+    pub reserve: Account<'info, Reserve>,
+
+    pub lending_market: Account<'info, LendingMarket>,
+}
+
 #[derive(Accounts)]
 pub struct DepositReserveLiquidity<'info> {
     #[account(mut)]
@@ -364,6 +897,7 @@ pub struct DepositReserveLiquidity<'info> {
         has_one = lending_market,
         has_one = liquidity_mint,
         has_one = collateral_mint,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
     )]
     pub reserve: Account<'info, Reserve>,
 
@@ -416,6 +950,7 @@ pub struct RedeemReserveCollateral<'info> {
         has_one = lending_market,
         has_one = liquidity_mint,
         has_one = collateral_mint,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
     )]
     pub reserve: Account<'info, Reserve>,
 
@@ -449,6 +984,96 @@ pub struct RedeemReserveCollateral<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct BorrowObligationLiquidity<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(constraint = lending_market.authority == owner.key() @ ErrorCode::BorrowerNotAuthority)]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    /// CHECK: PDA for signing
+    #[account(
+        seeds = [b"lending_market_authority", lending_market.key().as_ref()],
+        bump,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        has_one = lending_market,
+        has_one = liquidity_mint,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
+    )]
+    pub reserve: Account<'info, Reserve>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Obligation::SPACE,
+        seeds = [b"obligation", reserve.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub obligation: Account<'info, Obligation>,
+
+    pub liquidity_mint: Account<'info, Mint>,
+
+    /// Reserve's liquidity supply vault
+    #[account(
+        mut,
+        address = reserve.liquidity_supply,
+    )]
+    pub reserve_liquidity_supply: Account<'info, TokenAccount>,
+
+    /// Borrower's liquidity token account (destination)
+    #[account(
+        mut,
+        token::mint = liquidity_mint,
+    )]
+    pub user_liquidity: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RepayObligationLiquidity<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = liquidity_mint,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
+    )]
+    pub reserve: Account<'info, Reserve>,
+
+    /// Anyone may repay an obligation, like on Kamino
+    #[account(
+        mut,
+        has_one = reserve,
+    )]
+    pub obligation: Account<'info, Obligation>,
+
+    pub liquidity_mint: Account<'info, Mint>,
+
+    /// Reserve's liquidity supply vault
+    #[account(
+        mut,
+        address = reserve.liquidity_supply,
+    )]
+    pub reserve_liquidity_supply: Account<'info, TokenAccount>,
+
+    /// Repayer's liquidity token account (source)
+    #[account(
+        mut,
+        token::mint = liquidity_mint,
+        token::authority = owner,
+    )]
+    pub user_liquidity: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 pub struct AccrueYield<'info> {
     #[account(mut)]
@@ -460,10 +1085,47 @@ pub struct AccrueYield<'info> {
     #[account(
         mut,
         has_one = lending_market,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
     )]
     pub reserve: Account<'info, Reserve>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateReserveConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(has_one = authority)]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    #[account(
+        mut,
+        has_one = lending_market,
+        constraint = reserve.version == RESERVE_VERSION @ ErrorCode::ReserveNotMigrated,
+    )]
+    pub reserve: Account<'info, Reserve>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateReserve<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(has_one = authority)]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub liquidity_mint: Account<'info, Mint>,
+
+    /// CHECK: may predate the current Reserve layout, so it's deserialized after the resize
+    #[account(
+        mut,
+        seeds = [b"reserve", lending_market.key().as_ref(), liquidity_mint.key().as_ref()],
+        bump,
+    )]
+    pub reserve: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 // ============ Events ============
 
 #[event]
@@ -482,6 +1144,41 @@ pub struct RedeemEvent {
     pub exchange_rate: u64,
 }
 
+#[event]
+pub struct ReserveConfigUpdatedEvent {
+    pub reserve: Pubkey,
+    pub yield_rate_bps: u64,
+    pub deposit_cap: u64,
+    pub base_borrow_rate_bps: u64,
+    pub borrow_rate_slope_bps: u64,
+}
+
+#[event]
+pub struct ReserveLimitsUpdatedEvent {
+    pub reserve: Pubkey,
+    pub pause_flags: u8,
+    pub max_withdraw_per_slot: u64,
+    pub fail_until_slot: u64,
+}
+
+#[event]
+pub struct BorrowEvent {
+    pub reserve: Pubkey,
+    pub obligation: Pubkey,
+    pub liquidity_amount: u64,
+    pub borrowed_liquidity: u64,
+    pub exchange_rate: u64,
+}
+
+#[event]
+pub struct RepayEvent {
+    pub reserve: Pubkey,
+    pub obligation: Pubkey,
+    pub liquidity_amount: u64,
+    pub borrowed_liquidity: u64,
+    pub exchange_rate: u64,
+}
+
 // ============ Errors ============
 
 #[error_code]
@@ -492,4 +1189,103 @@ pub enum ErrorCode {
     ZeroLiquidity,
     #[msg("Insufficient liquidity in reserve")]
     InsufficientLiquidity,
+    #[msg("Reserve is on an older layout; run migrate_reserve first")]
+    ReserveNotMigrated,
+    #[msg("Reserve version is newer than this program")]
+    UnsupportedReserveVersion,
+    #[msg("Only the lending market authority can borrow from the mock")]
+    BorrowerNotAuthority,
+    #[msg("Deposit would exceed the reserve's deposit cap")]
+    DepositCapExceeded,
+    #[msg("Reserve is stale; call refresh_reserve first in the same slot")]
+    ReserveStale,
+    #[msg("Reserve has this operation paused")]
+    ReservePaused,
+    #[msg("Redemption would exceed the reserve's per-slot withdrawal cap")]
+    WithdrawCapExceeded,
+    #[msg("Injected failure (update_reserve_limits fail_until_slot)")]
+    InjectedFailure,
+    #[msg("Collateral mint must be unminted, with the market authority as mint authority")]
+    InvalidCollateralMint,
+    #[msg("Market overview already tracks the maximum number of reserves")]
+    MarketFull,
+    #[msg("Reserve belongs to another lending market")]
+    ReserveNotInMarket,
+    #[msg("Reserve arithmetic overflowed")]
+    MathOverflow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reserve_at(exchange_rate: u64) -> Reserve {
+        Reserve {
+            exchange_rate,
+            total_collateral: 1,
+            yield_rate_bps: 500,
+            base_borrow_rate_bps: DEFAULT_BORROW_RATE_BPS,
+            cumulative_borrow_rate: BORROW_RATE_SCALE,
+            ..Default::default()
+        }
+    }
+
+    fn overflow() -> anchor_lang::error::Error {
+        ErrorCode::MathOverflow.into()
+    }
+
+    #[test]
+    fn conversions_cover_the_u64_range() {
+        // Scaling by 1e6 in u64 overflowed past ~1.8e13 base units
+        let at_par = reserve_at(1_000_000);
+        assert_eq!(at_par.collateral_for(u64::MAX).unwrap(), u64::MAX);
+        assert_eq!(at_par.liquidity_for(u64::MAX).unwrap(), u64::MAX);
+
+        assert_eq!(reserve_at(999_999).collateral_for(u64::MAX).unwrap_err(), overflow());
+        assert_eq!(reserve_at(1_000_001).liquidity_for(u64::MAX).unwrap_err(), overflow());
+        assert_eq!(reserve_at(0).collateral_for(1).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn accrue_after_a_long_gap() {
+        // slots_passed * yield_rate_bps is past u64::MAX here
+        let mut reserve = reserve_at(1_000_000);
+        let slot = u64::MAX / 500 + 1;
+        reserve.accrue(slot).unwrap();
+        let yield_factor = 1_000_000 + slot as u128 * 500 / SLOTS_PER_YEAR as u128;
+        assert_eq!(reserve.exchange_rate as u128, yield_factor);
+        assert_eq!(reserve.last_update_slot, slot);
+
+        // A rate that doesn't fit fails rather than wrapping
+        let mut reserve = reserve_at(1_000_000);
+        reserve.yield_rate_bps = u64::MAX;
+        assert_eq!(reserve.accrue(u64::MAX).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn borrow_interest_overflow_fails() {
+        let mut reserve = reserve_at(1_000_000);
+        reserve.total_liquidity = u64::MAX;
+        reserve.borrowed_liquidity = u64::MAX;
+        assert_eq!(reserve.accrue(SLOTS_PER_YEAR).unwrap_err(), overflow());
+
+        let mut obligation = Obligation {
+            bump: 0,
+            reserve: Pubkey::default(),
+            owner: Pubkey::default(),
+            borrowed_amount: u64::MAX,
+            cumulative_borrow_rate: BORROW_RATE_SCALE,
+        };
+        reserve.cumulative_borrow_rate = 2 * BORROW_RATE_SCALE;
+        assert_eq!(obligation.refresh(&reserve).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn withdrawal_window_overflow_fails() {
+        let mut reserve = reserve_at(1_000_000);
+        reserve.record_withdrawal(1, u64::MAX).unwrap();
+        assert_eq!(reserve.record_withdrawal(1, 1).unwrap_err(), overflow());
+        // A new slot starts a new window
+        reserve.record_withdrawal(2, 1).unwrap();
+    }
 }
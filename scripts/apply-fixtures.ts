@@ -0,0 +1,213 @@
+/**
+ * Provision a Deterministic Devnet Fixture Set
+ *
+ * Reads the manifest written by `cargo xtask fixtures` and creates whatever is
+ * missing on devnet, so every run lands on the same addresses and balances:
+ * 1. Funds every fixture signer from the provider wallet
+ * 2. Creates the USDC and cToken mints from their fixture keypairs
+ * 3. Sets up the Mock Kamino lending market + reserve
+ * 4. Initializes each Ghost Pool and its computation log
+ * 5. Mints each user up to their fixture USDC balance
+ *
+ * Safe to re-run: existing accounts are skipped and balances are topped up, never doubled.
+ *
+ * Usage: npx ts-node scripts/apply-fixtures.ts [fixture_dir]   (default: fixtures/devnet)
+ */
+
+import * as anchor from '@coral-xyz/anchor';
+import { Program, BN } from '@coral-xyz/anchor';
+import { GhostPool } from '../target/types/ghost_pool';
+import { MockKamino } from '../target/types/mock_kamino';
+import {
+  getComputationAccAddress,
+  getClusterAccAddress,
+  getMXEAccAddress,
+  getMempoolAccAddress,
+  getExecutingPoolAccAddress,
+  getCompDefAccAddress,
+} from '@arcium-hq/client';
+import { randomBytes } from 'crypto';
+import * as fs from 'fs';
+import * as path from 'path';
+import { Keypair, PublicKey, SystemProgram } from '@solana/web3.js';
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from '@solana/spl-token';
+
+const CLUSTER_OFFSET = 456;
+const ARCIUM_PROGRAM_ID = new PublicKey('Arcj82pX7HxYKLR92qvgZUAd7vGS1k4hQvAFcPATFdEQ');
+
+function computeCompDefOffset(name: string): number {
+  const crypto = require('crypto');
+  const hash = crypto.createHash('sha256').update(name).digest();
+  return hash.readUInt32LE(0);
+}
+
+async function main() {
+  const fixtureDir = process.argv[2] ?? 'fixtures/devnet';
+  const manifestPath = path.join(fixtureDir, 'manifest.json');
+  if (!fs.existsSync(manifestPath)) {
+    throw new Error(`${manifestPath} not found - run \`cargo xtask fixtures\` first`);
+  }
+  const manifest = JSON.parse(fs.readFileSync(manifestPath, 'utf8'));
+  const loadKeypair = (file: string) =>
+    Keypair.fromSecretKey(Uint8Array.from(JSON.parse(fs.readFileSync(path.join(fixtureDir, file), 'utf8'))));
+
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const connection = provider.connection;
+  const payer = provider.wallet.payer;
+
+  const ghostPoolProgram = anchor.workspace.GhostPool as Program<GhostPool>;
+  const mockKaminoProgram = anchor.workspace.MockKamino as Program<MockKamino>;
+
+  if (ghostPoolProgram.programId.toBase58() !== manifest.programs.ghost_pool ||
+      mockKaminoProgram.programId.toBase58() !== manifest.programs.mock_kamino) {
+    throw new Error('Workspace program ids do not match the manifest - regenerate fixtures');
+  }
+
+  console.log('=== Applying Fixtures ===');
+  console.log('Seed:', manifest.seed);
+  console.log('Manifest:', manifestPath);
+
+  const kaminoAdmin = loadKeypair(manifest.kamino.admin_keypair);
+  const usdcMintKeypair = loadKeypair(manifest.usdc.mint_keypair);
+  const collateralMintKeypair = loadKeypair(manifest.kamino.collateral_mint_keypair);
+  const usdcMint = usdcMintKeypair.publicKey;
+
+  // ==========================================
+  // STEP 1: Fund fixture signers
+  // ==========================================
+  console.log('\n--- Step 1: Funding Signers ---');
+  const signers: [Keypair, number][] = [
+    [kaminoAdmin, manifest.kamino.admin_lamports],
+    ...manifest.pools.map((p: any) => [loadKeypair(p.authority_keypair), p.lamports]),
+    ...manifest.users.map((u: any) => [loadKeypair(u.keypair), u.lamports]),
+  ];
+  for (const [signer, lamports] of signers) {
+    const balance = await connection.getBalance(signer.publicKey);
+    if (balance >= lamports) continue;
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.transfer({
+        fromPubkey: payer.publicKey,
+        toPubkey: signer.publicKey,
+        lamports: lamports - balance,
+      })
+    );
+    await provider.sendAndConfirm(tx);
+    console.log('Funded', signer.publicKey.toBase58());
+  }
+
+  // ==========================================
+  // STEP 2: Create mints
+  // ==========================================
+  console.log('\n--- Step 2: Creating Mints ---');
+  const marketAuthority = new PublicKey(manifest.kamino.lending_market_authority);
+  if (!(await connection.getAccountInfo(usdcMint))) {
+    await createMint(connection, payer, kaminoAdmin.publicKey, kaminoAdmin.publicKey,
+      manifest.usdc.decimals, usdcMintKeypair);
+  }
+  if (!(await connection.getAccountInfo(collateralMintKeypair.publicKey))) {
+    await createMint(connection, payer, marketAuthority, null,
+      manifest.usdc.decimals, collateralMintKeypair);
+  }
+  console.log('USDC Mint:', usdcMint.toBase58());
+  console.log('cToken Mint:', collateralMintKeypair.publicKey.toBase58());
+
+  // ==========================================
+  // STEP 3: Mock Kamino market + reserve
+  // ==========================================
+  console.log('\n--- Step 3: Setting up Mock Kamino ---');
+  if (!(await connection.getAccountInfo(new PublicKey(manifest.kamino.lending_market)))) {
+    await mockKaminoProgram.methods
+      .initLendingMarket()
+      .accountsPartial({ authority: kaminoAdmin.publicKey })
+      .signers([kaminoAdmin])
+      .rpc();
+  }
+  if (!(await connection.getAccountInfo(new PublicKey(manifest.kamino.reserve)))) {
+    await mockKaminoProgram.methods
+      .initReserve(new BN(manifest.kamino.initial_exchange_rate))
+      .accountsPartial({
+        authority: kaminoAdmin.publicKey,
+        lendingMarket: new PublicKey(manifest.kamino.lending_market),
+        liquidityMint: usdcMint,
+        collateralMint: collateralMintKeypair.publicKey,
+      })
+      .signers([kaminoAdmin])
+      .rpc();
+  }
+  console.log('Lending market:', manifest.kamino.lending_market);
+  console.log('Reserve:', manifest.kamino.reserve);
+
+  // ==========================================
+  // STEP 4: Ghost Pools
+  // ==========================================
+  console.log('\n--- Step 4: Initializing Ghost Pools ---');
+  const mxeAccount = getMXEAccAddress(ghostPoolProgram.programId);
+  for (const pool of manifest.pools) {
+    const authority = loadKeypair(pool.authority_keypair);
+    const ghostPool = new PublicKey(pool.ghost_pool);
+
+    if (!(await connection.getAccountInfo(ghostPool))) {
+      const initOffset = new BN(randomBytes(8));
+      await ghostPoolProgram.methods
+        .initializePool(initOffset, new BN(randomBytes(16)), new BN(pool.investment_threshold), new BN(pool.pool_id))
+        .accountsPartial({
+          authority: authority.publicKey,
+          ghostPool,
+          depositMint: usdcMint,
+          vault: new PublicKey(pool.vault),
+          mxeAccount,
+          compDefAccount: getCompDefAccAddress(ghostPoolProgram.programId, computeCompDefOffset('init_pool_state')),
+          computationAccount: getComputationAccAddress(CLUSTER_OFFSET, initOffset),
+          clusterAccount: getClusterAccAddress(CLUSTER_OFFSET),
+          mempoolAccount: getMempoolAccAddress(CLUSTER_OFFSET),
+          executingPool: getExecutingPoolAccAddress(CLUSTER_OFFSET),
+          arciumProgram: ARCIUM_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+    }
+
+    if (!(await connection.getAccountInfo(new PublicKey(pool.computation_log)))) {
+      await ghostPoolProgram.methods
+        .initComputationLog()
+        .accountsPartial({ authority: authority.publicKey, ghostPool })
+        .signers([authority])
+        .rpc();
+    }
+    console.log('Ghost Pool:', pool.ghost_pool);
+  }
+
+  // ==========================================
+  // STEP 5: Fund users with USDC
+  // ==========================================
+  console.log('\n--- Step 5: Minting User USDC ---');
+  for (const user of manifest.users) {
+    const ata = await getOrCreateAssociatedTokenAccount(connection, payer, usdcMint, new PublicKey(user.pubkey));
+    const current = (await getAccount(connection, ata.address)).amount;
+    const target = BigInt(user.usdc_balance);
+    if (current < target) {
+      await mintTo(connection, payer, usdcMint, ata.address, kaminoAdmin, target - current);
+    }
+    console.log(`  ${user.name}: ${user.pubkey} (${Number(target) / 1_000_000} USDC)`);
+  }
+
+  console.log('\nFixtures applied.');
+}
+
+main().catch(e => {
+  console.error('Error:', e.message);
+  if (e.logs) {
+    console.error('\nLogs:');
+    e.logs.forEach((log: string) => console.error('  ', log));
+  }
+  process.exit(1);
+});
@@ -0,0 +1,102 @@
+/**
+ * Decode Ghost Pool Events From Transaction Logs
+ *
+ * Pools emit their deposit/withdrawal events either as regular Anchor events or,
+ * with `set_event_mode(Packed)`, as packed logs: `"gP" || tag || fields`, without
+ * the discriminator and pool key. Both decode to the same `{ name, data }` shape;
+ * packed events take the pool from the transaction's ghost_pool account.
+ *
+ * Usage: npx ts-node scripts/decode-events.ts <signature> <ghost_pool>
+ */
+
+import * as anchor from '@coral-xyz/anchor';
+import { Program, BN } from '@coral-xyz/anchor';
+import { GhostPool } from '../target/types/ghost_pool';
+import { PublicKey } from '@solana/web3.js';
+
+const PACKED_EVENT_MAGIC = Buffer.from('gP');
+
+export interface PoolEvent {
+  name: string;
+  data: Record<string, any>;
+  packed: boolean;
+}
+
+/** Mirrors the `PackedEvent` impls in programs/ghost_pool/src/lib.rs */
+const PACKED_DECODERS: Record<number, [string, (b: Buffer) => Record<string, any>]> = {
+  1: ['depositEvent', b => ({ depositCount: new BN(b.subarray(0, 8), 'le') })],
+  2: ['withdrawalAuthorizedEvent', b => ({ amount: new BN(b.subarray(0, 8), 'le'), idx: b[8] })],
+  3: ['withdrawalClaimedEvent', b => ({ amount: new BN(b.subarray(0, 8), 'le') })],
+  4: ['withdrawalCompletedEvent', () => ({})],
+};
+
+function decodePacked(bytes: Buffer, pool: PublicKey): PoolEvent | null {
+  if (bytes.length < 3 || !bytes.subarray(0, 2).equals(PACKED_EVENT_MAGIC)) return null;
+  const decoder = PACKED_DECODERS[bytes[2]];
+  if (!decoder) return null;
+  const [name, decode] = decoder;
+  return { name, data: { pool, ...decode(bytes.subarray(3)) }, packed: true };
+}
+
+/**
+ * Decode every ghost_pool event in `logs`, in emission order
+ * Only `Program data:` lines logged while ghost_pool itself is executing are considered,
+ * so events of CPI'd programs are never mistaken for pool events.
+ */
+export function decodePoolEvents(program: Program<GhostPool>, logs: string[], pool: PublicKey): PoolEvent[] {
+  const programId = program.programId.toBase58();
+  const stack: string[] = [];
+  const events: PoolEvent[] = [];
+
+  for (const log of logs) {
+    const invoke = log.match(/^Program (\w+) invoke \[\d+\]$/);
+    if (invoke) {
+      stack.push(invoke[1]);
+      continue;
+    }
+    if (/^Program \w+ (success|failed)/.test(log)) {
+      stack.pop();
+      continue;
+    }
+    if (!log.startsWith('Program data: ') || stack[stack.length - 1] !== programId) continue;
+
+    const b64 = log.slice('Program data: '.length);
+    const packed = decodePacked(Buffer.from(b64, 'base64'), pool);
+    if (packed) {
+      events.push(packed);
+      continue;
+    }
+    const event = program.coder.events.decode(b64);
+    if (event) events.push({ name: event.name, data: event.data, packed: false });
+  }
+  return events;
+}
+
+async function main() {
+  const [signature, poolArg] = process.argv.slice(2);
+  if (!signature || !poolArg) {
+    throw new Error('usage: npx ts-node scripts/decode-events.ts <signature> <ghost_pool>');
+  }
+
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.GhostPool as Program<GhostPool>;
+
+  const tx = await provider.connection.getTransaction(signature, {
+    commitment: 'confirmed',
+    maxSupportedTransactionVersion: 0,
+  });
+  if (!tx?.meta?.logMessages) throw new Error(`No logs for ${signature}`);
+
+  for (const event of decodePoolEvents(program, tx.meta.logMessages, new PublicKey(poolArg))) {
+    const fields = Object.entries(event.data).map(([k, v]) => `${k}=${v.toString()}`);
+    console.log(`${event.name}${event.packed ? ' (packed)' : ''}: ${fields.join(' ')}`);
+  }
+}
+
+if (require.main === module) {
+  main().catch(e => {
+    console.error('Error:', e.message);
+    process.exit(1);
+  });
+}
@@ -269,9 +269,7 @@ async function main() {
       .accountsPartial({
         user: user.publicKey,
         ghostPool: EXISTING_POOL.ghostPool,
-        vault: EXISTING_POOL.vault,
         userTokenAccount: userAta.address,
-        tokenProgram: TOKEN_PROGRAM_ID,
         mxeAccount: mxeAccount,
         compDefAccount: getCompDefAccAddress(program.programId, computeCompDefOffset('authorize_withdrawal')),
         computationAccount: withdrawComputation,
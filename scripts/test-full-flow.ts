@@ -434,7 +434,7 @@ async function main() {
     console.log('\n--- Step 5: Executing Mock Kamino Investment ---');
 
     const investTx = await poolProgram.methods
-      .investInKamino()
+      .investInKamino(new BN(0)) // no minimum collateral
       .accountsPartial({
         authority: poolAuthority.publicKey,
         ghostPool: ghostPool,
@@ -109,23 +109,17 @@ async function main() {
     console.log(`  Size: ${(upload.size / 1024 / 1024).toFixed(2)}MB\n`);
   }
 
-  // Generate Rust code for program
-  console.log('─'.repeat(80));
-  console.log('\n📝 Add these URLs to your program:\n');
-  console.log('// In programs/ghost_pool/src/lib.rs\n');
-
-  const rustCode = uploads.map(u => `const ${u.name.toUpperCase()}_URL: &str = "${u.url}";`).join('\n');
-  console.log(rustCode);
-
-  // Save URLs to JSON
+  // Same layout as upload-to-pinata.ts: init-comp-defs reads circuits.<name>.url
   const urlsFile = path.join(__dirname, '../circuit-urls.json');
-  fs.writeFileSync(urlsFile, JSON.stringify(uploads, null, 2));
+  const circuits = Object.fromEntries(
+    uploads.map(u => [u.name, { ipfsHash: u.ipfsHash, url: u.url, size: `${(u.size / 1024).toFixed(0)}KB` }])
+  );
+  fs.writeFileSync(urlsFile, JSON.stringify({ uploadedAt: new Date().toISOString(), circuits }, null, 2));
   console.log(`\n💾 URLs saved to: ${urlsFile}`);
 
   console.log('\n🎉 Done! Next steps:');
-  console.log('   1. Update program init functions to use CircuitSource::OffChain');
-  console.log('   2. Redeploy the program with "anchor deploy"');
-  console.log('   3. Reinitialize comp defs with the new offchain storage\n');
+  console.log('   1. New deployment: cargo run -p ghost-pool-cli -- init-comp-defs (pins these URLs)');
+  console.log('   2. Existing registry: propose-circuit-upgrade / upgrade-circuit per circuit\n');
 }
 
 main().catch(console.error);
@@ -147,10 +147,11 @@ async function main() {
   console.log(`\n💾 Saved to: circuit-urls.json`);
 
   console.log('\n📝 Next steps:');
-  console.log('   1. Generate new program keypair');
-  console.log('   2. Update declare_id!() in lib.rs');
-  console.log('   3. Deploy with arcium');
-  console.log('   4. Initialize comp defs\n');
+  console.log('   1. New deployment: cargo run -p ghost-pool-cli -- init-comp-defs');
+  console.log('      (init_circuit_registry pins these URLs from circuit-urls.json)');
+  console.log('   2. Existing registry: re-pin each circuit (no redeploy needed):');
+  console.log('      cargo run -p ghost-pool-cli -- propose-circuit-upgrade --circuit <name> --source <url> --hash <hex>');
+  console.log('      cargo run -p ghost-pool-cli -- upgrade-circuit --circuit <name>\n');
 }
 
 main().catch(console.error);
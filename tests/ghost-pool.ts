@@ -1,6 +1,7 @@
 import * as anchor from '@coral-xyz/anchor';
 import { Program, BN } from '@coral-xyz/anchor';
 import { GhostPool } from '../target/types/ghost_pool';
+import { CallbackWrapper } from '../target/types/callback_wrapper';
 import {
   RescueCipher,
   awaitComputationFinalization,
@@ -28,14 +29,34 @@ import {
   getOrCreateAssociatedTokenAccount,
 } from '@solana/spl-token';
 import { expect } from 'chai';
+import * as fs from 'fs';
+import * as path from 'path';
+
+// Uploaded off-chain circuits, in the order init_circuit_registry takes their URLs
+const OFFCHAIN_CIRCUITS = [
+  'init_pool_state',
+  'process_deposit',
+  'check_investment_needed',
+  'record_investment',
+  'record_yield',
+  'authorize_withdrawal',
+  'process_withdrawal',
+];
+
+function offchainCircuitUrls(): string[] {
+  const uploads = JSON.parse(fs.readFileSync(path.join(__dirname, '..', 'circuit-urls.json'), 'utf8'));
+  return OFFCHAIN_CIRCUITS.map((circuit) => uploads.circuits[circuit].url);
+}
 
 describe('ghost-pool', () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
   const program = anchor.workspace.GhostPool as Program<GhostPool>;
+  const wrapperProgram = anchor.workspace.CallbackWrapper as Program<CallbackWrapper>;
 
   const CLUSTER_OFFSET = 456; // devnet cluster
   const ARCIUM_PROGRAM_ID = new PublicKey('Arcj82pX7HxYKLR92qvgZUAd7vGS1k4hQvAFcPATFdEQ');
+  const POOL_ID = new BN(0);
 
   // Computation definition offsets (from lib.rs)
   const compDefOffsets = {
@@ -65,7 +86,7 @@ describe('ghost-pool', () => {
   // Helper to get PDAs
   function getPoolPDA() {
     return PublicKey.findProgramAddressSync(
-      [Buffer.from('ghost_pool'), authority.toBuffer()],
+      [Buffer.from('ghost_pool'), authority.toBuffer(), POOL_ID.toArrayLike(Buffer, 'le', 8)],
       program.programId
     )[0];
   }
@@ -115,6 +136,28 @@ describe('ghost-pool', () => {
   });
 
   describe('Initialization', () => {
+    it('Initializes the circuit registry', async () => {
+      const [circuitRegistry] = PublicKey.findProgramAddressSync([Buffer.from('circuit_registry')], program.programId);
+      if (await provider.connection.getAccountInfo(circuitRegistry)) {
+        console.log('Circuit registry already exists');
+        return;
+      }
+      const [programData] = PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111')
+      );
+
+      const tx = await program.methods
+        .initCircuitRegistry(offchainCircuitUrls())
+        .accountsPartial({ authority, circuitRegistry, programData })
+        .rpc();
+      console.log('✅ Circuit registry initialized:', tx);
+
+      // The seven IPFS-pinned circuits; the rest are uploaded on-chain
+      const registry = await program.account.circuitRegistry.fetch(circuitRegistry);
+      expect(registry.count).to.equal(7);
+    });
+
     it('Initializes init_pool_state computation definition', async () => {
       console.log('\n=== Initializing init_pool_state comp def ===');
       const compDefAccount = getCompDefAccAddress(program.programId, compDefOffsets.initPool);
@@ -299,11 +342,11 @@ describe('ghost-pool', () => {
 
       try {
         const tx = await program.methods
-          .initializePool(computationOffset, nonce, threshold)
+          .initializePool(computationOffset, nonce, threshold, POOL_ID)
           .accountsPartial({
             authority: authority,
             ghostPool: ghostPool,
-            usdcMint: usdcMint,
+            depositMint: usdcMint,
             vault: vault,
             // signPdaAccount will be auto-derived by Anchor
             mxeAccount: mxeAccount,
@@ -344,6 +387,54 @@ describe('ghost-pool', () => {
     });
   });
 
+  describe('Callback Guard', () => {
+    // A callback instruction with valid accounts; the Failure output keeps it cheap to encode
+    async function initPoolCallbackIx() {
+      return program.methods
+        .initPoolStateCallback({ failure: {} })
+        .accountsPartial({
+          arciumProgram: ARCIUM_PROGRAM_ID,
+          compDefAccount: getCompDefAccAddress(program.programId, compDefOffsets.initPool),
+          mxeAccount: mxeAccount,
+          computationAccount: getComputationAccAddress(CLUSTER_OFFSET, new BN(randomBytes(8))),
+          clusterAccount: getClusterAccAddress(CLUSTER_OFFSET),
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          ghostPool: ghostPool,
+        })
+        .instruction();
+    }
+
+    it('Rejects a callback sent directly (not after callback_computation)', async () => {
+      let rejected = false;
+      try {
+        await provider.sendAndConfirm(new anchor.web3.Transaction().add(await initPoolCallbackIx()));
+      } catch (err: any) {
+        rejected = true;
+        // Passes the top-level guard, then fails Arcium's own transaction check
+        const logs = (err.logs ?? []).join('\n');
+        expect(logs).to.not.include('WrappedCallback');
+        expect(logs).to.include('InvalidCallbackTx');
+      }
+      expect(rejected).to.equal(true);
+    });
+
+    it('Rejects a callback invoked through a wrapper program', async () => {
+      const ix = await initPoolCallbackIx();
+      let rejected = false;
+      try {
+        await wrapperProgram.methods
+          .forward(ix.data)
+          .accounts({ target: program.programId })
+          .remainingAccounts(ix.keys)
+          .rpc();
+      } catch (err: any) {
+        rejected = true;
+        expect((err.logs ?? []).join('\n')).to.include('WrappedCallback');
+      }
+      expect(rejected).to.equal(true);
+    });
+  });
+
   describe('Summary', () => {
     it('Prints deployment summary', () => {
       console.log('\n=================================================');
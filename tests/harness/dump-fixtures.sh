@@ -0,0 +1,20 @@
+#!/usr/bin/env bash
+# Dump the Arcium program and the accounts ghost_pool's computations touch into
+# tests/harness/fixtures/arcium, for the LiteSVM harness. Commit the result
+#
+# usage: ARCIUM_CLUSTER_OFFSET=456 tests/harness/dump-fixtures.sh [rpc-url]
+set -euo pipefail
+
+root="$(cd "$(dirname "$0")/../.." && pwd)"
+out="$root/tests/harness/fixtures/arcium"
+url="${1:-https://api.devnet.solana.com}"
+offset="${ARCIUM_CLUSTER_OFFSET:?set ARCIUM_CLUSTER_OFFSET to the cluster ghost_pool's MXE is bound to}"
+addresses=(cargo run --quiet --manifest-path "$root/Cargo.toml" -p ghost-pool-tests --bin fixture-addresses --)
+
+mkdir -p "$out"
+solana program dump --url "$url" "$("${addresses[@]}" --program)" "$out/arcium.so"
+"${addresses[@]}" "$offset" | while read -r address; do
+    solana account --url "$url" --output json --output-file "$out/$address.json" "$address" > /dev/null
+done
+echo "$offset" > "$out/cluster-offset"
+echo "dumped cluster $offset into $out"
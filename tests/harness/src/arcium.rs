@@ -0,0 +1,189 @@
+//! Arcium accounts and callbacks
+//!
+//! LiteSVM has no Arcium deployment, so the harness loads one dumped from devnet by
+//! `dump-fixtures.sh` and checked in under `tests/harness/fixtures/arcium`: the Arcium program,
+//! ghost_pool's MXE and the cluster it's bound to, that cluster's mempool and execpool, the fee
+//! pool, the Arcium clock and ghost_pool's computation definitions. Queueing a computation then
+//! runs the real Arcium program. The MPC is what's mocked: the test decides each output, and
+//! [`TestCluster`] signs it with a BLS key patched into the cluster account in place of the
+//! nodes' aggregate key. Outputs signed with any other key fail verification, which is how the
+//! harness aborts a computation.
+//!
+//! Two layouts here track arcium-anchor 0.6.3 and are the first thing to check on an Arcium
+//! upgrade: where the cluster keeps its key ([`CLUSTER_BLS_KEY_FROM_END`]) and what the
+//! cluster signs ([`signed_message`]). Re-run `dump-fixtures.sh` then and commit what it
+//! writes, so the tests keep running without devnet access.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::Id;
+use arcium_anchor::prelude::{Arcium, ARCIUM_CLOCK_ACCOUNT_ADDRESS, ARCIUM_FEE_POOL_ACCOUNT_ADDRESS};
+use base64::Engine;
+use ghost_pool_client::{ArciumEnv, COMP_DEF_CIRCUITS, PROGRAM_ID};
+use litesvm::LiteSVM;
+use serde_json::Value;
+use solana_alt_bn128_bls::{G2CompressedPoint, PrivKey, Sha256Normalized};
+use solana_sdk::account::Account;
+use solana_sdk::hash::hash;
+
+use crate::output::Output;
+
+/// Where `dump-fixtures.sh` writes, relative to the harness crate
+pub const FIXTURES_DIR: &str = "fixtures/arcium";
+/// The dumped Arcium program
+const PROGRAM_FILE: &str = "arcium.so";
+/// Offset of the cluster the fixtures were dumped for
+const CLUSTER_OFFSET_FILE: &str = "cluster-offset";
+
+/// The cluster's aggregate BLS key (a compressed G2 point) is its last field before the bump
+const CLUSTER_BLS_KEY_FROM_END: usize = 1 + 64;
+
+/// Scalar of the test cluster's BLS key
+const TEST_CLUSTER_SECRET: [u8; 32] = {
+    let mut secret = [0u8; 32];
+    secret[31] = 7;
+    secret
+};
+
+/// Scalar of a key the cluster account doesn't hold
+const UNTRUSTED_SECRET: [u8; 32] = {
+    let mut secret = [0u8; 32];
+    secret[31] = 11;
+    secret
+};
+
+/// Borsh tag of `SignedComputationOutputs::Success`
+const SUCCESS_TAG: u8 = 0;
+
+/// The checked-in fixtures
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR)
+}
+
+/// Every Arcium account a ghost_pool computation touches on `env`'s cluster
+pub fn fixture_addresses(env: &ArciumEnv) -> Vec<Pubkey> {
+    let mut addresses = vec![
+        env.mxe(),
+        env.mempool(),
+        env.execpool(),
+        env.cluster(),
+        ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+        ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    ];
+    addresses.extend(COMP_DEF_CIRCUITS.iter().map(|circuit| env.comp_def(circuit)));
+    addresses
+}
+
+/// Load the dumped Arcium program and accounts into `svm`, returning the cluster's environment
+pub fn load_fixtures(svm: &mut LiteSVM, dir: &Path) -> Result<ArciumEnv, String> {
+    let missing = |what: &str, e: std::io::Error| {
+        format!("{} in {}: {} (re-run tests/harness/dump-fixtures.sh)", what, dir.display(), e)
+    };
+    let cluster_offset = fs::read_to_string(dir.join(CLUSTER_OFFSET_FILE))
+        .map_err(|e| missing(CLUSTER_OFFSET_FILE, e))?
+        .trim()
+        .parse()
+        .map_err(|_| format!("bad {}", CLUSTER_OFFSET_FILE))?;
+    svm.add_program_from_file(Arcium::id(), dir.join(PROGRAM_FILE))
+        .map_err(|e| missing(PROGRAM_FILE, e))?;
+
+    let env = ArciumEnv::new(cluster_offset);
+    for address in fixture_addresses(&env) {
+        let path = dir.join(format!("{}.json", address));
+        let json = fs::read_to_string(&path).map_err(|e| missing(&format!("{}.json", address), e))?;
+        let account = parse_account(&json).ok_or_else(|| format!("bad account dump {}", path.display()))?;
+        svm.set_account(address, account)
+            .map_err(|e| format!("failed to load {}: {:?}", address, e))?;
+    }
+    Ok(env)
+}
+
+/// An account as `solana account --output json` prints it
+fn parse_account(json: &str) -> Option<Account> {
+    let json: Value = serde_json::from_str(json).ok()?;
+    let account = &json["account"];
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(account["data"][0].as_str()?)
+        .ok()?;
+    Some(Account {
+        lamports: account["lamports"].as_u64()?,
+        data,
+        owner: account["owner"].as_str()?.parse().ok()?,
+        executable: account["executable"].as_bool()?,
+        rent_epoch: account["rentEpoch"].as_u64().unwrap_or_default(),
+    })
+}
+
+/// What the cluster signs for a computation: the computation account, then its output
+pub fn signed_message(computation_account: &Pubkey, output: &Output) -> Vec<u8> {
+    let mut message = computation_account.to_bytes().to_vec();
+    message.extend_from_slice(output.as_bytes());
+    message
+}
+
+/// Stands in for the cluster's nodes: the only key callbacks verify against
+pub struct TestCluster {
+    secret: PrivKey,
+}
+
+impl Default for TestCluster {
+    fn default() -> Self {
+        Self {
+            secret: PrivKey(TEST_CLUSTER_SECRET),
+        }
+    }
+}
+
+impl TestCluster {
+    /// A cluster whose outputs don't verify against the installed key: every callback it
+    /// sends takes the program's abort branch
+    pub fn untrusted() -> Self {
+        Self {
+            secret: PrivKey(UNTRUSTED_SECRET),
+        }
+    }
+
+    /// Make `cluster`'s aggregate key this cluster's
+    pub fn install(&self, svm: &mut LiteSVM, cluster: &Pubkey) -> Result<(), String> {
+        let mut account = svm
+            .get_account(cluster)
+            .ok_or_else(|| format!("cluster {} isn't loaded", cluster))?;
+        let public_key = G2CompressedPoint::try_from(&self.secret)
+            .map_err(|e| format!("bad test cluster key: {:?}", e))?;
+        let offset = account
+            .data
+            .len()
+            .checked_sub(CLUSTER_BLS_KEY_FROM_END)
+            .ok_or("cluster account too short")?;
+        account.data[offset..offset + 64].copy_from_slice(&public_key.0);
+        svm.set_account(*cluster, account)
+            .map_err(|e| format!("failed to patch {}: {:?}", cluster, e))
+    }
+
+    pub fn sign(&self, computation_account: &Pubkey, output: &Output) -> [u8; 64] {
+        self.secret
+            .sign::<Sha256Normalized, _>(&signed_message(computation_account, output))
+            .expect("the test cluster key signs any message")
+            .0
+    }
+
+    /// `{circuit}_callback` as the cluster sends it once the computation at
+    /// `computation_account` finished with `output`
+    pub fn callback(
+        &self,
+        circuit: &str,
+        computation_account: &Pubkey,
+        output: &Output,
+        accounts: Vec<AccountMeta>,
+    ) -> Instruction {
+        let discriminator = &hash(format!("global:{}_callback", circuit).as_bytes()).to_bytes()[..8];
+        let mut data = discriminator.to_vec();
+        data.push(SUCCESS_TAG);
+        data.extend_from_slice(output.as_bytes());
+        data.extend_from_slice(&self.sign(computation_account, output));
+        Instruction::new_with_bytes(PROGRAM_ID, &data, accounts)
+    }
+}
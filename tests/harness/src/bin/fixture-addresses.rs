@@ -0,0 +1,34 @@
+//! Print what `dump-fixtures.sh` dumps for cluster `<offset>`: the Arcium accounts, one per
+//! line, or with `--program` the Arcium program
+
+use std::process::ExitCode;
+
+use anchor_lang::Id;
+use arcium_anchor::prelude::Arcium;
+use ghost_pool_client::ArciumEnv;
+use ghost_pool_tests::arcium::fixture_addresses;
+
+const USAGE: &str = "usage: fixture-addresses <cluster-offset> | --program";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [flag] if flag == "--program" => println!("{}", Arcium::id()),
+        [offset] => match offset.parse() {
+            Ok(offset) => {
+                for address in fixture_addresses(&ArciumEnv::new(offset)) {
+                    println!("{}", address);
+                }
+            }
+            Err(_) => {
+                eprintln!("bad cluster offset {:?}", offset);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
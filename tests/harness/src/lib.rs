@@ -0,0 +1,273 @@
+//! Ghost Pool integration harness
+//!
+//! Runs ghost_pool and mock_kamino in LiteSVM from their `target/deploy` builds, next to the
+//! checked-in Arcium program and accounts dumped from devnet (see `arcium.rs`). Instructions
+//! are the client crate's, so a test exercises the same account lists integrators send; the
+//! MPC is replaced by [`Harness::callback`], which delivers whatever output the test decides,
+//! signed the way the cluster would sign it, and [`Harness::aborted_callback`].
+//!
+//! Build the programs with `arcium build` first; mock_kamino must be built for the devnet
+//! lending program address (Anchor.toml), the one pools start with.
+
+use std::path::{Path, PathBuf};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{AccountDeserialize, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use ghost_pool::{ComputationLog, KAMINO_LENDING_PROGRAM_ID};
+use ghost_pool_client::{computation_log_address, ArciumEnv, Cipher, PROGRAM_ID};
+use litesvm::types::TransactionMetadata;
+use litesvm::LiteSVM;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+pub mod arcium;
+pub mod output;
+
+use arcium::TestCluster;
+use output::Output;
+
+/// Lamports the payer starts with
+const PAYER_LAMPORTS: u64 = 100_000_000_000;
+/// Queue instructions and callbacks both run well past the default budget
+const COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Slot duration the clock advances by
+const SLOT_MS: i64 = 400;
+
+pub fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .ancestors()
+        .nth(2)
+        .expect("the harness lives two levels inside the workspace")
+        .to_path_buf()
+}
+
+/// Stands in for the Rescue cipher: the MXE is mocked, so nothing ever decrypts the inputs
+pub struct PlaintextCipher;
+
+impl Cipher for PlaintextCipher {
+    fn encrypt(&self, plaintext: &[u128], nonce: u128) -> Vec<[u8; 32]> {
+        plaintext
+            .iter()
+            .map(|value| {
+                let mut ciphertext = [0u8; 32];
+                ciphertext[..16].copy_from_slice(&value.to_le_bytes());
+                ciphertext[16..].copy_from_slice(&nonce.to_le_bytes());
+                ciphertext
+            })
+            .collect()
+    }
+}
+
+/// Fills a callback context from the program's generated `accounts` structs: the six accounts
+/// every callback starts with, then the given ones, i.e. the callback accounts its queue
+/// instruction passed
+#[macro_export]
+macro_rules! callback_accounts {
+    (
+        $harness:expr, $circuit:expr, $computation_offset:expr,
+        $context:ident { $($field:ident: $value:expr),* $(,)? }
+    ) => {
+        ::ghost_pool::accounts::$context {
+            arcium_program: <::arcium_anchor::prelude::Arcium as ::anchor_lang::Id>::id(),
+            comp_def_account: $harness.env.comp_def($circuit),
+            mxe_account: $harness.env.mxe(),
+            computation_account: $harness.env.computation($computation_offset),
+            cluster_account: $harness.env.cluster(),
+            instructions_sysvar: ::anchor_lang::solana_program::sysvar::instructions::ID,
+            $($field: $value,)*
+        }
+    };
+}
+
+pub struct Harness {
+    pub svm: LiteSVM,
+    /// Pays for every transaction, and the pool authority in the end-to-end flow
+    pub payer: Keypair,
+    pub env: ArciumEnv,
+    pub cluster: TestCluster,
+}
+
+impl Harness {
+    pub fn new() -> Result<Self, String> {
+        let root = workspace_root();
+        let mut svm = LiteSVM::new();
+        for (program, name) in [(PROGRAM_ID, "ghost_pool"), (KAMINO_LENDING_PROGRAM_ID, "mock_kamino")] {
+            let path = root.join("target/deploy").join(format!("{}.so", name));
+            svm.add_program_from_file(program, &path)
+                .map_err(|e| format!("failed to load {}: {} (run arcium build)", path.display(), e))?;
+        }
+        let env = arcium::load_fixtures(&mut svm, &arcium::fixtures_dir())?;
+        let cluster = TestCluster::default();
+        cluster.install(&mut svm, &env.cluster())?;
+
+        let payer = Keypair::new();
+        svm.airdrop(&payer.pubkey(), PAYER_LAMPORTS)
+            .map_err(|e| format!("airdrop failed: {:?}", e))?;
+        Ok(Self { svm, payer, env, cluster })
+    }
+
+    /// Send `ixs` paid by the payer, with `signers` signing besides it
+    pub fn send(&mut self, ixs: &[Instruction], signers: &[&Keypair]) -> Result<TransactionMetadata, String> {
+        let mut all_ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNIT_LIMIT)];
+        all_ixs.extend_from_slice(ixs);
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(
+            &all_ixs,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.svm.latest_blockhash(),
+        );
+        let result = self
+            .svm
+            .send_transaction(tx)
+            .map_err(|e| format!("{:?}\n{}", e.err, e.meta.logs.join("\n")));
+        // A repeated instruction must not look like a duplicate transaction
+        self.svm.expire_blockhash();
+        result
+    }
+
+    pub fn fund(&mut self, address: &Pubkey, lamports: u64) {
+        self.svm
+            .airdrop(address, lamports)
+            .unwrap_or_else(|e| panic!("airdrop to {} failed: {:?}", address, e));
+    }
+
+    pub fn data(&self, address: &Pubkey) -> Vec<u8> {
+        self.svm
+            .get_account(address)
+            .unwrap_or_else(|| panic!("{} doesn't exist", address))
+            .data
+    }
+
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        T::try_deserialize(&mut self.data(address).as_slice())
+            .unwrap_or_else(|e| panic!("failed to decode {}: {}", address, e))
+    }
+
+    /// Write an SPL Token account of `T` (a Mint or token account) at `address`
+    fn set_token_state<T: Pack>(&mut self, address: Pubkey, state: T) {
+        let mut data = vec![0u8; T::LEN];
+        T::pack(state, &mut data).expect("packs into its own length");
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(T::LEN),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm
+            .set_account(address, account)
+            .unwrap_or_else(|e| panic!("failed to write {}: {:?}", address, e));
+    }
+
+    /// New SPL Token mint with nothing minted
+    pub fn create_mint(&mut self, mint_authority: &Pubkey, decimals: u8) -> Pubkey {
+        let mint = Keypair::new().pubkey();
+        self.set_token_state(
+            mint,
+            spl_token::state::Mint {
+                mint_authority: Some(*mint_authority).into(),
+                supply: 0,
+                decimals,
+                is_initialized: true,
+                freeze_authority: None.into(),
+            },
+        );
+        mint
+    }
+
+    /// New token account of `owner` holding `amount` of `mint` (added to its supply)
+    pub fn token_account(&mut self, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+        let mut mint_state = spl_token::state::Mint::unpack(&self.data(mint)).expect("not an SPL Token mint");
+        mint_state.supply += amount;
+        self.set_token_state(*mint, mint_state);
+
+        let address = Keypair::new().pubkey();
+        self.set_token_state(
+            address,
+            spl_token::state::Account {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+        );
+        address
+    }
+
+    /// Rewrite the token account at `address`, e.g. to hand it to another owner or freeze it
+    pub fn update_token_account(&mut self, address: &Pubkey, update: impl FnOnce(&mut spl_token::state::Account)) {
+        let mut state = spl_token::state::Account::unpack(&self.data(address))
+            .unwrap_or_else(|e| panic!("{} isn't a token account: {}", address, e));
+        update(&mut state);
+        self.set_token_state(*address, state);
+    }
+
+    pub fn token_balance(&self, address: &Pubkey) -> u64 {
+        spl_token::state::Account::unpack(&self.data(address))
+            .unwrap_or_else(|e| panic!("{} isn't a token account: {}", address, e))
+            .amount
+    }
+
+    /// Move the clock `slots` ahead, the unix timestamp with it
+    pub fn advance_slots(&mut self, slots: u64) {
+        let mut clock: Clock = self.svm.get_sysvar();
+        clock.slot += slots;
+        clock.unix_timestamp += slots as i64 * SLOT_MS / 1000;
+        self.svm.set_sysvar(&clock);
+    }
+
+    /// Deliver `output` as the result of the computation queued at `computation_offset`
+    /// `accounts` is the callback's context, usually built with [`callback_accounts!`]
+    pub fn callback(
+        &mut self,
+        circuit: &str,
+        computation_offset: u64,
+        output: &Output,
+        accounts: impl ToAccountMetas,
+    ) -> Result<TransactionMetadata, String> {
+        let computation_account = self.env.computation(computation_offset);
+        let ix = self
+            .cluster
+            .callback(circuit, &computation_account, output, accounts.to_account_metas(None));
+        self.send(&[ix], &[])
+    }
+
+    /// Deliver `output` for the computation at `computation_offset` signed with a key the
+    /// cluster doesn't hold, as an aborted computation reaches its callback
+    pub fn aborted_callback(
+        &mut self,
+        circuit: &str,
+        computation_offset: u64,
+        output: &Output,
+        accounts: impl ToAccountMetas,
+    ) -> Result<TransactionMetadata, String> {
+        let computation_account = self.env.computation(computation_offset);
+        let ix = TestCluster::untrusted().callback(
+            circuit,
+            &computation_account,
+            output,
+            accounts.to_account_metas(None),
+        );
+        self.send(&[ix], &[])
+    }
+
+    /// How the pool's computation log settled the computation at `computation_offset`:
+    /// None while pending, else whether it succeeded
+    pub fn computation_succeeded(&self, pool: &Pubkey, computation_offset: u64) -> Option<bool> {
+        let log: ComputationLog = self.account(&computation_log_address(pool));
+        log.entries
+            .iter()
+            .find(|entry| entry.computation_offset == computation_offset)
+            .filter(|entry| entry.completion_slot != 0)
+            .map(|entry| entry.succeeded)
+    }
+}
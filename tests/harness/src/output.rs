@@ -0,0 +1,64 @@
+//! Computation outputs as a callback receives them
+//!
+//! The `*Output` types the Arcium macros generate for each callback aren't constructible
+//! outside the program, so outputs are written field by field in the circuit's return order,
+//! the way the MXE serializes them: revealed values little-endian, `Enc<Mxe, T>` as its nonce
+//! then one ciphertext per field, `EncData<T>` as its ciphertexts alone.
+
+use ghost_pool::POOL_STATE_FIELDS;
+
+/// Fresh ciphertexts of a pool's state (their content is never checked on chain)
+pub fn pool_state(seed: u8) -> Vec<[u8; 32]> {
+    (0..POOL_STATE_FIELDS).map(|i| [seed.wrapping_add(i as u8); 32]).collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Output(Vec<u8>);
+
+impl Output {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `Enc<Mxe, T>`: the nonce the ciphertexts are under, then the ciphertexts
+    pub fn encrypted(self, nonce: u128, ciphertexts: &[[u8; 32]]) -> Self {
+        self.u128(nonce).enc_data(ciphertexts)
+    }
+
+    /// `EncData<T>`: ciphertexts under the nonce the program already tracks
+    pub fn enc_data(mut self, ciphertexts: &[[u8; 32]]) -> Self {
+        for ciphertext in ciphertexts {
+            self.0.extend_from_slice(ciphertext);
+        }
+        self
+    }
+
+    pub fn bool(mut self, value: bool) -> Self {
+        self.0.push(value as u8);
+        self
+    }
+
+    pub fn u8(mut self, value: u8) -> Self {
+        self.0.push(value);
+        self
+    }
+
+    pub fn u16(mut self, value: u16) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u128(mut self, value: u128) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
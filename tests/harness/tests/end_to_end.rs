@@ -0,0 +1,524 @@
+//! deposit → invest → yield → withdraw against mock_kamino, and the ways a withdrawal falls
+//! short of paying, and bucketed public counters; every MPC output decided here
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use ghost_pool::{GhostPool, PendingWithdrawal, PoolStatus, PositionStatus, KAMINO_LENDING_PROGRAM_ID};
+use ghost_pool_client::{
+    check_and_invest, claim_withdrawal, computation_log_address, deposit, deposit_escrow_address, encrypt_deposit,
+    encrypt_password, failed_deposit_address, ghost_pool_address, harvest_yield, initialize_pool, invest_in_kamino,
+    mock_reserve_address, pending_computation_address, pending_withdrawal_address, pubkey_fingerprint,
+    random_computation_offset, record_investment, redeem_from_kamino, set_counter_privacy, vault_address, withdraw,
+    withdrawal_receipt_address, ClientKey, CrankAccounts, DepositParams, KaminoReserve, PoolRef,
+    CHECK_INVESTMENT_CIRCUIT, CLAIM_CIRCUIT, COUNTER_EPOCH_CIRCUIT, DEPOSIT_CIRCUIT, INIT_POOL_CIRCUIT, PROGRAM_ID,
+    RECORD_INVESTMENT_CIRCUIT, RECORD_YIELD_CIRCUIT, WITHDRAW_CIRCUIT,
+};
+use ghost_pool_tests::output::{pool_state, Output};
+use ghost_pool_tests::{callback_accounts, Harness, PlaintextCipher};
+use solana_sdk::signature::{Keypair, Signer};
+
+const DECIMALS: u8 = 6;
+const DEPOSIT: u64 = 1_000_000_000;
+/// What check_investment_needed approves for Kamino
+const INVEST: u64 = 600_000_000;
+/// Liquidity accrued to the reserve; the pool holds all of its collateral
+const YIELD: u64 = 60_000_000;
+const WITHDRAW: u64 = 200_000_000;
+/// cTokens redeemed back into the vault
+const REDEEM: u64 = 100_000_000;
+/// 1:1 liquidity per cToken, scaled by 1e6
+const INITIAL_EXCHANGE_RATE: u64 = 1_000_000;
+const PASSWORD: &str = "correct horse battery staple";
+
+fn kamino_pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &KAMINO_LENDING_PROGRAM_ID).0
+}
+
+fn kamino_ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction::new_with_bytes(KAMINO_LENDING_PROGRAM_ID, &data.data(), accounts.to_account_metas(None))
+}
+
+/// A mock_kamino market with a reserve for `mint`, returning the reserve
+fn create_reserve(h: &mut Harness, mint: &Pubkey) -> Pubkey {
+    let authority = h.payer.pubkey();
+    let market = kamino_pda(&[b"lending_market", authority.as_ref()]);
+    let market_authority = kamino_pda(&[b"lending_market_authority", market.as_ref()]);
+    let collateral_mint = h.create_mint(&market_authority, DECIMALS);
+    let reserve = mock_reserve_address(&market, mint);
+
+    let init_market = kamino_ix(
+        mock_kamino::accounts::InitLendingMarket {
+            authority,
+            lending_market: market,
+            system_program: system_program::ID,
+        },
+        mock_kamino::instruction::InitLendingMarket {},
+    );
+    let init_reserve = kamino_ix(
+        mock_kamino::accounts::InitReserve {
+            authority,
+            lending_market: market,
+            lending_market_authority: market_authority,
+            liquidity_mint: *mint,
+            collateral_mint,
+            liquidity_supply: kamino_pda(&[b"reserve_liquidity", market.as_ref(), mint.as_ref()]),
+            reserve,
+            market_overview: kamino_pda(&[b"market_overview", market.as_ref()]),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        },
+        mock_kamino::instruction::InitReserve {
+            initial_exchange_rate: INITIAL_EXCHANGE_RATE,
+        },
+    );
+    h.send(&[init_market, init_reserve], &[]).expect("mock Kamino reserve");
+    reserve
+}
+
+/// An active pool over a new mint, with its computation log
+fn active_pool(h: &mut Harness) -> PoolRef {
+    let authority = h.payer.pubkey();
+    let mint = h.create_mint(&authority, DECIMALS);
+
+    let offset = random_computation_offset();
+    let ix = initialize_pool(&h.env, &authority, &mint, &spl_token::ID, 0, 1, offset);
+    h.send(&[ix], &[]).expect("initialize_pool");
+    let pool = PoolRef {
+        address: ghost_pool_address(&authority, 0),
+        deposit_mint: mint,
+        token_program: spl_token::ID,
+        share_mint: None,
+    };
+    let accounts = callback_accounts!(h, INIT_POOL_CIRCUIT, offset, InitPoolStateCallback {
+        ghost_pool: pool.address,
+    });
+    h.callback(INIT_POOL_CIRCUIT, offset, &Output::new().enc_data(&pool_state(1)), accounts)
+        .expect("init_pool_state callback");
+    assert_eq!(h.account::<GhostPool>(&pool.address).status, PoolStatus::Active);
+
+    let init_log = Instruction::new_with_bytes(
+        PROGRAM_ID,
+        &ghost_pool::instruction::InitComputationLog {}.data(),
+        ghost_pool::accounts::InitComputationLog {
+            authority,
+            ghost_pool: pool.address,
+            computation_log: computation_log_address(&pool.address),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+    );
+    h.send(&[init_log], &[]).expect("init_computation_log");
+    pool
+}
+
+/// A user who deposited DEPOSIT into `pool`, and the token account it came from
+fn depositor(h: &mut Harness, pool: &PoolRef) -> (Keypair, Pubkey) {
+    let user = Keypair::new();
+    h.fund(&user.pubkey(), 1_000_000_000);
+    let user_tokens = h.token_account(&pool.deposit_mint, &user.pubkey(), DEPOSIT);
+    let key = ClientKey::generate();
+    let encrypted = encrypt_deposit(&PlaintextCipher, &key, PASSWORD, &[], None, None);
+    let params = DepositParams {
+        amount: DEPOSIT,
+        ..Default::default()
+    };
+    let offset = random_computation_offset();
+    let ix = deposit(&h.env, pool, &user.pubkey(), &user_tokens, &user_tokens, offset, &encrypted, &params);
+    h.send(&[ix], &[&user]).expect("deposit");
+
+    let nonce = h.account::<GhostPool>(&pool.address).state_nonce + 1;
+    let output = Output::new()
+        .encrypted(nonce, &pool_state(2))
+        .bool(true) // accepted
+        .bool(false) // merged
+        .bool(true) // slot_found
+        .u64(DEPOSIT)
+        .bool(false); // over_cap
+    let accounts = callback_accounts!(h, DEPOSIT_CIRCUIT, offset, ProcessDepositCallback {
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        vault: Some(vault_address(&pool.address)),
+        deposit_mint: Some(pool.deposit_mint),
+        refund_token_account: Some(user_tokens),
+        token_program: Some(spl_token::ID),
+        pending_withdrawal: None,
+        failed_deposit: Some(failed_deposit_address(&pool.address, offset)),
+        depositor: Some(user.pubkey()),
+        deposit_escrow: Some(deposit_escrow_address(&pool.address)),
+    });
+    h.callback(DEPOSIT_CIRCUIT, offset, &output, accounts).expect("process_deposit callback");
+    assert_eq!(h.computation_succeeded(&pool.address, offset), Some(true));
+    assert_eq!(h.account::<GhostPool>(&pool.address).state_nonce, nonce);
+    (user, user_tokens)
+}
+
+/// Request a WITHDRAW withdrawal by `user` to `destination`, returning its computation offset
+fn request_withdrawal(h: &mut Harness, pool: &PoolRef, user: &Keypair, destination: &Pubkey) -> u64 {
+    let encrypted = encrypt_password(&PlaintextCipher, &ClientKey::generate(), PASSWORD);
+    let offset = random_computation_offset();
+    let ix = withdraw(&h.env, pool, &user.pubkey(), destination, offset, WITHDRAW, &encrypted, None);
+    h.send(&[ix], &[user]).expect("withdraw");
+    offset
+}
+
+/// authorize_withdrawal's output for a WITHDRAW withdrawal from slot 0, bound to the
+/// destination owner the request recorded
+fn authorization(h: &Harness, pool: &PoolRef, user: &Keypair, authorized: bool) -> Output {
+    let destination = h
+        .account::<PendingWithdrawal>(&pending_withdrawal_address(&pool.address, &user.pubkey()))
+        .destination_owner;
+    Output::new()
+        .bool(authorized)
+        .u64(if authorized { WITHDRAW } else { 0 })
+        .u8(0) // found_idx
+        .bool(true) // destination_allowed
+        .u64(0) // penalty
+        .bool(true) // matured
+        .u128(destination)
+}
+
+fn authorize_accounts(
+    h: &Harness,
+    pool: &PoolRef,
+    user: &Keypair,
+    withdraw_offset: u64,
+) -> ghost_pool::accounts::AuthorizeWithdrawalCallback {
+    callback_accounts!(h, WITHDRAW_CIRCUIT, withdraw_offset, AuthorizeWithdrawalCallback {
+        ghost_pool: pool.address,
+        pending_withdrawal: pending_withdrawal_address(&pool.address, &user.pubkey()),
+        withdrawal_receipt: withdrawal_receipt_address(&pool.address, withdraw_offset),
+        computation_log: computation_log_address(&pool.address),
+    })
+}
+
+#[test]
+fn deposit_invest_yield_withdraw() {
+    let mut h = Harness::new().unwrap();
+    let authority = h.payer.pubkey();
+
+    // ---- Pool ----
+    let pool = active_pool(&mut h);
+    let mint = pool.deposit_mint;
+    let reserve = create_reserve(&mut h, &mint);
+    let reserve_keys = KaminoReserve::from_account_data(reserve, KAMINO_LENDING_PROGRAM_ID, &h.data(&reserve))
+        .expect("reserve layout");
+    let vault = vault_address(&pool.address);
+    let collateral = h.token_account(&reserve_keys.collateral_mint, &vault, 0);
+    let set_collateral = Instruction::new_with_bytes(
+        PROGRAM_ID,
+        &ghost_pool::instruction::SetCollateralAccount {}.data(),
+        ghost_pool::accounts::SetCollateralAccount {
+            authority,
+            ghost_pool: pool.address,
+            collateral_token_account: collateral,
+        }
+        .to_account_metas(None),
+    );
+    h.send(&[set_collateral], &[]).expect("set_collateral_account");
+
+    // ---- Deposit ----
+    let (user, user_tokens) = depositor(&mut h, &pool);
+    assert_eq!(h.token_balance(&vault), DEPOSIT);
+    assert_eq!(h.token_balance(&user_tokens), 0);
+    assert_eq!(h.account::<GhostPool>(&pool.address).total_deposits, 1);
+
+    // ---- Invest ----
+    let offset = random_computation_offset();
+    let ix = check_and_invest(&h.env, &pool.address, &authority, CrankAccounts::default(), offset);
+    h.send(&[ix], &[]).expect("check_and_invest");
+    let output = Output::new().bool(true).u64(INVEST).u64(INVEST).u64(0);
+    let accounts = callback_accounts!(h, CHECK_INVESTMENT_CIRCUIT, offset, CheckInvestmentNeededCallback {
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+    });
+    h.callback(CHECK_INVESTMENT_CIRCUIT, offset, &output, accounts)
+        .expect("check_investment_needed callback");
+    assert_eq!(h.account::<GhostPool>(&pool.address).pending_investment_amount, INVEST);
+
+    // The reserve is still at par, so it can't mint more cTokens than it takes liquidity
+    let min_collateral_out = reserve_keys.min_collateral_out(INVEST, 0);
+    assert_eq!(min_collateral_out, INVEST);
+    let ix = invest_in_kamino(&pool.address, &authority, &mint, &collateral, &reserve_keys, min_collateral_out + 1);
+    let err = h.send(&[ix], &[]).expect_err("invest_in_kamino above the exchange rate");
+    assert!(err.contains("SlippageExceeded"), "{}", err);
+    let ix = invest_in_kamino(&pool.address, &authority, &mint, &collateral, &reserve_keys, min_collateral_out);
+    h.send(&[ix], &[]).expect("invest_in_kamino");
+    assert_eq!(h.token_balance(&vault), DEPOSIT - INVEST);
+    assert_eq!(h.token_balance(&collateral), INVEST);
+    assert_eq!(h.account::<GhostPool>(&pool.address).total_collateral_received, INVEST);
+
+    let offset = random_computation_offset();
+    h.send(&[record_investment(&h.env, &pool.address, &authority, offset)], &[])
+        .expect("record_investment_mpc");
+    let accounts = callback_accounts!(h, RECORD_INVESTMENT_CIRCUIT, offset, RecordInvestmentCallback {
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        pending_computation: pending_computation_address(&pool.address, offset),
+        requester: authority,
+    });
+    h.callback(RECORD_INVESTMENT_CIRCUIT, offset, &Output::new().enc_data(&pool_state(3)), accounts)
+        .expect("record_investment callback");
+    assert_eq!(h.computation_succeeded(&pool.address, offset), Some(true));
+    let state = h.account::<GhostPool>(&pool.address);
+    assert_eq!(state.total_invested, INVEST);
+    assert_eq!(state.unrecorded_investment_amount, 0);
+
+    // ---- Yield ----
+    let accrue = kamino_ix(
+        mock_kamino::accounts::AccrueYield {
+            authority,
+            lending_market: reserve_keys.lending_market,
+            reserve,
+        },
+        mock_kamino::instruction::AccrueYield {
+            additional_liquidity: YIELD,
+        },
+    );
+    h.send(&[accrue], &[]).expect("accrue_yield");
+    h.advance_slots(10);
+
+    let offset = random_computation_offset();
+    let ix = harvest_yield(
+        &h.env,
+        &pool.address,
+        &authority,
+        CrankAccounts::default(),
+        &reserve,
+        &collateral,
+        offset,
+    );
+    h.send(&[ix], &[]).expect("harvest_yield");
+    let accounts = callback_accounts!(h, RECORD_YIELD_CIRCUIT, offset, RecordYieldCallback {
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+        pending_computation: pending_computation_address(&pool.address, offset),
+        requester: authority,
+    });
+    h.callback(RECORD_YIELD_CIRCUIT, offset, &Output::new().enc_data(&pool_state(4)), accounts)
+        .expect("record_yield callback");
+    assert_eq!(h.computation_succeeded(&pool.address, offset), Some(true));
+    assert_eq!(h.account::<GhostPool>(&pool.address).kamino_position_value, INVEST + YIELD);
+
+    // ---- Withdraw ----
+    let withdraw_offset = request_withdrawal(&mut h, &pool, &user, &user_tokens);
+    let pending_withdrawal = pending_withdrawal_address(&pool.address, &user.pubkey());
+    let destination = h.account::<PendingWithdrawal>(&pending_withdrawal).destination_owner;
+    assert_eq!(destination, pubkey_fingerprint(&user.pubkey()));
+    let output = authorization(&h, &pool, &user, true);
+    let accounts = authorize_accounts(&h, &pool, &user, withdraw_offset);
+    h.callback(WITHDRAW_CIRCUIT, withdraw_offset, &output, accounts)
+        .expect("authorize_withdrawal callback");
+    assert_eq!(h.computation_succeeded(&pool.address, withdraw_offset), Some(true));
+
+    let offset = random_computation_offset();
+    let ix = claim_withdrawal(&h.env, &pool, &user.pubkey(), &user_tokens, withdraw_offset, offset, None);
+    h.send(&[ix], &[&user]).expect("claim_withdrawal");
+    assert_eq!(h.token_balance(&user_tokens), WITHDRAW);
+    assert_eq!(h.token_balance(&vault), DEPOSIT - INVEST - WITHDRAW);
+
+    let accounts = callback_accounts!(h, CLAIM_CIRCUIT, offset, ProcessWithdrawalCallback {
+        ghost_pool: pool.address,
+        withdrawal_receipt: withdrawal_receipt_address(&pool.address, withdraw_offset),
+        computation_log: computation_log_address(&pool.address),
+    });
+    h.callback(CLAIM_CIRCUIT, offset, &Output::new().enc_data(&pool_state(5)), accounts)
+        .expect("process_withdrawal callback");
+    assert_eq!(h.computation_succeeded(&pool.address, offset), Some(true));
+    let state = h.account::<GhostPool>(&pool.address);
+    assert_eq!(state.total_withdrawals, 1);
+    assert_eq!(state.debits_in_flight, 0);
+    assert_eq!(state.state_write_in_flight, Pubkey::default());
+
+    // ---- Redeem ----
+    // The yield moved the rate to 1.1, so the cTokens are worth more than they cost
+    let reserve_keys = KaminoReserve::from_account_data(reserve, KAMINO_LENDING_PROGRAM_ID, &h.data(&reserve))
+        .expect("reserve layout");
+    let min_liquidity_out = reserve_keys.min_liquidity_out(REDEEM, 0);
+    assert_eq!(min_liquidity_out, REDEEM * (INVEST + YIELD) / INVEST);
+    let redeem = |min_liquidity_out| {
+        redeem_from_kamino(&pool.address, &authority, &mint, &collateral, &reserve_keys, REDEEM, min_liquidity_out)
+    };
+    let err = h
+        .send(&[redeem(min_liquidity_out + 1)], &[])
+        .expect_err("redeem_from_kamino above the exchange rate");
+    assert!(err.contains("SlippageExceeded"), "{}", err);
+    assert_eq!(h.token_balance(&collateral), INVEST);
+    h.send(&[redeem(min_liquidity_out)], &[]).expect("redeem_from_kamino");
+    assert_eq!(h.token_balance(&collateral), INVEST - REDEEM);
+    assert_eq!(h.token_balance(&vault), DEPOSIT - INVEST - WITHDRAW + min_liquidity_out);
+}
+
+fn cancel_pending_withdrawal(h: &mut Harness, pool: &PoolRef, user: &Keypair) -> Result<(), String> {
+    let ix = Instruction::new_with_bytes(
+        PROGRAM_ID,
+        &ghost_pool::instruction::CancelPendingWithdrawal {}.data(),
+        ghost_pool::accounts::CancelPendingWithdrawal {
+            user: user.pubkey(),
+            pending_withdrawal: pending_withdrawal_address(&pool.address, &user.pubkey()),
+        }
+        .to_account_metas(None),
+    );
+    h.send(&[ix], &[user]).map(|_| ())
+}
+
+#[test]
+fn rejected_withdrawal_is_not_claimable() {
+    let mut h = Harness::new().unwrap();
+    let pool = active_pool(&mut h);
+    let (user, user_tokens) = depositor(&mut h, &pool);
+
+    let withdraw_offset = request_withdrawal(&mut h, &pool, &user, &user_tokens);
+    let output = authorization(&h, &pool, &user, false);
+    let accounts = authorize_accounts(&h, &pool, &user, withdraw_offset);
+    h.callback(WITHDRAW_CIRCUIT, withdraw_offset, &output, accounts)
+        .expect("authorize_withdrawal callback");
+    assert_eq!(h.computation_succeeded(&pool.address, withdraw_offset), Some(false));
+    let pending = h.account::<PendingWithdrawal>(&pending_withdrawal_address(&pool.address, &user.pubkey()));
+    assert_eq!(pending.status, PositionStatus::Requested);
+    assert_eq!(pending.amount, 0);
+
+    let offset = random_computation_offset();
+    let ix = claim_withdrawal(&h.env, &pool, &user.pubkey(), &user_tokens, withdraw_offset, offset, None);
+    let err = h.send(&[ix], &[&user]).expect_err("claim of a rejected withdrawal");
+    assert!(err.contains("WithdrawalUnauthorized"), "{}", err);
+    assert_eq!(h.token_balance(&user_tokens), 0);
+    assert_eq!(h.token_balance(&vault_address(&pool.address)), DEPOSIT);
+
+    // Rejection expires the request, so it can be cancelled from the next slot on
+    h.advance_slots(1);
+    cancel_pending_withdrawal(&mut h, &pool, &user).expect("cancel_pending_withdrawal");
+}
+
+#[test]
+fn aborted_withdrawal_can_be_requested_again() {
+    let mut h = Harness::new().unwrap();
+    let pool = active_pool(&mut h);
+    let (user, user_tokens) = depositor(&mut h, &pool);
+
+    let withdraw_offset = request_withdrawal(&mut h, &pool, &user, &user_tokens);
+    // Even an output that would authorize the withdrawal is dropped when it doesn't verify
+    let output = authorization(&h, &pool, &user, true);
+    let accounts = authorize_accounts(&h, &pool, &user, withdraw_offset);
+    h.aborted_callback(WITHDRAW_CIRCUIT, withdraw_offset, &output, accounts)
+        .expect("authorize_withdrawal callback");
+    assert_eq!(h.computation_succeeded(&pool.address, withdraw_offset), Some(false));
+    let pending = h.account::<PendingWithdrawal>(&pending_withdrawal_address(&pool.address, &user.pubkey()));
+    assert_eq!(pending.status, PositionStatus::Requested);
+    assert_eq!(pending.amount, 0);
+    let state = h.account::<GhostPool>(&pool.address);
+    assert_eq!(state.debits_in_flight, 0);
+    assert_eq!(state.state_write_in_flight, Pubkey::default());
+
+    let offset = random_computation_offset();
+    let ix = claim_withdrawal(&h.env, &pool, &user.pubkey(), &user_tokens, withdraw_offset, offset, None);
+    let err = h.send(&[ix], &[&user]).expect_err("claim of an aborted withdrawal");
+    assert!(err.contains("WithdrawalUnauthorized"), "{}", err);
+
+    h.advance_slots(1);
+    cancel_pending_withdrawal(&mut h, &pool, &user).expect("cancel_pending_withdrawal");
+    let withdraw_offset = request_withdrawal(&mut h, &pool, &user, &user_tokens);
+    let output = authorization(&h, &pool, &user, true);
+    let accounts = authorize_accounts(&h, &pool, &user, withdraw_offset);
+    h.callback(WITHDRAW_CIRCUIT, withdraw_offset, &output, accounts)
+        .expect("authorize_withdrawal callback");
+    assert_eq!(h.computation_succeeded(&pool.address, withdraw_offset), Some(true));
+}
+
+fn retry_payout(h: &mut Harness, pool: &PoolRef, user: &Keypair, withdraw_offset: u64, destination: &Pubkey) {
+    let ix = Instruction::new_with_bytes(
+        PROGRAM_ID,
+        &ghost_pool::instruction::RetryPayout {}.data(),
+        ghost_pool::accounts::RetryPayout {
+            user: user.pubkey(),
+            ghost_pool: pool.address,
+            pending_withdrawal: pending_withdrawal_address(&pool.address, &user.pubkey()),
+            withdrawal_receipt: withdrawal_receipt_address(&pool.address, withdraw_offset),
+            vault: vault_address(&pool.address),
+            deposit_mint: pool.deposit_mint,
+            destination_token_account: *destination,
+            token_program: pool.token_program,
+            share_mint: None,
+            share_token_account: None,
+        }
+        .to_account_metas(None),
+    );
+    h.send(&[ix], &[user]).expect("retry_payout");
+}
+
+#[test]
+fn payout_waits_while_the_destination_has_another_owner() {
+    let mut h = Harness::new().unwrap();
+    let pool = active_pool(&mut h);
+    let (user, user_tokens) = depositor(&mut h, &pool);
+    let withdraw_offset = request_withdrawal(&mut h, &pool, &user, &user_tokens);
+    let output = authorization(&h, &pool, &user, true);
+    let accounts = authorize_accounts(&h, &pool, &user, withdraw_offset);
+    h.callback(WITHDRAW_CIRCUIT, withdraw_offset, &output, accounts)
+        .expect("authorize_withdrawal callback");
+
+    // A frozen destination sends the claim to retry_payout
+    h.update_token_account(&user_tokens, |account| account.state = spl_token::state::AccountState::Frozen);
+    let offset = random_computation_offset();
+    let ix = claim_withdrawal(&h.env, &pool, &user.pubkey(), &user_tokens, withdraw_offset, offset, None);
+    h.send(&[ix], &[&user]).expect("claim_withdrawal");
+    let pending_withdrawal = pending_withdrawal_address(&pool.address, &user.pubkey());
+    assert_eq!(h.account::<PendingWithdrawal>(&pending_withdrawal).status, PositionStatus::PayoutPending);
+
+    // Thawed but handed to someone else since the authorization: payable, yet not to them
+    let other_owner = Keypair::new().pubkey();
+    h.update_token_account(&user_tokens, |account| {
+        account.state = spl_token::state::AccountState::Initialized;
+        account.owner = other_owner;
+    });
+    retry_payout(&mut h, &pool, &user, withdraw_offset, &user_tokens);
+    assert_eq!(h.token_balance(&user_tokens), 0);
+    let pending = h.account::<PendingWithdrawal>(&pending_withdrawal);
+    assert_eq!(pending.status, PositionStatus::PayoutPending);
+    assert_eq!(pending.payout_attempts, 2);
+
+    h.update_token_account(&user_tokens, |account| account.owner = user.pubkey());
+    retry_payout(&mut h, &pool, &user, withdraw_offset, &user_tokens);
+    assert_eq!(h.token_balance(&user_tokens), WITHDRAW);
+    assert!(h.svm.get_account(&pending_withdrawal).is_none_or(|account| account.lamports == 0));
+}
+
+/// Close a counter epoch on `pool`, the MPC revealing `deposits` and `withdrawals`
+fn close_counter_epoch(h: &mut Harness, pool: &PoolRef, deposits: u64, withdrawals: u64) {
+    let payer = h.payer.pubkey();
+    let offset = random_computation_offset();
+    let ix = ghost_pool_client::close_counter_epoch(&h.env, &pool.address, &payer, offset);
+    h.send(&[ix], &[]).expect("close_counter_epoch");
+    let accounts = callback_accounts!(h, COUNTER_EPOCH_CIRCUIT, offset, RevealDepositCountCallback {
+        ghost_pool: pool.address,
+        computation_log: computation_log_address(&pool.address),
+    });
+    let output = Output::new().u64(deposits).u64(withdrawals);
+    h.callback(COUNTER_EPOCH_CIRCUIT, offset, &output, accounts)
+        .expect("reveal_deposit_count callback");
+    assert_eq!(h.computation_succeeded(&pool.address, offset), Some(true));
+}
+
+#[test]
+fn bucketed_counters_move_only_at_epoch_close() {
+    let mut h = Harness::new().unwrap();
+    let authority = h.payer.pubkey();
+    let pool = active_pool(&mut h);
+    depositor(&mut h, &pool);
+    assert_eq!(h.account::<GhostPool>(&pool.address).total_deposits, 1);
+
+    let ix = set_counter_privacy(&pool.address, &authority, 10, 0);
+    h.send(&[ix], &[]).expect("set_counter_privacy");
+    depositor(&mut h, &pool);
+    assert_eq!(h.account::<GhostPool>(&pool.address).total_deposits, 1);
+
+    // Two lifetime deposits round down to 0, below the count published before bucketing
+    close_counter_epoch(&mut h, &pool, 0, 0);
+    let state = h.account::<GhostPool>(&pool.address);
+    assert_eq!((state.total_deposits, state.total_withdrawals), (1, 0));
+
+    close_counter_epoch(&mut h, &pool, 10, 0);
+    let state = h.account::<GhostPool>(&pool.address);
+    assert_eq!((state.total_deposits, state.total_withdrawals), (10, 0));
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+
+declare_id!("FW1eccYzYyTTHTaoADApZdxH2R4xJXLBoorwtwZ4u4Za");
+
+/// Test-only CPI passthrough
+/// Stands in for a program that wraps a Ghost Pool callback in its own instruction,
+/// so the tests can show callbacks refuse to run below the top level
+#[program]
+pub mod callback_wrapper {
+    use super::*;
+
+    /// Invoke `target` with `data`, passing the remaining accounts through unchanged
+    pub fn forward<'info>(ctx: Context<'_, '_, 'info, 'info, Forward<'info>>, data: Vec<u8>) -> Result<()> {
+        let accounts = ctx
+            .remaining_accounts
+            .iter()
+            .map(|a| AccountMeta {
+                pubkey: *a.key,
+                is_signer: a.is_signer,
+                is_writable: a.is_writable,
+            })
+            .collect();
+        let ix = Instruction {
+            program_id: ctx.accounts.target.key(),
+            accounts,
+            data,
+        };
+
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.target.to_account_info());
+        invoke(&ix, &infos)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Forward<'info> {
+    /// CHECK: any program; the forwarded instruction is validated by its target
+    #[account(executable)]
+    pub target: UncheckedAccount<'info>,
+}
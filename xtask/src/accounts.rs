@@ -0,0 +1,181 @@
+//! Unchecked-account policy for Anchor account structs
+//!
+//! Every `#[derive(Accounts)]` context is checked: an `UncheckedAccount` or `AccountInfo` field
+//! must be pinned with an `address =` constraint or carry a `/// CHECK:` saying who validates
+//! it. Contexts only an authority can sign for opt out with [`ADMIN_MARKER`] above the struct.
+//!
+//! Plain std so the program's build script can include it as well (`#[path]`), which makes a
+//! violation fail the program build and not only `cargo xtask check-accounts`.
+
+/// Marker placed above an account struct to exempt it: admin contexts, where every signer is
+/// trusted. User-facing and callback contexts can't carry it
+pub const ADMIN_MARKER: &str = "// policy: admin";
+
+/// A `/// CHECK:` comment must explain who validates the account, not just name it
+const MIN_CHECK_WORDS: usize = 3;
+
+pub struct Violation {
+    /// 1-based line of the field
+    pub line: usize,
+    pub context: String,
+    pub field: String,
+}
+
+impl Violation {
+    pub fn describe(&self) -> String {
+        match self.field.as_str() {
+            "" => format!("{} is a callback context marked `{}`", self.context, ADMIN_MARKER),
+            field => format!(
+                "{}.{} is unchecked without an `address =` constraint or a documented `/// CHECK:`",
+                self.context, field
+            ),
+        }
+    }
+}
+
+/// Contexts scanned in one source file and the violations among them
+#[derive(Default)]
+pub struct Report {
+    pub checked: usize,
+    pub exempt: usize,
+    pub violations: Vec<Violation>,
+}
+
+/// Scan one source file
+pub fn check_source(src: &str) -> Report {
+    let lines: Vec<&str> = src.lines().collect();
+    let mut report = Report::default();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some(name) = struct_name(lines[i]) else {
+            i += 1;
+            continue;
+        };
+
+        let header = struct_header(&lines, i);
+        let is_accounts = header.iter().any(|l| l.starts_with("#[derive(Accounts)]"));
+        let is_callback = header.iter().any(|l| l.starts_with("#[callback_accounts("));
+        let is_admin = header.iter().any(|l| *l == ADMIN_MARKER);
+        if is_callback && is_admin {
+            report.violations.push(Violation {
+                line: i + 1,
+                context: name.to_string(),
+                field: String::new(),
+            });
+        }
+        let checked = is_accounts && !(is_admin && !is_callback);
+
+        i += 1;
+        let mut prelude: Vec<&str> = Vec::new();
+        while i < lines.len() && !lines[i].starts_with('}') {
+            let line = lines[i].trim();
+            if let Some((field, ty)) = field_decl(line) {
+                if checked && is_unchecked(ty) && !is_justified(&prelude) {
+                    report.violations.push(Violation {
+                        line: i + 1,
+                        context: name.to_string(),
+                        field: field.to_string(),
+                    });
+                }
+                prelude.clear();
+            } else {
+                prelude.push(line);
+            }
+            i += 1;
+        }
+
+        if checked {
+            report.checked += 1;
+        } else if is_accounts {
+            report.exempt += 1;
+        }
+    }
+
+    report
+}
+
+/// `pub struct Name<'info> {` -> `Name`
+fn struct_name(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("pub struct ")?;
+    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))?;
+    rest[end..].starts_with("<'info>").then(|| &rest[..end])
+}
+
+/// Attribute, doc and marker lines directly above a struct declaration, including every
+/// line of an attribute split over several (`#[instruction(` ... `)]`)
+fn struct_header<'a>(lines: &[&'a str], decl: usize) -> Vec<&'a str> {
+    let mut header = Vec::new();
+    let mut in_attribute = false;
+    for line in lines[..decl].iter().rev().map(|l| l.trim()) {
+        if in_attribute {
+            in_attribute = !line.starts_with("#[");
+        } else if line.ends_with(")]") && !line.starts_with("#[") {
+            in_attribute = true;
+        } else if !(line.starts_with("#[") || line.starts_with("//")) {
+            break;
+        }
+        header.push(line);
+    }
+    header
+}
+
+/// `pub name: Type<'info>,` -> (`name`, `Type<'info>`)
+fn field_decl(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("pub ")?.strip_suffix(',')?;
+    let (name, ty) = rest.split_once(':')?;
+    Some((name.trim(), ty.trim()))
+}
+
+fn is_unchecked(ty: &str) -> bool {
+    ty.starts_with("UncheckedAccount<") || ty.starts_with("AccountInfo<")
+}
+
+/// An unchecked field is fine if pinned by address or carrying a real CHECK justification
+fn is_justified(prelude: &[&str]) -> bool {
+    let pinned = prelude
+        .iter()
+        .filter(|l| !l.starts_with("//"))
+        .any(|l| l.contains("address ="));
+
+    let documented = prelude.iter().any(|l| {
+        l.strip_prefix("/// CHECK:")
+            .map(|reason| reason.split_whitespace().count() >= MIN_CHECK_WORDS)
+            .unwrap_or(false)
+    });
+
+    pinned || documented
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNCHECKED_FIELD: &str = "    /// CHECK: x\n    pub target: UncheckedAccount<'info>,\n}\n";
+
+    fn context(header: &str) -> String {
+        format!("{}\npub struct Ctx<'info> {{\n{}", header, UNCHECKED_FIELD)
+    }
+
+    #[test]
+    fn unmarked_contexts_are_checked() {
+        let report = check_source(&context("#[derive(Accounts)]\n#[instruction(\n    amount: u64,\n)]"));
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].field, "target");
+    }
+
+    #[test]
+    fn admin_contexts_opt_out() {
+        let report = check_source(&context("// policy: admin\n#[derive(Accounts)]"));
+        assert_eq!((report.checked, report.exempt), (0, 1));
+        assert!(report.violations.is_empty());
+    }
+
+    #[test]
+    fn callbacks_cannot_opt_out() {
+        let report = check_source(&context("// policy: admin\n#[callback_accounts(\"x\")]\n#[derive(Accounts)]"));
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.violations.len(), 2);
+    }
+}
@@ -0,0 +1,276 @@
+//! Deterministic devnet fixtures
+//!
+//! Every keypair is derived from `sha256(DOMAIN || seed || label)`, and every
+//! other address is a PDA or ATA of those keys, so the same seed always yields
+//! the same fixture set. `scripts/apply-fixtures.ts` provisions it on devnet.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use ed25519_dalek::SigningKey;
+use serde_json::{json, Value};
+use solana_pubkey::Pubkey;
+use solana_sha256_hasher::hashv;
+
+/// Domain separator so fixture keys never collide with other seed-derived keys
+const DOMAIN: &[u8] = b"ghost-pool-fixtures/v1";
+
+const DEFAULT_SEED: &str = "ghost-pool-devnet";
+const DEFAULT_OUT: &str = "fixtures/devnet";
+const DEFAULT_POOLS: usize = 1;
+const DEFAULT_USERS: usize = 4;
+
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hdZbsZHTd3rrqzV8Xx4zWG4sE";
+
+const USDC_DECIMALS: u8 = 6;
+/// 1:1 liquidity per cToken, scaled by 1e6 (mock_kamino::init_reserve)
+const INITIAL_EXCHANGE_RATE: u64 = 1_000_000;
+/// 50K USDC
+const INVESTMENT_THRESHOLD: u64 = 50_000_000_000;
+/// SOL sent to every fixture signer for rent and fees
+const SIGNER_LAMPORTS: u64 = 2_000_000_000;
+/// User `i` is minted `(i + 1) * USER_USDC_STEP`
+const USER_USDC_STEP: u64 = 1_000_000_000;
+
+struct Options {
+    seed: String,
+    out: PathBuf,
+    pools: usize,
+    users: usize,
+}
+
+struct Programs {
+    ghost_pool: Pubkey,
+    mock_kamino: Pubkey,
+}
+
+/// Keypairs written to disk, in generation order
+struct Keys<'a> {
+    seed: &'a str,
+    written: Vec<(String, SigningKey)>,
+}
+
+impl Keys<'_> {
+    fn derive(&mut self, label: &str) -> Pubkey {
+        let secret = hashv(&[DOMAIN, self.seed.as_bytes(), b"/", label.as_bytes()]).to_bytes();
+        let key = SigningKey::from_bytes(&secret);
+        let pubkey = Pubkey::new_from_array(key.verifying_key().to_bytes());
+        self.written.push((label.to_string(), key));
+        pubkey
+    }
+}
+
+pub fn run(args: &[String], root: &Path) -> ExitCode {
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("fixtures: {}", e);
+            eprintln!("usage: cargo xtask fixtures [--seed <seed>] [--out <dir>] [--pools <n>] [--users <n>]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let programs = match read_programs(&root.join("Anchor.toml")) {
+        Ok(programs) => programs,
+        Err(e) => {
+            eprintln!("fixtures: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut keys = Keys { seed: &opts.seed, written: Vec::new() };
+    let manifest = build_manifest(&opts, &programs, &mut keys);
+
+    let out = root.join(&opts.out);
+    if let Err(e) = write_fixtures(&out, &manifest, &keys.written) {
+        eprintln!("fixtures: failed to write {}: {}", out.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "fixtures: seed {:?} -> {} ({} keypairs, {} pool(s), {} user(s))",
+        opts.seed,
+        out.display(),
+        keys.written.len(),
+        opts.pools,
+        opts.users,
+    );
+    ExitCode::SUCCESS
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options {
+        seed: DEFAULT_SEED.to_string(),
+        out: PathBuf::from(DEFAULT_OUT),
+        pools: DEFAULT_POOLS,
+        users: DEFAULT_USERS,
+    };
+
+    let mut it = args.iter();
+    while let Some(flag) = it.next() {
+        let value = it.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--seed" => opts.seed = value.clone(),
+            "--out" => opts.out = PathBuf::from(value),
+            "--pools" => opts.pools = value.parse().map_err(|_| format!("bad --pools {:?}", value))?,
+            "--users" => opts.users = value.parse().map_err(|_| format!("bad --users {:?}", value))?,
+            _ => return Err(format!("unknown flag {:?}", flag)),
+        }
+    }
+
+    if opts.pools == 0 {
+        return Err("--pools must be at least 1".to_string());
+    }
+    Ok(opts)
+}
+
+/// Read the devnet program ids from `[programs.devnet]` so fixtures follow deployments
+fn read_programs(anchor_toml: &Path) -> Result<Programs, String> {
+    let src = std::fs::read_to_string(anchor_toml)
+        .map_err(|e| format!("failed to read {}: {}", anchor_toml.display(), e))?;
+
+    let mut in_section = false;
+    let mut ghost_pool = None;
+    let mut mock_kamino = None;
+    for line in src.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == "[programs.devnet]";
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=').filter(|_| in_section) else {
+            continue;
+        };
+        let id = Pubkey::from_str(value.trim().trim_matches('"'))
+            .map_err(|e| format!("bad program id for {}: {}", name.trim(), e))?;
+        match name.trim() {
+            "ghost_pool" => ghost_pool = Some(id),
+            "mock_kamino" => mock_kamino = Some(id),
+            _ => {}
+        }
+    }
+
+    Ok(Programs {
+        ghost_pool: ghost_pool.ok_or("ghost_pool missing from [programs.devnet]")?,
+        mock_kamino: mock_kamino.ok_or("mock_kamino missing from [programs.devnet]")?,
+    })
+}
+
+fn pda(seeds: &[&[u8]], program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(seeds, program).0
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+    let ata_program = Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap();
+    pda(&[owner.as_ref(), token_program.as_ref(), mint.as_ref()], &ata_program)
+}
+
+fn key_path(label: &str) -> String {
+    format!("keys/{}.json", label)
+}
+
+fn build_manifest(opts: &Options, programs: &Programs, keys: &mut Keys) -> Value {
+    let usdc_mint = keys.derive("usdc_mint");
+
+    // Mock Kamino market and USDC reserve
+    let kamino_admin = keys.derive("kamino_admin");
+    let collateral_mint = keys.derive("collateral_mint");
+    let lending_market = pda(&[b"lending_market", kamino_admin.as_ref()], &programs.mock_kamino);
+    let market_authority = pda(
+        &[b"lending_market_authority", lending_market.as_ref()],
+        &programs.mock_kamino,
+    );
+    let reserve = pda(
+        &[b"reserve", lending_market.as_ref(), usdc_mint.as_ref()],
+        &programs.mock_kamino,
+    );
+    let liquidity_supply = pda(
+        &[b"reserve_liquidity", lending_market.as_ref(), usdc_mint.as_ref()],
+        &programs.mock_kamino,
+    );
+
+    // One authority runs every pool, told apart by pool_id
+    let pool_authority = keys.derive("pool_authority");
+    let pools: Vec<Value> = (0..opts.pools as u64)
+        .map(|pool_id| {
+            let ghost_pool = pda(
+                &[b"ghost_pool", pool_authority.as_ref(), &pool_id.to_le_bytes()],
+                &programs.ghost_pool,
+            );
+            json!({
+                "pool_id": pool_id,
+                "authority": pool_authority.to_string(),
+                "authority_keypair": key_path("pool_authority"),
+                "ghost_pool": ghost_pool.to_string(),
+                "vault": pda(&[b"vault", ghost_pool.as_ref()], &programs.ghost_pool).to_string(),
+                "computation_log": pda(&[b"computation_log", ghost_pool.as_ref()], &programs.ghost_pool).to_string(),
+                "rotator": pda(&[b"rotator", ghost_pool.as_ref()], &programs.ghost_pool).to_string(),
+                "collateral_account": ata(&ghost_pool, &collateral_mint).to_string(),
+                "investment_threshold": INVESTMENT_THRESHOLD,
+                "lamports": SIGNER_LAMPORTS,
+            })
+        })
+        .collect();
+
+    let users: Vec<Value> = (0..opts.users)
+        .map(|i| {
+            let label = format!("user_{}", i);
+            let user = keys.derive(&label);
+            json!({
+                "name": label,
+                "pubkey": user.to_string(),
+                "keypair": key_path(&label),
+                "usdc_account": ata(&user, &usdc_mint).to_string(),
+                "usdc_balance": (i as u64 + 1) * USER_USDC_STEP,
+                "lamports": SIGNER_LAMPORTS,
+            })
+        })
+        .collect();
+
+    json!({
+        "version": 2,
+        "seed": opts.seed,
+        "cluster": "devnet",
+        "programs": {
+            "ghost_pool": programs.ghost_pool.to_string(),
+            "mock_kamino": programs.mock_kamino.to_string(),
+        },
+        "usdc": {
+            "mint": usdc_mint.to_string(),
+            "mint_keypair": key_path("usdc_mint"),
+            "decimals": USDC_DECIMALS,
+            "mint_authority": kamino_admin.to_string(),
+        },
+        "kamino": {
+            "admin": kamino_admin.to_string(),
+            "admin_keypair": key_path("kamino_admin"),
+            "admin_lamports": SIGNER_LAMPORTS,
+            "lending_market": lending_market.to_string(),
+            "lending_market_authority": market_authority.to_string(),
+            "reserve": reserve.to_string(),
+            "liquidity_supply": liquidity_supply.to_string(),
+            "collateral_mint": collateral_mint.to_string(),
+            "collateral_mint_keypair": key_path("collateral_mint"),
+            "initial_exchange_rate": INITIAL_EXCHANGE_RATE,
+        },
+        "pools": pools,
+        "users": users,
+    })
+}
+
+/// Keypairs use the Solana CLI format (64-byte JSON array) so `solana`/web3 load them directly
+fn write_fixtures(out: &Path, manifest: &Value, keys: &[(String, SigningKey)]) -> std::io::Result<()> {
+    let key_dir = out.join("keys");
+    std::fs::create_dir_all(&key_dir)?;
+
+    for (label, key) in keys {
+        let bytes = key.to_keypair_bytes().to_vec();
+        std::fs::write(key_dir.join(format!("{}.json", label)), serde_json::to_string(&bytes)?)?;
+    }
+
+    let mut manifest = serde_json::to_string_pretty(manifest)?;
+    manifest.push('\n');
+    std::fs::write(out.join("manifest.json"), manifest)
+}
@@ -0,0 +1,66 @@
+//! Ghost Pool development tasks
+//!
+//! Run with `cargo xtask <task>`:
+//! - `check-accounts`: enforce the unchecked-account policy (`accounts.rs`) on every account
+//!   context; the program's build script enforces it too
+//! - `fixtures`: generate a deterministic devnet fixture set (keypairs + manifest) from a seed
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod accounts;
+mod fixtures;
+
+/// Program sources scanned for Anchor account structs
+const PROGRAM_SOURCES: &[&str] = &["programs/ghost_pool/src/lib.rs"];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("check-accounts") => check_accounts(),
+        Some("fixtures") => fixtures::run(&args[1..], &workspace_root()),
+        _ => {
+            eprintln!("usage: cargo xtask <check-accounts | fixtures>");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives inside the workspace")
+        .to_path_buf()
+}
+
+fn check_accounts() -> ExitCode {
+    let root = workspace_root();
+    let mut checked = 0;
+    let mut exempt = 0;
+    let mut failed = 0;
+
+    for source in PROGRAM_SOURCES {
+        let path = root.join(source);
+        let src = match std::fs::read_to_string(&path) {
+            Ok(src) => src,
+            Err(e) => {
+                eprintln!("failed to read {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let report = accounts::check_source(&src);
+        for v in &report.violations {
+            eprintln!("{}:{}: {}", path.display(), v.line, v.describe());
+        }
+        checked += report.checked;
+        exempt += report.exempt;
+        failed += report.violations.len();
+    }
+
+    if failed == 0 {
+        println!("check-accounts: {} contexts OK, {} admin contexts exempt", checked, exempt);
+        return ExitCode::SUCCESS;
+    }
+    eprintln!("check-accounts: {} violation(s)", failed);
+    ExitCode::FAILURE
+}